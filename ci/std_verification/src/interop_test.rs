@@ -0,0 +1,33 @@
+use psp::test_runner::TestRunner;
+use std::io::{Read, Seek, SeekFrom, Write};
+
+const PATH: &str = "host0:/std_interop_test.txt";
+
+pub fn test_main(test_runner: &mut TestRunner) {
+    // The crate's own `File` should work with code written against `std::io`.
+    let written = psp::fs::File::create(PATH)
+        .map_err(std::io::Error::from)
+        .and_then(|mut file| file.write_all(b"hello, std"));
+    test_runner.check("psp_file_std_write", written.is_ok(), true);
+
+    let mut file = psp::fs::File::open(PATH).unwrap();
+    let mut contents = String::new();
+    let read = file
+        .seek(SeekFrom::Start(7))
+        .and_then(|_| file.read_to_string(&mut contents));
+    drop(file);
+
+    test_runner.check("psp_file_std_read", read.ok(), Some(3));
+    test_runner.check("psp_file_std_contents", contents, "std".to_string());
+
+    let missing = psp::fs::File::open("host0:/std_interop_missing")
+        .map_err(std::io::Error::from)
+        .err()
+        .map(|e| e.kind());
+    test_runner.check("error_kind", missing, Some(std::io::ErrorKind::NotFound));
+
+    let stdout_ok = writeln!(psp::stdio::stdout(), "std_verification: psp stdout").is_ok();
+    test_runner.check("psp_stdout_std_write", stdout_ok, true);
+
+    test_runner.check("remove_file", psp::fs::remove_file(PATH).is_ok(), true);
+}
@@ -3,6 +3,7 @@
 
 use psp::test_runner::TestRunner;
 
+mod interop_test;
 mod time_test;
 
 psp::module!("std_verification", 1, 1);
@@ -10,6 +11,7 @@ psp::module!("std_verification", 1, 1);
 fn psp_main() {
     let tests = &[
         time_test::test_main,
+        interop_test::test_main,
     ];
 
     let mut runner = TestRunner::new_dprintln_runner();
@@ -0,0 +1,247 @@
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+use psp::audio::at3::{At3Header, AtracCodec, HeaderError, LoopPoints};
+use psp::audio::mp3::id3_size;
+use psp::audio::{Channels, MixCore, Pcm, SampleRing, Sink, WavWriter};
+use psp::io::Cursor;
+use psp::test_runner::TestRunner;
+
+struct Ramp {
+    next: i16,
+    end: i16,
+}
+
+impl Sink for Ramp {
+    fn channels(&self) -> Channels {
+        Channels::Mono
+    }
+
+    fn sample_rate(&self) -> u32 {
+        44100
+    }
+
+    fn read(&mut self, buf: &mut [i16]) -> usize {
+        let mut frames = 0;
+
+        for sample in buf.iter_mut() {
+            if self.next >= self.end {
+                break;
+            }
+
+            *sample = self.next;
+            self.next += 1;
+            frames += 1;
+        }
+
+        frames
+    }
+}
+
+/// The little-endian bytes of `values`.
+fn words(values: &[u32]) -> Vec<u8> {
+    values
+        .iter()
+        .flat_map(|v| v.to_le_bytes().to_vec())
+        .collect()
+}
+
+fn at3_file() -> Vec<u8> {
+    let mut file = Vec::new();
+    file.extend_from_slice(b"RIFF\0\0\0\0WAVE");
+
+    // ATRAC3 at 132 kbit/s, 384 byte frames.
+    file.extend_from_slice(b"fmt \x20\0\0\0");
+    file.extend_from_slice(&[0x70, 0x02, 2, 0]);
+    file.extend(words(&[44100, 16537]));
+    file.extend_from_slice(&[0x80, 0x01, 0, 0]);
+    file.extend_from_slice(&[0; 16]);
+
+    file.extend_from_slice(b"fact\x08\0\0\0");
+    file.extend(words(&[441000, 1024]));
+
+    file.extend_from_slice(b"smpl\x3c\0\0\0");
+    file.extend(words(&[
+        0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 1000, 400000, 0, 0,
+    ]));
+
+    file.extend_from_slice(b"data\x00\x10\0\0");
+    file
+}
+
+fn id3(bytes: &[u8]) -> u64 {
+    id3_size(&mut Cursor::new(bytes)).unwrap()
+}
+
+fn render(core: &mut MixCore, frames: usize) -> Vec<i16> {
+    let mut out = alloc::vec![0; frames * 2];
+    core.mix(&mut out);
+    out
+}
+
+pub fn test_main(test_runner: &mut TestRunner) {
+    let mut core = MixCore::new();
+    core.play(Pcm::new(alloc::vec![1000, -2000], Channels::Mono, 44100));
+    test_runner.check(
+        "mono_center",
+        render(&mut core, 3),
+        alloc::vec![1000, 1000, -2000, -2000, 0, 0],
+    );
+
+    let mut core = MixCore::new();
+    let id = core.play(Pcm::new(alloc::vec![1000, 2000], Channels::Stereo, 44100));
+    core.voice_mut(id).unwrap().set_pan(-1.0);
+    test_runner.check("pan_left", render(&mut core, 1), alloc::vec![1000, 0]);
+
+    let mut core = MixCore::new();
+    core.play(Pcm::new(alloc::vec![0, 1000, 2000], Channels::Mono, 22050));
+    test_runner.check(
+        "resample",
+        render(&mut core, 6),
+        alloc::vec![0, 0, 500, 500, 1000, 1000, 1500, 1500, 2000, 2000, 2000, 2000],
+    );
+
+    let mut core = MixCore::new();
+    let id = core.play(Pcm::new(
+        alloc::vec![0, 100, 200, 300, 400],
+        Channels::Mono,
+        44100,
+    ));
+    core.voice_mut(id).unwrap().set_pitch(2.0);
+    test_runner.check(
+        "pitch",
+        render(&mut core, 4),
+        alloc::vec![0, 0, 200, 200, 400, 400, 0, 0],
+    );
+    test_runner.check(
+        "stopped_at_end",
+        core.voice(id).unwrap().is_playing(),
+        false,
+    );
+
+    let mut core = MixCore::new();
+    let id = core.play(Pcm::new(alloc::vec![7, 8], Channels::Mono, 44100));
+    core.voice_mut(id).unwrap().set_looping(true);
+    test_runner.check(
+        "looping",
+        render(&mut core, 3),
+        alloc::vec![7, 7, 8, 8, 7, 7],
+    );
+
+    let mut core = MixCore::new();
+    core.play(Pcm::new(
+        alloc::vec![30000, -30000],
+        Channels::Stereo,
+        44100,
+    ));
+    core.play(Pcm::new(
+        alloc::vec![30000, -30000],
+        Channels::Stereo,
+        44100,
+    ));
+    test_runner.check("clipping", render(&mut core, 1), alloc::vec![32767, -32768]);
+
+    let mut core = MixCore::new();
+    core.set_master_volume(0.5);
+    core.play(Pcm::new(alloc::vec![1000], Channels::Mono, 44100));
+    test_runner.check("master_volume", render(&mut core, 1), alloc::vec![500, 500]);
+
+    let mut core = MixCore::new();
+    core.play(Box::new(Ramp { next: 1, end: 3 }) as Box<dyn Sink>);
+    test_runner.check(
+        "stream",
+        render(&mut core, 3),
+        alloc::vec![1, 1, 2, 2, 0, 0],
+    );
+    test_runner.check("stream_removed", core.is_empty(), true);
+
+    let file = at3_file();
+    test_runner.check(
+        "at3_header",
+        At3Header::parse(&file),
+        Ok(At3Header {
+            codec: AtracCodec::Atrac3,
+            channels: 2,
+            sample_rate: 44100,
+            bitrate: 132,
+            bytes_per_frame: 384,
+            samples: Some(441000),
+            loop_points: Some(LoopPoints {
+                start: 1000,
+                end: 400000,
+            }),
+            data_offset: file.len() as u32,
+            data_size: 0x1000,
+        }),
+    );
+    test_runner.check(
+        "at3_truncated",
+        At3Header::parse(&file[..40]),
+        Err(HeaderError::Truncated),
+    );
+    test_runner.check(
+        "at3_not_riff",
+        At3Header::parse(b"OggS"),
+        Err(HeaderError::NotRiff),
+    );
+
+    let mut file = at3_file();
+    file[56..60].copy_from_slice(&u32::MAX.to_le_bytes());
+    test_runner.check(
+        "at3_chunk_overflow",
+        At3Header::parse(&file),
+        Err(HeaderError::Truncated),
+    );
+
+    let mut file = at3_file();
+    file[28..32].copy_from_slice(&0x2000_0000u32.to_le_bytes());
+    test_runner.check(
+        "at3_bitrate_overflow",
+        At3Header::parse(&file),
+        Err(HeaderError::Invalid),
+    );
+
+    let mut ring = SampleRing::new(4);
+    ring.push(&[1, 2, 3]);
+    ring.push(&[4, 5]);
+    let mut out = [0; 8];
+    test_runner.check(
+        "ring_overrun",
+        (ring.pop(&mut out), out, ring.dropped()),
+        (4, [2, 3, 4, 5, 0, 0, 0, 0], 1),
+    );
+    ring.push(&[6, 7, 8, 9, 10, 11]);
+    let mut out = [0; 2];
+    test_runner.check(
+        "ring_partial",
+        (ring.pop(&mut out), out, ring.len()),
+        (2, [8, 9], 2),
+    );
+
+    let mut wav = WavWriter::new(Cursor::new(Vec::new()), 22050, 1).unwrap();
+    wav.write_samples(&[1, -1]).unwrap();
+    wav.write_samples(&[0x1234]).unwrap();
+    let file = wav.finish().unwrap().into_inner();
+    test_runner.check("wav_len", file.len(), 50);
+    test_runner.check("wav_riff_size", &file[4..8], &42u32.to_le_bytes()[..]);
+    test_runner.check("wav_rate", &file[24..28], &22050u32.to_le_bytes()[..]);
+    test_runner.check("wav_data_size", &file[40..44], &6u32.to_le_bytes()[..]);
+    test_runner.check(
+        "wav_samples",
+        &file[44..],
+        &[1, 0, 0xff, 0xff, 0x34, 0x12][..],
+    );
+
+    test_runner.check("id3_none", id3(b"\xff\xfb\x90\x00 frame data"), 0);
+    test_runner.check("id3_short", id3(b"ID3\x04\0"), 0);
+    test_runner.check("id3_empty", id3(b"ID3\x04\0\0\0\0\0\0"), 10);
+    test_runner.check(
+        "id3_syncsafe",
+        id3(b"ID3\x03\0\0\x00\x00\x02\x01 tag"),
+        10 + 257,
+    );
+    test_runner.check(
+        "id3_footer",
+        id3(b"ID3\x04\0\x10\x7f\x7f\x7f\x7f"),
+        10 + 0x0fff_ffff + 10,
+    );
+}
@@ -0,0 +1,30 @@
+use alloc::format;
+use alloc::string::String;
+use psp::error::{self, Error, Facility};
+use psp::test_runner::TestRunner;
+
+pub fn test_main(test_runner: &mut TestRunner) {
+    let nofile = Error::from_raw(0x8002_012f_u32 as i32);
+    let enoent = Error::from_raw(0x8001_0002_u32 as i32);
+    let unknown = Error::from_raw(0x8099_1234_u32 as i32);
+
+    test_runner.check_list(&[
+        ("facility_kernel", nofile.facility(), Facility::Kernel),
+        ("facility_errno", enoent.facility(), Facility::Errno),
+        ("facility_other", unknown.facility(), Facility::Other(0x099)),
+    ]);
+    test_runner.check("errno", enoent.errno(), Some(2));
+    test_runner.check("known_constant", nofile, error::SCE_KERNEL_ERROR_NOFILE);
+    test_runner.check(
+        "display_known",
+        format!("{}", nofile),
+        String::from("SCE_KERNEL_ERROR_NOFILE (0x8002012f)"),
+    );
+    test_runner.check(
+        "display_unknown",
+        format!("{}", unknown),
+        String::from("facility 0x099 error 0x1234 (0x80991234)"),
+    );
+    test_runner.check("check_ok", error::check(3), Ok(3));
+    test_runner.check("check_err", error::check(enoent.raw()), Err(enoent));
+}
@@ -0,0 +1,49 @@
+use core::time::Duration;
+use psp::events::{self, EventsError, ExitBehavior};
+use psp::test_runner::TestRunner;
+use psp::thread;
+
+pub fn test_main(test_runner: &mut TestRunner) {
+    test_runner.check("poll_before_init", events::poll_event(), None);
+    test_runner.check("wait_before_init", events::wait_event(), None);
+
+    test_runner.check("init", events::init(ExitBehavior::Deferred).is_ok(), true);
+    test_runner.check(
+        "init_twice",
+        matches!(
+            events::init(ExitBehavior::Deferred),
+            Err(EventsError::AlreadyStarted)
+        ),
+        true,
+    );
+
+    let first = events::add_handler(|_| {});
+    let second = events::add_handler(|_| {});
+    test_runner.check("handler_ids_differ", first != second, true);
+    test_runner.check("remove_handler", events::remove_handler(first), true);
+    test_runner.check("remove_handler_twice", events::remove_handler(first), false);
+    test_runner.check("poll_event", events::poll_event(), None);
+
+    // A thread blocked in `wait_event` is woken up by `shutdown`.
+    let waiter = thread::spawn(events::wait_event);
+    thread::sleep(Duration::from_millis(10));
+    events::shutdown();
+    test_runner.check("wait_woken_by_shutdown", waiter.join(), Ok(None));
+
+    test_runner.check("poll_after_shutdown", events::poll_event(), None);
+    test_runner.check("wait_after_shutdown", events::wait_event(), None);
+    test_runner.check(
+        "remove_after_shutdown",
+        events::remove_handler(second),
+        false,
+    );
+    events::shutdown();
+
+    test_runner.check(
+        "init_again",
+        events::init(ExitBehavior::Deferred).is_ok(),
+        true,
+    );
+    test_runner.check("handlers_cleared", events::remove_handler(second), false);
+    events::shutdown();
+}
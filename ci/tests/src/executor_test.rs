@@ -0,0 +1,62 @@
+use alloc::rc::Rc;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::cell::RefCell;
+use psp::executor::{self, Executor, ExecutorError};
+use psp::fs::{self, File};
+use psp::test_runner::TestRunner;
+
+const TEST_FILE: &str = "host0:/psp_executor_test.bin";
+const LEN: usize = 64 * 1024;
+
+/// Read the whole test file with a single asynchronous read.
+async fn read_file() -> Option<Vec<u8>> {
+    let mut file = File::open(TEST_FILE).ok()?;
+    let mut buf = vec![0; LEN];
+    let n = file.read_async(&mut buf).await.ok()?;
+    buf.truncate(n);
+    Some(buf)
+}
+
+pub fn test_main(test_runner: &mut TestRunner) {
+    let data: Vec<u8> = (0..LEN).map(|i| (i * 7) as u8).collect();
+    test_runner.check("write_file", fs::write(TEST_FILE, &data).is_ok(), true);
+
+    let mut executor = Executor::new().unwrap();
+    test_runner.check(
+        "second_executor",
+        matches!(Executor::new(), Err(ExecutorError::InUse)),
+        true,
+    );
+
+    // Each task is woken by its own IO completion callback.
+    let results = Rc::new(RefCell::new(Vec::new()));
+    for id in 0..2 {
+        let results = results.clone();
+        executor.spawn(async move {
+            let buf = read_file().await;
+            results.borrow_mut().push((id, buf));
+        });
+    }
+
+    let main_read = executor.block_on(read_file());
+    executor.run();
+
+    test_runner.check("block_on_read", main_read.as_ref() == Some(&data), true);
+    test_runner.check("spawned_reads", results.borrow().len(), 2);
+
+    for (id, buf) in results.borrow().iter() {
+        test_runner.check(
+            "spawned_read",
+            (*id, buf.as_ref() == Some(&data)),
+            (*id, true),
+        );
+    }
+
+    drop(executor);
+
+    let read = executor::block_on(read_file());
+    test_runner.check("free_block_on_read", read == Some(data), true);
+
+    fs::remove_file(TEST_FILE).unwrap();
+}
@@ -0,0 +1,86 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+use psp::fs::{self, Device, File};
+use psp::io::{ErrorKind, Read, Seek, SeekFrom, Write};
+use psp::sys::ScePspDateTime;
+use psp::test_runner::TestRunner;
+
+const TEST_DIR: &str = "host0:/psp_fs_test/nested/dir";
+const TEST_FILE: &str = "host0:/psp_fs_test/nested/dir/file.bin";
+const RENAMED_FILE: &str = "host0:/psp_fs_test/nested/dir/renamed.bin";
+
+pub fn test_main(test_runner: &mut TestRunner) {
+    test_runner.check_list(&[
+        ("split_device_prefix", fs::split_device("ms0:/PSP/GAME"), (Some("ms0:"), "/PSP/GAME")),
+        ("split_device_relative", fs::split_device("data/a:b"), (None, "data/a:b")),
+    ]);
+    test_runner.check("join_paths", fs::join("host0:/a/", "/b"), String::from("host0:/a/b"));
+    test_runner.check(
+        "datetime_to_unix",
+        fs::datetime_to_unix(&ScePspDateTime {
+            year: 2004,
+            month: 12,
+            day: 12,
+            hour: 1,
+            minutes: 2,
+            seconds: 3,
+            microseconds: 0,
+        })
+        .map(|d| d.as_secs()),
+        Some(1102813323),
+    );
+    test_runner.check(
+        "datetime_to_unix_micros",
+        fs::datetime_to_unix(&ScePspDateTime {
+            year: 1970,
+            month: 1,
+            day: 1,
+            hour: 0,
+            minutes: 0,
+            seconds: 0,
+            microseconds: u32::MAX,
+        })
+        .map(|d| d.as_nanos()),
+        Some(u32::MAX as u128 * 1000),
+    );
+    test_runner.check("flash3_prefix", Device::Flash(3).prefix().ok(), Some("flash3:"));
+    test_runner.check(
+        "flash4_prefix",
+        Device::Flash(4).prefix().map_err(|e| e.kind()),
+        Err(ErrorKind::InvalidInput),
+    );
+
+    test_runner.check("create_dir_all", fs::create_dir_all(TEST_DIR).is_ok(), true);
+    test_runner.check("create_dir_all_twice", fs::create_dir_all(TEST_DIR).is_ok(), true);
+
+    let written = fs::write(TEST_FILE, b"hello psp").is_ok();
+    test_runner.check("write_file", written, true);
+
+    let contents = fs::read_to_string(TEST_FILE).unwrap_or_default();
+    test_runner.check("read_file", contents.as_str(), "hello psp");
+
+    let len = fs::metadata(TEST_FILE).map(|m| m.len()).unwrap_or(0);
+    test_runner.check("metadata_len", len, 9);
+
+    let mut file = File::options().read(true).write(true).open(TEST_FILE).unwrap();
+    file.seek(SeekFrom::Start(6)).unwrap();
+    file.write_all(b"PSP").unwrap();
+    file.seek(SeekFrom::Start(0)).unwrap();
+    let mut buf = Vec::new();
+    file.read_to_end(&mut buf).unwrap();
+    drop(file);
+    test_runner.check_large_collection("seek_and_overwrite", &buf, b"hello PSP");
+
+    test_runner.check("rename", fs::rename(TEST_FILE, RENAMED_FILE).is_ok(), true);
+
+    let names: Vec<String> = fs::read_dir(TEST_DIR)
+        .map(|dir| dir.filter_map(Result::ok).map(|e| String::from(e.file_name())).collect())
+        .unwrap_or_default();
+    test_runner.check_large_collection("read_dir", &names, &[String::from("renamed.bin")]);
+
+    let missing = File::open(TEST_FILE).map(|_| ()).map_err(|e| e.kind());
+    test_runner.check("open_missing", missing, Err(ErrorKind::NotFound));
+
+    test_runner.check("remove_dir_all", fs::remove_dir_all("host0:/psp_fs_test").is_ok(), true);
+    test_runner.check("removed", fs::exists("host0:/psp_fs_test"), false);
+}
@@ -0,0 +1,138 @@
+use alloc::vec;
+use alloc::vec::Vec;
+use psp::image::{Image, ImageError, ImageFormat};
+use psp::sys::TexturePixelFormat;
+use psp::test_runner::TestRunner;
+
+/// A 2x2 24-bit bitmap, stored bottom-up: red, green over blue, white.
+fn bmp() -> Vec<u8> {
+    let mut data = b"BM".to_vec();
+    data.extend_from_slice(&[0; 8]);
+    data.extend_from_slice(&54u32.to_le_bytes());
+    data.extend_from_slice(&40u32.to_le_bytes());
+    data.extend_from_slice(&2i32.to_le_bytes());
+    data.extend_from_slice(&2i32.to_le_bytes());
+    data.extend_from_slice(&1u16.to_le_bytes());
+    data.extend_from_slice(&24u16.to_le_bytes());
+    data.extend_from_slice(&[0; 24]);
+
+    // Rows are padded to 4 bytes.
+    data.extend_from_slice(&[0, 0, 255, 0, 255, 0, 0, 0]);
+    data.extend_from_slice(&[255, 0, 0, 255, 255, 255, 0, 0]);
+
+    data
+}
+
+/// A 3x1 run length encoded TGA: a run of two red pixels, then a blue one.
+fn tga() -> Vec<u8> {
+    let mut data = vec![0, 0, 10, 0, 0, 0, 0, 0, 0, 0, 0, 0, 3, 0, 1, 0, 24, 0x20];
+    data.extend_from_slice(&[0x81, 0, 0, 255, 0x00, 255, 0, 0]);
+
+    data
+}
+
+/// A 2x1 RGBA PNG, compressed with a stored block. CRCs are not checked.
+fn png() -> Vec<u8> {
+    fn chunk(data: &mut Vec<u8>, kind: &[u8], body: &[u8]) {
+        data.extend_from_slice(&(body.len() as u32).to_be_bytes());
+        data.extend_from_slice(kind);
+        data.extend_from_slice(body);
+        data.extend_from_slice(&[0; 4]);
+    }
+
+    let mut data = vec![0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n'];
+
+    let mut header = Vec::new();
+    header.extend_from_slice(&2u32.to_be_bytes());
+    header.extend_from_slice(&1u32.to_be_bytes());
+    header.extend_from_slice(&[8, 6, 0, 0, 0]);
+    chunk(&mut data, b"IHDR", &header);
+
+    // The second pixel uses the sub filter.
+    let row = [1, 10, 20, 30, 255, 5, 5, 5, 0];
+    let mut zlib = vec![0x78, 0x01, 0x01, 9, 0, !9, 0xff];
+    zlib.extend_from_slice(&row);
+    zlib.extend_from_slice(&[0; 4]);
+    chunk(&mut data, b"IDAT", &zlib);
+    chunk(&mut data, b"IEND", &[]);
+
+    data
+}
+
+pub fn test_main(test_runner: &mut TestRunner) {
+    test_runner.check(
+        "image_detect",
+        ImageFormat::detect(&[0xff, 0xd8, 0xff, 0xe0]),
+        Some(ImageFormat::Jpeg),
+    );
+    test_runner.check(
+        "image_unknown",
+        Image::decode(b"xxxx"),
+        Err(ImageError::UnknownFormat),
+    );
+    test_runner.check(
+        "image_bmp",
+        Image::decode(&bmp()).map(Image::into_pixels),
+        Ok(vec![0xffff_0000, 0xffff_ffff, 0xff00_00ff, 0xff00_ff00]),
+    );
+    test_runner.check(
+        "image_tga_rle",
+        Image::decode(&tga()).map(Image::into_pixels),
+        Ok(vec![0xff00_00ff, 0xff00_00ff, 0xffff_0000]),
+    );
+    test_runner.check(
+        "image_png",
+        Image::decode(&png()).map(Image::into_pixels),
+        Ok(vec![0xff1e_140a, 0xff23_190f]),
+    );
+    test_runner.check(
+        "image_truncated",
+        Image::decode(&bmp()[..60]),
+        Err(ImageError::Truncated),
+    );
+
+    let image = Image::new(3, 2, vec![0xff00_00ff, 0xff00_ff00, 0xffff_0000, !0, 0, !0]);
+
+    let texture = image
+        .to_texture(TexturePixelFormat::Psm5650, false)
+        .unwrap();
+    test_runner.check(
+        "texture_padding",
+        (texture.buffer_width(), texture.buffer_height()),
+        (8, 2),
+    );
+    test_runner.check(
+        "texture_5650",
+        &texture.data()[..6],
+        &[0x1f, 0, 0xe0, 0x07, 0, 0xf8][..],
+    );
+
+    let texture = image.to_texture(TexturePixelFormat::Psm5650, true).unwrap();
+    test_runner.check("texture_swizzle_height", texture.buffer_height(), 8);
+    test_runner.check(
+        "texture_swizzle",
+        &texture.data()[16..18],
+        &[0xff, 0xff][..],
+    );
+
+    // The palette is sorted, so the indices are red 1, green 2, blue 3.
+    let texture = image.to_texture(TexturePixelFormat::PsmT4, false).unwrap();
+    test_runner.check("texture_t4", &texture.data()[..2], &[0x21, 0x03][..]);
+    test_runner.check("texture_t4_clut", texture.clut().map(<[u8]>::len), Some(64));
+
+    let mut data = tga();
+    data[12..16].copy_from_slice(&[0, 8, 0, 8]);
+    test_runner.check(
+        "too_many_pixels",
+        Image::decode(&data).map(|_| ()),
+        Err(ImageError::TooLarge),
+    );
+
+    test_runner.check(
+        "texture_too_large",
+        Image::new(513, 1, vec![0; 513])
+            .to_texture(TexturePixelFormat::Psm8888, false)
+            .map(|_| ()),
+        Err(ImageError::TooLarge),
+    );
+}
@@ -0,0 +1,416 @@
+use alloc::vec::Vec;
+use core::time::Duration;
+use psp::fs;
+use psp::input::{
+    Accessory, ActionMap, Curve, Deadzone, Frame, Input, InputEvent, InputRecorder, InputState,
+    Plugs, Recording, RemoteState, Repeat, Replay, ReplayError, StickConfig,
+};
+use psp::io::Cursor;
+use psp::sys::{CtrlButtons, HprmKey};
+use psp::test_runner::TestRunner;
+
+const RECORDING_FILE: &str = "host0:/psp_input_test.rec";
+
+const REMOTE: Plugs = Plugs {
+    headphones: true,
+    remote: true,
+    microphone: false,
+};
+
+fn ms(ms: u64) -> Duration {
+    Duration::from_millis(ms)
+}
+
+/// Update `state` and collect the events.
+fn update(state: &mut RemoteState, keys: HprmKey, plugs: Plugs, now: Duration) -> Vec<InputEvent> {
+    let mut events = Vec::new();
+    state.update_with(keys, plugs, now, |event| events.push(event));
+    events
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum Action {
+    Jump,
+    Dash,
+    Menu,
+}
+
+/// Update `state` with `buttons` held at `time` and collect the events.
+fn press(state: &mut InputState, buttons: CtrlButtons, time: Duration) -> Vec<InputEvent> {
+    let mut events = Vec::new();
+    state.update_with(Frame::new(time, buttons), |event| events.push(event));
+    events
+}
+
+/// The stick axes through `deadzone` and `curve`, rounded to hundredths.
+fn stick(deadzone: Deadzone, curve: Curve, lx: u8, ly: u8) -> (i32, i32) {
+    let config = StickConfig {
+        deadzone,
+        curve,
+        invert_y: false,
+    };
+    let (x, y) = config.apply(lx, ly);
+
+    ((x * 100.0) as i32, (y * 100.0) as i32)
+}
+
+pub fn test_main(test_runner: &mut TestRunner) {
+    let mut state = RemoteState::new();
+    test_runner.check(
+        "remote_plugged",
+        update(&mut state, HprmKey::empty(), REMOTE, ms(0)),
+        alloc::vec![
+            InputEvent::Plugged(Accessory::Headphones),
+            InputEvent::Plugged(Accessory::Remote),
+        ],
+    );
+    test_runner.check(
+        "remote_pressed",
+        update(&mut state, HprmKey::PLAY_PAUSE, REMOTE, ms(16)),
+        alloc::vec![InputEvent::RemotePressed(HprmKey::PLAY_PAUSE)],
+    );
+    test_runner.check(
+        "remote_held",
+        (
+            state.is_pressed(HprmKey::PLAY_PAUSE),
+            state.is_held(HprmKey::PLAY_PAUSE),
+        ),
+        (true, true),
+    );
+    test_runner.check(
+        "remote_no_repeat",
+        update(&mut state, HprmKey::PLAY_PAUSE, REMOTE, ms(500)),
+        Vec::new(),
+    );
+    test_runner.check(
+        "remote_repeat",
+        update(&mut state, HprmKey::PLAY_PAUSE, REMOTE, ms(516)),
+        alloc::vec![InputEvent::RemoteRepeated(HprmKey::PLAY_PAUSE)],
+    );
+    test_runner.check(
+        "remote_repeat_interval",
+        (
+            update(&mut state, HprmKey::PLAY_PAUSE, REMOTE, ms(600)).len(),
+            update(&mut state, HprmKey::PLAY_PAUSE, REMOTE, ms(616)).len(),
+        ),
+        (0, 1),
+    );
+    test_runner.check(
+        "remote_released",
+        update(&mut state, HprmKey::empty(), REMOTE, ms(632)),
+        alloc::vec![InputEvent::RemoteReleased(HprmKey::PLAY_PAUSE)],
+    );
+    test_runner.check(
+        "remote_not_held",
+        (
+            state.is_released(HprmKey::PLAY_PAUSE),
+            state.is_held(HprmKey::PLAY_PAUSE),
+        ),
+        (true, false),
+    );
+
+    let mut state = RemoteState::new();
+    update(&mut state, HprmKey::VOL_UP, REMOTE, ms(0));
+    test_runner.check(
+        "remote_hold",
+        update(
+            &mut state,
+            HprmKey::HOLD | HprmKey::VOL_DOWN,
+            REMOTE,
+            ms(16),
+        ),
+        alloc::vec![
+            InputEvent::RemoteReleased(HprmKey::VOL_UP),
+            InputEvent::RemotePressed(HprmKey::HOLD),
+        ],
+    );
+
+    let mut state = RemoteState::new();
+    state.set_repeat(Repeat {
+        delay: ms(100),
+        interval: ms(50),
+    });
+    update(&mut state, HprmKey::FORWARD, REMOTE, ms(0));
+    test_runner.check(
+        "remote_custom_repeat",
+        update(&mut state, HprmKey::FORWARD, REMOTE, ms(100)),
+        alloc::vec![InputEvent::RemoteRepeated(HprmKey::FORWARD)],
+    );
+    test_runner.check(
+        "remote_late_repeat",
+        (
+            update(&mut state, HprmKey::FORWARD, REMOTE, ms(400)).len(),
+            update(&mut state, HprmKey::FORWARD, REMOTE, ms(420)).len(),
+        ),
+        (1, 0),
+    );
+    test_runner.check(
+        "remote_unplugged",
+        update(&mut state, HprmKey::FORWARD, Plugs::default(), ms(432)),
+        alloc::vec![
+            InputEvent::Unplugged(Accessory::Headphones),
+            InputEvent::Unplugged(Accessory::Remote),
+            InputEvent::RemoteReleased(HprmKey::FORWARD),
+        ],
+    );
+
+    let mut state = InputState::new();
+    test_runner.check(
+        "ctrl_pressed",
+        press(&mut state, CtrlButtons::CROSS | CtrlButtons::UP, ms(0)),
+        alloc::vec![
+            InputEvent::Pressed(CtrlButtons::UP),
+            InputEvent::Pressed(CtrlButtons::CROSS),
+        ],
+    );
+    test_runner.check(
+        "ctrl_held",
+        (
+            state.is_held(CtrlButtons::CROSS | CtrlButtons::UP),
+            state.is_held(CtrlButtons::CROSS | CtrlButtons::CIRCLE),
+            state.is_pressed(CtrlButtons::CIRCLE),
+        ),
+        (true, false, false),
+    );
+    test_runner.check(
+        "ctrl_released",
+        press(&mut state, CtrlButtons::UP, ms(16)),
+        alloc::vec![InputEvent::Released(CtrlButtons::CROSS)],
+    );
+    test_runner.check(
+        "ctrl_repeat",
+        (
+            press(&mut state, CtrlButtons::UP, ms(499)).len(),
+            press(&mut state, CtrlButtons::UP, ms(516)),
+            state.is_repeated(CtrlButtons::UP),
+        ),
+        (0, alloc::vec![InputEvent::Repeated(CtrlButtons::UP)], true),
+    );
+    test_runner.check(
+        "ctrl_previous",
+        (state.previous_buttons(), state.buttons()),
+        (CtrlButtons::UP, CtrlButtons::UP),
+    );
+
+    test_runner.check(
+        "stick_rest",
+        stick(Deadzone::Radial(0.1), Curve::Linear, 128, 128),
+        (0, 0),
+    );
+    test_runner.check(
+        "stick_range",
+        (
+            stick(Deadzone::Axial(0.0), Curve::Linear, 255, 0),
+            stick(Deadzone::Axial(0.0), Curve::Linear, 0, 255),
+        ),
+        ((100, -100), (-100, 100)),
+    );
+    test_runner.check(
+        "stick_deadzone",
+        (
+            stick(Deadzone::Radial(0.2), Curve::Linear, 150, 128),
+            stick(Deadzone::Radial(0.2), Curve::Linear, 255, 128),
+        ),
+        ((0, 0), (100, 0)),
+    );
+    test_runner.check(
+        "stick_deadzone_scaled",
+        stick(Deadzone::Axial(0.5), Curve::Linear, 128 + 95, 128 - 64),
+        (49, 0),
+    );
+    test_runner.check(
+        "stick_radial",
+        stick(Deadzone::Radial(0.1), Curve::Linear, 255, 255),
+        (70, 70),
+    );
+    test_runner.check(
+        "stick_curve",
+        (
+            stick(Deadzone::Axial(0.0), Curve::Quadratic, 128 + 64, 128),
+            stick(Deadzone::Axial(0.0), Curve::Cubic, 128, 128 - 64),
+        ),
+        ((25, 0), (0, -12)),
+    );
+    let config = StickConfig {
+        invert_y: true,
+        ..StickConfig::default()
+    };
+    test_runner.check("stick_invert", config.apply(128, 0), (0.0, 1.0));
+
+    let mut map = ActionMap::new();
+    map.bind(Action::Jump, CtrlButtons::CROSS);
+    map.bind(Action::Dash, CtrlButtons::LTRIGGER | CtrlButtons::RTRIGGER);
+    map.bind(Action::Menu, CtrlButtons::START);
+    map.bind(Action::Menu, CtrlButtons::SELECT);
+
+    let mut state = InputState::new();
+    state.update(Frame::new(
+        ms(0),
+        CtrlButtons::CROSS | CtrlButtons::LTRIGGER,
+    ));
+    test_runner.check(
+        "map_pressed",
+        (
+            map.pressed_actions(&state),
+            map.is_held(&state, Action::Dash),
+        ),
+        (alloc::vec![Action::Jump], false),
+    );
+    state.update(Frame::new(
+        ms(16),
+        CtrlButtons::LTRIGGER | CtrlButtons::RTRIGGER,
+    ));
+    test_runner.check(
+        "map_combo",
+        (
+            map.is_pressed(&state, Action::Dash),
+            map.is_released(&state, Action::Jump),
+        ),
+        (true, true),
+    );
+    state.update(Frame::new(
+        ms(32),
+        CtrlButtons::RTRIGGER | CtrlButtons::SELECT,
+    ));
+    test_runner.check(
+        "map_combo_released",
+        (
+            map.is_released(&state, Action::Dash),
+            map.is_pressed(&state, Action::Menu),
+        ),
+        (true, true),
+    );
+    map.rebind(Action::Jump, CtrlButtons::CIRCLE);
+    test_runner.check(
+        "map_rebind",
+        (
+            map.bindings(Action::Jump).collect::<Vec<_>>(),
+            map.actions(CtrlButtons::SELECT).collect::<Vec<_>>(),
+        ),
+        (alloc::vec![CtrlButtons::CIRCLE], alloc::vec![Action::Menu]),
+    );
+
+    let frames = alloc::vec![
+        Frame::new(ms(0), CtrlButtons::empty()),
+        Frame {
+            time: ms(17),
+            buttons: CtrlButtons::CROSS,
+            lx: 255,
+            ly: 3,
+        },
+        Frame::new(ms(33), CtrlButtons::START | CtrlButtons::LEFT),
+    ];
+    let recording = Recording::from_frames(frames.clone());
+    let bytes = recording.to_bytes();
+    test_runner.check(
+        "replay_bytes",
+        &bytes[..18],
+        &[
+            b'P', b'S', b'P', b'I', 1, 0, 0, 0, // Header.
+            0, 0, 0, 0, 0, 0, 0, 0, 128, 128, // First frame.
+        ][..],
+    );
+    test_runner.check(
+        "replay_frame",
+        &bytes[18..28],
+        &[0x68, 0x42, 0, 0, 0, 0x40, 0, 0, 255, 3][..],
+    );
+    test_runner.check(
+        "replay_round_trip",
+        Recording::from_bytes(&bytes).map(|recording| recording.frames().to_vec()),
+        Ok(frames.clone()),
+    );
+    test_runner.check("replay_duration", recording.duration(), ms(33));
+    test_runner.check(
+        "replay_errors",
+        (
+            Recording::from_bytes(b"PSPX\x01\0\0\0"),
+            Recording::from_bytes(b"PSPI\x02\0\0\0"),
+            Recording::from_bytes(&bytes[..bytes.len() - 1]),
+            Recording::from_bytes(b"PSP"),
+        ),
+        (
+            Err(ReplayError::NotRecording),
+            Err(ReplayError::UnsupportedVersion(2)),
+            Err(ReplayError::Truncated),
+            Err(ReplayError::NotRecording),
+        ),
+    );
+
+    let mut recorder = InputRecorder::new(Cursor::new(Vec::new())).unwrap();
+    for frame in &frames {
+        let frame = Frame {
+            time: frame.time + ms(1000),
+            ..*frame
+        };
+        recorder.record(&frame).unwrap();
+    }
+    test_runner.check(
+        "recorder_bytes",
+        recorder.finish().map(Cursor::into_inner),
+        Ok(bytes.clone()),
+    );
+
+    // Move down a menu of four items with a tap, a tap held until it
+    // repeats, and pick the item with cross.
+    let menu = Recording::from_frames(alloc::vec![
+        Frame::new(ms(0), CtrlButtons::DOWN),
+        Frame::new(ms(16), CtrlButtons::empty()),
+        Frame::new(ms(32), CtrlButtons::DOWN),
+        Frame::new(ms(532), CtrlButtons::DOWN),
+        Frame::new(ms(548), CtrlButtons::CROSS),
+    ]);
+    let mut replay = Replay::new(menu.clone());
+    let mut state = InputState::new();
+    let mut selected = 0;
+    let mut picked = None;
+    replay.play(&mut state, |state, _| {
+        if state.is_repeated(CtrlButtons::DOWN) {
+            selected = (selected + 1) % 4;
+        }
+
+        if state.is_pressed(CtrlButtons::CROSS) {
+            picked = Some(selected);
+        }
+    });
+    test_runner.check(
+        "replay_play",
+        (picked, replay.position(), replay.is_finished()),
+        (Some(3), 5, true),
+    );
+
+    let mut recorder = InputRecorder::create(RECORDING_FILE).unwrap();
+    for &frame in menu.frames() {
+        recorder.record(&frame).unwrap();
+    }
+    recorder.finish().unwrap();
+    test_runner.check(
+        "replay_file",
+        Replay::open(RECORDING_FILE).map(|replay| replay.recording().clone()),
+        Ok(menu.clone()),
+    );
+    fs::remove_file(RECORDING_FILE).unwrap();
+
+    let mut input = Input::new();
+    input.start_replay(Replay::new(menu));
+    let mut pressed = Vec::new();
+    for _ in 0..5 {
+        input.update();
+
+        while let Some(event) = input.poll_event() {
+            if let InputEvent::Pressed(button) = event {
+                pressed.push(button);
+            }
+        }
+    }
+    test_runner.check(
+        "input_replay",
+        (pressed, input.is_replaying()),
+        (
+            alloc::vec![CtrlButtons::DOWN, CtrlButtons::DOWN, CtrlButtons::CROSS],
+            true,
+        ),
+    );
+    input.update();
+    test_runner.check("input_replay_finished", input.is_replaying(), false);
+}
@@ -5,8 +5,23 @@
 
 use psp::test_runner::TestRunner;
 
+mod audio_test;
 mod bmp_screenshot_test;
+mod error_test;
+mod events_test;
+mod executor_test;
+mod fs_test;
+mod image_test;
+mod input_test;
 mod math_test;
+mod modules_test;
+mod net_test;
+mod power_test;
+mod savedata_test;
+mod system_test;
+mod text_test;
+mod thread_test;
+mod video_test;
 mod vram_test;
 
 psp::module!("ci_tests", 1, 1);
@@ -16,6 +31,21 @@ fn psp_main() {
         bmp_screenshot_test::test_main,
         vram_test::test_main,
         math_test::test_main,
+        fs_test::test_main,
+        error_test::test_main,
+        events_test::test_main,
+        executor_test::test_main,
+        net_test::test_main,
+        savedata_test::test_main,
+        system_test::test_main,
+        thread_test::test_main,
+        modules_test::test_main,
+        audio_test::test_main,
+        video_test::test_main,
+        image_test::test_main,
+        text_test::test_main,
+        input_test::test_main,
+        power_test::test_main,
     ];
 
     let mut runner = TestRunner::new_file_runner();
@@ -0,0 +1,98 @@
+use psp::error;
+use psp::modules::{ModuleGuard, UtilityModule};
+use psp::sys::{self, AvModule, Module, NetModule};
+use psp::test_runner::TestRunner;
+
+pub fn test_main(test_runner: &mut TestRunner) {
+    test_runner.check(
+        "http_dependencies",
+        UtilityModule::from(NetModule::NetHttp).dependencies(),
+        &[
+            UtilityModule::Net(NetModule::NetCommon),
+            UtilityModule::Net(NetModule::NetInet),
+            UtilityModule::Net(NetModule::NetParseUri),
+            UtilityModule::Net(NetModule::NetParseHttp),
+        ][..],
+    );
+    test_runner.check(
+        "module_dependencies",
+        UtilityModule::from(Module::NetHttp).dependencies(),
+        UtilityModule::from(NetModule::NetHttp).dependencies(),
+    );
+
+    let first = ModuleGuard::load(&[AvModule::Mp3.into()]);
+    test_runner.check("load", first.is_ok(), true);
+    test_runner.check(
+        "load_order",
+        first
+            .as_ref()
+            .map(|guard| guard.modules().to_vec())
+            .unwrap_or_default(),
+        alloc::vec![
+            UtilityModule::Av(AvModule::AvCodec),
+            UtilityModule::Av(AvModule::Mp3)
+        ],
+    );
+
+    // The second guard shares the modules loaded by the first.
+    let second = ModuleGuard::load(&[AvModule::AvCodec.into()]);
+    test_runner.check("load_shared", second.is_ok(), true);
+    drop(first);
+    drop(second);
+
+    test_runner.check(
+        "reload",
+        ModuleGuard::load(&[AvModule::Mp3.into()]).is_ok(),
+        true,
+    );
+
+    canonical(test_runner);
+}
+
+fn canonical(test_runner: &mut TestRunner) {
+    test_runner.check(
+        "canonical_net",
+        UtilityModule::from(Module::NetCommon).canonical(),
+        UtilityModule::Net(NetModule::NetCommon),
+    );
+    test_runner.check(
+        "canonical_av",
+        UtilityModule::from(Module::AvMp3).canonical(),
+        UtilityModule::Av(AvModule::Mp3),
+    );
+    test_runner.check(
+        "canonical_other",
+        UtilityModule::from(Module::NpCommon).canonical(),
+        UtilityModule::Module(Module::NpCommon),
+    );
+
+    // Two guards reach `NetCommon`, one through `Module` and one through
+    // `NetModule`, and share a single count.
+    let first = ModuleGuard::load(&[Module::NetInet.into()]);
+    let second = ModuleGuard::load(&[NetModule::NetCommon.into()]);
+    test_runner.check(
+        "shared_order",
+        first
+            .as_ref()
+            .map(|guard| guard.modules().to_vec())
+            .unwrap_or_default(),
+        alloc::vec![
+            UtilityModule::Net(NetModule::NetCommon),
+            UtilityModule::Net(NetModule::NetInet)
+        ],
+    );
+    test_runner.check("shared_second", second.is_ok(), true);
+
+    // The second guard still holds `NetCommon` once the first is gone.
+    drop(first);
+    let ret = unsafe { sys::sceUtilityLoadNetModule(NetModule::NetCommon) };
+    if ret >= 0 {
+        unsafe { sys::sceUtilityUnloadNetModule(NetModule::NetCommon) };
+    }
+    test_runner.check(
+        "shared_still_loaded",
+        ret,
+        error::SCE_UTILITY_ERROR_MODULE_ALREADY_LOADED.raw(),
+    );
+    drop(second);
+}
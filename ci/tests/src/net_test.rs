@@ -0,0 +1,184 @@
+use alloc::format;
+use alloc::string::String;
+use core::time::Duration;
+use psp::io::ErrorKind;
+use psp::net::adhoc::{Adhoc, MacAddr};
+use psp::net::http::{append_query, find_header};
+use psp::net::{
+    Ipv4Addr, NetworkConfig, NetworkError, NetworkStack, Resolver, SocketAddrV4, ToSocketAddrs,
+};
+use psp::sys::{Module, NetModule};
+use psp::test_runner::TestRunner;
+
+pub fn test_main(test_runner: &mut TestRunner) {
+    let addr = SocketAddrV4::new(Ipv4Addr::new(192, 168, 0, 10), 8080);
+    let raw = addr.to_raw();
+
+    test_runner.check_list(&[
+        ("parse_ip", "192.168.0.10".parse().ok(), Some(*addr.ip())),
+        ("parse_leading_zero", "192.168.00.10".parse::<Ipv4Addr>().ok(), None),
+        ("parse_too_short", "192.168.0".parse::<Ipv4Addr>().ok(), None),
+        ("parse_too_long", "1.2.3.4.5".parse::<Ipv4Addr>().ok(), None),
+        ("parse_overflow", "256.0.0.1".parse::<Ipv4Addr>().ok(), None),
+    ]);
+    test_runner.check_list(&[
+        ("parse_socket_addr", "192.168.0.10:8080".parse().ok(), Some(addr)),
+        ("parse_missing_port", "192.168.0.10".parse::<SocketAddrV4>().ok(), None),
+        ("raw_round_trip", SocketAddrV4::from_raw(&raw), Some(addr)),
+    ]);
+    test_runner.check_list(&[
+        ("raw_port_big_endian", raw.sin_port.to_ne_bytes(), [0x1f, 0x90]),
+        ("raw_addr_network_order", raw.sin_addr.0.to_ne_bytes(), [192, 168, 0, 10]),
+    ]);
+    test_runner.check("display", format!("{}", addr), String::from("192.168.0.10:8080"));
+    test_runner.check("u32_host_order", u32::from(Ipv4Addr::LOCALHOST), 0x7f00_0001);
+    test_runner.check(
+        "to_socket_addrs",
+        ("10.0.0.1", 80).to_socket_addrs().ok().and_then(|mut a| a.next()),
+        Some(SocketAddrV4::new(Ipv4Addr::new(10, 0, 0, 1), 80)),
+    );
+    test_runner.check(
+        "host_without_port",
+        "example.com".to_socket_addrs().err().map(|e| e.kind()),
+        Some(ErrorKind::InvalidInput),
+    );
+    test_runner.check(
+        "host_with_bad_port",
+        "example.com:http".to_socket_addrs().err().map(|e| e.kind()),
+        Some(ErrorKind::InvalidInput),
+    );
+    test_runner.check(
+        "lookup_ip_literal",
+        psp::net::lookup_host("10.0.0.1").ok(),
+        Some(alloc::vec![Ipv4Addr::new(10, 0, 0, 1)]),
+    );
+    test_runner.check(
+        "errno_kind",
+        psp::io::Error::from(psp::Error::from_errno(111)).kind(),
+        ErrorKind::ConnectionRefused,
+    );
+    test_runner.check(
+        "mac_display",
+        format!("{}", MacAddr([0x00, 0x1f, 0xa7, 0x0b, 0xc0, 0xff])),
+        String::from("00:1f:a7:0b:c0:ff"),
+    );
+    test_runner.check(
+        "adhoc_error_kind",
+        psp::io::Error::from(psp::error::SCE_NET_ADHOC_ERROR_WOULD_BLOCK).kind(),
+        ErrorKind::WouldBlock,
+    );
+
+    http(test_runner);
+    stack_lifecycle(test_runner);
+}
+
+fn http(test_runner: &mut TestRunner) {
+    let headers = "HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\nX-Empty:\r\n\
+                   Set-Cookie: a=1\r\nset-cookie: b=2\r\n";
+
+    test_runner.check_list(&[
+        ("header", find_header(headers, "Content-Type"), Some("text/plain")),
+        ("header_case", find_header(headers, "content-type"), Some("text/plain")),
+        ("header_first", find_header(headers, "Set-Cookie"), Some("a=1")),
+        ("header_empty", find_header(headers, "X-Empty"), Some("")),
+        ("header_missing", find_header(headers, "Location"), None),
+        ("header_status_line", find_header(headers, "HTTP/1.1 200 OK"), None),
+    ]);
+
+    let mut url = String::from("http://example.com/search");
+    append_query(&mut url, "q", "rust psp");
+    append_query(&mut url, "page", "2");
+    test_runner.check("query", url.as_str(), "http://example.com/search?q=rust%20psp&page=2");
+
+    let mut url = String::from("http://example.com/?");
+    append_query(&mut url, "name", "a&b=c/d~e");
+    test_runner.check("query_escape", url.as_str(), "http://example.com/?name=a%26b%3Dc%2Fd~e");
+
+    let mut url = String::from("http://example.com/?x=1");
+    append_query(&mut url, "\u{e9}", "");
+    test_runner.check("query_utf8", url.as_str(), "http://example.com/?x=1&%C3%A9=");
+
+    test_runner.check(
+        "http_module_ids",
+        (NetModule::NetParseHttp as u32, NetModule::NetHttp as u32, Module::NetHttp as u32),
+        (5, 6, 0x105),
+    );
+}
+
+/// Set when the handler registered in `stack_lifecycle` is dropped.
+static mut HANDLER_DROPPED: bool = false;
+
+struct DropFlag;
+
+impl Drop for DropFlag {
+    fn drop(&mut self) {
+        unsafe { HANDLER_DROPPED = true };
+    }
+}
+
+fn stack_lifecycle(test_runner: &mut TestRunner) {
+    // A bring-up that fails part way undoes what it did, so the next one
+    // starts from scratch.
+    let bad = NetworkConfig {
+        pool_size: 0,
+        ..NetworkConfig::default()
+    };
+    test_runner.check(
+        "stack_bad_config",
+        matches!(NetworkStack::with_config(&bad), Err(NetworkError::Kernel(_))),
+        true,
+    );
+
+    let mut stack = match NetworkStack::new() {
+        Ok(stack) => stack,
+        Err(e) => return test_runner.check("stack_new", Err(e), Ok(())),
+    };
+
+    test_runner.check(
+        "stack_second",
+        NetworkStack::new().err(),
+        Some(NetworkError::AlreadyInitialized),
+    );
+    test_runner.check(
+        "stack_with_adhoc",
+        Adhoc::new("ULUS99999").err(),
+        Some(NetworkError::AlreadyInitialized),
+    );
+
+    let flag = DropFlag;
+    let added = stack.add_handler(move |_| {
+        let _ = &flag;
+    });
+    test_runner.check("stack_add_handler", added.is_ok(), true);
+    test_runner.check("stack_handler_alive", unsafe { HANDLER_DROPPED }, false);
+
+    dns(test_runner, &stack);
+
+    // Dropping removes the handlers before terminating the libraries, and
+    // terminates them in reverse, so that they can be initialized again.
+    drop(stack);
+    test_runner.check("stack_handler_dropped", unsafe { HANDLER_DROPPED }, true);
+    test_runner.check("stack_after_drop", NetworkStack::new().err(), None);
+}
+
+fn dns(test_runner: &mut TestRunner, stack: &NetworkStack) {
+    let connected = stack.connect(1, Some(Duration::from_secs(10)));
+    test_runner.check("stack_connect", connected, Ok(()));
+
+    let resolver = match Resolver::new() {
+        Ok(resolver) => resolver,
+        Err(e) => return test_runner.check("resolver_new", Err(e.kind()), Ok(())),
+    };
+
+    test_runner.check(
+        "resolve_localhost",
+        resolver.lookup_host("localhost").ok(),
+        Some(alloc::vec![Ipv4Addr::LOCALHOST]),
+    );
+    test_runner.check("resolve_invalid", resolver.lookup_host("name.invalid").is_err(), true);
+    test_runner.check(
+        "resolve_too_long",
+        resolver.lookup_host(&"a".repeat(300)).err().map(|e| e.kind()),
+        Some(ErrorKind::InvalidInput),
+    );
+}
@@ -0,0 +1,36 @@
+use psp::power::{self, BatteryStatus, ClockProfile, PowerLock};
+use psp::test_runner::TestRunner;
+
+pub fn test_main(test_runner: &mut TestRunner) {
+    test_runner.check(
+        "clock_profile_frequencies",
+        (ClockProfile::Mhz333.cpu(), ClockProfile::Mhz333.bus()),
+        (333, 166),
+    );
+    test_runner.check(
+        "clock_profile_from_frequencies",
+        (
+            ClockProfile::from_frequencies(266, 133),
+            ClockProfile::from_frequencies(300, 150),
+            ClockProfile::from_frequencies(333, 111),
+        ),
+        (Some(ClockProfile::Mhz266), None, None),
+    );
+
+    for &profile in &ClockProfile::ALL {
+        test_runner.check("set_clock", power::set_clock(profile), Ok(()));
+        test_runner.check("clock", power::clock(), Some(profile));
+    }
+    power::set_clock(ClockProfile::Mhz222).unwrap();
+
+    let lock = PowerLock::new();
+    test_runner.check("power_lock", lock.is_ok(), true);
+    test_runner.check("power_lock_nested", PowerLock::new().is_ok(), true);
+    drop(lock);
+
+    test_runner.check(
+        "battery_percent",
+        BatteryStatus::read().map_or(true, |battery| battery.percent <= 100),
+        true,
+    );
+}
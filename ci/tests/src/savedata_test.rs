@@ -0,0 +1,94 @@
+use alloc::string::String;
+use psp::error;
+use psp::savedata::{Loaded, SaveData, SaveDataError, SaveInfo, Slot};
+use psp::test_runner::TestRunner;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Progress {
+    chapter: u8,
+    name: String,
+    score: u32,
+}
+
+pub fn test_main(test_runner: &mut TestRunner) {
+    let saves = SaveData::new("ULUS99999");
+
+    test_runner.check(
+        "path",
+        saves.path("0000"),
+        String::from("ms0:/PSP/SAVEDATA/ULUS999990000"),
+    );
+    test_runner.check_list(&[
+        (
+            "no_data",
+            SaveDataError::from(error::SCE_UTILITY_SAVEDATA_ERROR_LOAD_NO_DATA),
+            SaveDataError::NoData,
+        ),
+        (
+            "corrupted",
+            SaveDataError::from(error::SCE_UTILITY_SAVEDATA_ERROR_RW_DATA_BROKEN),
+            SaveDataError::Corrupted,
+        ),
+        (
+            "memory_stick_full",
+            SaveDataError::from(error::SCE_UTILITY_SAVEDATA_ERROR_SAVE_MS_NOSPACE),
+            SaveDataError::MemoryStickFull,
+        ),
+        (
+            "other",
+            SaveDataError::from(error::SCE_KERNEL_ERROR_ERROR),
+            SaveDataError::Kernel(error::SCE_KERNEL_ERROR_ERROR),
+        ),
+    ]);
+    test_runner.check(
+        "name_too_long",
+        saves.load(Slot::Auto("a_name_too_long_for_a_save"), || {}).err(),
+        Some(SaveDataError::InvalidInput),
+    );
+    test_runner.check(
+        "delete_missing",
+        saves.delete(Slot::Auto("MISSING"), || {}).err(),
+        Some(SaveDataError::NoData),
+    );
+
+    typed(test_runner, &saves);
+}
+
+fn typed(test_runner: &mut TestRunner, saves: &SaveData<'_>) {
+    let progress = Progress {
+        chapter: 3,
+        name: String::from("Tester"),
+        score: 12_345,
+    };
+    let info = SaveInfo {
+        title: "Chapter 3",
+        ..SaveInfo::default()
+    };
+
+    let saved = saves.save_value(Slot::Auto("TYPED"), &info, &progress, || {});
+    test_runner.check("save_value", saved, Ok(String::from("TYPED")));
+
+    let loaded = saves.load(Slot::Auto("TYPED"), || {});
+    test_runner.check(
+        "load_value",
+        loaded.and_then(|loaded| loaded.value::<Progress>()),
+        Ok(progress),
+    );
+
+    let deleted = saves.delete(Slot::Auto("TYPED"), || {});
+    test_runner.check("delete", deleted, Ok(String::from("TYPED")));
+    test_runner.check("deleted", saves.exists("TYPED"), false);
+
+    let garbage = Loaded {
+        name: String::from("TYPED"),
+        data: alloc::vec![0xff; 3],
+        title: String::new(),
+        detail: String::new(),
+    };
+    test_runner.check(
+        "value_invalid",
+        garbage.value::<Progress>().err(),
+        Some(SaveDataError::InvalidData),
+    );
+}
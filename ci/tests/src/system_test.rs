@@ -0,0 +1,28 @@
+use core::time::Duration;
+use psp::sys::{
+    SystemParamAdhocChannel, SystemParamDateFormat, SystemParamDaylightSavings,
+    SystemParamLanguage, SystemParamTimeFormat, SystemParamWlanPowerSaveState,
+};
+use psp::system::{ConfirmButton, Settings, UtcOffset};
+use psp::test_runner::TestRunner;
+
+pub fn test_main(test_runner: &mut TestRunner) {
+    let west = UtcOffset::from_minutes(-330);
+
+    test_runner.check("offset_duration", west.as_duration(), Duration::from_secs(330 * 60));
+    test_runner.check("offset_negative", west.is_negative(), true);
+    test_runner.check("cancel_button", ConfirmButton::Circle.cancel_button(), ConfirmButton::Cross);
+
+    let settings = Settings {
+        nickname: "rust".into(),
+        language: SystemParamLanguage::English,
+        confirm_button: ConfirmButton::Cross,
+        date_format: SystemParamDateFormat::YYYYMMDD,
+        time_format: SystemParamTimeFormat::Hour24,
+        timezone: west,
+        daylight_savings: SystemParamDaylightSavings::Dst,
+        adhoc_channel: SystemParamAdhocChannel::ChannelAutomatic,
+        wlan_power_save: SystemParamWlanPowerSaveState::On,
+    };
+    test_runner.check("local_offset_dst", settings.local_offset().minutes(), -270);
+}
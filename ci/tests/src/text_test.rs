@@ -0,0 +1,152 @@
+use alloc::vec::Vec;
+use psp::test_runner::TestRunner;
+use psp::sys::{SceFontFamilyCode, SceFontLanguageCode, SceFontStyleCode};
+use psp::text::{layout, Align, Font, FontLib, GlyphMetrics, LayoutOptions};
+
+/// A font where every character is 10 pixels wide, and "AV" kerns by -2.
+struct Fixed;
+
+impl GlyphMetrics for Fixed {
+    fn advance(&mut self, _c: char) -> f32 {
+        10.0
+    }
+
+    fn kerning(&mut self, left: char, right: char) -> f32 {
+        if (left, right) == ('A', 'V') {
+            -2.0
+        } else {
+            0.0
+        }
+    }
+
+    fn ascender(&self) -> f32 {
+        12.0
+    }
+
+    fn line_height(&self) -> f32 {
+        16.0
+    }
+}
+
+/// The pen positions of the characters of `text`.
+fn positions(text: &str, options: &LayoutOptions) -> Vec<(char, f32, f32)> {
+    layout(&mut Fixed, text, options)
+        .glyphs()
+        .iter()
+        .map(|glyph| (glyph.c, glyph.x, glyph.y))
+        .collect()
+}
+
+fn wrapped(max_width: f32) -> LayoutOptions {
+    LayoutOptions {
+        max_width: Some(max_width),
+        ..LayoutOptions::default()
+    }
+}
+
+pub fn test_main(test_runner: &mut TestRunner) {
+    let text = layout(&mut Fixed, "ab cd", &LayoutOptions::default());
+    test_runner.check("text_width", (text.width(), text.height()), (50.0, 16.0));
+    test_runner.check(
+        "text_positions",
+        positions("ab cd", &LayoutOptions::default()),
+        alloc::vec![
+            ('a', 0.0, 12.0),
+            ('b', 10.0, 12.0),
+            ('c', 30.0, 12.0),
+            ('d', 40.0, 12.0),
+        ],
+    );
+    test_runner.check(
+        "text_kerning",
+        positions("AVA", &LayoutOptions::default()),
+        alloc::vec![('A', 0.0, 12.0), ('V', 8.0, 12.0), ('A', 18.0, 12.0)],
+    );
+    test_runner.check(
+        "text_newline",
+        layout(&mut Fixed, "a\n\nb", &LayoutOptions::default()).line_count(),
+        3,
+    );
+    test_runner.check(
+        "text_wrap",
+        positions("ab cd", &wrapped(35.0)),
+        alloc::vec![
+            ('a', 0.0, 12.0),
+            ('b', 10.0, 12.0),
+            ('c', 0.0, 28.0),
+            ('d', 10.0, 28.0),
+        ],
+    );
+    test_runner.check(
+        "text_break_word",
+        layout(&mut Fixed, "abcdef", &wrapped(25.0)).line_count(),
+        3,
+    );
+    test_runner.check(
+        "text_wrap_cjk",
+        positions("あいう", &wrapped(25.0)),
+        alloc::vec![('あ', 0.0, 12.0), ('い', 10.0, 12.0), ('う', 0.0, 28.0)],
+    );
+    test_runner.check(
+        "text_align",
+        positions(
+            "ab",
+            &LayoutOptions {
+                max_width: Some(40.0),
+                align: Align::Right,
+                ..LayoutOptions::default()
+            },
+        ),
+        alloc::vec![('a', 20.0, 12.0), ('b', 30.0, 12.0)],
+    );
+    test_runner.check(
+        "text_align_center",
+        positions(
+            "a\nbcd",
+            &LayoutOptions {
+                align: Align::Center,
+                ..LayoutOptions::default()
+            },
+        ),
+        alloc::vec![
+            ('a', 10.0, 12.0),
+            ('b', 0.0, 28.0),
+            ('c', 10.0, 28.0),
+            ('d', 20.0, 28.0),
+        ],
+    );
+
+    system_font(test_runner);
+}
+
+/// Kerning with the Latin system font.
+fn system_font(test_runner: &mut TestRunner) {
+    let lib = match FontLib::new(1) {
+        Ok(lib) => lib,
+        Err(e) => return test_runner.check("font_lib", Err(e), Ok(())),
+    };
+    let mut font = match Font::find(
+        &lib,
+        SceFontFamilyCode::SansSerif,
+        SceFontStyleCode::Regular,
+        SceFontLanguageCode::Latin,
+    ) {
+        Ok(font) => font,
+        Err(e) => return test_runner.check("font_find", Err(e), Ok(())),
+    };
+
+    test_runner.check("font_kerning_reference", font.kerning('n', 'n'), 0.0);
+    test_runner.check("font_kerning_space", font.kerning('r', ' '), 0.0);
+
+    // Kerning only ever narrows gaps.
+    let printable = || (b'!'..=b'~').map(char::from);
+    let widened: Vec<(char, char)> = printable()
+        .flat_map(|left| printable().map(move |right| (left, right)))
+        .filter(|&(left, right)| font.kerning(left, right) > 0.0)
+        .collect();
+    test_runner.check("font_kerning_narrows", widened, Vec::new());
+
+    let kerned = font.advance('r') + font.kerning('r', '.');
+    let text = font.layout("r.", &LayoutOptions::default());
+    test_runner.check("font_kerning_layout", text.glyphs()[1].x, kerned);
+}
@@ -0,0 +1,56 @@
+use core::time::Duration;
+use psp::io::Write;
+use psp::sync::{Mutex, Semaphore};
+use psp::test_runner::TestRunner;
+use psp::thread::{self, Builder, JoinError, SharedThreads};
+use psp::{env, stdio, sys};
+
+pub fn test_main(test_runner: &mut TestRunner) {
+    test_runner.check("spawn_join", thread::spawn(|| 6 * 7).join(), Ok(42));
+
+    let named = Builder::new()
+        .name("psp_thread_test")
+        .priority(thread::DEFAULT_PRIORITY - 1)
+        .stack_size(16 * 1024)
+        .spawn(|| 1)
+        .map(|handle| handle.join());
+    test_runner.check("builder_spawn_join", named.ok(), Some(Ok(1)));
+
+    let panicked = thread::spawn(|| panic!("expected panic")).join();
+    test_runner.check("join_panicked", panicked.err(), Some(JoinError::Panicked));
+
+    // A detached thread keeps running after its handle is dropped.
+    let done = Semaphore::new(0, 1).unwrap();
+    let uid = done.as_raw();
+    drop(thread::spawn(move || {
+        thread::sleep(Duration::from_millis(10));
+        unsafe { sys::sceKernelSignalSema(uid, 1) };
+    }));
+    let detached = done.wait_timeout(1, Duration::from_secs(1)).is_ok();
+    test_runner.check("detach", detached, true);
+
+    let mut shared = SharedThreads::new(Mutex::new(0));
+    for _ in 0..2 {
+        shared
+            .spawn(Builder::new(), |count| *count.lock() += 1)
+            .unwrap();
+    }
+    test_runner.check("shared_busy", shared.state_mut().is_none(), true);
+    shared.join();
+    test_runner.check(
+        "shared_count",
+        shared.state_mut().map(|count| *count.get_mut()),
+        Some(2),
+    );
+
+    let first = env::args().next();
+    test_runner.check(
+        "args_module_path",
+        first.map_or(false, |arg| !arg.is_empty()),
+        true,
+    );
+    test_runner.check("args_len", env::args().len() >= 1, true);
+
+    let written = stdio::stdout().write_all(b"thread_test: stdout\n");
+    test_runner.check("stdout_write", written.is_ok(), true);
+}
@@ -0,0 +1,64 @@
+use alloc::vec::Vec;
+use core::time::Duration;
+use psp::test_runner::TestRunner;
+use psp::video::{HeaderError, PmfHeader, HEADER_SIZE};
+
+/// A header with a 480x272 video stream and, if `audio`, an ATRAC3plus one.
+fn pmf_header(audio: bool) -> Vec<u8> {
+    let mut header = alloc::vec![0; HEADER_SIZE];
+    header[..8].copy_from_slice(b"PSMF0012");
+    header[0x08..0x0c].copy_from_slice(&0x800u32.to_be_bytes());
+    header[0x0c..0x10].copy_from_slice(&0x10_0000u32.to_be_bytes());
+
+    // Timestamps are 48-bit, from 1 s to 11 s.
+    header[0x56..0x5a].copy_from_slice(&90_000u32.to_be_bytes());
+    header[0x5c..0x60].copy_from_slice(&990_000u32.to_be_bytes());
+
+    header[0x81] = if audio { 2 } else { 1 };
+    header[0x82] = 0xe0;
+    header[0x82 + 12] = 30;
+    header[0x82 + 13] = 17;
+
+    if audio {
+        header[0x92] = 0xbd;
+    }
+
+    header
+}
+
+pub fn test_main(test_runner: &mut TestRunner) {
+    let header = pmf_header(true);
+    test_runner.check(
+        "pmf_header",
+        PmfHeader::parse(&header),
+        Ok(PmfHeader {
+            stream_offset: 0x800,
+            stream_size: 0x10_0000,
+            width: 480,
+            height: 272,
+            has_audio: true,
+            first_timestamp: 90_000,
+            last_timestamp: 990_000,
+        }),
+    );
+    test_runner.check(
+        "pmf_duration",
+        PmfHeader::parse(&header).map(|header| header.duration()),
+        Ok(Duration::from_secs(10)),
+    );
+    test_runner.check(
+        "pmf_no_audio",
+        PmfHeader::parse(&pmf_header(false)).map(|header| header.has_audio),
+        Ok(false),
+    );
+    test_runner.check(
+        "pmf_truncated",
+        PmfHeader::parse(&header[..0x88]),
+        Err(HeaderError::Truncated),
+    );
+    test_runner.check(
+        "pmf_not_pmf",
+        PmfHeader::parse(b"RIFF"),
+        Err(HeaderError::NotPmf),
+    );
+}
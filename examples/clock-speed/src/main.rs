@@ -1,23 +1,27 @@
 #![no_std]
 #![no_main]
 
+use psp::power::{self, ClockProfile};
+
 psp::module!("sample_clock_speed", 1, 1);
 
 fn psp_main() {
     psp::enable_home_button();
 
-    unsafe {
-        let cpu = psp::sys::scePowerGetCpuClockFrequency();
-        let bus = psp::sys::scePowerGetBusClockFrequency();
-
-        psp::dprintln!("PSP is operating at {}/{}MHz", cpu, bus);
-        psp::dprintln!("Setting clock speed to maximum...");
-
-        psp::sys::scePowerSetClockFrequency(333, 333, 166);
+    psp::dprintln!(
+        "PSP is operating at {}/{}MHz",
+        power::cpu_frequency(),
+        power::bus_frequency()
+    );
+    psp::dprintln!("Setting clock speed to maximum...");
 
-        let cpu = psp::sys::scePowerGetCpuClockFrequency();
-        let bus = psp::sys::scePowerGetBusClockFrequency();
-
-        psp::dprintln!("PSP is now operating at {}/{}MHz", cpu, bus);
+    if let Err(e) = power::set_clock(ClockProfile::Mhz333) {
+        psp::dprintln!("Failed to set clock speed: {}", e);
     }
+
+    psp::dprintln!(
+        "PSP is now operating at {}/{}MHz",
+        power::cpu_frequency(),
+        power::bus_frequency()
+    );
 }
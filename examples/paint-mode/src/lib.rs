@@ -11,4 +11,4 @@ pub mod background;
 pub use background::get_background;
 
 pub mod analog_stick_to_delta;
-pub use analog_stick_to_delta::convert_analog_to_delta_with_sensitivity_deadzone;
+pub use analog_stick_to_delta::convert_analog_to_delta;
@@ -4,10 +4,7 @@
 #![feature(exclusive_range_pattern)]
 #![feature(half_open_range_patterns)]
 
-use psp_paint_mode::{
-    convert_analog_to_delta_with_sensitivity_deadzone, draw_debug_textbox, get_background,
-    DrawObject,
-};
+use psp_paint_mode::{convert_analog_to_delta, draw_debug_textbox, get_background, DrawObject};
 
 use embedded_graphics::prelude::*;
 
@@ -71,8 +68,7 @@ fn psp_main() {
             draw_obj = DrawObject::new_x(cur_location, cur_size);
         }
 
-        let delta_x_pixels = convert_analog_to_delta_with_sensitivity_deadzone(pad_data.lx);
-        let delta_y_pixels = convert_analog_to_delta_with_sensitivity_deadzone(pad_data.ly);
+        let (delta_x_pixels, delta_y_pixels) = convert_analog_to_delta(pad_data.lx, pad_data.ly);
         draw_obj.move_by(
             delta_x_pixels,
             delta_y_pixels,
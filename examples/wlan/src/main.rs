@@ -1,6 +1,6 @@
 //! This example only demonstrates functionality regarding the WLAN chip. It is
-//! not a networking example. You might want to look into `sceNet*` functions
-//! for actual network access.
+//! not a networking example. See `psp::net::NetworkStack` for actual network
+//! access.
 
 #![no_std]
 #![no_main]
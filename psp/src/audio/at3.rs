@@ -0,0 +1,182 @@
+//! Parsing of the RIFF headers of ATRAC3 and ATRAC3plus (`.at3`) files.
+//!
+//! `AtracPlayer` parses the header to learn the sample rate and loop points
+//! before it sets up the decoder. The parser works on a byte slice, so it
+//! can also check files in tests.
+
+use crate::io;
+
+/// The `fmt ` tag of ATRAC3 data.
+const TAG_ATRAC3: u16 = 0x0270;
+
+/// The `fmt ` tag of `WAVE_FORMAT_EXTENSIBLE`, used by ATRAC3plus.
+const TAG_EXTENSIBLE: u16 = 0xfffe;
+
+/// The start of the ATRAC3plus sub-format GUID.
+const ATRAC3PLUS_GUID: [u8; 4] = [0xbf, 0xaa, 0x23, 0xe9];
+
+/// The codec of an AT3 file.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum AtracCodec {
+    /// ATRAC3, with 1024 samples per frame.
+    Atrac3,
+    /// ATRAC3plus, with 2048 samples per frame.
+    Atrac3Plus,
+}
+
+impl AtracCodec {
+    /// The number of samples decoded from each frame.
+    pub fn samples_per_frame(self) -> u32 {
+        match self {
+            AtracCodec::Atrac3 => 1024,
+            AtracCodec::Atrac3Plus => 2048,
+        }
+    }
+}
+
+/// A loop stored in the file, in samples.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct LoopPoints {
+    /// The first sample of the loop.
+    pub start: u32,
+    /// The last sample of the loop, included.
+    pub end: u32,
+}
+
+/// Error returned when parsing an AT3 header.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum HeaderError {
+    /// The data does not start with a RIFF WAVE header.
+    NotRiff,
+    /// The data ends before the `data` chunk.
+    Truncated,
+    /// There is no `fmt ` chunk before the `data` chunk.
+    MissingFormat,
+    /// The audio is not ATRAC3 or ATRAC3plus. Holds the format tag.
+    UnsupportedCodec(u16),
+    /// A field of the `fmt ` chunk is out of range.
+    Invalid,
+}
+
+impl From<HeaderError> for io::Error {
+    fn from(_: HeaderError) -> Self {
+        io::Error::new(io::ErrorKind::InvalidData)
+    }
+}
+
+/// The information in the header of an AT3 file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct At3Header {
+    /// The codec of the audio data.
+    pub codec: AtracCodec,
+    /// The number of channels.
+    pub channels: u16,
+    /// The sample rate, in Hz.
+    pub sample_rate: u32,
+    /// The bitrate, in kbit/s.
+    pub bitrate: u32,
+    /// The size of a compressed frame, in bytes.
+    pub bytes_per_frame: u16,
+    /// The number of samples in the file, from the `fact` chunk.
+    pub samples: Option<u32>,
+    /// The loop of the `smpl` chunk, if any.
+    pub loop_points: Option<LoopPoints>,
+    /// The offset of the audio data from the start of the file.
+    pub data_offset: u32,
+    /// The size of the audio data, in bytes.
+    pub data_size: u32,
+}
+
+impl At3Header {
+    /// Parse the header at the start of `data`. Only the bytes up to the
+    /// start of the `data` chunk are needed.
+    pub fn parse(data: &[u8]) -> Result<Self, HeaderError> {
+        if data.len() < 12 || &data[..4] != b"RIFF" || &data[8..12] != b"WAVE" {
+            return Err(HeaderError::NotRiff);
+        }
+
+        let mut format = None;
+        let mut samples = None;
+        let mut loop_points = None;
+        let mut offset = 12;
+
+        loop {
+            let id = data.get(offset..offset + 4).ok_or(HeaderError::Truncated)?;
+            let size = read_u32(data, offset + 4).ok_or(HeaderError::Truncated)? as usize;
+            let body = offset + 8;
+
+            if id == b"data" {
+                let (codec, channels, sample_rate, bitrate, bytes_per_frame) =
+                    format.ok_or(HeaderError::MissingFormat)?;
+
+                return Ok(Self {
+                    codec,
+                    channels,
+                    sample_rate,
+                    bitrate,
+                    bytes_per_frame,
+                    samples,
+                    loop_points,
+                    data_offset: body as u32,
+                    data_size: size as u32,
+                });
+            }
+
+            let end = body.checked_add(size).ok_or(HeaderError::Truncated)?;
+            let chunk = data.get(body..end).ok_or(HeaderError::Truncated)?;
+
+            match id {
+                b"fmt " => format = Some(parse_format(chunk)?),
+                b"fact" => samples = read_u32(chunk, 0),
+                b"smpl" => loop_points = parse_loop(chunk),
+                _ => {}
+            }
+
+            // Chunks are padded to an even size.
+            offset = end.checked_add(size & 1).ok_or(HeaderError::Truncated)?;
+        }
+    }
+}
+
+fn parse_format(chunk: &[u8]) -> Result<(AtracCodec, u16, u32, u32, u16), HeaderError> {
+    let tag = read_u16(chunk, 0).ok_or(HeaderError::Truncated)?;
+    let channels = read_u16(chunk, 2).ok_or(HeaderError::Truncated)?;
+    let sample_rate = read_u32(chunk, 4).ok_or(HeaderError::Truncated)?;
+    let bytes_per_second = read_u32(chunk, 8).ok_or(HeaderError::Truncated)?;
+    let bytes_per_frame = read_u16(chunk, 12).ok_or(HeaderError::Truncated)?;
+
+    let codec = match tag {
+        TAG_ATRAC3 => AtracCodec::Atrac3,
+        TAG_EXTENSIBLE if chunk.get(24..28) == Some(&ATRAC3PLUS_GUID[..]) => AtracCodec::Atrac3Plus,
+        _ => return Err(HeaderError::UnsupportedCodec(tag)),
+    };
+
+    let bitrate = bytes_per_second.checked_mul(8).ok_or(HeaderError::Invalid)? / 1000;
+
+    Ok((codec, channels, sample_rate, bitrate, bytes_per_frame))
+}
+
+fn parse_loop(chunk: &[u8]) -> Option<LoopPoints> {
+    // The loop count is at offset 28, and the first loop at 36. Each loop is
+    // an id, a type, then its start and end.
+    if read_u32(chunk, 28)? == 0 {
+        return None;
+    }
+
+    Some(LoopPoints {
+        start: read_u32(chunk, 44)?,
+        end: read_u32(chunk, 48)?,
+    })
+}
+
+fn read_u16(data: &[u8], offset: usize) -> Option<u16> {
+    let bytes = data.get(offset..offset + 2)?;
+
+    Some(u16::from_le_bytes([bytes[0], bytes[1]]))
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Option<u32> {
+    let bytes = data.get(offset..offset + 4)?;
+
+    Some(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
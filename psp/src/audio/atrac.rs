@@ -0,0 +1,365 @@
+//! ATRAC3 and ATRAC3plus decoding with the `sceAtrac3plus` library.
+
+use super::at3::At3Header;
+use super::{play_src, read_blocks, read_full, zeroed_box, BlockDecoder, Channels, Sink};
+use crate::error::{self, Error};
+use crate::io::{self, Read, Seek, SeekFrom};
+use crate::modules::ModuleGuard;
+use crate::sys::{self, Atrac3BufferInfo, AvModule};
+use alloc::boxed::Box;
+use alloc::vec;
+use core::{ffi::c_void, fmt, mem, ptr, slice};
+
+/// Size of the ring buffer used in streaming mode.
+pub const STREAM_BUF_SIZE: usize = 64 * 1024;
+
+/// Size of the decoded PCM buffer, in samples: one ATRAC3plus frame.
+const PCM_BUF_SAMPLES: usize = 2048 * 2;
+
+#[repr(C, align(64))]
+struct PcmBuffer([i16; PCM_BUF_SAMPLES]);
+
+/// Decodes an AT3 file, such as the `xmb_music_at3` shown in the XMB.
+///
+/// In full-buffer mode, created with `load`, the whole file is held in
+/// memory. In streaming mode, created with `new`, the file is read through a
+/// ring buffer of `STREAM_BUF_SIZE` bytes as decoding goes. Decoded audio is
+/// 16-bit stereo at 44.1 kHz.
+///
+/// The player can be played on the SRC hardware channel with `play`, or
+/// handed to a `Mixer` with `Mixer::stream` as it implements `Sink`.
+///
+/// # Example
+///
+/// ```ignore
+/// use psp::audio::AtracPlayer;
+/// use psp::fs::File;
+///
+/// let mut player = AtracPlayer::new(File::open("ms0:/MUSIC/theme.at3")?)?;
+/// if player.header().loop_points.is_some() {
+///     player.set_loop_count(None)?;
+/// }
+/// player.play()?;
+/// # Ok::<(), psp::io::Error>(())
+/// ```
+pub struct AtracPlayer<R> {
+    reader: R,
+    id: i32,
+    /// The data handed to the decoder: the whole file, or the ring buffer.
+    _data: Box<[u8]>,
+    /// The end of a streamed file, after its loop, which the decoder plays
+    /// once it is done looping.
+    _second: Option<Box<[u8]>>,
+    pcm: Box<PcmBuffer>,
+    header: At3Header,
+    streaming: bool,
+    /// Decoded samples not yet returned by `Sink::read`, as a range of `pcm`.
+    pending: (usize, usize),
+    max_frames: usize,
+    finished: bool,
+    _modules: ModuleGuard,
+}
+
+impl<R: Read + Seek> AtracPlayer<R> {
+    /// Load the ATRAC modules and prepare to stream `reader`.
+    ///
+    /// Files that fit in the ring buffer are decoded in full-buffer mode.
+    pub fn new(reader: R) -> io::Result<Self> {
+        Self::with_buffer(reader, STREAM_BUF_SIZE)
+    }
+
+    /// Load the ATRAC modules, then read all of `reader` into memory.
+    pub fn load(mut reader: R) -> io::Result<Self> {
+        let size = reader.seek(SeekFrom::End(0))?;
+
+        Self::with_buffer(reader, size as usize)
+    }
+
+    fn with_buffer(mut reader: R, buf_size: usize) -> io::Result<Self> {
+        let modules = ModuleGuard::load(&[AvModule::Atrac3Plus.into()])?;
+
+        let size = reader.seek(SeekFrom::End(0))? as usize;
+        reader.seek(SeekFrom::Start(0))?;
+
+        let buf_size = core::cmp::min(buf_size, size);
+        let mut data = vec![0; buf_size].into_boxed_slice();
+        let read = read_full(&mut reader, &mut data)?;
+        let header = At3Header::parse(&data[..read])?;
+
+        // A buffer smaller than the file is used as a ring, which the decoder
+        // wants filled entirely to start with.
+        let streaming = buf_size < size;
+        let id = error::check(unsafe {
+            if streaming {
+                sys::sceAtracSetDataAndGetID(data.as_mut_ptr() as *mut c_void, read)
+            } else {
+                let (read, buf_size) = (read as u32, buf_size as u32);
+                sys::sceAtracSetHalfwayBufferAndGetID(data.as_mut_ptr(), read, buf_size)
+            }
+        })?;
+
+        // From here on, dropping `player` releases the id.
+        let mut player = Self {
+            reader,
+            id,
+            _data: data,
+            _second: None,
+            pcm: unsafe { zeroed_box::<PcmBuffer>() },
+            header,
+            streaming,
+            pending: (0, 0),
+            max_frames: 0,
+            finished: false,
+            _modules: modules,
+        };
+
+        let mut max = 0;
+        error::check(unsafe { sys::sceAtracGetMaxSample(id, &mut max) })?;
+        player.max_frames = max as usize;
+        player.load_second_buffer()?;
+        player.fill()?;
+
+        Ok(player)
+    }
+
+    /// The information in the file header.
+    pub fn header(&self) -> &At3Header {
+        &self.header
+    }
+
+    /// Returns `true` in streaming mode, `false` if the whole file is in
+    /// memory.
+    pub fn is_streaming(&self) -> bool {
+        self.streaming
+    }
+
+    /// The bitrate, in kbit/s, as reported by the decoder.
+    pub fn bitrate(&self) -> Result<u32, Error> {
+        let mut bitrate = 0;
+        error::check(unsafe { sys::sceAtracGetBitrate(self.id, &mut bitrate) })?;
+
+        Ok(bitrate as u32)
+    }
+
+    /// The position of the next sample to be decoded.
+    pub fn position(&self) -> Result<u32, Error> {
+        let mut position = 0;
+        error::check(unsafe { sys::sceAtracGetNextDecodePosition(self.id, &mut position) })?;
+
+        Ok(position)
+    }
+
+    /// Set how many times the loop of the file plays again when reached:
+    /// `Some(0)` plays it once, `None` loops forever.
+    ///
+    /// Fails with `SCE_ATRAC_ERROR_NO_LOOP_INFORMATION` if the file has no
+    /// loop points.
+    pub fn set_loop_count(&mut self, count: Option<u32>) -> Result<(), Error> {
+        let count = count.map(|n| n as i32).unwrap_or(-1);
+        error::check(unsafe { sys::sceAtracSetLoopNum(self.id, count) })?;
+        self.finished = false;
+
+        Ok(())
+    }
+
+    /// Continue decoding from `sample`.
+    pub fn seek(&mut self, sample: u32) -> io::Result<()> {
+        let mut info: Atrac3BufferInfo = unsafe { mem::zeroed() };
+        error::check(unsafe { sys::sceAtracGetBufferInfoForReseting(self.id, sample, &mut info) })?;
+
+        // The decoder tells us where in the file to resume reading, and
+        // where in its buffers to put the data.
+        let first = self.read_at(
+            info.ui_read_position_first_buf,
+            info.puc_write_position_first_buf,
+            info.ui_writable_byte_first_buf,
+        )?;
+        let second = self.read_at(
+            info.ui_read_position_second_buf,
+            info.puc_write_position_second_buf,
+            info.ui_writable_byte_second_buf,
+        )?;
+
+        error::check(unsafe {
+            sys::sceAtracResetPlayPosition(self.id, sample, first as u32, second as u32)
+        })?;
+        self.pending = (0, 0);
+        self.finished = false;
+
+        Ok(())
+    }
+
+    /// Restart decoding from the beginning of the file.
+    pub fn rewind(&mut self) -> io::Result<()> {
+        self.seek(0)
+    }
+
+    /// Decode the next frame as interleaved stereo samples. Returns an empty
+    /// slice at the end of the file.
+    pub fn decode(&mut self) -> io::Result<&[i16]> {
+        let len = self.decode_frame()?;
+
+        Ok(&self.pcm.0[..len])
+    }
+
+    /// Play the rest of the file on the SRC hardware channel, blocking until
+    /// it ends.
+    pub fn play(&mut self) -> io::Result<()> {
+        let (sample_rate, frames) = (self.header.sample_rate, self.max_frames);
+
+        play_src(self, sample_rate, frames, Self::decode)
+    }
+
+    /// Decode a frame into `pcm`, returning the number of samples.
+    fn decode_frame(&mut self) -> io::Result<usize> {
+        if self.finished {
+            return Ok(0);
+        }
+
+        let mut frames = 0;
+        let mut end = 0;
+        let mut remain = 0;
+        let ret = unsafe {
+            sys::sceAtracDecodeData(
+                self.id,
+                self.pcm.0.as_mut_ptr() as *mut u16,
+                &mut frames,
+                &mut end,
+                &mut remain,
+            )
+        };
+
+        if ret == error::SCE_ATRAC_ERROR_ALL_DATA_DECODED.raw() {
+            self.finished = true;
+            return Ok(0);
+        }
+
+        error::check(ret)?;
+        self.finished = end != 0;
+
+        // A negative count means all remaining data is already in memory.
+        if remain >= 0 {
+            self.fill()?;
+        }
+
+        Ok(core::cmp::min(frames.max(0) as usize * 2, PCM_BUF_SAMPLES))
+    }
+
+    /// Copy more of the file into the ring buffer, if there is room.
+    ///
+    /// The free space may wrap around the end of the ring, in which case the
+    /// decoder hands it out in two parts.
+    fn fill(&mut self) -> io::Result<()> {
+        loop {
+            let mut dst: *mut u8 = ptr::null_mut();
+            let mut len = 0;
+            let mut offset = 0;
+
+            match error::check(unsafe {
+                sys::sceAtracGetStreamDataInfo(self.id, &mut dst, &mut len, &mut offset)
+            }) {
+                Ok(_) => {}
+                Err(error::SCE_ATRAC_ERROR_ALL_DATA_LOADED) => return Ok(()),
+                Err(e) => return Err(e.into()),
+            }
+
+            if len == 0 {
+                return Ok(());
+            }
+
+            // `dst` points into `data`.
+            let n = self.read_at(offset, dst, len)?;
+            error::check(unsafe { sys::sceAtracAddStreamData(self.id, n as u32) })?;
+
+            if n < len as usize {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Hand the decoder the end of the file after the loop, if it needs it.
+    ///
+    /// A streamed file whose loop ends before the end of the file plays the
+    /// rest once it is done looping, but by then the ring buffer holds the
+    /// loop. The decoder keeps the rest in a second buffer instead.
+    fn load_second_buffer(&mut self) -> io::Result<()> {
+        let mut position = 0;
+        let mut len = 0;
+
+        match error::check(unsafe {
+            sys::sceAtracGetSecondBufferInfo(self.id, &mut position, &mut len)
+        }) {
+            Ok(_) => {}
+            Err(error::SCE_ATRAC_ERROR_SECOND_BUFFER_NOT_NEEDED) => return Ok(()),
+            Err(e) => return Err(e.into()),
+        }
+
+        let mut second = vec![0; len as usize].into_boxed_slice();
+        let read = self.read_at(position, second.as_mut_ptr(), len)?;
+        error::check(unsafe {
+            sys::sceAtracSetSecondBuffer(self.id, second.as_mut_ptr(), read as u32)
+        })?;
+        self._second = Some(second);
+
+        Ok(())
+    }
+
+    /// Read `len` bytes of the file from `position` into `dst`, which must
+    /// point into a buffer of the decoder, returning the number read.
+    fn read_at(&mut self, position: u32, dst: *mut u8, len: u32) -> io::Result<usize> {
+        if len == 0 {
+            return Ok(0);
+        }
+
+        self.reader.seek(SeekFrom::Start(position as u64))?;
+        let buf = unsafe { slice::from_raw_parts_mut(dst, len as usize) };
+
+        read_full(&mut self.reader, buf)
+    }
+}
+
+impl<R: Read + Seek + Send> Sink for AtracPlayer<R> {
+    fn channels(&self) -> Channels {
+        Channels::Stereo
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.header.sample_rate
+    }
+
+    fn read(&mut self, buf: &mut [i16]) -> usize {
+        read_blocks(self, buf)
+    }
+}
+
+impl<R: Read + Seek> BlockDecoder for AtracPlayer<R> {
+    fn decode_next(&mut self) -> io::Result<(usize, usize)> {
+        self.decode_frame().map(|len| (0, len))
+    }
+
+    fn pcm(&self) -> &[i16] {
+        &self.pcm.0
+    }
+
+    fn pending(&mut self) -> &mut (usize, usize) {
+        &mut self.pending
+    }
+}
+
+impl<R> fmt::Debug for AtracPlayer<R> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AtracPlayer")
+            .field("id", &self.id)
+            .field("header", &self.header)
+            .field("streaming", &self.streaming)
+            .finish()
+    }
+}
+
+impl<R> Drop for AtracPlayer<R> {
+    fn drop(&mut self) {
+        unsafe {
+            sys::sceAtracReleaseAtracID(self.id);
+        }
+    }
+}
@@ -0,0 +1,443 @@
+//! Recording from the microphone.
+//!
+//! A `Recorder` reads the headset microphone with `sceAudioInputBlocking` on a
+//! background thread, into a `SampleRing` the caller reads from. Samples are
+//! mono, 16-bit. `WavWriter` saves them as a WAV file.
+
+use super::DEFAULT_PRIORITY;
+use crate::error::{self, Result};
+use crate::fs::File;
+use crate::io::{self, Seek, SeekFrom, Write};
+use crate::sync::{critical_section, Mutex};
+use crate::sys::{self, AudioInputFrequency};
+use crate::thread::{self, SharedThreads};
+use alloc::collections::VecDeque;
+use core::cell::UnsafeCell;
+use core::ffi::c_void;
+use core::time::Duration;
+
+/// Number of samples read from the hardware at once.
+pub const BLOCK_SAMPLES: usize = 512;
+
+/// Default input gain.
+pub const DEFAULT_GAIN: i32 = 0x800;
+
+/// Set while a `Recorder` owns the audio input.
+static mut INPUT_BUSY: bool = false;
+
+/// A bounded FIFO of samples. When full, the oldest samples are dropped to
+/// make room for new ones.
+#[derive(Debug, Clone)]
+pub struct SampleRing {
+    samples: VecDeque<i16>,
+    capacity: usize,
+    dropped: usize,
+}
+
+impl SampleRing {
+    /// Create an empty ring holding up to `capacity` samples.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            samples: VecDeque::with_capacity(capacity),
+            capacity,
+            dropped: 0,
+        }
+    }
+
+    /// The most samples the ring holds.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// The number of samples waiting to be read.
+    pub fn len(&self) -> usize {
+        self.samples.len()
+    }
+
+    /// Returns `true` if there are no samples to read.
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+
+    /// The number of samples dropped because the ring was full.
+    pub fn dropped(&self) -> usize {
+        self.dropped
+    }
+
+    /// Append `samples`, dropping the oldest ones if there is no room.
+    pub fn push(&mut self, samples: &[i16]) {
+        let skip = samples.len().saturating_sub(self.capacity);
+        let samples = &samples[skip..];
+
+        let overflow = (self.samples.len() + samples.len()).saturating_sub(self.capacity);
+        self.samples.drain(..overflow);
+        self.dropped += skip + overflow;
+
+        self.samples.extend(samples);
+    }
+
+    /// Move the oldest samples into `out`, returning how many were moved.
+    pub fn pop(&mut self, out: &mut [i16]) -> usize {
+        let count = core::cmp::min(out.len(), self.samples.len());
+
+        for (slot, sample) in out.iter_mut().zip(self.samples.drain(..count)) {
+            *slot = sample;
+        }
+
+        count
+    }
+
+    /// Drop every sample.
+    pub fn clear(&mut self) {
+        self.samples.clear();
+    }
+}
+
+/// Settings of a `Recorder`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct RecorderConfig {
+    /// The sample rate to record at.
+    pub frequency: AudioInputFrequency,
+    /// The hardware input gain.
+    pub gain: i32,
+    /// How much audio is kept when it is not read fast enough.
+    pub buffer: Duration,
+    /// The priority of the recording thread. Lower values mean higher
+    /// priority.
+    pub priority: i32,
+}
+
+impl Default for RecorderConfig {
+    fn default() -> Self {
+        Self {
+            frequency: AudioInputFrequency::Khz44_1,
+            gain: DEFAULT_GAIN,
+            buffer: Duration::from_secs(2),
+            priority: DEFAULT_PRIORITY,
+        }
+    }
+}
+
+/// State shared between a `Recorder` and its thread.
+struct Shared {
+    ring: Mutex<SampleRing>,
+    frequency: AudioInputFrequency,
+    /// Cleared to stop the thread, or by the thread when the microphone is
+    /// unplugged. Only accessed in a critical section.
+    running: UnsafeCell<bool>,
+}
+
+// `running` is only accessed in a critical section.
+unsafe impl Sync for Shared {}
+
+impl Shared {
+    fn running(&self) -> bool {
+        critical_section(|| unsafe { *self.running.get() })
+    }
+
+    fn stop(&self) {
+        critical_section(|| unsafe { *self.running.get() = false });
+    }
+}
+
+/// Records the microphone in the background.
+///
+/// Only one recorder can exist at a time. Dropping it stops recording, as
+/// does unplugging the microphone.
+pub struct Recorder {
+    shared: SharedThreads<Shared>,
+}
+
+impl Recorder {
+    /// Returns `true` if a microphone is plugged in.
+    pub fn microphone_present() -> bool {
+        unsafe { sys::sceHprmIsMicrophoneExist() == 1 }
+    }
+
+    /// Start recording at `frequency`, with the default settings otherwise.
+    pub fn new(frequency: AudioInputFrequency) -> Result<Self> {
+        Self::with_config(RecorderConfig {
+            frequency,
+            ..RecorderConfig::default()
+        })
+    }
+
+    /// Start recording with `config`.
+    ///
+    /// Fails with `SCE_ERROR_ERRNO_ENODEV` if there is no microphone, and
+    /// with `SCE_ERROR_ERRNO_EBUSY` if another recorder exists.
+    pub fn with_config(config: RecorderConfig) -> Result<Self> {
+        if !Self::microphone_present() {
+            return Err(error::SCE_ERROR_ERRNO_ENODEV);
+        }
+
+        let acquired = critical_section(|| unsafe {
+            if INPUT_BUSY {
+                false
+            } else {
+                INPUT_BUSY = true;
+                true
+            }
+        });
+
+        if !acquired {
+            return Err(error::SCE_ERROR_ERRNO_EBUSY);
+        }
+
+        if let Err(e) = error::check(unsafe { sys::sceAudioInputInit(0, config.gain, 0) }) {
+            release_input();
+            return Err(e);
+        }
+
+        let rate = config.frequency as i32 as u64;
+        let capacity = (rate * config.buffer.as_millis() as u64 / 1000) as usize;
+        let mut shared = SharedThreads::new(Shared {
+            ring: Mutex::new(SampleRing::new(core::cmp::max(capacity, BLOCK_SAMPLES))),
+            frequency: config.frequency,
+            running: UnsafeCell::new(true),
+        });
+
+        let builder = thread::Builder::new()
+            .name("psp_audio_input")
+            .priority(config.priority)
+            .stack_size(8 * 1024);
+
+        if let Err(e) = shared.spawn(builder, input_thread) {
+            release_input();
+
+            return Err(e);
+        }
+
+        Ok(Self { shared })
+    }
+
+    fn shared(&self) -> &Shared {
+        self.shared.state()
+    }
+
+    /// Returns `false` once the microphone was unplugged, which stops the
+    /// recording. The samples recorded until then can still be read.
+    pub fn is_recording(&self) -> bool {
+        self.shared().running()
+    }
+
+    /// The sample rate of the recording.
+    pub fn sample_rate(&self) -> u32 {
+        self.shared().frequency as i32 as u32
+    }
+
+    /// The number of samples that can be read without waiting.
+    pub fn available(&self) -> usize {
+        self.shared().ring.lock().len()
+    }
+
+    /// The number of samples lost because they were not read in time.
+    pub fn dropped(&self) -> usize {
+        self.shared().ring.lock().dropped()
+    }
+
+    /// Discard the samples recorded so far.
+    pub fn clear(&mut self) {
+        self.shared().ring.lock().clear();
+    }
+
+    /// Read the samples recorded so far into `buf`, without waiting, and
+    /// return how many were read.
+    pub fn read(&mut self, buf: &mut [i16]) -> usize {
+        self.shared().ring.lock().pop(buf)
+    }
+
+    /// Fill `buf`, waiting for the samples to be recorded.
+    ///
+    /// Fails with `SCE_ERROR_ERRNO_ENODEV` if the microphone is unplugged
+    /// before `buf` is filled.
+    pub fn read_blocking(&mut self, buf: &mut [i16]) -> Result<()> {
+        let mut filled = 0;
+
+        while filled < buf.len() {
+            // Checked before reading, so that the last samples recorded
+            // before the microphone was unplugged are still read.
+            let recording = self.is_recording();
+            filled += self.read(&mut buf[filled..]);
+
+            if filled < buf.len() {
+                if !recording {
+                    return Err(error::SCE_ERROR_ERRNO_ENODEV);
+                }
+
+                // A little less than a block at the highest rate.
+                unsafe { sys::sceKernelDelayThread(10_000) };
+            }
+        }
+
+        Ok(())
+    }
+
+    /// An iterator over blocks of recorded samples, waiting for each, which
+    /// ends when the microphone is unplugged.
+    pub fn blocks(&mut self) -> Blocks<'_> {
+        Blocks { recorder: self }
+    }
+
+    /// Record the next `duration` of audio to a WAV file at `path`.
+    pub fn record_wav(&mut self, path: &str, duration: Duration) -> io::Result<()> {
+        let rate = self.sample_rate();
+        let mut left = (rate as u64 * duration.as_millis() as u64 / 1000) as usize;
+
+        let mut wav = WavWriter::new(File::create(path)?, rate, 1)?;
+        let mut buf = [0i16; BLOCK_SAMPLES];
+
+        while left > 0 {
+            let count = core::cmp::min(left, buf.len());
+            self.read_blocking(&mut buf[..count])?;
+            wav.write_samples(&buf[..count])?;
+            left -= count;
+        }
+
+        wav.finish()?;
+
+        Ok(())
+    }
+}
+
+impl Drop for Recorder {
+    fn drop(&mut self) {
+        self.shared().stop();
+        self.shared.join();
+
+        release_input();
+    }
+}
+
+/// Blocks of samples from a `Recorder`, created by `Recorder::blocks`.
+pub struct Blocks<'a> {
+    recorder: &'a mut Recorder,
+}
+
+impl Iterator for Blocks<'_> {
+    type Item = [i16; BLOCK_SAMPLES];
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut block = [0; BLOCK_SAMPLES];
+        self.recorder.read_blocking(&mut block).ok()?;
+
+        Some(block)
+    }
+}
+
+fn release_input() {
+    critical_section(|| unsafe { INPUT_BUSY = false });
+}
+
+fn input_thread(shared: &Shared) {
+    let mut block = [0i16; BLOCK_SAMPLES];
+
+    while shared.running() {
+        if !Recorder::microphone_present() {
+            shared.stop();
+            break;
+        }
+
+        unsafe {
+            sys::sceAudioInputBlocking(
+                BLOCK_SAMPLES as i32,
+                shared.frequency,
+                block.as_mut_ptr() as *mut c_void,
+            );
+        }
+
+        shared.ring.lock().push(&block);
+    }
+}
+
+/// Size of the header written by `WavWriter`.
+const WAV_HEADER_SIZE: usize = 44;
+
+/// Writes 16-bit PCM to a WAV file, filling in the sizes in the header when
+/// finished.
+pub struct WavWriter<W: Write + Seek> {
+    writer: W,
+    sample_rate: u32,
+    channels: u16,
+    data_len: u32,
+}
+
+impl<W: Write + Seek> WavWriter<W> {
+    /// Write the header of an empty file to `writer`.
+    pub fn new(mut writer: W, sample_rate: u32, channels: u16) -> io::Result<Self> {
+        writer.write_all(&wav_header(sample_rate, channels, 0))?;
+
+        Ok(Self {
+            writer,
+            sample_rate,
+            channels,
+            data_len: 0,
+        })
+    }
+
+    /// Append interleaved samples.
+    ///
+    /// Fails with `InvalidInput`, writing nothing, if the file would grow
+    /// past the 4 GiB a WAV file can describe.
+    pub fn write_samples(&mut self, samples: &[i16]) -> io::Result<()> {
+        let len = self.data_len as u64 + samples.len() as u64 * 2;
+
+        // The RIFF size counts everything after its first 8 bytes.
+        if len + (WAV_HEADER_SIZE - 8) as u64 > u32::MAX as u64 {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput));
+        }
+
+        let mut bytes = [0; 256];
+
+        for chunk in samples.chunks(bytes.len() / 2) {
+            for (out, sample) in bytes.chunks_exact_mut(2).zip(chunk) {
+                out.copy_from_slice(&sample.to_le_bytes());
+            }
+
+            self.writer.write_all(&bytes[..chunk.len() * 2])?;
+        }
+
+        self.data_len = len as u32;
+
+        Ok(())
+    }
+
+    /// The number of samples written so far.
+    pub fn samples(&self) -> usize {
+        self.data_len as usize / 2
+    }
+
+    /// Rewrite the header with the final sizes, and return the writer.
+    pub fn finish(mut self) -> io::Result<W> {
+        let header = wav_header(self.sample_rate, self.channels, self.data_len);
+
+        self.writer.seek(SeekFrom::Start(0))?;
+        self.writer.write_all(&header)?;
+        self.writer.seek(SeekFrom::End(0))?;
+        self.writer.flush()?;
+
+        Ok(self.writer)
+    }
+}
+
+/// The header of a 16-bit PCM WAV file with `data_len` bytes of samples.
+fn wav_header(sample_rate: u32, channels: u16, data_len: u32) -> [u8; WAV_HEADER_SIZE] {
+    let block_align = channels * 2;
+    let mut header = [0; WAV_HEADER_SIZE];
+
+    header[0..4].copy_from_slice(b"RIFF");
+    header[4..8].copy_from_slice(&data_len.saturating_add(36).to_le_bytes());
+    header[8..12].copy_from_slice(b"WAVE");
+    header[12..16].copy_from_slice(b"fmt ");
+    header[16..20].copy_from_slice(&16u32.to_le_bytes());
+    header[20..22].copy_from_slice(&1u16.to_le_bytes());
+    header[22..24].copy_from_slice(&channels.to_le_bytes());
+    header[24..28].copy_from_slice(&sample_rate.to_le_bytes());
+    header[28..32].copy_from_slice(&(sample_rate * block_align as u32).to_le_bytes());
+    header[32..34].copy_from_slice(&block_align.to_le_bytes());
+    header[34..36].copy_from_slice(&16u16.to_le_bytes());
+    header[36..40].copy_from_slice(b"data");
+    header[40..44].copy_from_slice(&data_len.to_le_bytes());
+
+    header
+}
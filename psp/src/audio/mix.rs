@@ -0,0 +1,501 @@
+//! The mixing core of `Mixer`.
+//!
+//! Nothing in here talks to the hardware, so the core can be driven and
+//! tested on its own: add voices, then call `MixCore::mix` to render them.
+
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+
+/// The rate of the mixed output, in Hz. Voices at other rates are resampled.
+pub const OUTPUT_RATE: u32 = 44100;
+
+/// Number of frames a `Sink` is asked for at once.
+const STREAM_CHUNK: usize = 512;
+
+/// Unity gain in Q15 fixed point.
+const UNITY: f32 = 32768.0;
+
+/// The channel layout of PCM data.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Channels {
+    /// One sample per frame.
+    Mono,
+    /// Two interleaved samples per frame, left first.
+    Stereo,
+}
+
+impl Channels {
+    /// The number of samples in a frame.
+    pub fn count(self) -> usize {
+        match self {
+            Channels::Mono => 1,
+            Channels::Stereo => 2,
+        }
+    }
+}
+
+/// A source of PCM data produced on the fly, such as a decoder.
+///
+/// The mixer pulls from a sink on its output thread as the voice plays.
+pub trait Sink: Send {
+    /// The channel layout of the samples written by `read`.
+    fn channels(&self) -> Channels;
+
+    /// The sample rate of the data, in Hz.
+    fn sample_rate(&self) -> u32;
+
+    /// Fill `buf` with interleaved samples, returning the number of frames
+    /// written. Returning 0 ends the stream.
+    ///
+    /// `buf` always holds a whole number of frames. This is called with the
+    /// mixer locked, so it should not block for long.
+    fn read(&mut self, buf: &mut [i16]) -> usize;
+}
+
+/// Sound data held in memory.
+#[derive(Debug, Clone)]
+pub struct Pcm {
+    samples: Box<[i16]>,
+    channels: Channels,
+    sample_rate: u32,
+}
+
+impl Pcm {
+    /// Wrap interleaved `samples`. A trailing partial frame is ignored.
+    pub fn new(samples: impl Into<Box<[i16]>>, channels: Channels, sample_rate: u32) -> Self {
+        Self {
+            samples: samples.into(),
+            channels,
+            sample_rate,
+        }
+    }
+
+    /// The channel layout of the samples.
+    pub fn channels(&self) -> Channels {
+        self.channels
+    }
+
+    /// The sample rate, in Hz.
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    /// The number of frames.
+    pub fn frames(&self) -> usize {
+        self.samples.len() / self.channels.count()
+    }
+
+    /// The interleaved samples.
+    pub fn samples(&self) -> &[i16] {
+        &self.samples
+    }
+}
+
+/// What a voice plays.
+pub enum Source {
+    /// Sound data held in memory. Playback can be rewound and looped.
+    Pcm(Pcm),
+    /// Streamed data. The voice is removed once the stream ends.
+    Stream(Box<dyn Sink>),
+}
+
+impl From<Pcm> for Source {
+    fn from(pcm: Pcm) -> Self {
+        Source::Pcm(pcm)
+    }
+}
+
+impl From<Box<dyn Sink>> for Source {
+    fn from(sink: Box<dyn Sink>) -> Self {
+        Source::Stream(sink)
+    }
+}
+
+/// Identifies a voice of a `MixCore`. Ids are never reused.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct VoiceId(u32);
+
+enum Reader {
+    Pcm {
+        pcm: Pcm,
+        cursor: usize,
+    },
+    Stream {
+        sink: Box<dyn Sink>,
+        buf: Vec<i16>,
+        read: usize,
+        filled: usize,
+    },
+}
+
+impl Reader {
+    fn channels(&self) -> Channels {
+        match self {
+            Reader::Pcm { pcm, .. } => pcm.channels,
+            Reader::Stream { sink, .. } => sink.channels(),
+        }
+    }
+
+    fn sample_rate(&self) -> u32 {
+        match self {
+            Reader::Pcm { pcm, .. } => pcm.sample_rate,
+            Reader::Stream { sink, .. } => sink.sample_rate(),
+        }
+    }
+
+    /// The next frame as a left and right sample, or `None` at the end.
+    fn next_frame(&mut self, looping: bool) -> Option<[i32; 2]> {
+        let channels = self.channels().count();
+
+        let frame = match self {
+            Reader::Pcm { pcm, cursor } => {
+                let frames = pcm.frames();
+
+                if *cursor >= frames {
+                    if !looping || frames == 0 {
+                        return None;
+                    }
+
+                    *cursor = 0;
+                }
+
+                let start = *cursor * channels;
+                *cursor += 1;
+                &pcm.samples[start..start + channels]
+            }
+            Reader::Stream {
+                sink,
+                buf,
+                read,
+                filled,
+            } => {
+                if *read >= *filled {
+                    let len = buf.len();
+                    *filled = core::cmp::min(sink.read(buf), len / channels);
+                    *read = 0;
+
+                    if *filled == 0 {
+                        return None;
+                    }
+                }
+
+                let start = *read * channels;
+                *read += 1;
+                &buf[start..start + channels]
+            }
+        };
+
+        let left = frame[0] as i32;
+        let right = frame[channels - 1] as i32;
+
+        Some([left, right])
+    }
+}
+
+/// A sound playing, or ready to play, in a `MixCore`.
+pub struct Voice {
+    id: VoiceId,
+    reader: Reader,
+    volume: f32,
+    pan: f32,
+    pitch: f32,
+    looping: bool,
+    playing: bool,
+    /// Position between `current` and `next`, in 1/65536 of a frame.
+    frac: u32,
+    /// Position increment per output frame, in 1/65536 of a frame.
+    step: u32,
+    current: Option<[i32; 2]>,
+    next: Option<[i32; 2]>,
+    primed: bool,
+    /// Set once a stream ended, to remove the voice.
+    finished: bool,
+}
+
+impl Voice {
+    fn new(id: VoiceId, source: Source) -> Self {
+        let reader = match source {
+            Source::Pcm(pcm) => Reader::Pcm { pcm, cursor: 0 },
+            Source::Stream(sink) => {
+                let len = STREAM_CHUNK * sink.channels().count();
+
+                Reader::Stream {
+                    sink,
+                    buf: alloc::vec![0; len],
+                    read: 0,
+                    filled: 0,
+                }
+            }
+        };
+
+        let mut voice = Self {
+            id,
+            reader,
+            volume: 1.0,
+            pan: 0.0,
+            pitch: 1.0,
+            looping: false,
+            playing: false,
+            frac: 0,
+            step: 0,
+            current: None,
+            next: None,
+            primed: false,
+            finished: false,
+        };
+        voice.update_step();
+
+        voice
+    }
+
+    /// The id of this voice.
+    pub fn id(&self) -> VoiceId {
+        self.id
+    }
+
+    /// Start or resume playback.
+    pub fn play(&mut self) {
+        self.playing = true;
+    }
+
+    /// Pause playback, keeping the position.
+    pub fn pause(&mut self) {
+        self.playing = false;
+    }
+
+    /// Stop playback and rewind to the start. Streams cannot be rewound, so
+    /// they are only paused.
+    pub fn stop(&mut self) {
+        self.playing = false;
+
+        if let Reader::Pcm { cursor, .. } = &mut self.reader {
+            *cursor = 0;
+            self.frac = 0;
+            self.primed = false;
+        }
+    }
+
+    /// Returns `true` while the voice is playing.
+    pub fn is_playing(&self) -> bool {
+        self.playing
+    }
+
+    /// The volume, from 0.0 (silent) to 1.0 (unchanged).
+    pub fn volume(&self) -> f32 {
+        self.volume
+    }
+
+    /// Set the volume, clamped to 0.0..=1.0.
+    pub fn set_volume(&mut self, volume: f32) {
+        self.volume = clamp(volume, 0.0, 1.0);
+    }
+
+    /// The stereo position, from -1.0 (left) to 1.0 (right).
+    pub fn pan(&self) -> f32 {
+        self.pan
+    }
+
+    /// Set the stereo position, clamped to -1.0..=1.0.
+    pub fn set_pan(&mut self, pan: f32) {
+        self.pan = clamp(pan, -1.0, 1.0);
+    }
+
+    /// The playback speed, 1.0 being the original pitch.
+    pub fn pitch(&self) -> f32 {
+        self.pitch
+    }
+
+    /// Set the playback speed, clamped to 1/16..=16.
+    pub fn set_pitch(&mut self, pitch: f32) {
+        self.pitch = clamp(pitch, 1.0 / 16.0, 16.0);
+        self.update_step();
+    }
+
+    /// Returns `true` if the voice restarts when it reaches its end.
+    pub fn is_looping(&self) -> bool {
+        self.looping
+    }
+
+    /// Restart from the beginning at the end of the data. Only applies to
+    /// `Source::Pcm` voices.
+    pub fn set_looping(&mut self, looping: bool) {
+        self.looping = looping;
+    }
+
+    fn update_step(&mut self) {
+        let ratio = self.reader.sample_rate() as f32 * self.pitch / OUTPUT_RATE as f32;
+        self.step = core::cmp::max((ratio * 65536.0) as u32, 1);
+    }
+
+    /// Add `out.len() / 2` stereo frames to `acc`. Returns `false` once the
+    /// data ran out.
+    fn mix_into(&mut self, acc: &mut [i32], master: f32) -> bool {
+        let volume = self.volume * master;
+        let left_gain = (volume * min(1.0, 1.0 - self.pan) * UNITY) as i32;
+        let right_gain = (volume * min(1.0, 1.0 + self.pan) * UNITY) as i32;
+
+        if !self.primed {
+            self.current = self.reader.next_frame(self.looping);
+            self.next = self.reader.next_frame(self.looping);
+            self.primed = true;
+        }
+
+        for out in acc.chunks_exact_mut(2) {
+            let current = match self.current {
+                Some(frame) => frame,
+                None => return false,
+            };
+            let next = self.next.unwrap_or(current);
+
+            // Linear interpolation, with the fraction in Q15 to stay in i32.
+            let t = (self.frac >> 1) as i32;
+            let left = current[0] + (((next[0] - current[0]) * t) >> 15);
+            let right = current[1] + (((next[1] - current[1]) * t) >> 15);
+
+            out[0] += (left * left_gain) >> 15;
+            out[1] += (right * right_gain) >> 15;
+
+            self.frac += self.step;
+            while self.frac >= 1 << 16 {
+                self.frac -= 1 << 16;
+                self.current = self.next;
+                self.next = match self.current {
+                    Some(_) => self.reader.next_frame(self.looping),
+                    None => None,
+                };
+            }
+        }
+
+        self.current.is_some()
+    }
+}
+
+/// A set of voices mixed into 44.1 kHz stereo.
+pub struct MixCore {
+    voices: Vec<Voice>,
+    next_id: u32,
+    master: f32,
+    acc: Vec<i32>,
+}
+
+impl MixCore {
+    /// Create a mixer core without any voices.
+    pub fn new() -> Self {
+        Self {
+            voices: Vec::new(),
+            next_id: 0,
+            master: 1.0,
+            acc: Vec::new(),
+        }
+    }
+
+    /// Add a paused voice playing `source`.
+    pub fn add(&mut self, source: impl Into<Source>) -> VoiceId {
+        let id = VoiceId(self.next_id);
+        self.next_id += 1;
+        self.voices.push(Voice::new(id, source.into()));
+
+        id
+    }
+
+    /// Add a voice playing `source` and start it.
+    pub fn play(&mut self, source: impl Into<Source>) -> VoiceId {
+        let id = self.add(source);
+        self.voices.last_mut().unwrap().play();
+
+        id
+    }
+
+    /// Remove a voice. Returns `false` if it no longer exists.
+    pub fn remove(&mut self, id: VoiceId) -> bool {
+        let len = self.voices.len();
+        self.voices.retain(|voice| voice.id != id);
+
+        self.voices.len() != len
+    }
+
+    /// The voice with the given id, if it still exists.
+    pub fn voice(&self, id: VoiceId) -> Option<&Voice> {
+        self.voices.iter().find(|voice| voice.id == id)
+    }
+
+    /// The voice with the given id, if it still exists.
+    pub fn voice_mut(&mut self, id: VoiceId) -> Option<&mut Voice> {
+        self.voices.iter_mut().find(|voice| voice.id == id)
+    }
+
+    /// The number of voices, playing or not.
+    pub fn len(&self) -> usize {
+        self.voices.len()
+    }
+
+    /// Returns `true` if there are no voices.
+    pub fn is_empty(&self) -> bool {
+        self.voices.is_empty()
+    }
+
+    /// The volume applied to every voice.
+    pub fn master_volume(&self) -> f32 {
+        self.master
+    }
+
+    /// Set the volume applied to every voice, clamped to 0.0..=1.0.
+    pub fn set_master_volume(&mut self, volume: f32) {
+        self.master = clamp(volume, 0.0, 1.0);
+    }
+
+    /// Render the playing voices into `out` as interleaved stereo at
+    /// `OUTPUT_RATE`, overwriting its contents.
+    ///
+    /// Voices that reach their end are stopped and rewound, or removed if
+    /// they play a stream.
+    pub fn mix(&mut self, out: &mut [i16]) {
+        let len = out.len() & !1;
+        self.acc.clear();
+        self.acc.resize(len, 0);
+
+        let master = self.master;
+        let acc = &mut self.acc;
+
+        for voice in self.voices.iter_mut().filter(|voice| voice.playing) {
+            if !voice.mix_into(acc, master) {
+                voice.stop();
+                voice.finished = matches!(voice.reader, Reader::Stream { .. });
+            }
+        }
+
+        self.voices.retain(|voice| !voice.finished);
+
+        for (sample, &mixed) in out.iter_mut().zip(acc.iter()) {
+            *sample = clamp(mixed, i16::MIN as i32, i16::MAX as i32) as i16;
+        }
+
+        for sample in &mut out[len..] {
+            *sample = 0;
+        }
+    }
+}
+
+impl Default for MixCore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn clamp<T: PartialOrd>(value: T, low: T, high: T) -> T {
+    if value < low {
+        low
+    } else if value > high {
+        high
+    } else {
+        value
+    }
+}
+
+fn min(a: f32, b: f32) -> f32 {
+    if a < b {
+        a
+    } else {
+        b
+    }
+}
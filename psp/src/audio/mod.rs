@@ -0,0 +1,318 @@
+//! Software audio mixing on top of the hardware channels.
+//!
+//! The PSP has 8 hardware channels and no mixing. A `Mixer` reserves one
+//! channel and feeds it from a high priority thread, mixing any number of
+//! software voices into 44.1 kHz stereo. Voices play either PCM data held in
+//! memory or a stream pulled from a `Sink`, each with its own volume, pan and
+//! pitch.
+//!
+//! The mixing itself happens in `MixCore`, which does not touch the hardware
+//! and can be used on its own.
+//!
+//! Compressed music is decoded by `Mp3Player` and `AtracPlayer`, which can
+//! play on their own hardware channel or be streamed through the mixer.
+//!
+//! The microphone is recorded by a `Recorder`, and `WavWriter` saves
+//! recordings as WAV files.
+//!
+//! # Example
+//!
+//! ```ignore
+//! use psp::audio::{Channels, Mixer, Pcm};
+//!
+//! let mixer = Mixer::new()?;
+//! let square: Vec<i16> = (0..22050).map(|i| if i & 64 == 0 { 8000 } else { -8000 }).collect();
+//!
+//! let id = mixer.play(Pcm::new(square, Channels::Mono, 22050));
+//! if let Some(voice) = mixer.lock().voice_mut(id) {
+//!     voice.set_pan(-0.5);
+//! }
+//! # Ok::<(), psp::Error>(())
+//! ```
+
+pub mod at3;
+pub mod atrac;
+pub mod input;
+pub mod mix;
+pub mod mp3;
+
+pub use atrac::AtracPlayer;
+pub use input::{Recorder, RecorderConfig, SampleRing, WavWriter};
+pub use mix::{Channels, MixCore, Pcm, Sink, Source, Voice, VoiceId, OUTPUT_RATE};
+pub use mp3::Mp3Player;
+
+pub(crate) use crate::io::read_full;
+
+use crate::error::{self, Result};
+use crate::io;
+use crate::sync::{critical_section, Mutex, MutexGuard};
+use crate::sys::{self, AudioFormat, AudioOutputFrequency};
+use crate::thread::{self, SharedThreads};
+use alloc::alloc::{alloc_zeroed, handle_alloc_error, Layout};
+use alloc::boxed::Box;
+use core::cell::UnsafeCell;
+use core::ffi::c_void;
+
+/// Number of frames sent to the hardware at once, about 23 ms.
+pub const BUFFER_FRAMES: usize = 1024;
+
+/// Default priority of the output thread, above the main thread.
+pub const DEFAULT_PRIORITY: i32 = 16;
+
+/// State shared between a `Mixer` and its output thread.
+struct Shared {
+    core: Mutex<MixCore>,
+    channel: i32,
+    /// Only accessed in a critical section.
+    running: UnsafeCell<bool>,
+}
+
+// `running` is only accessed in a critical section.
+unsafe impl Sync for Shared {}
+
+impl Shared {
+    fn running(&self) -> bool {
+        critical_section(|| unsafe { *self.running.get() })
+    }
+}
+
+/// Mixes software voices into a hardware audio channel.
+///
+/// Dropping the mixer stops its thread and releases the channel.
+pub struct Mixer {
+    shared: SharedThreads<Shared>,
+}
+
+unsafe impl Send for Mixer {}
+unsafe impl Sync for Mixer {}
+
+impl Mixer {
+    /// Reserve a hardware channel and start mixing.
+    pub fn new() -> Result<Self> {
+        Self::with_priority(DEFAULT_PRIORITY)
+    }
+
+    /// Like `new`, with a custom priority for the output thread. Lower values
+    /// mean higher priority.
+    pub fn with_priority(priority: i32) -> Result<Self> {
+        let channel = error::check(unsafe {
+            sys::sceAudioChReserve(
+                sys::AUDIO_NEXT_CHANNEL,
+                BUFFER_FRAMES as i32,
+                AudioFormat::Stereo,
+            )
+        })?;
+
+        let mut shared = SharedThreads::new(Shared {
+            core: Mutex::new(MixCore::new()),
+            channel,
+            running: UnsafeCell::new(true),
+        });
+
+        let builder = thread::Builder::new()
+            .name("psp_audio_mixer")
+            .priority(priority)
+            .stack_size(16 * 1024);
+
+        if let Err(e) = shared.spawn(builder, output_thread) {
+            unsafe { sys::sceAudioChRelease(channel) };
+
+            return Err(e);
+        }
+
+        Ok(Self { shared })
+    }
+
+    /// Lock the mixer to add, remove or change voices.
+    ///
+    /// Mixing waits while the lock is held, so keep it short.
+    pub fn lock(&self) -> MutexGuard<'_, MixCore> {
+        self.shared.state().core.lock()
+    }
+
+    /// Add a voice playing `source` and start it.
+    pub fn play(&self, source: impl Into<Source>) -> VoiceId {
+        self.lock().play(source)
+    }
+
+    /// Stream `sink` through a new voice, which is removed when it ends.
+    pub fn stream(&self, sink: impl Sink + 'static) -> VoiceId {
+        self.lock().play(Box::new(sink) as Box<dyn Sink>)
+    }
+
+    /// The hardware channel used by the mixer.
+    pub fn channel(&self) -> i32 {
+        self.shared.state().channel
+    }
+}
+
+impl Drop for Mixer {
+    fn drop(&mut self) {
+        let running = self.shared.state().running.get();
+        critical_section(|| unsafe { *running = false });
+        self.shared.join();
+
+        unsafe {
+            let channel = self.shared.state().channel;
+
+            // The channel cannot be released while it still plays.
+            while sys::sceAudioGetChannelRestLen(channel) > 0 {
+                sys::sceKernelDelayThread(1000);
+            }
+
+            sys::sceAudioChRelease(channel);
+        }
+    }
+}
+
+fn output_thread(shared: &Shared) {
+    // The hardware reads a buffer while the next one is mixed.
+    let mut buffers = [
+        alloc::vec![0i16; BUFFER_FRAMES * 2],
+        alloc::vec![0i16; BUFFER_FRAMES * 2],
+    ];
+    let mut index = 0;
+
+    while shared.running() {
+        let buf = &mut buffers[index];
+        shared.core.lock().mix(buf);
+
+        unsafe {
+            sys::sceAudioOutputBlocking(
+                shared.channel,
+                sys::AUDIO_VOLUME_MAX as i32,
+                buf.as_mut_ptr() as *mut c_void,
+            );
+        }
+
+        index ^= 1;
+    }
+}
+
+/// Allocate a zeroed `T` directly on the heap, keeping large decoder buffers
+/// off the stack.
+///
+/// # Safety
+///
+/// All zeroes must be a valid `T`.
+pub(crate) unsafe fn zeroed_box<T>() -> Box<T> {
+    let layout = Layout::new::<T>();
+    let ptr = alloc_zeroed(layout) as *mut T;
+
+    if ptr.is_null() {
+        handle_alloc_error(layout);
+    }
+
+    Box::from_raw(ptr)
+}
+
+/// A decoder whose output `Sink::read` copies out a block at a time.
+pub(crate) trait BlockDecoder {
+    /// Decode the next block, returning its range in `pcm`. An empty range
+    /// ends the stream.
+    fn decode_next(&mut self) -> io::Result<(usize, usize)>;
+
+    /// The buffer blocks are decoded into.
+    fn pcm(&self) -> &[i16];
+
+    /// The decoded samples not yet returned, as a range of `pcm`.
+    fn pending(&mut self) -> &mut (usize, usize);
+}
+
+/// Fill `buf` with interleaved stereo from `decoder`, decoding blocks as
+/// needed, and return the number of frames written. This is `Sink::read`
+/// for the decoders.
+pub(crate) fn read_blocks(decoder: &mut impl BlockDecoder, buf: &mut [i16]) -> usize {
+    let mut written = 0;
+
+    while written + 1 < buf.len() {
+        let (start, end) = *decoder.pending();
+
+        if start >= end {
+            match decoder.decode_next() {
+                Ok(range) if range.0 < range.1 => *decoder.pending() = range,
+                _ => break,
+            }
+
+            continue;
+        }
+
+        let len = core::cmp::min(end - start, (buf.len() - written) & !1);
+        buf[written..written + len].copy_from_slice(&decoder.pcm()[start..start + len]);
+
+        decoder.pending().0 += len;
+        written += len;
+    }
+
+    written / 2
+}
+
+/// Play interleaved stereo from `decode` on the SRC channel, which resamples
+/// in hardware, until it returns an empty slice.
+///
+/// `frames` is the most frames `decode` returns at once.
+pub(crate) fn play_src<T>(
+    state: &mut T,
+    sample_rate: u32,
+    frames: usize,
+    mut decode: impl FnMut(&mut T) -> io::Result<&[i16]>,
+) -> io::Result<()> {
+    let frequency = match sample_rate {
+        48000 => AudioOutputFrequency::Khz48,
+        44100 => AudioOutputFrequency::Khz44_1,
+        32000 => AudioOutputFrequency::Khz32,
+        24000 => AudioOutputFrequency::Khz24,
+        22050 => AudioOutputFrequency::Khz22_05,
+        16000 => AudioOutputFrequency::Khz16,
+        12000 => AudioOutputFrequency::Khz12,
+        11025 => AudioOutputFrequency::Khz11_025,
+        8000 => AudioOutputFrequency::Khz8,
+        _ => return Err(error::SCE_ERROR_ERRNO_EINVAL.into()),
+    };
+
+    let frames = sys::audio_sample_align(frames as i32);
+    error::check(unsafe { sys::sceAudioSRCChReserve(frames, frequency, 2) })?;
+
+    // The hardware reads a buffer while the next one is decoded.
+    let mut buffers = [
+        alloc::vec![0i16; frames as usize * 2],
+        alloc::vec![0i16; frames as usize * 2],
+    ];
+    let mut index = 0;
+
+    let result = loop {
+        let buf = &mut buffers[index];
+
+        match decode(state) {
+            Ok(pcm) if pcm.is_empty() => break Ok(()),
+            Ok(pcm) => {
+                let len = core::cmp::min(pcm.len(), buf.len());
+                buf[..len].copy_from_slice(&pcm[..len]);
+
+                for sample in &mut buf[len..] {
+                    *sample = 0;
+                }
+            }
+            Err(e) => break Err(e),
+        }
+
+        unsafe {
+            sys::sceAudioSRCOutputBlocking(
+                sys::AUDIO_VOLUME_MAX as i32,
+                buf.as_mut_ptr() as *mut c_void,
+            );
+        }
+
+        index ^= 1;
+    };
+
+    unsafe {
+        while sys::sceAudioOutput2GetRestSample() > 0 {
+            sys::sceKernelDelayThread(1000);
+        }
+
+        sys::sceAudioSRCChRelease();
+    }
+
+    result
+}
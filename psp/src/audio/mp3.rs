@@ -0,0 +1,346 @@
+//! MP3 decoding with the `sceMp3` library.
+
+use super::{play_src, read_blocks, read_full, zeroed_box, BlockDecoder, Channels, Sink};
+use crate::error::{self, Error};
+use crate::io::{self, Read, Seek, SeekFrom};
+use crate::modules::ModuleGuard;
+use crate::sync::critical_section;
+use crate::sys::{self, AvModule, Handle, SceMp3InitArg};
+use alloc::boxed::Box;
+use core::{ffi::c_void, fmt, ptr, slice};
+
+/// Size of the buffer the decoder reads MP3 data from.
+const STREAM_BUF_SIZE: usize = 16 * 1024;
+
+/// Size of the decoded PCM buffer, in samples. Holds four 1152-frame blocks.
+const PCM_BUF_SAMPLES: usize = 4 * 1152 * 2;
+
+/// Buffers handed to the decoder, which wants them 64-byte aligned.
+#[repr(C, align(64))]
+struct Buffers {
+    stream: [u8; STREAM_BUF_SIZE],
+    pcm: [i16; PCM_BUF_SAMPLES],
+}
+
+/// Number of players alive, which share the `sceMp3` resources.
+static mut PLAYERS: u32 = 0;
+
+/// Set while the resources are being initialized or terminated, so that
+/// other players wait rather than use them half set up.
+static mut RESOURCE_BUSY: bool = false;
+
+/// Streams and decodes an MP3 file.
+///
+/// The data is pulled from any `Read + Seek` source as decoding goes, so
+/// only a small buffer is kept in memory. A leading ID3v2 tag is skipped.
+/// Decoded audio is always 16-bit stereo.
+///
+/// The player can be played on the SRC hardware channel with `play`, or
+/// handed to a `Mixer` with `Mixer::stream` as it implements `Sink`.
+///
+/// # Example
+///
+/// ```ignore
+/// use psp::audio::Mp3Player;
+/// use psp::fs::File;
+///
+/// let mut player = Mp3Player::new(File::open("ms0:/MUSIC/song.mp3")?)?;
+/// player.set_loop_count(None)?;
+/// player.play()?;
+/// # Ok::<(), psp::io::Error>(())
+/// ```
+pub struct Mp3Player<R> {
+    reader: R,
+    handle: Handle,
+    buffers: Box<Buffers>,
+    /// Decoded samples not yet returned by `Sink::read`, as a range of
+    /// `buffers.pcm`.
+    pending: (usize, usize),
+    sample_rate: u32,
+    bitrate: u32,
+    channels: u32,
+    max_frames: usize,
+    finished: bool,
+    _modules: ModuleGuard,
+}
+
+impl<R: Read + Seek> Mp3Player<R> {
+    /// Load the MP3 modules and prepare to decode `reader`.
+    pub fn new(mut reader: R) -> io::Result<Self> {
+        let modules = ModuleGuard::load(&[AvModule::Mp3.into()])?;
+
+        reader.seek(SeekFrom::Start(0))?;
+        let start = id3_size(&mut reader)?;
+        let end = reader.seek(SeekFrom::End(0))?;
+        let mut buffers = unsafe { zeroed_box::<Buffers>() };
+
+        let mut init = SceMp3InitArg {
+            mp3_stream_start: start as u32,
+            unk1: 0,
+            mp3_stream_end: end as u32,
+            unk2: 0,
+            mp3_buf: buffers.stream.as_mut_ptr() as *mut c_void,
+            mp3_buf_size: STREAM_BUF_SIZE as i32,
+            pcm_buf: buffers.pcm.as_mut_ptr() as *mut c_void,
+            pcm_buf_size: (PCM_BUF_SAMPLES * 2) as i32,
+        };
+
+        init_resource()?;
+        let handle = match error::check(unsafe { sys::sceMp3ReserveMp3Handle(&mut init) }) {
+            Ok(id) => Handle(id),
+            Err(e) => {
+                term_resource();
+                return Err(e.into());
+            }
+        };
+
+        // From here on, dropping `player` releases the handle.
+        let mut player = Self {
+            reader,
+            handle,
+            buffers,
+            pending: (0, 0),
+            sample_rate: 0,
+            bitrate: 0,
+            channels: 0,
+            max_frames: 0,
+            finished: false,
+            _modules: modules,
+        };
+
+        player.fill()?;
+        error::check(unsafe { sys::sceMp3Init(handle) })?;
+
+        unsafe {
+            player.sample_rate = error::check(sys::sceMp3GetSamplingRate(handle))? as u32;
+            player.bitrate = error::check(sys::sceMp3GetBitRate(handle))? as u32;
+            player.channels = error::check(sys::sceMp3GetMp3ChannelNum(handle))? as u32;
+            player.max_frames = error::check(sys::sceMp3GetMaxOutputSample(handle))? as usize;
+        }
+
+        Ok(player)
+    }
+
+    /// The sample rate of the stream, in Hz.
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    /// The bitrate of the stream, in kbit/s.
+    pub fn bitrate(&self) -> u32 {
+        self.bitrate
+    }
+
+    /// The number of channels in the MP3 data. Decoded audio is stereo
+    /// either way.
+    pub fn channels(&self) -> u32 {
+        self.channels
+    }
+
+    /// The number of frames decoded so far.
+    pub fn decoded_frames(&self) -> Result<u32, Error> {
+        error::check(unsafe { sys::sceMp3GetSumDecodedSample(self.handle) }).map(|n| n as u32)
+    }
+
+    /// Set how many times the stream restarts when it ends: `Some(0)` plays
+    /// it once, `None` loops forever.
+    pub fn set_loop_count(&mut self, count: Option<u32>) -> Result<(), Error> {
+        let count = count.map(|n| n as i32).unwrap_or(-1);
+        error::check(unsafe { sys::sceMp3SetLoopNum(self.handle, count) })?;
+        self.finished = false;
+
+        Ok(())
+    }
+
+    /// Restart decoding from the beginning of the stream.
+    pub fn rewind(&mut self) -> Result<(), Error> {
+        error::check(unsafe { sys::sceMp3ResetPlayPosition(self.handle) })?;
+        self.pending = (0, 0);
+        self.finished = false;
+
+        Ok(())
+    }
+
+    /// Decode the next block of interleaved stereo samples. Returns an empty
+    /// slice at the end of the stream.
+    pub fn decode(&mut self) -> io::Result<&[i16]> {
+        let (start, end) = self.decode_block()?;
+
+        Ok(&self.buffers.pcm[start..end])
+    }
+
+    /// Play the rest of the stream on the SRC hardware channel, blocking
+    /// until it ends.
+    pub fn play(&mut self) -> io::Result<()> {
+        let (sample_rate, frames) = (self.sample_rate, self.max_frames);
+
+        play_src(self, sample_rate, frames, Self::decode)
+    }
+
+    /// Decode a block into `buffers.pcm`, returning its range.
+    fn decode_block(&mut self) -> io::Result<(usize, usize)> {
+        if self.finished {
+            return Ok((0, 0));
+        }
+
+        self.fill()?;
+
+        let mut out: *mut i16 = ptr::null_mut();
+        let ret = unsafe { sys::sceMp3Decode(self.handle, &mut out) };
+
+        if ret == 0 || ret == error::SCE_MP3_ERROR_END_OF_STREAM.raw() {
+            self.finished = true;
+            return Ok((0, 0));
+        }
+
+        let len = error::check(ret)? as usize / 2;
+        let start = (out as usize - self.buffers.pcm.as_ptr() as usize) / 2;
+        let end = core::cmp::min(start + len, PCM_BUF_SAMPLES);
+
+        Ok((start, end))
+    }
+
+    /// Copy more MP3 data into the stream buffer if the decoder needs it.
+    fn fill(&mut self) -> io::Result<()> {
+        if unsafe { sys::sceMp3CheckStreamDataNeeded(self.handle) } <= 0 {
+            return Ok(());
+        }
+
+        let mut dst: *mut u8 = ptr::null_mut();
+        let mut len = 0;
+        let mut pos = 0;
+        error::check(unsafe {
+            sys::sceMp3GetInfoToAddStreamData(self.handle, &mut dst, &mut len, &mut pos)
+        })?;
+
+        self.reader.seek(SeekFrom::Start(pos as u32 as u64))?;
+
+        // `dst` points into `buffers.stream`.
+        let buf = unsafe { slice::from_raw_parts_mut(dst, len.max(0) as usize) };
+        let n = read_full(&mut self.reader, buf)?;
+        error::check(unsafe { sys::sceMp3NotifyAddStreamData(self.handle, n as i32) })?;
+
+        Ok(())
+    }
+}
+
+impl<R: Read + Seek + Send> Sink for Mp3Player<R> {
+    fn channels(&self) -> Channels {
+        Channels::Stereo
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    fn read(&mut self, buf: &mut [i16]) -> usize {
+        read_blocks(self, buf)
+    }
+}
+
+impl<R: Read + Seek> BlockDecoder for Mp3Player<R> {
+    fn decode_next(&mut self) -> io::Result<(usize, usize)> {
+        self.decode_block()
+    }
+
+    fn pcm(&self) -> &[i16] {
+        &self.buffers.pcm
+    }
+
+    fn pending(&mut self) -> &mut (usize, usize) {
+        &mut self.pending
+    }
+}
+
+impl<R> fmt::Debug for Mp3Player<R> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Mp3Player")
+            .field("handle", &self.handle)
+            .field("sample_rate", &self.sample_rate)
+            .field("bitrate", &self.bitrate)
+            .field("channels", &self.channels)
+            .finish()
+    }
+}
+
+impl<R> Drop for Mp3Player<R> {
+    fn drop(&mut self) {
+        release(self.handle);
+    }
+}
+
+fn release(handle: Handle) {
+    unsafe {
+        sys::sceMp3ReleaseMp3Handle(handle);
+    }
+
+    term_resource();
+}
+
+fn init_resource() -> Result<(), Error> {
+    loop {
+        let first = critical_section(|| unsafe {
+            if RESOURCE_BUSY {
+                return None;
+            }
+
+            PLAYERS += 1;
+            RESOURCE_BUSY = PLAYERS == 1;
+            Some(RESOURCE_BUSY)
+        });
+
+        match first {
+            Some(true) => break,
+            Some(false) => return Ok(()),
+            None => unsafe {
+                sys::sceKernelDelayThread(1000);
+            },
+        }
+    }
+
+    // `RESOURCE_BUSY` is set, so other players wait for this to finish.
+    let result = error::check(unsafe { sys::sceMp3InitResource() });
+
+    critical_section(|| unsafe {
+        if result.is_err() {
+            PLAYERS -= 1;
+        }
+        RESOURCE_BUSY = false;
+    });
+
+    result.map(drop)
+}
+
+fn term_resource() {
+    let last = critical_section(|| unsafe {
+        PLAYERS -= 1;
+        RESOURCE_BUSY = PLAYERS == 0;
+        RESOURCE_BUSY
+    });
+
+    if last {
+        unsafe {
+            sys::sceMp3TermResource();
+        }
+
+        critical_section(|| unsafe { RESOURCE_BUSY = false });
+    }
+}
+
+/// The size of the ID3v2 tag at the start of `reader`, or 0 if there is none.
+///
+/// This is where the MP3 data starts. `reader` is left after the tag header.
+pub fn id3_size(reader: &mut impl Read) -> io::Result<u64> {
+    let mut header = [0; 10];
+
+    if read_full(reader, &mut header)? < header.len() || &header[..3] != b"ID3" {
+        return Ok(0);
+    }
+
+    // The size is a 28-bit "syncsafe" integer, excluding the header and the
+    // optional footer.
+    let size = header[6..].iter().fold(0, |size, &b| (size << 7) | (b & 0x7f) as u64);
+    let footer = if header[5] & 0x10 != 0 { 10 } else { 0 };
+
+    Ok(10 + size + footer)
+}
@@ -0,0 +1,546 @@
+//! System utility dialogs: messages, the on-screen keyboard and network
+//! configuration.
+//!
+//! Each dialog can be shown in two ways:
+//!
+//! * `show` blocks until the dialog closes, calling a closure once per frame.
+//! * `start` opens the dialog, after which `update` must be called once per
+//!   frame until it returns the result. This fits into an existing main loop.
+//!
+//! Utility dialogs draw on top of the current frame, so the application must
+//! keep rendering while one is open: each frame it must draw and finish a GU
+//! frame, wait for vblank and swap buffers.
+//!
+//! The dialog language and the button used to accept are taken from the
+//! system settings, see `psp::system`.
+//!
+//! # Example
+//!
+//! ```ignore
+//! use psp::dialog::{MessageDialog, Response};
+//!
+//! # fn render_frame() {}
+//! let answer = MessageDialog::new("Overwrite the save?")
+//!     .yes_no()
+//!     .show(render_frame)?;
+//!
+//! if answer == Response::Yes {
+//!     // ...
+//! }
+//! # Ok::<(), psp::Error>(())
+//! ```
+
+use crate::error::{self, Error, Result};
+use crate::sys::{
+    self, SceUtilityOskData, SceUtilityOskInputLanguage, SceUtilityOskInputType,
+    SceUtilityOskParams, SceUtilityOskResult, SceUtilityOskState, SystemParamLanguage,
+    UtilityDialogCommon, UtilityMsgDialogMode, UtilityMsgDialogOption, UtilityMsgDialogParams,
+    UtilityMsgDialogPressed, UtilityNetconfAction, UtilityNetconfAdhoc, UtilityNetconfData,
+};
+use crate::system::{self, ConfirmButton};
+use alloc::boxed::Box;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::{mem, ptr};
+
+// Values returned by the `sceUtility*GetStatus` functions.
+const STATUS_NONE: i32 = 0;
+const STATUS_VISIBLE: i32 = 2;
+const STATUS_QUIT: i32 = 3;
+
+/// The common header of a dialog parameter struct of type `T`.
+pub(crate) fn common<T>() -> UtilityDialogCommon {
+    UtilityDialogCommon {
+        size: mem::size_of::<T>() as u32,
+        language: system::language().unwrap_or(SystemParamLanguage::English),
+        button_accept: system::confirm_button()
+            .unwrap_or(ConfirmButton::Cross)
+            .into(),
+        // Thread priorities used by the pspsdk samples.
+        graphics_thread: 0x11,
+        access_thread: 0x13,
+        font_thread: 0x12,
+        sound_thread: 0x10,
+        result: 0,
+        reserved: [0; 4],
+    }
+}
+
+/// The status functions of one kind of utility dialog.
+pub(crate) struct Utility {
+    pub(crate) status: fn() -> i32,
+    pub(crate) update: fn(),
+    pub(crate) shutdown: fn(),
+}
+
+/// Drives a utility dialog from start to shutdown.
+pub(crate) struct Pump {
+    utility: &'static Utility,
+    running: bool,
+}
+
+impl Pump {
+    pub(crate) const fn new(utility: &'static Utility) -> Self {
+        Self {
+            utility,
+            running: false,
+        }
+    }
+
+    pub(crate) fn start(&mut self, init: impl FnOnce() -> i32) -> Result<()> {
+        if self.running {
+            return Err(error::SCE_ERROR_ERRNO_EBUSY);
+        }
+
+        error::check(init())?;
+        self.running = true;
+
+        Ok(())
+    }
+
+    /// Advance the dialog by a frame. Returns `true` once it has shut down.
+    pub(crate) fn poll(&mut self) -> Result<bool> {
+        if !self.running {
+            return Err(error::SCE_ERROR_ERRNO_EINVAL);
+        }
+
+        match error::check((self.utility.status)())? {
+            STATUS_VISIBLE => (self.utility.update)(),
+            STATUS_QUIT => (self.utility.shutdown)(),
+            STATUS_NONE => {
+                self.running = false;
+                return Ok(true);
+            }
+            _ => (),
+        }
+
+        Ok(false)
+    }
+
+    pub(crate) fn run(&mut self, mut render_frame: impl FnMut()) -> Result<()> {
+        while !self.poll()? {
+            render_frame();
+        }
+
+        Ok(())
+    }
+}
+
+impl Drop for Pump {
+    /// Close a dialog which is still open, so that the parameters it points
+    /// to can be freed.
+    fn drop(&mut self) {
+        let mut shutdown = false;
+
+        while self.running {
+            match (self.utility.status)() {
+                STATUS_VISIBLE | STATUS_QUIT if !shutdown => {
+                    (self.utility.shutdown)();
+                    shutdown = true;
+                }
+                STATUS_VISIBLE => (self.utility.update)(),
+                s if s <= STATUS_NONE => self.running = false,
+                _ => (),
+            }
+
+            if self.running {
+                let _ = crate::display::wait_vblank_start();
+            }
+        }
+    }
+}
+
+static MESSAGE: Utility = Utility {
+    status: || unsafe { sys::sceUtilityMsgDialogGetStatus() },
+    update: || unsafe { sys::sceUtilityMsgDialogUpdate(1) },
+    shutdown: || unsafe { sys::sceUtilityMsgDialogShutdownStart() },
+};
+
+static KEYBOARD: Utility = Utility {
+    status: || unsafe { sys::sceUtilityOskGetStatus() },
+    update: || unsafe {
+        sys::sceUtilityOskUpdate(1);
+    },
+    shutdown: || unsafe {
+        sys::sceUtilityOskShutdownStart();
+    },
+};
+
+static NETCONF: Utility = Utility {
+    status: || unsafe { sys::sceUtilityNetconfGetStatus() },
+    update: || unsafe {
+        sys::sceUtilityNetconfUpdate(1);
+    },
+    shutdown: || unsafe {
+        sys::sceUtilityNetconfShutdownStart();
+    },
+};
+
+/// The button used to close a `MessageDialog`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Response {
+    /// The accept button, or "Yes" in a yes/no dialog.
+    Yes,
+    /// "No" in a yes/no dialog.
+    No,
+    /// The cancel button.
+    Back,
+}
+
+/// A message or error dialog.
+///
+/// The dialog is closed if the value is dropped while it is open.
+pub struct MessageDialog {
+    params: Box<UtilityMsgDialogParams>,
+    pump: Pump,
+}
+
+impl MessageDialog {
+    /// A dialog showing `message`. It is truncated to 511 bytes.
+    pub fn new(message: &str) -> Self {
+        let mut text = [0; 512];
+        let mut len = core::cmp::min(message.len(), text.len() - 1);
+        while !message.is_char_boundary(len) {
+            len -= 1;
+        }
+        text[..len].copy_from_slice(&message.as_bytes()[..len]);
+
+        Self::with_params(UtilityMsgDialogMode::Text, 0, text, UtilityMsgDialogOption::TEXT)
+    }
+
+    /// A dialog showing the system's description of `error`.
+    pub fn error(error: Error) -> Self {
+        Self::with_params(
+            UtilityMsgDialogMode::Error,
+            error.raw() as u32,
+            [0; 512],
+            UtilityMsgDialogOption::ERROR,
+        )
+    }
+
+    fn with_params(
+        mode: UtilityMsgDialogMode,
+        error_value: u32,
+        message: [u8; 512],
+        options: UtilityMsgDialogOption,
+    ) -> Self {
+        Self {
+            params: Box::new(UtilityMsgDialogParams {
+                base: common::<UtilityMsgDialogParams>(),
+                unknown: 0,
+                mode,
+                error_value,
+                message,
+                options,
+                button_pressed: UtilityMsgDialogPressed::Unknown1,
+            }),
+            pump: Pump::new(&MESSAGE),
+        }
+    }
+
+    /// Show "Yes" and "No" buttons instead of a single "OK".
+    pub fn yes_no(mut self) -> Self {
+        self.params.options |= UtilityMsgDialogOption::YES_NO_BUTTONS;
+        self
+    }
+
+    /// Select "No" initially in a yes/no dialog.
+    pub fn default_no(mut self) -> Self {
+        self.params.options |= UtilityMsgDialogOption::DEFAULT_NO;
+        self
+    }
+
+    /// Open the dialog. Call `update` once per frame until it returns the
+    /// response.
+    pub fn start(&mut self) -> Result<()> {
+        let params = &mut *self.params;
+        self.pump.start(|| unsafe { sys::sceUtilityMsgDialogInitStart(params) })
+    }
+
+    /// Advance the open dialog by a frame, returning the response once it has
+    /// closed.
+    pub fn update(&mut self) -> Result<Option<Response>> {
+        if !self.pump.poll()? {
+            return Ok(None);
+        }
+
+        Ok(Some(self.response()))
+    }
+
+    /// Open the dialog and block until it closes, calling `render_frame` once
+    /// per frame.
+    pub fn show(mut self, render_frame: impl FnMut()) -> Result<Response> {
+        self.start()?;
+        self.pump.run(render_frame)?;
+
+        Ok(self.response())
+    }
+
+    fn response(&self) -> Response {
+        match self.params.button_pressed {
+            UtilityMsgDialogPressed::No => Response::No,
+            UtilityMsgDialogPressed::Back => Response::Back,
+            _ => Response::Yes,
+        }
+    }
+}
+
+/// Owned buffers of an on-screen keyboard, which the system writes into while
+/// it is open.
+struct KeyboardState {
+    params: SceUtilityOskParams,
+    data: SceUtilityOskData,
+    description: Vec<u16>,
+    initial_text: Vec<u16>,
+    output: Vec<u16>,
+}
+
+/// The on-screen keyboard, for entering a single line or block of text.
+///
+/// The keyboard is closed if the value is dropped while it is open.
+pub struct Keyboard {
+    state: Box<KeyboardState>,
+    pump: Pump,
+}
+
+impl Keyboard {
+    /// A keyboard accepting up to 64 characters on one line.
+    pub fn new() -> Self {
+        let state = Box::new(KeyboardState {
+            params: SceUtilityOskParams {
+                base: common::<SceUtilityOskParams>(),
+                datacount: 1,
+                data: ptr::null_mut(),
+                state: SceUtilityOskState::None,
+                unk_60: 0,
+            },
+            data: SceUtilityOskData {
+                unk_00: 0,
+                unk_04: 0,
+                language: SceUtilityOskInputLanguage::Default,
+                unk_12: 0,
+                inputtype: SceUtilityOskInputType::All,
+                lines: 1,
+                unk_24: 0,
+                desc: ptr::null_mut(),
+                intext: ptr::null_mut(),
+                outtextlength: 0,
+                outtext: ptr::null_mut(),
+                result: SceUtilityOskResult::Unchanged,
+                outtextlimit: 0,
+            },
+            description: alloc::vec![0],
+            initial_text: alloc::vec![0],
+            output: Vec::new(),
+        });
+
+        Self {
+            state,
+            pump: Pump::new(&KEYBOARD),
+        }
+        .max_len(64)
+    }
+
+    /// Set the text describing what to enter.
+    pub fn description(mut self, description: &str) -> Self {
+        self.state.description = utf16(description);
+        self
+    }
+
+    /// Set the text the keyboard starts with.
+    pub fn initial_text(mut self, text: &str) -> Self {
+        self.state.initial_text = utf16(text);
+        self
+    }
+
+    /// Set the maximum number of characters which can be entered.
+    pub fn max_len(mut self, len: usize) -> Self {
+        self.state.output = alloc::vec![0; len + 1];
+        self
+    }
+
+    /// Set the number of lines of the input field.
+    pub fn lines(mut self, lines: i32) -> Self {
+        self.state.data.lines = lines;
+        self
+    }
+
+    /// Restrict the characters which can be entered.
+    pub fn input_type(mut self, input_type: SceUtilityOskInputType) -> Self {
+        self.state.data.inputtype = input_type;
+        self
+    }
+
+    /// Set the keyboard layout. The default follows the system language.
+    pub fn language(mut self, language: SceUtilityOskInputLanguage) -> Self {
+        self.state.data.language = language;
+        self
+    }
+
+    /// Open the keyboard. Call `update` once per frame until it returns the
+    /// result.
+    pub fn start(&mut self) -> Result<()> {
+        let state = &mut *self.state;
+
+        state.data.desc = state.description.as_mut_ptr();
+        state.data.intext = state.initial_text.as_mut_ptr();
+        state.data.outtext = state.output.as_mut_ptr();
+        state.data.outtextlength = state.output.len() as i32;
+        state.data.outtextlimit = state.output.len() as i32 - 1;
+        state.data.result = SceUtilityOskResult::Unchanged;
+        state.params.data = &mut state.data;
+
+        let params = &mut state.params;
+        self.pump.start(|| unsafe { sys::sceUtilityOskInitStart(params) })
+    }
+
+    /// Advance the open keyboard by a frame. Once it has closed, returns the
+    /// entered text, or `None` if the user cancelled.
+    pub fn update(&mut self) -> Result<Option<Option<String>>> {
+        if !self.pump.poll()? {
+            return Ok(None);
+        }
+
+        Ok(Some(self.result()))
+    }
+
+    /// Open the keyboard and block until it closes, calling `render_frame`
+    /// once per frame. Returns `None` if the user cancelled.
+    pub fn show(mut self, render_frame: impl FnMut()) -> Result<Option<String>> {
+        self.start()?;
+        self.pump.run(render_frame)?;
+
+        Ok(self.result())
+    }
+
+    fn result(&self) -> Option<String> {
+        if self.state.data.result == SceUtilityOskResult::Cancelled {
+            return None;
+        }
+
+        let output = &self.state.output;
+        let len = output.iter().position(|&c| c == 0).unwrap_or(output.len());
+
+        Some(String::from_utf16_lossy(&output[..len]))
+    }
+}
+
+impl Default for Keyboard {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn utf16(s: &str) -> Vec<u16> {
+    s.encode_utf16().chain(core::iter::once(0)).collect()
+}
+
+/// Owned parameters of a netconf dialog.
+struct NetconfState {
+    data: UtilityNetconfData,
+    adhoc: UtilityNetconfAdhoc,
+}
+
+/// The network configuration dialog, which connects to an access point or
+/// ad-hoc lobby with the system UI.
+///
+/// The networking modules must already be initialized, for example by a
+/// `NetworkStack`. The dialog is closed if the value is dropped while it is
+/// open.
+pub struct NetconfDialog {
+    state: Box<NetconfState>,
+    pump: Pump,
+}
+
+impl NetconfDialog {
+    fn with_action(action: UtilityNetconfAction) -> Self {
+        Self {
+            state: Box::new(NetconfState {
+                data: UtilityNetconfData {
+                    base: common::<UtilityNetconfData>(),
+                    action,
+                    adhocparam: ptr::null_mut(),
+                    hotspot: 0,
+                    hotspot_connected: 0,
+                    wifisp: 0,
+                },
+                adhoc: UtilityNetconfAdhoc {
+                    name: [0; 8],
+                    timeout: 0,
+                },
+            }),
+            pump: Pump::new(&NETCONF),
+        }
+    }
+
+    /// Let the user pick an access point profile and connect to it.
+    pub fn connect() -> Self {
+        Self::with_action(UtilityNetconfAction::ConnectAP)
+    }
+
+    /// Show the status of the current access point connection.
+    pub fn status() -> Self {
+        Self::with_action(UtilityNetconfAction::DisplayStatus)
+    }
+
+    /// Connect to the ad-hoc lobby called `name`, waiting up to `timeout`
+    /// seconds for it. Names longer than 8 bytes are truncated.
+    pub fn adhoc(name: &str, timeout: u32) -> Self {
+        let mut dialog = Self::with_action(UtilityNetconfAction::ConnectAdhoc);
+
+        let len = core::cmp::min(name.len(), 8);
+        dialog.state.adhoc.name[..len].copy_from_slice(&name.as_bytes()[..len]);
+        dialog.state.adhoc.timeout = timeout;
+
+        dialog
+    }
+
+    /// Allow hotspot connections, which require logging in with the browser.
+    pub fn hotspot(mut self, allowed: bool) -> Self {
+        self.state.data.hotspot = allowed as i32;
+        self
+    }
+
+    /// Open the dialog. Call `update` once per frame until it returns the
+    /// result.
+    pub fn start(&mut self) -> Result<()> {
+        let state = &mut *self.state;
+        state.data.adhocparam = &mut state.adhoc;
+
+        let data = &mut state.data;
+        self.pump.start(|| unsafe { sys::sceUtilityNetconfInitStart(data) })
+    }
+
+    /// Advance the open dialog by a frame. Once it has closed, returns `true`
+    /// if it completed, or `false` if the user cancelled.
+    pub fn update(&mut self) -> Result<Option<bool>> {
+        if !self.pump.poll()? {
+            return Ok(None);
+        }
+
+        self.result().map(Some)
+    }
+
+    /// Open the dialog and block until it closes, calling `render_frame` once
+    /// per frame. Returns `false` if the user cancelled.
+    pub fn show(mut self, render_frame: impl FnMut()) -> Result<bool> {
+        self.start()?;
+        self.pump.run(render_frame)?;
+
+        self.result()
+    }
+
+    /// Returns `true` if connected through a hotspot, once the dialog has
+    /// closed.
+    pub fn hotspot_connected(&self) -> bool {
+        self.state.data.hotspot_connected != 0
+    }
+
+    fn result(&self) -> Result<bool> {
+        match self.state.data.base.result {
+            0 => Ok(true),
+            1 => Ok(false),
+            e => Err(Error::from_raw(e)),
+        }
+    }
+}
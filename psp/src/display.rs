@@ -0,0 +1,82 @@
+//! Safe wrappers over the `sceDisplay*` functions.
+
+use crate::error::{self, Result};
+use crate::sys::{self, DisplayMode, DisplayPixelFormat, DisplaySetBufSync};
+use core::{ffi::c_void, ptr};
+
+/// The framebuffer currently being displayed.
+#[derive(Debug, Copy, Clone)]
+pub struct FrameBuf {
+    /// Address of the first pixel.
+    pub top_addr: *mut c_void,
+    /// Buffer width in pixels. Always a power of 2.
+    pub buffer_width: usize,
+    /// Pixel format of the framebuffer.
+    pub pixel_format: DisplayPixelFormat,
+}
+
+/// Set the display mode and resolution.
+pub fn set_mode(mode: DisplayMode, width: usize, height: usize) -> Result<()> {
+    error::check(unsafe { sys::sceDisplaySetMode(mode, width, height) } as i32).map(|_| ())
+}
+
+/// Get the current display mode as a `(mode, width, height)` triple.
+pub fn mode() -> Result<(i32, i32, i32)> {
+    let (mut mode, mut width, mut height) = (0, 0, 0);
+    error::check(unsafe { sys::sceDisplayGetMode(&mut mode, &mut width, &mut height) })?;
+
+    Ok((mode, width, height))
+}
+
+/// Display a framebuffer.
+///
+/// # Safety
+///
+/// `top_addr` must point to a buffer of at least `buffer_width * 272` pixels
+/// which stays valid for as long as it is displayed.
+pub unsafe fn set_frame_buf(
+    top_addr: *const u8,
+    buffer_width: usize,
+    pixel_format: DisplayPixelFormat,
+    sync: DisplaySetBufSync,
+) -> Result<()> {
+    let ret = sys::sceDisplaySetFrameBuf(top_addr, buffer_width, pixel_format, sync);
+    error::check(ret as i32).map(|_| ())
+}
+
+/// Get the framebuffer which is currently being displayed.
+pub fn frame_buf(sync: DisplaySetBufSync) -> Result<FrameBuf> {
+    let mut top_addr = ptr::null_mut();
+    let mut buffer_width = 0;
+    let mut pixel_format = DisplayPixelFormat::Psm8888;
+
+    error::check(unsafe {
+        sys::sceDisplayGetFrameBuf(&mut top_addr, &mut buffer_width, &mut pixel_format, sync)
+    })?;
+
+    Ok(FrameBuf {
+        top_addr,
+        buffer_width,
+        pixel_format,
+    })
+}
+
+/// Block until the start of the next vertical blank.
+pub fn wait_vblank_start() -> Result<()> {
+    error::check(unsafe { sys::sceDisplayWaitVblankStart() }).map(|_| ())
+}
+
+/// Like `wait_vblank_start`, running callbacks while waiting.
+pub fn wait_vblank_start_cb() -> Result<()> {
+    error::check(unsafe { sys::sceDisplayWaitVblankStartCB() }).map(|_| ())
+}
+
+/// The number of vertical blanks since boot.
+pub fn vcount() -> u32 {
+    unsafe { sys::sceDisplayGetVcount() }
+}
+
+/// Returns `true` if the display is currently in its vertical blank period.
+pub fn is_vblank() -> bool {
+    unsafe { sys::sceDisplayIsVblank() == 1 }
+}
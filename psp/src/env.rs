@@ -0,0 +1,61 @@
+//! Inspection of the process's environment.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+static mut ARGS: Option<Vec<String>> = None;
+
+/// Record the arguments passed to `module_start`.
+///
+/// The kernel passes the arguments as a single block of `len` bytes,
+/// containing NUL-terminated strings. The first is the path of the module.
+///
+/// This is called by the `module!` macro before `psp_main`.
+#[doc(hidden)]
+pub unsafe fn init(len: usize, argp: *const u8) {
+    let mut args = Vec::new();
+
+    if !argp.is_null() && len > 0 {
+        let block = core::slice::from_raw_parts(argp, len);
+
+        for arg in block.split(|&b| b == 0) {
+            if !arg.is_empty() {
+                args.push(String::from_utf8_lossy(arg).into_owned());
+            }
+        }
+    }
+
+    ARGS = Some(args);
+}
+
+/// Returns the arguments the module was started with.
+///
+/// The first element is usually the path to the executable, e.g.
+/// `ms0:/PSP/GAME/MYGAME/EBOOT.PBP`.
+pub fn args() -> Args {
+    let args = unsafe { ARGS.as_ref() };
+
+    Args {
+        inner: args.map(|a| a.as_slice()).unwrap_or(&[]).iter(),
+    }
+}
+
+/// An iterator over the arguments of a process, yielding a `String` for each.
+#[derive(Debug, Clone)]
+pub struct Args {
+    inner: core::slice::Iter<'static, String>,
+}
+
+impl Iterator for Args {
+    type Item = String;
+
+    fn next(&mut self) -> Option<String> {
+        self.inner.next().cloned()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl ExactSizeIterator for Args {}
@@ -0,0 +1,283 @@
+//! Typed SCE error codes.
+//!
+//! Most `sce*` functions return a negative `i32` on failure. These values are
+//! not arbitrary: they are structured error codes of the form
+//!
+//! ```text
+//!  31  30    28 27          16 15              0
+//! +---+--------+--------------+-----------------+
+//! | E |        |   facility   |      code       |
+//! +---+--------+--------------+-----------------+
+//! ```
+//!
+//! where `E` is set for errors. `Error` wraps such a code and decodes it, with
+//! human readable names for the most common values.
+
+use core::fmt;
+
+/// A specialized `Result` type for fallible system calls.
+pub type Result<T> = core::result::Result<T, Error>;
+
+/// An SCE error code.
+#[derive(Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Error(i32);
+
+/// The subsystem an error code originated from.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Facility {
+    /// Generic errors, `SCE_ERROR_*`.
+    Null,
+    /// POSIX errno values, `SCE_ERROR_ERRNO_*`.
+    Errno,
+    /// Kernel errors, `SCE_KERNEL_ERROR_*`.
+    Kernel,
+    /// Utility module errors (savedata, dialogs, module loading).
+    Utility,
+    /// Audio driver errors.
+    Audio,
+    /// Network stack errors (inet, resolver, apctl, adhoc).
+    Network,
+    /// HTTP library errors.
+    Http,
+    /// ATRAC codec errors.
+    Atrac,
+    /// Any other facility.
+    Other(u16),
+}
+
+impl Facility {
+    fn from_raw(facility: u16) -> Self {
+        match facility {
+            0x000 => Facility::Null,
+            0x001 => Facility::Errno,
+            0x002 => Facility::Kernel,
+            0x011 => Facility::Utility,
+            0x026 => Facility::Audio,
+            0x041 => Facility::Network,
+            0x043 => Facility::Http,
+            0x063 => Facility::Atrac,
+            other => Facility::Other(other),
+        }
+    }
+}
+
+impl Error {
+    /// Wrap a raw error code.
+    pub const fn from_raw(code: i32) -> Self {
+        Self(code)
+    }
+
+    /// The raw error code, as returned by the system call.
+    pub const fn raw(&self) -> i32 {
+        self.0
+    }
+
+    /// The facility (subsystem) this error belongs to.
+    pub fn facility(&self) -> Facility {
+        Facility::from_raw(((self.0 as u32 >> 16) & 0xfff) as u16)
+    }
+
+    /// The facility-specific error number.
+    pub fn code(&self) -> u16 {
+        self.0 as u16
+    }
+
+    /// Wrap a POSIX errno value, such as one returned by `sceNetInetGetErrno`.
+    pub const fn from_errno(errno: u16) -> Self {
+        Self((0x8001_0000 | errno as u32) as i32)
+    }
+
+    /// Returns `true` if this is a POSIX errno value, and the errno if so.
+    pub fn errno(&self) -> Option<u16> {
+        match self.facility() {
+            Facility::Errno => Some(self.code()),
+            _ => None,
+        }
+    }
+
+    /// The symbolic name of this error, if it is a well known value.
+    pub fn name(&self) -> Option<&'static str> {
+        KNOWN_ERRORS
+            .iter()
+            .find(|(code, _)| *code as i32 == self.0)
+            .map(|(_, name)| *name)
+    }
+}
+
+impl fmt::Debug for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.name() {
+            Some(name) => write!(f, "Error({}, {:#010x})", name, self.0),
+            None => write!(f, "Error({:#010x})", self.0),
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(name) = self.name() {
+            return write!(f, "{} ({:#010x})", name, self.0);
+        }
+
+        match self.facility() {
+            Facility::Other(facility) => write!(
+                f,
+                "facility {:#05x} error {:#06x} ({:#010x})",
+                facility,
+                self.code(),
+                self.0,
+            ),
+            facility => write!(
+                f,
+                "{:?} error {:#06x} ({:#010x})",
+                facility,
+                self.code(),
+                self.0,
+            ),
+        }
+    }
+}
+
+/// Convert the return value of a system call into a `Result`.
+///
+/// Negative values are errors, anything else is passed through.
+pub fn check(ret: i32) -> Result<i32> {
+    if ret < 0 {
+        Err(Error(ret))
+    } else {
+        Ok(ret)
+    }
+}
+
+/// Like `check`, for functions returning a `SceUid`.
+pub fn check_uid(uid: crate::sys::SceUid) -> Result<crate::sys::SceUid> {
+    check(uid.0).map(|_| uid)
+}
+
+macro_rules! error_codes {
+    ($($(#[$meta:meta])* $name:ident = $code:expr,)*) => {
+        $(
+            $(#[$meta])*
+            pub const $name: Error = Error($code as u32 as i32);
+        )*
+
+        const KNOWN_ERRORS: &[(u32, &str)] = &[
+            $(($code, stringify!($name)),)*
+        ];
+    }
+}
+
+error_codes! {
+    SCE_ERROR_ERRNO_EPERM = 0x8001_0001,
+    SCE_ERROR_ERRNO_ENOENT = 0x8001_0002,
+    SCE_ERROR_ERRNO_EIO = 0x8001_0005,
+    SCE_ERROR_ERRNO_EBADF = 0x8001_0009,
+    SCE_ERROR_ERRNO_EAGAIN = 0x8001_000b,
+    SCE_ERROR_ERRNO_ENOMEM = 0x8001_000c,
+    SCE_ERROR_ERRNO_EACCES = 0x8001_000d,
+    SCE_ERROR_ERRNO_EBUSY = 0x8001_0010,
+    SCE_ERROR_ERRNO_EEXIST = 0x8001_0011,
+    SCE_ERROR_ERRNO_ENODEV = 0x8001_0013,
+    SCE_ERROR_ERRNO_ENOTDIR = 0x8001_0014,
+    SCE_ERROR_ERRNO_EISDIR = 0x8001_0015,
+    SCE_ERROR_ERRNO_EINVAL = 0x8001_0016,
+    SCE_ERROR_ERRNO_EMFILE = 0x8001_0018,
+    SCE_ERROR_ERRNO_ENOSPC = 0x8001_001c,
+    SCE_ERROR_ERRNO_EROFS = 0x8001_001e,
+    SCE_ERROR_ERRNO_ENOTEMPTY = 0x8001_005a,
+    SCE_ERROR_ERRNO_ENAMETOOLONG = 0x8001_005b,
+    SCE_ERROR_ERRNO_EPIPE = 0x8001_0020,
+    SCE_ERROR_ERRNO_ECONNRESET = 0x8001_0068,
+    SCE_ERROR_ERRNO_ENOBUFS = 0x8001_0069,
+    SCE_ERROR_ERRNO_ENOTSOCK = 0x8001_006c,
+    SCE_ERROR_ERRNO_ECONNREFUSED = 0x8001_006f,
+    SCE_ERROR_ERRNO_EADDRINUSE = 0x8001_0070,
+    SCE_ERROR_ERRNO_ECONNABORTED = 0x8001_0071,
+    SCE_ERROR_ERRNO_ENETUNREACH = 0x8001_0072,
+    SCE_ERROR_ERRNO_ETIMEDOUT = 0x8001_0074,
+    SCE_ERROR_ERRNO_EHOSTUNREACH = 0x8001_0076,
+    SCE_ERROR_ERRNO_EINPROGRESS = 0x8001_0077,
+    SCE_ERROR_ERRNO_EALREADY = 0x8001_0078,
+    SCE_ERROR_ERRNO_EMSGSIZE = 0x8001_007a,
+    SCE_ERROR_ERRNO_EADDRNOTAVAIL = 0x8001_007d,
+    SCE_ERROR_ERRNO_EISCONN = 0x8001_007f,
+    SCE_ERROR_ERRNO_ENOTCONN = 0x8001_0080,
+
+    SCE_KERNEL_ERROR_ERROR = 0x8002_0001,
+    SCE_KERNEL_ERROR_NOTIMP = 0x8002_0002,
+    SCE_KERNEL_ERROR_ILLEGAL_CONTEXT = 0x8002_0064,
+    SCE_KERNEL_ERROR_ILLEGAL_ADDRESS = 0x8002_00d3,
+    SCE_KERNEL_ERROR_NOFILE = 0x8002_012f,
+    SCE_KERNEL_ERROR_EXCLUSIVE_LOAD = 0x8002_0146,
+    SCE_KERNEL_ERROR_NO_MEMORY = 0x8002_0190,
+    SCE_KERNEL_ERROR_ILLEGAL_ATTR = 0x8002_0191,
+    SCE_KERNEL_ERROR_ILLEGAL_ENTRY = 0x8002_0192,
+    SCE_KERNEL_ERROR_ILLEGAL_PRIORITY = 0x8002_0193,
+    SCE_KERNEL_ERROR_ILLEGAL_STACK_SIZE = 0x8002_0194,
+    SCE_KERNEL_ERROR_ILLEGAL_MODE = 0x8002_0195,
+    SCE_KERNEL_ERROR_ILLEGAL_THID = 0x8002_0197,
+    SCE_KERNEL_ERROR_UNKNOWN_THID = 0x8002_0198,
+    SCE_KERNEL_ERROR_UNKNOWN_SEMID = 0x8002_0199,
+    SCE_KERNEL_ERROR_UNKNOWN_EVFID = 0x8002_019a,
+    SCE_KERNEL_ERROR_UNKNOWN_MBXID = 0x8002_019b,
+    SCE_KERNEL_ERROR_UNKNOWN_ALMID = 0x8002_019f,
+    SCE_KERNEL_ERROR_UNKNOWN_CBID = 0x8002_01a1,
+    SCE_KERNEL_ERROR_DORMANT = 0x8002_01a2,
+    SCE_KERNEL_ERROR_NOT_DORMANT = 0x8002_01a4,
+    SCE_KERNEL_ERROR_WAIT_TIMEOUT = 0x8002_01a8,
+    SCE_KERNEL_ERROR_WAIT_CANCEL = 0x8002_01a9,
+    SCE_KERNEL_ERROR_SEMA_ZERO = 0x8002_01ad,
+    SCE_KERNEL_ERROR_SEMA_OVF = 0x8002_01ae,
+    SCE_KERNEL_ERROR_EVF_COND = 0x8002_01af,
+    SCE_KERNEL_ERROR_MBOX_NOMSG = 0x8002_01b2,
+    SCE_KERNEL_ERROR_WAIT_DELETE = 0x8002_01b5,
+
+    SCE_NET_ADHOC_ERROR_INVALID_ADDR = 0x8041_0702,
+    SCE_NET_ADHOC_ERROR_INVALID_PORT = 0x8041_0703,
+    SCE_NET_ADHOC_ERROR_WOULD_BLOCK = 0x8041_0709,
+    SCE_NET_ADHOC_ERROR_PORT_IN_USE = 0x8041_070a,
+    SCE_NET_ADHOC_ERROR_NOT_CONNECTED = 0x8041_070b,
+    SCE_NET_ADHOC_ERROR_DISCONNECTED = 0x8041_070c,
+    SCE_NET_ADHOC_ERROR_TIMEOUT = 0x8041_0715,
+    SCE_NET_ADHOC_ERROR_CONNECTION_REFUSED = 0x8041_0718,
+
+    SCE_UTILITY_SAVEDATA_ERROR_LOAD_NO_MS = 0x8011_0301,
+    SCE_UTILITY_SAVEDATA_ERROR_LOAD_DATA_BROKEN = 0x8011_0306,
+    SCE_UTILITY_SAVEDATA_ERROR_LOAD_NO_DATA = 0x8011_0307,
+    SCE_UTILITY_SAVEDATA_ERROR_RW_NO_MEMSTICK = 0x8011_0321,
+    SCE_UTILITY_SAVEDATA_ERROR_RW_MEMSTICK_FULL = 0x8011_0323,
+    SCE_UTILITY_SAVEDATA_ERROR_RW_DATA_BROKEN = 0x8011_0326,
+    SCE_UTILITY_SAVEDATA_ERROR_RW_NO_DATA = 0x8011_0327,
+    SCE_UTILITY_SAVEDATA_ERROR_DELETE_NO_MS = 0x8011_0341,
+    SCE_UTILITY_SAVEDATA_ERROR_DELETE_NO_DATA = 0x8011_0347,
+    SCE_UTILITY_SAVEDATA_ERROR_SAVE_NO_MS = 0x8011_0381,
+    SCE_UTILITY_SAVEDATA_ERROR_SAVE_MS_NOSPACE = 0x8011_0383,
+    SCE_UTILITY_SAVEDATA_ERROR_SAVE_MS_PROTECTED = 0x8011_0384,
+
+    SCE_UTILITY_ERROR_MODULE_BAD_ID = 0x8011_1101,
+    SCE_UTILITY_ERROR_MODULE_ALREADY_LOADED = 0x8011_1102,
+    SCE_UTILITY_ERROR_MODULE_NOT_LOADED = 0x8011_1103,
+
+    SCE_ATRAC_ERROR_NO_ATRACID = 0x8063_0003,
+    SCE_ATRAC_ERROR_UNKNOWN_FORMAT = 0x8063_0006,
+    SCE_ATRAC_ERROR_ALL_DATA_LOADED = 0x8063_0009,
+    SCE_ATRAC_ERROR_SECOND_BUFFER_NEEDED = 0x8063_0012,
+    SCE_ATRAC_ERROR_NO_LOOP_INFORMATION = 0x8063_0021,
+    SCE_ATRAC_ERROR_SECOND_BUFFER_NOT_NEEDED = 0x8063_0022,
+    SCE_ATRAC_ERROR_ALL_DATA_DECODED = 0x8063_0024,
+
+    SCE_MP3_ERROR_INVALID_HANDLE = 0x8067_1001,
+    SCE_MP3_ERROR_UNRESERVED_HANDLE = 0x8067_1102,
+    SCE_MP3_ERROR_NO_RESOURCE = 0x8067_1201,
+    SCE_MP3_ERROR_END_OF_STREAM = 0x8067_1402,
+
+    SCE_MPEG_ERROR_INVALID_VALUE = 0x8061_01fe,
+    SCE_MPEG_ERROR_NO_DATA = 0x8061_8001,
+
+    SCE_FONT_ERROR_OUT_OF_MEMORY = 0x8046_0001,
+    SCE_FONT_ERROR_INVALID_LIB_ID = 0x8046_0002,
+    SCE_FONT_ERROR_INVALID_PARAMETER = 0x8046_0003,
+    SCE_FONT_ERROR_NO_FILE = 0x8046_0004,
+    SCE_FONT_ERROR_TOO_MANY_OPEN_FONTS = 0x8046_0009,
+    SCE_FONT_ERROR_INVALID_FONT_DATA = 0x8046_000a,
+}
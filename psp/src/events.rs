@@ -0,0 +1,394 @@
+//! Callback-driven system events.
+//!
+//! The PSP OS notifies applications about the home button, power state,
+//! memory stick and UMD changes through kernel callbacks. These callbacks are
+//! only ever run on a thread which is sleeping in a `*CB` function, so this
+//! module starts a dedicated callback thread and translates every
+//! notification into an `Event`.
+//!
+//! Events can be consumed in two ways:
+//!
+//! - Handlers registered with `add_handler` are run directly on the callback
+//!   thread, as soon as the event occurs. This is the place to save state
+//!   before the application exits.
+//! - Every event is also pushed to a queue, which can be drained from any
+//!   thread with `poll_event` or `wait_event`.
+//!
+//! ```ignore
+//! psp::events::init(psp::events::ExitBehavior::Immediate).unwrap();
+//! psp::events::add_handler(|event| {
+//!     if let psp::events::Event::ExitRequested = event {
+//!         save_game();
+//!     }
+//! });
+//! ```
+
+use crate::sync::Mutex;
+use crate::sys::{self, MsCbEvent, PowerInfo, SceUid, ThreadAttributes, UmdStateFlags};
+use alloc::boxed::Box;
+use alloc::collections::VecDeque;
+use alloc::vec::Vec;
+use core::{ffi::c_void, ptr};
+
+/// Maximum number of undelivered events kept in the queue. When the queue is
+/// full, the oldest event is discarded.
+pub const QUEUE_CAPACITY: usize = 32;
+
+/// A system event.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Event {
+    /// The user has chosen to quit the application from the home menu.
+    ExitRequested,
+    /// The unit is about to enter suspend mode.
+    PowerSuspend,
+    /// The unit is resuming from suspend mode.
+    PowerResume,
+    /// A memory stick has been inserted.
+    MemoryStickInserted,
+    /// The memory stick has been ejected.
+    MemoryStickEjected,
+    /// A UMD has been inserted.
+    UmdInserted,
+    /// The UMD has been ejected.
+    UmdEjected,
+}
+
+/// What to do once all handlers have processed `Event::ExitRequested`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ExitBehavior {
+    /// Call `sceKernelExitGame` immediately after the handlers have run.
+    Immediate,
+    /// Leave it to the application to call `exit` at a convenient time.
+    Deferred,
+}
+
+/// Error returned when the event subsystem cannot be started.
+#[derive(Debug)]
+pub enum EventsError {
+    /// `init` was already called, and `shutdown` has not been called since.
+    AlreadyStarted,
+    /// A kernel call failed.
+    Kernel(crate::Error),
+}
+
+/// Identifies a handler registered with `add_handler`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct HandlerId(u32);
+
+type Handler = Box<dyn FnMut(&Event) + Send>;
+
+/// State shared with the callback thread. It is created by the first `init`
+/// and never freed, so references to it stay valid after `shutdown`.
+struct EventSystem {
+    queue: Mutex<VecDeque<Event>>,
+    handlers: Mutex<Vec<(HandlerId, Handler)>>,
+    next_handler_id: Mutex<u32>,
+}
+
+static mut EVENTS: Option<EventSystem> = None;
+
+/// Set by `init` and cleared by `shutdown` to ask the callback thread to
+/// unregister and exit.
+static mut RUNNING: bool = false;
+
+static mut EXIT_BEHAVIOR: ExitBehavior = ExitBehavior::Immediate;
+
+/// Counts the number of events in the queue, so that readers can block.
+static mut QUEUE_SEMA: SceUid = SceUid(-1);
+
+static mut THREAD: SceUid = SceUid(-1);
+
+fn system() -> Option<&'static EventSystem> {
+    unsafe {
+        if RUNNING {
+            EVENTS.as_ref()
+        } else {
+            None
+        }
+    }
+}
+
+/// Start the callback thread and begin listening for system events.
+///
+/// This also enables the home button. This must not be called concurrently
+/// with any other function in this module.
+pub fn init(exit_behavior: ExitBehavior) -> Result<(), EventsError> {
+    if system().is_some() {
+        return Err(EventsError::AlreadyStarted);
+    }
+
+    unsafe {
+        let queue_sema = sys::sceKernelCreateSema(
+            &b"psp_events_queue\0"[0],
+            0,
+            0,
+            QUEUE_CAPACITY as i32,
+            ptr::null_mut(),
+        );
+
+        if queue_sema.0 < 0 {
+            return Err(EventsError::Kernel(crate::Error::from_raw(queue_sema.0)));
+        }
+
+        let thread = sys::sceKernelCreateThread(
+            &b"psp_events_thread\0"[0],
+            callback_thread,
+            // Higher priority than the default main thread, so that handlers
+            // run promptly.
+            17,
+            0x4000,
+            ThreadAttributes::USER,
+            ptr::null_mut(),
+        );
+
+        if thread.0 < 0 {
+            sys::sceKernelDeleteSema(queue_sema);
+            return Err(EventsError::Kernel(crate::Error::from_raw(thread.0)));
+        }
+
+        if EVENTS.is_none() {
+            EVENTS = Some(EventSystem {
+                queue: Mutex::new(VecDeque::with_capacity(QUEUE_CAPACITY)),
+                handlers: Mutex::new(Vec::new()),
+                next_handler_id: Mutex::new(0),
+            });
+        }
+
+        EXIT_BEHAVIOR = exit_behavior;
+        QUEUE_SEMA = queue_sema;
+        THREAD = thread;
+        RUNNING = true;
+
+        let ret = sys::sceKernelStartThread(thread, 0, ptr::null_mut());
+        if ret < 0 {
+            RUNNING = false;
+            sys::sceKernelDeleteThread(thread);
+            sys::sceKernelDeleteSema(queue_sema);
+            return Err(EventsError::Kernel(crate::Error::from_raw(ret)));
+        }
+    }
+
+    Ok(())
+}
+
+/// Stop the callback thread and unregister all system callbacks.
+///
+/// Registered handlers and pending events are discarded, and threads blocked
+/// in `wait_event` return `None`. `init` may be called again afterwards.
+pub fn shutdown() {
+    let events = match system() {
+        Some(events) => events,
+        None => return,
+    };
+
+    unsafe {
+        RUNNING = false;
+        sys::sceKernelWakeupThread(THREAD);
+        sys::sceKernelWaitThreadEnd(THREAD, ptr::null_mut());
+        sys::sceKernelDeleteThread(THREAD);
+        // Deleting the semaphore wakes up any thread in `wait_event`.
+        sys::sceKernelDeleteSema(QUEUE_SEMA);
+    }
+
+    events.handlers.lock().clear();
+    events.queue.lock().clear();
+}
+
+/// Register a handler to be run on the callback thread for every event.
+///
+/// Handlers must not call `add_handler` or `remove_handler` themselves, as the
+/// handler list is locked while they run.
+pub fn add_handler<F>(handler: F) -> HandlerId
+where
+    F: FnMut(&Event) + Send + 'static,
+{
+    let events = system().expect("psp::events::init must be called first");
+
+    let id = {
+        let mut next = events.next_handler_id.lock();
+        *next += 1;
+        HandlerId(*next)
+    };
+
+    events.handlers.lock().push((id, Box::new(handler)));
+    id
+}
+
+/// Unregister a handler. Returns `false` if no such handler was found.
+pub fn remove_handler(id: HandlerId) -> bool {
+    let events = match system() {
+        Some(events) => events,
+        None => return false,
+    };
+
+    let mut handlers = events.handlers.lock();
+    let len = handlers.len();
+    handlers.retain(|(handler_id, _)| *handler_id != id);
+
+    handlers.len() != len
+}
+
+/// Take the oldest pending event from the queue, without blocking.
+pub fn poll_event() -> Option<Event> {
+    let events = system()?;
+
+    if unsafe { sys::sceKernelPollSema(QUEUE_SEMA, 1) } < 0 {
+        return None;
+    }
+
+    events.queue.lock().pop_front()
+}
+
+/// Block until an event is available, and return it.
+///
+/// Returns `None` if `init` has not been called, or once `shutdown` is.
+pub fn wait_event() -> Option<Event> {
+    loop {
+        let events = system()?;
+
+        if unsafe { sys::sceKernelWaitSema(QUEUE_SEMA, 1, ptr::null_mut()) } < 0 {
+            return None;
+        }
+
+        if let Some(event) = events.queue.lock().pop_front() {
+            return Some(event);
+        }
+    }
+}
+
+/// Exit the application and return to the XMB.
+///
+/// This is intended to be used with `ExitBehavior::Deferred`, once the
+/// application has finished cleaning up after `Event::ExitRequested`.
+pub fn exit() -> ! {
+    unsafe {
+        sys::sceKernelExitGame();
+    }
+
+    loop {}
+}
+
+fn dispatch(event: Event) {
+    let events = match system() {
+        Some(events) => events,
+        None => return,
+    };
+
+    for (_, handler) in events.handlers.lock().iter_mut() {
+        handler(&event);
+    }
+
+    let mut queue = events.queue.lock();
+    if queue.len() == QUEUE_CAPACITY {
+        // Drop the oldest event. The semaphore count is unchanged as the
+        // number of queued events stays the same.
+        queue.pop_front();
+    } else {
+        unsafe {
+            sys::sceKernelSignalSema(QUEUE_SEMA, 1);
+        }
+    }
+
+    queue.push_back(event);
+    drop(queue);
+
+    if event == Event::ExitRequested && unsafe { EXIT_BEHAVIOR } == ExitBehavior::Immediate {
+        exit();
+    }
+}
+
+unsafe extern fn exit_callback(_arg1: i32, _arg2: i32, _arg: *mut c_void) -> i32 {
+    dispatch(Event::ExitRequested);
+    0
+}
+
+unsafe extern fn power_callback(_arg1: i32, power_info: i32, _arg: *mut c_void) -> i32 {
+    let info = PowerInfo::from_bits_truncate(power_info as u32);
+
+    if info.contains(PowerInfo::SUSPENDING) {
+        dispatch(Event::PowerSuspend);
+    } else if info.contains(PowerInfo::RESUMING) {
+        dispatch(Event::PowerResume);
+    }
+
+    0
+}
+
+unsafe extern fn ms_callback(_arg1: i32, event: i32, _arg: *mut c_void) -> i32 {
+    if event == MsCbEvent::Inserted as i32 {
+        dispatch(Event::MemoryStickInserted);
+    } else if event == MsCbEvent::Ejected as i32 {
+        dispatch(Event::MemoryStickEjected);
+    }
+
+    0
+}
+
+unsafe extern fn umd_callback(_arg1: i32, state: i32, _arg: *mut c_void) -> i32 {
+    let state = UmdStateFlags::from_bits_truncate(state);
+
+    if state.contains(UmdStateFlags::NOT_PRESENT) {
+        dispatch(Event::UmdEjected);
+    } else if state.contains(UmdStateFlags::PRESENT) {
+        dispatch(Event::UmdInserted);
+    }
+
+    0
+}
+
+unsafe extern fn callback_thread(_args: usize, _argp: *mut c_void) -> i32 {
+    let exit_cb = sys::sceKernelCreateCallback(
+        &b"psp_exit_callback\0"[0],
+        exit_callback,
+        ptr::null_mut(),
+    );
+    sys::sceKernelRegisterExitCallback(exit_cb);
+
+    // The remaining sources are optional. Registration may fail, e.g. on
+    // emulators without a memory stick driver, in which case the
+    // corresponding events are simply never delivered.
+    let power_cb = sys::sceKernelCreateCallback(
+        &b"psp_power_callback\0"[0],
+        power_callback,
+        ptr::null_mut(),
+    );
+    let power_slot = sys::scePowerRegisterCallback(-1, power_cb);
+
+    let ms_cb = sys::sceKernelCreateCallback(
+        &b"psp_ms_callback\0"[0],
+        ms_callback,
+        ptr::null_mut(),
+    );
+    let ms_registered = sys::MScmRegisterMSInsertEjectCallback(ms_cb) >= 0;
+
+    let umd_cb = sys::sceKernelCreateCallback(
+        &b"psp_umd_callback\0"[0],
+        umd_callback,
+        ptr::null_mut(),
+    );
+    let umd_registered = sys::sceUmdRegisterUMDCallBack(umd_cb.0) >= 0;
+
+    while RUNNING {
+        sys::sceKernelSleepThreadCB();
+    }
+
+    if umd_registered {
+        sys::sceUmdUnRegisterUMDCallBack(umd_cb.0);
+    }
+
+    if ms_registered {
+        sys::MScmUnregisterMSInsertEjectCallback(ms_cb);
+    }
+
+    if power_slot >= 0 {
+        sys::scePowerUnregisterCallback(power_slot);
+    }
+
+    // There is no way to unregister the exit callback. Deleting it along with
+    // the others disables the home button until a new one is registered.
+    for cb in &[exit_cb, power_cb, ms_cb, umd_cb] {
+        sys::sceKernelDeleteCallback(*cb);
+    }
+
+    0
+}
@@ -0,0 +1,468 @@
+//! A minimal, single-threaded executor for `Future`s.
+//!
+//! This allows overlapping slow operations, such as streaming data from the
+//! memory stick, with rendering, without dedicating a kernel thread to each.
+//! Tasks are woken directly by the kernel: asynchronous file operations
+//! through `sceIoSetAsyncCallback`, timers through alarms and vblank waits
+//! through the vblank interrupt.
+//!
+//! ```ignore
+//! let mut executor = Executor::new().unwrap();
+//! executor.spawn(async {
+//!     let mut file = psp::fs::File::open("ms0:/level.bin").unwrap();
+//!     let mut buf = vec![0; 64 * 1024];
+//!     let n = file.read_async(&mut buf).await.unwrap();
+//! });
+//!
+//! loop {
+//!     // Make progress on the load without blocking the frame.
+//!     executor.poll();
+//!     draw_loading_screen();
+//!     executor.block_on(psp::executor::vblank());
+//! }
+//! ```
+//!
+//! Only one `Executor` may exist at a time.
+
+use crate::sync::critical_section;
+use crate::sys::{self, EventFlagAttributes, EventFlagWaitTypes, Interrupt, SceUid};
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+use core::future::Future;
+use core::pin::Pin;
+use core::task::{Context, Poll, RawWaker, RawWakerVTable, Waker};
+use core::{ffi::c_void, ptr, time::Duration};
+
+/// Maximum number of tasks which may be spawned on the executor at once.
+pub const MAX_TASKS: usize = 63;
+
+/// Task id used for the future passed to `Executor::block_on`.
+const MAIN_TASK: usize = MAX_TASKS;
+
+/// Sub interrupt handler number used for the vblank handler.
+const VBLANK_SUB_INTR: i32 = 0;
+
+const WAKE_BIT: u32 = 1;
+
+static mut EXECUTOR_IN_USE: bool = false;
+static mut WAKE_EVF: SceUid = SceUid(-1);
+static mut IO_CALLBACK: SceUid = SceUid(-1);
+static mut CURRENT_TASK: Option<usize> = None;
+
+// These bitsets are shared with interrupt handlers, and must only be accessed
+// with interrupts suspended.
+static mut READY: u64 = 0;
+static mut VBLANK_WAITERS: u64 = 0;
+static mut VBLANK_HANDLER_REGISTERED: bool = false;
+
+/// Mark a task as ready to be polled. Safe to call from interrupt handlers.
+pub(crate) fn wake_task(id: usize) {
+    critical_section(|| unsafe { READY |= 1 << id });
+
+    unsafe {
+        sys::sceKernelSetEventFlag(WAKE_EVF, WAKE_BIT);
+    }
+}
+
+/// The id of the task currently being polled, if any.
+pub(crate) fn current_task() -> Option<usize> {
+    unsafe { CURRENT_TASK }
+}
+
+/// The callback which wakes tasks when an asynchronous IO operation finishes.
+/// The task id must be passed as the callback argument.
+pub(crate) fn io_callback() -> Option<SceUid> {
+    current_task().map(|_| unsafe { IO_CALLBACK })
+}
+
+unsafe fn waker_clone(data: *const ()) -> RawWaker {
+    RawWaker::new(data, &WAKER_VTABLE)
+}
+
+unsafe fn waker_wake(data: *const ()) {
+    wake_task(data as usize);
+}
+
+unsafe fn waker_drop(_data: *const ()) {}
+
+static WAKER_VTABLE: RawWakerVTable =
+    RawWakerVTable::new(waker_clone, waker_wake, waker_wake, waker_drop);
+
+fn task_waker(id: usize) -> Waker {
+    unsafe { Waker::from_raw(RawWaker::new(id as *const (), &WAKER_VTABLE)) }
+}
+
+/// The IO manager passes the argument given to `sceIoSetAsyncCallback` as
+/// `arg2`, while `arg` is the one given when creating the callback.
+unsafe extern fn io_callback_fn(_arg1: i32, task: i32, _arg: *mut c_void) -> i32 {
+    wake_task(task as usize);
+    0
+}
+
+unsafe extern fn alarm_handler(common: *mut c_void) -> u32 {
+    wake_task(common as usize);
+
+    // Do not reschedule.
+    0
+}
+
+unsafe extern fn vblank_handler(_sub_intr: i32, _arg: *mut c_void) -> i32 {
+    let waiters = VBLANK_WAITERS;
+    VBLANK_WAITERS = 0;
+
+    if waiters != 0 {
+        READY |= waiters;
+        sys::sceKernelSetEventFlag(WAKE_EVF, WAKE_BIT);
+    }
+
+    0
+}
+
+/// Error returned by `Executor::new`.
+#[derive(Debug)]
+pub enum ExecutorError {
+    /// Another executor already exists.
+    InUse,
+    /// Creating the kernel objects used for waking tasks failed.
+    Kernel(crate::Error),
+}
+
+type Task = Pin<Box<dyn Future<Output = ()>>>;
+
+/// A single-threaded executor.
+///
+/// The executor must be polled from the thread which created it, as IO
+/// completion callbacks are delivered to that thread.
+pub struct Executor {
+    tasks: Vec<Option<Task>>,
+}
+
+impl Executor {
+    /// Create the executor.
+    ///
+    /// Only one executor may exist at any given time.
+    pub fn new() -> Result<Self, ExecutorError> {
+        unsafe {
+            if EXECUTOR_IN_USE {
+                return Err(ExecutorError::InUse);
+            }
+
+            let evf = sys::sceKernelCreateEventFlag(
+                &b"psp_executor\0"[0],
+                EventFlagAttributes::empty(),
+                0,
+                ptr::null_mut(),
+            );
+
+            if evf.0 < 0 {
+                return Err(ExecutorError::Kernel(crate::Error::from_raw(evf.0)));
+            }
+
+            let callback = sys::sceKernelCreateCallback(
+                &b"psp_executor_io\0"[0],
+                io_callback_fn,
+                ptr::null_mut(),
+            );
+
+            if callback.0 < 0 {
+                sys::sceKernelDeleteEventFlag(evf);
+                return Err(ExecutorError::Kernel(crate::Error::from_raw(callback.0)));
+            }
+
+            EXECUTOR_IN_USE = true;
+            READY = 0;
+            VBLANK_WAITERS = 0;
+            WAKE_EVF = evf;
+            IO_CALLBACK = callback;
+        }
+
+        Ok(Self { tasks: Vec::new() })
+    }
+
+    /// Spawn a task onto the executor. It will first be polled during the
+    /// next call to `poll`, `run` or `block_on`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `MAX_TASKS` tasks are already pending.
+    pub fn spawn<F>(&mut self, future: F)
+    where
+        F: Future<Output = ()> + 'static,
+    {
+        let id = match self.tasks.iter().position(Option::is_none) {
+            Some(id) => id,
+            None if self.tasks.len() < MAX_TASKS => {
+                self.tasks.push(None);
+                self.tasks.len() - 1
+            }
+            None => panic!("Cannot spawn more than {} tasks", MAX_TASKS),
+        };
+
+        self.tasks[id] = Some(Box::pin(future));
+        wake_task(id);
+    }
+
+    /// Poll every task which has been woken, without blocking.
+    ///
+    /// Returns the number of tasks which are still pending.
+    pub fn poll(&mut self) -> usize {
+        // Deliver any pending IO completion callbacks.
+        unsafe {
+            sys::sceKernelCheckCallback();
+        }
+
+        let ready = critical_section(|| unsafe {
+            let ready = READY & !(1 << MAIN_TASK);
+            READY &= !ready;
+            ready
+        });
+
+        for (id, slot) in self.tasks.iter_mut().enumerate() {
+            if ready & (1 << id) == 0 {
+                continue;
+            }
+
+            if let Some(task) = slot {
+                if poll_task(id, task.as_mut()).is_ready() {
+                    *slot = None;
+                }
+            }
+        }
+
+        self.pending()
+    }
+
+    /// The number of tasks which have not yet completed.
+    pub fn pending(&self) -> usize {
+        self.tasks.iter().filter(|t| t.is_some()).count()
+    }
+
+    /// Run until every spawned task has completed, sleeping while no task is
+    /// ready.
+    pub fn run(&mut self) {
+        while self.poll() != 0 {
+            self.wait();
+        }
+    }
+
+    /// Run a future to completion on the current thread, polling spawned
+    /// tasks while it is pending.
+    pub fn block_on<F: Future>(&mut self, future: F) -> F::Output {
+        let mut future = future;
+
+        // The future is shadowed and never moved again.
+        let mut future = unsafe { Pin::new_unchecked(&mut future) };
+
+        // Make sure the future is polled at least once.
+        wake_task(MAIN_TASK);
+
+        loop {
+            let woken = critical_section(|| unsafe {
+                let woken = READY & (1 << MAIN_TASK) != 0;
+                READY &= !(1 << MAIN_TASK);
+                woken
+            });
+
+            if woken {
+                if let Poll::Ready(output) = poll_task(MAIN_TASK, future.as_mut()) {
+                    return output;
+                }
+            }
+
+            self.poll();
+            self.wait();
+        }
+    }
+
+    /// Sleep until a task is woken.
+    fn wait(&self) {
+        let mut bits = 0;
+
+        unsafe {
+            sys::sceKernelWaitEventFlagCB(
+                WAKE_EVF,
+                WAKE_BIT,
+                EventFlagWaitTypes::OR | EventFlagWaitTypes::CLEAR,
+                &mut bits,
+                ptr::null_mut(),
+            );
+        }
+    }
+}
+
+impl Drop for Executor {
+    fn drop(&mut self) {
+        // Drop tasks first, as they may cancel alarms or wait for IO.
+        self.tasks.clear();
+
+        unsafe {
+            if VBLANK_HANDLER_REGISTERED {
+                sys::sceKernelDisableSubIntr(Interrupt::Vblank as i32, VBLANK_SUB_INTR);
+                sys::sceKernelReleaseSubIntrHandler(Interrupt::Vblank as i32, VBLANK_SUB_INTR);
+                VBLANK_HANDLER_REGISTERED = false;
+            }
+
+            sys::sceKernelDeleteCallback(IO_CALLBACK);
+            sys::sceKernelDeleteEventFlag(WAKE_EVF);
+            EXECUTOR_IN_USE = false;
+        }
+    }
+}
+
+fn poll_task<T>(id: usize, future: Pin<&mut dyn Future<Output = T>>) -> Poll<T> {
+    let waker = task_waker(id);
+    let mut cx = Context::from_waker(&waker);
+
+    unsafe {
+        let previous = CURRENT_TASK.replace(id);
+        let poll = future.poll(&mut cx);
+        CURRENT_TASK = previous;
+        poll
+    }
+}
+
+/// Run a future to completion, using a temporary executor.
+///
+/// # Panics
+///
+/// Panics if another `Executor` already exists, in which case use
+/// `Executor::block_on`, or if the executor cannot be created.
+pub fn block_on<F: Future>(future: F) -> F::Output {
+    Executor::new()
+        .expect("failed to create an executor")
+        .block_on(future)
+}
+
+/// Returns a future which completes after `duration` has elapsed.
+pub fn sleep(duration: Duration) -> Sleep {
+    let now = unsafe { sys::sceKernelGetSystemTimeWide() };
+
+    Sleep {
+        deadline: now + duration.as_micros() as i64,
+        alarm: None,
+    }
+}
+
+/// Future returned by `sleep`.
+#[derive(Debug)]
+pub struct Sleep {
+    /// Deadline in microseconds, as returned by `sceKernelGetSystemTimeWide`.
+    deadline: i64,
+    alarm: Option<SceUid>,
+}
+
+impl Sleep {
+    fn cancel_alarm(&mut self) {
+        if let Some(alarm) = self.alarm.take() {
+            // This fails harmlessly if the alarm has already fired.
+            unsafe {
+                sys::sceKernelCancelAlarm(alarm);
+            }
+        }
+    }
+}
+
+impl Future for Sleep {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        let now = unsafe { sys::sceKernelGetSystemTimeWide() };
+
+        if now >= self.deadline {
+            self.cancel_alarm();
+            return Poll::Ready(());
+        }
+
+        let task = match current_task() {
+            Some(task) => task,
+            None => {
+                // Not running on our executor, fall back to busy polling.
+                cx.waker().wake_by_ref();
+                return Poll::Pending;
+            }
+        };
+
+        self.cancel_alarm();
+
+        let alarm = unsafe {
+            sys::sceKernelSetAlarm(
+                (self.deadline - now) as u32,
+                alarm_handler,
+                task as *mut c_void,
+            )
+        };
+
+        if alarm.0 < 0 {
+            cx.waker().wake_by_ref();
+        } else {
+            self.alarm = Some(alarm);
+        }
+
+        Poll::Pending
+    }
+}
+
+impl Drop for Sleep {
+    fn drop(&mut self) {
+        self.cancel_alarm();
+    }
+}
+
+/// Returns a future which completes at the start of the next vertical blank.
+pub fn vblank() -> Vblank {
+    Vblank {
+        vcount: unsafe { sys::sceDisplayGetVcount() },
+    }
+}
+
+/// Future returned by `vblank`.
+#[derive(Debug)]
+pub struct Vblank {
+    vcount: u32,
+}
+
+impl Vblank {
+    fn elapsed(&self) -> bool {
+        unsafe { sys::sceDisplayGetVcount() != self.vcount }
+    }
+}
+
+impl Future for Vblank {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        if self.elapsed() {
+            return Poll::Ready(());
+        }
+
+        let task = match current_task() {
+            Some(task) => task,
+            None => {
+                cx.waker().wake_by_ref();
+                return Poll::Pending;
+            }
+        };
+
+        unsafe {
+            if !VBLANK_HANDLER_REGISTERED {
+                sys::sceKernelRegisterSubIntrHandler(
+                    Interrupt::Vblank as i32,
+                    VBLANK_SUB_INTR,
+                    vblank_handler as *mut c_void,
+                    ptr::null_mut(),
+                );
+                sys::sceKernelEnableSubIntr(Interrupt::Vblank as i32, VBLANK_SUB_INTR);
+                VBLANK_HANDLER_REGISTERED = true;
+            }
+        }
+
+        critical_section(|| unsafe { VBLANK_WAITERS |= 1 << task });
+
+        // The vblank may have started before we registered as a waiter.
+        if self.elapsed() {
+            return Poll::Ready(());
+        }
+
+        Poll::Pending
+    }
+}
@@ -0,0 +1,803 @@
+//! Filesystem manipulation operations.
+//!
+//! This module mirrors `std::fs`. Paths are plain `&str`s, optionally
+//! prefixed with a device name such as `ms0:` or `host0:`. Paths without a
+//! device are resolved relative to the current directory of the process,
+//! which is normally the directory containing the `EBOOT.PBP`.
+//!
+//! ```ignore
+//! use psp::io::Write;
+//!
+//! psp::fs::create_dir_all("ms0:/PSP/SAVEDATA/MYGAME")?;
+//! let mut file = psp::fs::File::create("ms0:/PSP/SAVEDATA/MYGAME/save.bin")?;
+//! file.write_all(&data)?;
+//! ```
+
+use crate::executor;
+use crate::io::{self, cvt, Error, ErrorKind, Read, Seek, SeekFrom, Write};
+use crate::sys::{
+    self, IoOpenFlags, IoPermissions, IoStatAttr, IoStatMode, IoWhence, SceIoDirent, SceIoStat,
+    ScePspDateTime, SceUid,
+};
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::future::Future;
+use core::pin::Pin;
+use core::task::{Context, Poll};
+use core::{ffi::c_void, mem, time::Duration};
+
+/// A storage device known to the PSP IO manager.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Device {
+    /// The memory stick, `ms0:`.
+    MemoryStick,
+    /// The host filesystem exposed over PSPLink or by an emulator, `host0:`.
+    Host,
+    /// The UMD drive, accessed by sector, `umd0:`.
+    Umd,
+    /// The UMD filesystem, `disc0:`.
+    Disc,
+    /// Internal flash, `flash0:` to `flash3:`. Usually read only.
+    Flash(u8),
+}
+
+impl Device {
+    /// The path prefix for this device, including the trailing `:`.
+    ///
+    /// Fails with `ErrorKind::InvalidInput` for flash devices past `flash3:`.
+    pub fn prefix(&self) -> io::Result<&'static str> {
+        Ok(match self {
+            Device::MemoryStick => "ms0:",
+            Device::Host => "host0:",
+            Device::Umd => "umd0:",
+            Device::Disc => "disc0:",
+            Device::Flash(0) => "flash0:",
+            Device::Flash(1) => "flash1:",
+            Device::Flash(2) => "flash2:",
+            Device::Flash(3) => "flash3:",
+            Device::Flash(_) => return Err(Error::new(ErrorKind::InvalidInput)),
+        })
+    }
+
+    /// Parse a device prefix such as `ms0:`, with or without the `:`.
+    pub fn from_prefix(prefix: &str) -> Option<Self> {
+        let prefix = prefix.trim_end_matches(':');
+
+        match prefix {
+            "ms0" | "fatms0" => Some(Device::MemoryStick),
+            "host0" => Some(Device::Host),
+            "umd0" | "umd1" => Some(Device::Umd),
+            "disc0" => Some(Device::Disc),
+            "flash0" => Some(Device::Flash(0)),
+            "flash1" => Some(Device::Flash(1)),
+            "flash2" => Some(Device::Flash(2)),
+            "flash3" => Some(Device::Flash(3)),
+            _ => None,
+        }
+    }
+}
+
+/// Split a path into its device prefix (including the `:`) and the remainder.
+///
+/// ```ignore
+/// assert_eq!(split_device("ms0:/PSP/GAME"), (Some("ms0:"), "/PSP/GAME"));
+/// assert_eq!(split_device("data/level1.bin"), (None, "data/level1.bin"));
+/// ```
+pub fn split_device(path: &str) -> (Option<&str>, &str) {
+    match path.find(':') {
+        Some(i) if !path[..i].contains('/') => (Some(&path[..=i]), &path[i + 1..]),
+        _ => (None, path),
+    }
+}
+
+/// Returns the device a path refers to, if it has a known device prefix.
+pub fn device_of(path: &str) -> Option<Device> {
+    split_device(path).0.and_then(Device::from_prefix)
+}
+
+/// Join a file name onto a directory path, inserting a `/` if needed.
+pub fn join(base: &str, name: &str) -> String {
+    let mut path = String::with_capacity(base.len() + name.len() + 1);
+    path.push_str(base);
+
+    if !base.is_empty() && !base.ends_with('/') && !base.ends_with(':') {
+        path.push('/');
+    }
+
+    path.push_str(name.trim_start_matches('/'));
+    path
+}
+
+/// Convert a path into a NUL-terminated byte string for the `sceIo*` calls.
+pub(crate) fn c_path(path: &str) -> io::Result<Vec<u8>> {
+    if path.as_bytes().contains(&0) {
+        return Err(Error::new(ErrorKind::InvalidInput));
+    }
+
+    let mut bytes = Vec::with_capacity(path.len() + 1);
+    bytes.extend_from_slice(path.as_bytes());
+    bytes.push(0);
+
+    Ok(bytes)
+}
+
+/// Options and flags which can be used to configure how a file is opened.
+#[derive(Debug, Clone)]
+pub struct OpenOptions {
+    read: bool,
+    write: bool,
+    append: bool,
+    truncate: bool,
+    create: bool,
+    create_new: bool,
+    mode: IoPermissions,
+}
+
+impl OpenOptions {
+    /// Create a blank set of options, with all flags set to `false`.
+    pub fn new() -> Self {
+        Self {
+            read: false,
+            write: false,
+            append: false,
+            truncate: false,
+            create: false,
+            create_new: false,
+            mode: 0o777,
+        }
+    }
+
+    /// Open the file for reading.
+    pub fn read(&mut self, read: bool) -> &mut Self {
+        self.read = read;
+        self
+    }
+
+    /// Open the file for writing.
+    pub fn write(&mut self, write: bool) -> &mut Self {
+        self.write = write;
+        self
+    }
+
+    /// Open the file in append mode. Implies `write`.
+    pub fn append(&mut self, append: bool) -> &mut Self {
+        self.append = append;
+        self
+    }
+
+    /// Truncate the file to 0 bytes if it exists.
+    pub fn truncate(&mut self, truncate: bool) -> &mut Self {
+        self.truncate = truncate;
+        self
+    }
+
+    /// Create the file if it does not exist.
+    pub fn create(&mut self, create: bool) -> &mut Self {
+        self.create = create;
+        self
+    }
+
+    /// Create the file, failing if it already exists.
+    pub fn create_new(&mut self, create_new: bool) -> &mut Self {
+        self.create_new = create_new;
+        self
+    }
+
+    /// Set the octal unix permissions used when creating a file.
+    pub fn mode(&mut self, mode: IoPermissions) -> &mut Self {
+        self.mode = mode;
+        self
+    }
+
+    /// The `IoOpenFlags` corresponding to these options.
+    pub fn flags(&self) -> IoOpenFlags {
+        let write = self.write || self.append;
+
+        let mut flags = match (self.read, write) {
+            (true, true) => IoOpenFlags::RD_WR,
+            (false, true) => IoOpenFlags::WR_ONLY,
+            _ => IoOpenFlags::RD_ONLY,
+        };
+
+        if self.append {
+            flags |= IoOpenFlags::APPEND;
+        }
+
+        if self.truncate {
+            flags |= IoOpenFlags::TRUNC;
+        }
+
+        if self.create_new {
+            flags |= IoOpenFlags::CREAT | IoOpenFlags::EXCL;
+        } else if self.create {
+            flags |= IoOpenFlags::CREAT;
+        }
+
+        flags
+    }
+
+    /// Open a file at `path` with these options.
+    pub fn open(&self, path: &str) -> io::Result<File> {
+        let path = c_path(path)?;
+        let fd = unsafe { sys::sceIoOpen(path.as_ptr(), self.flags(), self.mode) };
+        cvt(fd.0)?;
+
+        Ok(File { fd })
+    }
+}
+
+impl Default for OpenOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// An open file on a PSP device. The file is closed on drop.
+#[derive(Debug)]
+pub struct File {
+    fd: SceUid,
+}
+
+impl File {
+    /// Open a file in read-only mode.
+    pub fn open(path: &str) -> io::Result<File> {
+        OpenOptions::new().read(true).open(path)
+    }
+
+    /// Open a file in write-only mode, creating or truncating it.
+    pub fn create(path: &str) -> io::Result<File> {
+        OpenOptions::new().write(true).create(true).truncate(true).open(path)
+    }
+
+    /// Returns a new `OpenOptions` object.
+    pub fn options() -> OpenOptions {
+        OpenOptions::new()
+    }
+
+    /// Returns the size of the file in bytes, without moving the cursor.
+    pub fn len(&mut self) -> io::Result<u64> {
+        let pos = self.stream_position()?;
+        let len = self.seek(SeekFrom::End(0))?;
+        self.seek(SeekFrom::Start(pos))?;
+
+        Ok(len)
+    }
+
+    /// Returns the underlying file descriptor.
+    pub fn as_raw_fd(&self) -> SceUid {
+        self.fd
+    }
+
+    /// Consume the file, returning the underlying descriptor without closing
+    /// it.
+    pub fn into_raw_fd(self) -> SceUid {
+        let fd = self.fd;
+        mem::forget(self);
+        fd
+    }
+
+    /// Read into `buf` asynchronously, returning the number of bytes read.
+    ///
+    /// When awaited on a `psp::executor::Executor`, the task is woken by the
+    /// IO manager once the read completes.
+    pub fn read_async<'a>(&'a mut self, buf: &'a mut [u8]) -> ReadAsync<'a> {
+        ReadAsync {
+            op: AsyncOp::new(self.fd),
+            buf,
+        }
+    }
+
+    /// Write `buf` asynchronously, returning the number of bytes written.
+    pub fn write_async<'a>(&'a mut self, buf: &'a [u8]) -> WriteAsync<'a> {
+        WriteAsync {
+            op: AsyncOp::new(self.fd),
+            buf,
+        }
+    }
+
+    /// Take ownership of a file descriptor returned by `sceIoOpen`.
+    ///
+    /// # Safety
+    ///
+    /// `fd` must be a valid, open descriptor which is not owned elsewhere.
+    pub unsafe fn from_raw_fd(fd: SceUid) -> Self {
+        Self { fd }
+    }
+}
+
+impl Read for File {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let ret = unsafe {
+            sys::sceIoRead(self.fd, buf.as_mut_ptr() as *mut c_void, buf.len() as u32)
+        };
+
+        cvt(ret).map(|n| n as usize)
+    }
+}
+
+impl Write for File {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let ret = unsafe {
+            sys::sceIoWrite(self.fd, buf.as_ptr() as *const c_void, buf.len())
+        };
+
+        cvt(ret).map(|n| n as usize)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        // Writes are unbuffered on our side.
+        Ok(())
+    }
+}
+
+impl Seek for File {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let (offset, whence) = match pos {
+            SeekFrom::Start(n) => (n as i64, IoWhence::Set),
+            SeekFrom::End(n) => (n, IoWhence::End),
+            SeekFrom::Current(n) => (n, IoWhence::Cur),
+        };
+
+        let ret = unsafe { sys::sceIoLseek(self.fd, offset, whence) };
+
+        if ret < 0 {
+            Err(Error::from_sce(ret as i32))
+        } else {
+            Ok(ret as u64)
+        }
+    }
+}
+
+impl Drop for File {
+    fn drop(&mut self) {
+        unsafe {
+            sys::sceIoClose(self.fd);
+        }
+    }
+}
+
+/// State shared by the asynchronous IO futures.
+#[derive(Debug)]
+struct AsyncOp {
+    fd: SceUid,
+    started: bool,
+    finished: bool,
+}
+
+impl AsyncOp {
+    fn new(fd: SceUid) -> Self {
+        Self {
+            fd,
+            started: false,
+            finished: false,
+        }
+    }
+
+    /// Start the operation with `start` if needed, then check for completion.
+    fn poll(
+        &mut self,
+        cx: &mut Context<'_>,
+        start: impl FnOnce(SceUid) -> i32,
+    ) -> Poll<io::Result<usize>> {
+        if !self.started {
+            // Ask the IO manager to notify the executor on completion. If we
+            // are not running on the executor, we fall back to busy polling.
+            let executor = (executor::io_callback(), executor::current_task());
+
+            if let (Some(cb), Some(task)) = executor {
+                unsafe {
+                    sys::sceIoSetAsyncCallback(self.fd, cb, task as *mut c_void);
+                }
+            }
+
+            if let Err(e) = cvt(start(self.fd)) {
+                self.finished = true;
+                return Poll::Ready(Err(e));
+            }
+
+            self.started = true;
+        }
+
+        let mut res = 0;
+        let ret = unsafe { sys::sceIoPollAsync(self.fd, &mut res) };
+
+        match ret {
+            // Still in progress.
+            1 => {
+                if executor::current_task().is_none() {
+                    cx.waker().wake_by_ref();
+                }
+
+                Poll::Pending
+            }
+
+            ret if ret < 0 => {
+                self.finished = true;
+                Poll::Ready(Err(Error::from_sce(ret)))
+            }
+
+            _ => {
+                self.finished = true;
+
+                if res < 0 {
+                    Poll::Ready(Err(Error::from_sce(res as i32)))
+                } else {
+                    Poll::Ready(Ok(res as usize))
+                }
+            }
+        }
+    }
+}
+
+impl Drop for AsyncOp {
+    fn drop(&mut self) {
+        // The kernel still holds a pointer to the buffer, so we must wait for
+        // the operation to finish before it can be released.
+        if self.started && !self.finished {
+            let mut res = 0;
+
+            unsafe {
+                sys::sceIoWaitAsync(self.fd, &mut res);
+            }
+        }
+    }
+}
+
+/// Future returned by `File::read_async`.
+#[derive(Debug)]
+pub struct ReadAsync<'a> {
+    op: AsyncOp,
+    buf: &'a mut [u8],
+}
+
+impl Future for ReadAsync<'_> {
+    type Output = io::Result<usize>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.get_mut();
+        let buf = &mut this.buf;
+
+        this.op.poll(cx, |fd| unsafe {
+            sys::sceIoReadAsync(fd, buf.as_mut_ptr() as *mut c_void, buf.len() as u32)
+        })
+    }
+}
+
+/// Future returned by `File::write_async`.
+#[derive(Debug)]
+pub struct WriteAsync<'a> {
+    op: AsyncOp,
+    buf: &'a [u8],
+}
+
+impl Future for WriteAsync<'_> {
+    type Output = io::Result<usize>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.get_mut();
+        let buf = this.buf;
+
+        this.op.poll(cx, |fd| unsafe {
+            sys::sceIoWriteAsync(fd, buf.as_ptr() as *const c_void, buf.len() as u32)
+        })
+    }
+}
+
+/// A structure representing a type of file.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct FileType {
+    mode: IoStatMode,
+    attr: IoStatAttr,
+}
+
+impl FileType {
+    /// Returns `true` if this is a directory.
+    pub fn is_dir(&self) -> bool {
+        self.mode.contains(IoStatMode::IFDIR) || self.attr.contains(IoStatAttr::IFDIR)
+    }
+
+    /// Returns `true` if this is a regular file.
+    pub fn is_file(&self) -> bool {
+        !self.is_dir()
+            && (self.mode.contains(IoStatMode::IFREG) || self.attr.contains(IoStatAttr::IFREG))
+    }
+
+    /// Returns `true` if this is a symbolic link.
+    pub fn is_symlink(&self) -> bool {
+        self.mode.contains(IoStatMode::IFLNK) || self.attr.contains(IoStatAttr::IFLNK)
+    }
+}
+
+/// Metadata information about a file.
+#[derive(Debug, Copy, Clone)]
+pub struct Metadata {
+    stat: SceIoStat,
+}
+
+impl Metadata {
+    /// Returns the file type for this metadata.
+    pub fn file_type(&self) -> FileType {
+        FileType {
+            mode: self.stat.st_mode,
+            attr: self.stat.st_attr,
+        }
+    }
+
+    /// Returns `true` if this metadata is for a directory.
+    pub fn is_dir(&self) -> bool {
+        self.file_type().is_dir()
+    }
+
+    /// Returns `true` if this metadata is for a regular file.
+    pub fn is_file(&self) -> bool {
+        self.file_type().is_file()
+    }
+
+    /// Returns the size of the file, in bytes.
+    pub fn len(&self) -> u64 {
+        self.stat.st_size as u64
+    }
+
+    /// Returns the unix permission bits of the file.
+    pub fn permissions(&self) -> IoStatMode {
+        self.stat.st_mode
+    }
+
+    /// Returns `true` if the owner of the file may not write to it.
+    pub fn is_readonly(&self) -> bool {
+        !self.stat.st_mode.contains(IoStatMode::IWUSR)
+    }
+
+    /// The creation time, as a duration since the unix epoch.
+    ///
+    /// Returns `None` if the device does not record a valid timestamp.
+    pub fn created(&self) -> Option<Duration> {
+        datetime_to_unix(&self.stat.st_ctime)
+    }
+
+    /// The last modification time, as a duration since the unix epoch.
+    pub fn modified(&self) -> Option<Duration> {
+        datetime_to_unix(&self.stat.st_mtime)
+    }
+
+    /// The last access time, as a duration since the unix epoch.
+    pub fn accessed(&self) -> Option<Duration> {
+        datetime_to_unix(&self.stat.st_atime)
+    }
+
+    /// Returns the raw `SceIoStat` structure.
+    pub fn as_raw(&self) -> &SceIoStat {
+        &self.stat
+    }
+}
+
+/// Convert a `ScePspDateTime` into a duration since the unix epoch.
+///
+/// The PSP stores file times without a timezone, so the result is only as
+/// accurate as the clock of the device which wrote the file. Returns `None`
+/// for out of range dates, such as the all-zero timestamps reported by some
+/// devices.
+pub fn datetime_to_unix(dt: &ScePspDateTime) -> Option<Duration> {
+    let (year, month, day) = (dt.year as i64, dt.month as i64, dt.day as i64);
+
+    if year < 1970 || month < 1 || month > 12 || day < 1 || day > 31 {
+        return None;
+    }
+
+    // Days since 1970-01-01, from Howard Hinnant's `days_from_civil`.
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = y / 400;
+    let yoe = y - era * 400;
+    let mp = (month + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    let days = era * 146097 + doe - 719468;
+
+    let secs = days * 86400
+        + dt.hour as i64 * 3600
+        + dt.minutes as i64 * 60
+        + dt.seconds as i64;
+
+    Some(Duration::from_secs(secs as u64) + Duration::from_nanos(dt.microseconds as u64 * 1000))
+}
+
+/// An entry returned by the `ReadDir` iterator.
+#[derive(Debug, Clone)]
+pub struct DirEntry {
+    path: String,
+    name: String,
+    metadata: Metadata,
+}
+
+impl DirEntry {
+    /// The full path to this entry, i.e. the directory joined with the name.
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// The bare file name of this entry.
+    pub fn file_name(&self) -> &str {
+        &self.name
+    }
+
+    /// The metadata for this entry. This is returned alongside the name by
+    /// the IO manager, so no extra call is made.
+    pub fn metadata(&self) -> Metadata {
+        self.metadata
+    }
+
+    /// The file type for this entry.
+    pub fn file_type(&self) -> FileType {
+        self.metadata.file_type()
+    }
+}
+
+/// Iterator over the entries in a directory. The `.` and `..` entries are
+/// skipped.
+#[derive(Debug)]
+pub struct ReadDir {
+    fd: SceUid,
+    dir: String,
+}
+
+impl Iterator for ReadDir {
+    type Item = io::Result<DirEntry>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            // `d_private` must be zeroed, or `sceIoDread` may write through it.
+            let mut dirent: SceIoDirent = unsafe { mem::zeroed() };
+            let ret = unsafe { sys::sceIoDread(self.fd, &mut dirent) };
+
+            match ret {
+                0 => return None,
+                n if n < 0 => return Some(Err(Error::from_sce(n))),
+                _ => {}
+            }
+
+            let len = dirent.d_name.iter().position(|&b| b == 0).unwrap_or(256);
+            let name = String::from_utf8_lossy(&dirent.d_name[..len]).into_owned();
+
+            if name == "." || name == ".." {
+                continue;
+            }
+
+            return Some(Ok(DirEntry {
+                path: join(&self.dir, &name),
+                name,
+                metadata: Metadata { stat: dirent.d_stat },
+            }));
+        }
+    }
+}
+
+impl Drop for ReadDir {
+    fn drop(&mut self) {
+        unsafe {
+            sys::sceIoDclose(self.fd);
+        }
+    }
+}
+
+/// Returns an iterator over the entries within a directory.
+pub fn read_dir(path: &str) -> io::Result<ReadDir> {
+    let c = c_path(path)?;
+    let fd = unsafe { sys::sceIoDopen(c.as_ptr()) };
+    cvt(fd.0)?;
+
+    Ok(ReadDir { fd, dir: String::from(path) })
+}
+
+/// Query the metadata of a file or directory.
+pub fn metadata(path: &str) -> io::Result<Metadata> {
+    let c = c_path(path)?;
+    let mut stat: SceIoStat = unsafe { mem::zeroed() };
+    cvt(unsafe { sys::sceIoGetstat(c.as_ptr(), &mut stat) })?;
+
+    Ok(Metadata { stat })
+}
+
+/// Returns `true` if the path points at an existing entity.
+pub fn exists(path: &str) -> bool {
+    metadata(path).is_ok()
+}
+
+/// Read the entire contents of a file into a byte vector.
+pub fn read(path: &str) -> io::Result<Vec<u8>> {
+    let mut file = File::open(path)?;
+    let mut bytes = Vec::with_capacity(file.len()? as usize);
+    file.read_to_end(&mut bytes)?;
+
+    Ok(bytes)
+}
+
+/// Read the entire contents of a file into a string.
+pub fn read_to_string(path: &str) -> io::Result<String> {
+    String::from_utf8(read(path)?).map_err(|_| Error::new(ErrorKind::InvalidData))
+}
+
+/// Write a slice as the entire contents of a file, replacing it if it exists.
+pub fn write(path: &str, contents: &[u8]) -> io::Result<()> {
+    File::create(path)?.write_all(contents)
+}
+
+/// Create a new, empty directory.
+pub fn create_dir(path: &str) -> io::Result<()> {
+    let c = c_path(path)?;
+    cvt(unsafe { sys::sceIoMkdir(c.as_ptr(), 0o777) }).map(|_| ())
+}
+
+/// Recursively create a directory and all of its missing parents.
+pub fn create_dir_all(path: &str) -> io::Result<()> {
+    let (device, rest) = split_device(path);
+    let prefix_len = device.map(|d| d.len()).unwrap_or(0);
+
+    let separators = rest
+        .char_indices()
+        .filter(|&(i, c)| c == '/' && i > 0)
+        .map(|(i, _)| prefix_len + i);
+
+    for end in separators.chain(core::iter::once(path.len())) {
+        let dir = path[..end].trim_end_matches('/');
+
+        if dir.len() <= prefix_len {
+            continue;
+        }
+
+        match create_dir(dir) {
+            Ok(()) => {}
+            Err(e) if e.kind() == ErrorKind::AlreadyExists => {}
+            // Some devices refuse `mkdir` on existing directories with a
+            // generic error. Accept that as long as it is really a directory.
+            Err(e) => match metadata(dir) {
+                Ok(m) if m.is_dir() => {}
+                _ => return Err(e),
+            },
+        }
+    }
+
+    Ok(())
+}
+
+/// Remove a file.
+pub fn remove_file(path: &str) -> io::Result<()> {
+    let c = c_path(path)?;
+    cvt(unsafe { sys::sceIoRemove(c.as_ptr()) }).map(|_| ())
+}
+
+/// Remove an empty directory.
+pub fn remove_dir(path: &str) -> io::Result<()> {
+    let c = c_path(path)?;
+    cvt(unsafe { sys::sceIoRmdir(c.as_ptr()) }).map(|_| ())
+}
+
+/// Remove a directory after removing all of its contents.
+pub fn remove_dir_all(path: &str) -> io::Result<()> {
+    // Collect first, as removing entries while iterating is not supported by
+    // every device.
+    let entries = read_dir(path)?.collect::<io::Result<Vec<_>>>()?;
+
+    for entry in entries {
+        if entry.file_type().is_dir() {
+            remove_dir_all(entry.path())?;
+        } else {
+            remove_file(entry.path())?;
+        }
+    }
+
+    remove_dir(path)
+}
+
+/// Rename a file or directory. Both paths must be on the same device.
+pub fn rename(from: &str, to: &str) -> io::Result<()> {
+    let from = c_path(from)?;
+    let to = c_path(to)?;
+
+    cvt(unsafe { sys::sceIoRename(from.as_ptr(), to.as_ptr()) }).map(|_| ())
+}
+
+/// Change the current directory, against which relative paths are resolved.
+pub fn set_current_dir(path: &str) -> io::Result<()> {
+    let c = c_path(path)?;
+    cvt(unsafe { sys::sceIoChdir(c.as_ptr()) }).map(|_| ())
+}
@@ -0,0 +1,191 @@
+//! Decoding of uncompressed Windows bitmaps.
+
+use super::{rgba, Image, ImageError};
+use alloc::vec;
+use alloc::vec::Vec;
+
+/// Size of the file header, before the DIB header.
+const FILE_HEADER_SIZE: usize = 14;
+
+/// Compression methods.
+const BI_RGB: u32 = 0;
+const BI_BITFIELDS: u32 = 3;
+const BI_ALPHABITFIELDS: u32 = 6;
+
+/// A channel stored in a bit mask of 16 and 32-bit pixels.
+#[derive(Copy, Clone)]
+struct Mask {
+    mask: u32,
+    shift: u32,
+    max: u32,
+}
+
+impl Mask {
+    fn new(mask: u32) -> Self {
+        let shift = if mask == 0 { 0 } else { mask.trailing_zeros() };
+
+        Self {
+            mask,
+            shift,
+            max: mask >> shift,
+        }
+    }
+
+    /// The channel of `pixel`, scaled to 8 bits, or `default` if the channel
+    /// is not stored.
+    fn get(self, pixel: u32, default: u8) -> u8 {
+        if self.max == 0 {
+            return default;
+        }
+
+        (((pixel & self.mask) >> self.shift) as u64 * 255 / self.max as u64) as u8
+    }
+}
+
+pub(super) fn decode(data: &[u8]) -> Result<Image, ImageError> {
+    if !data.starts_with(b"BM") {
+        return Err(ImageError::UnknownFormat);
+    }
+
+    let pixel_offset = read_u32(data, 10)? as usize;
+    let dib_size = read_u32(data, 14)? as usize;
+
+    // The OS/2 header has 16-bit sizes and 3-byte palette entries.
+    let (width, height, bpp, compression, colors, entry_size) = if dib_size == 12 {
+        let width = read_u16(data, 18)? as i32;
+        let height = read_u16(data, 20)? as i32;
+
+        (width, height, read_u16(data, 24)?, BI_RGB, 0, 3)
+    } else {
+        let width = read_u32(data, 18)? as i32;
+        let height = read_u32(data, 22)? as i32;
+        let bpp = read_u16(data, 28)?;
+        let compression = read_u32(data, 30)?;
+        let colors = read_u32(data, 46)? as usize;
+
+        (width, height, bpp, compression, colors, 4)
+    };
+
+    // Rows are stored bottom-up, unless the height is negative.
+    let top_down = height < 0;
+    let (width, height) = (width.max(0) as usize, (height as i64).abs() as usize);
+
+    if width == 0 || height == 0 {
+        return Err(ImageError::Corrupt);
+    }
+
+    super::check_size(width, height)?;
+
+    match bpp {
+        1 | 2 | 4 | 8 | 16 | 24 | 32 => {}
+        _ => return Err(ImageError::Unsupported),
+    }
+
+    let masks = match (compression, bpp) {
+        (BI_RGB, 16) => [0x7c00, 0x03e0, 0x001f, 0],
+        (BI_RGB, 32) => [0x00ff_0000, 0x0000_ff00, 0x0000_00ff, 0],
+        (BI_RGB, _) => [0; 4],
+        (BI_BITFIELDS, 16)
+        | (BI_BITFIELDS, 32)
+        | (BI_ALPHABITFIELDS, 16)
+        | (BI_ALPHABITFIELDS, 32) => {
+            // The masks follow the 40-byte header, and are part of the
+            // larger ones. The alpha mask is only in the larger headers.
+            let base = FILE_HEADER_SIZE + 40;
+            let alpha = if dib_size >= 56 || compression == BI_ALPHABITFIELDS {
+                read_u32(data, base + 12)?
+            } else {
+                0
+            };
+
+            [
+                read_u32(data, base)?,
+                read_u32(data, base + 4)?,
+                read_u32(data, base + 8)?,
+                alpha,
+            ]
+        }
+        _ => return Err(ImageError::Unsupported),
+    };
+    let masks = [
+        Mask::new(masks[0]),
+        Mask::new(masks[1]),
+        Mask::new(masks[2]),
+        Mask::new(masks[3]),
+    ];
+
+    let palette: Vec<u32> = if bpp <= 8 {
+        let count = if colors == 0 {
+            1 << bpp
+        } else {
+            colors.min(256)
+        };
+        let start = FILE_HEADER_SIZE + dib_size;
+        let bytes = data
+            .get(start..start + count * entry_size)
+            .ok_or(ImageError::Truncated)?;
+
+        bytes
+            .chunks_exact(entry_size)
+            .map(|c| rgba(c[2], c[1], c[0], 255))
+            .collect()
+    } else {
+        Vec::new()
+    };
+
+    // Rows are padded to 4 bytes.
+    let stride = (width * bpp as usize + 31) / 32 * 4;
+    let bytes = data
+        .get(pixel_offset..)
+        .and_then(|rest| rest.get(..stride * height))
+        .ok_or(ImageError::Truncated)?;
+
+    let mut pixels = vec![0; width * height];
+
+    for (i, row) in bytes.chunks_exact(stride).enumerate() {
+        let y = if top_down { i } else { height - 1 - i };
+        let out = &mut pixels[y * width..(y + 1) * width];
+
+        for (x, pixel) in out.iter_mut().enumerate() {
+            *pixel = match bpp {
+                1..=8 => {
+                    let bit = x * bpp as usize;
+                    let shift = 8 - bpp as usize - bit % 8;
+                    let index = (row[bit / 8] >> shift) & ((1u16 << bpp) - 1) as u8;
+
+                    *palette.get(index as usize).ok_or(ImageError::Corrupt)?
+                }
+                16 | 32 => {
+                    let value = if bpp == 16 {
+                        u16::from_le_bytes([row[x * 2], row[x * 2 + 1]]) as u32
+                    } else {
+                        let p = &row[x * 4..x * 4 + 4];
+                        u32::from_le_bytes([p[0], p[1], p[2], p[3]])
+                    };
+
+                    rgba(
+                        masks[0].get(value, 0),
+                        masks[1].get(value, 0),
+                        masks[2].get(value, 0),
+                        masks[3].get(value, 255),
+                    )
+                }
+                _ => rgba(row[x * 3 + 2], row[x * 3 + 1], row[x * 3], 255),
+            };
+        }
+    }
+
+    Ok(Image::new(width, height, pixels))
+}
+
+fn read_u16(data: &[u8], offset: usize) -> Result<u16, ImageError> {
+    let bytes = data.get(offset..offset + 2).ok_or(ImageError::Truncated)?;
+
+    Ok(u16::from_le_bytes([bytes[0], bytes[1]]))
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Result<u32, ImageError> {
+    let bytes = data.get(offset..offset + 4).ok_or(ImageError::Truncated)?;
+
+    Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
@@ -0,0 +1,312 @@
+//! Decompression of zlib streams (RFC 1950 and 1951), as used by PNG.
+
+use super::ImageError;
+use alloc::vec::Vec;
+
+/// Longest Huffman code, in bits.
+const MAX_BITS: usize = 15;
+
+/// Base lengths and extra bits of the length symbols 257..285.
+const LENGTH_BASE: [u16; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131,
+    163, 195, 227, 258,
+];
+const LENGTH_EXTRA: [u8; 29] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0,
+];
+
+/// Base distances and extra bits of the distance symbols 0..29.
+const DIST_BASE: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537,
+    2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577,
+];
+const DIST_EXTRA: [u8; 30] = [
+    0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13,
+    13,
+];
+
+/// The order in which code length code lengths are stored.
+const CODE_LENGTH_ORDER: [usize; 19] = [
+    16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15,
+];
+
+/// Reads bits least significant first.
+struct Bits<'a> {
+    data: &'a [u8],
+    pos: usize,
+    buf: u32,
+    count: u32,
+}
+
+impl<'a> Bits<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self {
+            data,
+            pos: 0,
+            buf: 0,
+            count: 0,
+        }
+    }
+
+    fn read(&mut self, n: u32) -> Result<u32, ImageError> {
+        while self.count < n {
+            let byte = *self.data.get(self.pos).ok_or(ImageError::Truncated)?;
+            self.pos += 1;
+            self.buf |= (byte as u32) << self.count;
+            self.count += 8;
+        }
+
+        let value = self.buf & ((1 << n) - 1);
+        self.buf >>= n;
+        self.count -= n;
+
+        Ok(value)
+    }
+
+    /// Skip to the next byte boundary.
+    fn align(&mut self) {
+        self.buf = 0;
+        self.count = 0;
+    }
+}
+
+/// A canonical Huffman code, as the number of codes of each length and the
+/// symbols ordered by code.
+struct Huffman {
+    counts: [u16; MAX_BITS + 1],
+    symbols: [u16; 288],
+}
+
+impl Huffman {
+    fn new(lengths: &[u8]) -> Result<Self, ImageError> {
+        let mut counts = [0u16; MAX_BITS + 1];
+        for &len in lengths {
+            counts[len as usize] += 1;
+        }
+
+        // Incomplete codes are allowed, over-subscribed ones are not.
+        let mut left = 1i32;
+        for &count in &counts[1..] {
+            left = (left << 1) - count as i32;
+            if left < 0 {
+                return Err(ImageError::Corrupt);
+            }
+        }
+
+        let mut offsets = [0u16; MAX_BITS + 1];
+        for len in 1..MAX_BITS {
+            offsets[len + 1] = offsets[len] + counts[len];
+        }
+
+        let mut symbols = [0u16; 288];
+        for (symbol, &len) in lengths.iter().enumerate() {
+            if len != 0 {
+                symbols[offsets[len as usize] as usize] = symbol as u16;
+                offsets[len as usize] += 1;
+            }
+        }
+
+        Ok(Self { counts, symbols })
+    }
+
+    fn decode(&self, bits: &mut Bits<'_>) -> Result<u16, ImageError> {
+        let (mut code, mut first, mut index) = (0i32, 0i32, 0i32);
+
+        for len in 1..=MAX_BITS {
+            code |= bits.read(1)? as i32;
+            let count = self.counts[len] as i32;
+
+            if code - count < first {
+                return Ok(self.symbols[(index + code - first) as usize]);
+            }
+
+            index += count;
+            first = (first + count) << 1;
+            code <<= 1;
+        }
+
+        Err(ImageError::Corrupt)
+    }
+}
+
+/// Decompress a zlib stream. The checksum is not verified.
+pub(super) fn decompress(data: &[u8]) -> Result<Vec<u8>, ImageError> {
+    if data.len() < 2 {
+        return Err(ImageError::Truncated);
+    }
+
+    let (cmf, flg) = (data[0], data[1]);
+    let check = ((cmf as u16) << 8 | flg as u16) % 31;
+
+    // Only deflate is defined, and preset dictionaries are not used by PNG.
+    if cmf & 0x0f != 8 || check != 0 || flg & 0x20 != 0 {
+        return Err(ImageError::Corrupt);
+    }
+
+    let mut out = Vec::new();
+    inflate(&data[2..], &mut out)?;
+
+    Ok(out)
+}
+
+/// Decompress raw deflate data into `out`.
+fn inflate(data: &[u8], out: &mut Vec<u8>) -> Result<(), ImageError> {
+    let mut bits = Bits::new(data);
+
+    loop {
+        let last = bits.read(1)? == 1;
+
+        match bits.read(2)? {
+            0 => stored(&mut bits, out)?,
+            1 => {
+                let (lengths, distances) = fixed_codes()?;
+                codes(&mut bits, out, &lengths, &distances)?;
+            }
+            2 => {
+                let (lengths, distances) = dynamic_codes(&mut bits)?;
+                codes(&mut bits, out, &lengths, &distances)?;
+            }
+            _ => return Err(ImageError::Corrupt),
+        }
+
+        if last {
+            return Ok(());
+        }
+    }
+}
+
+fn stored(bits: &mut Bits<'_>, out: &mut Vec<u8>) -> Result<(), ImageError> {
+    bits.align();
+
+    let header = bits
+        .data
+        .get(bits.pos..bits.pos + 4)
+        .ok_or(ImageError::Truncated)?;
+    let len = u16::from_le_bytes([header[0], header[1]]);
+    let nlen = u16::from_le_bytes([header[2], header[3]]);
+
+    if len != !nlen {
+        return Err(ImageError::Corrupt);
+    }
+
+    let start = bits.pos + 4;
+    let block = bits
+        .data
+        .get(start..start + len as usize)
+        .ok_or(ImageError::Truncated)?;
+    out.extend_from_slice(block);
+    bits.pos = start + len as usize;
+
+    Ok(())
+}
+
+fn fixed_codes() -> Result<(Huffman, Huffman), ImageError> {
+    let mut lengths = [0u8; 288];
+    for (symbol, len) in lengths.iter_mut().enumerate() {
+        *len = match symbol {
+            0..=143 => 8,
+            144..=255 => 9,
+            256..=279 => 7,
+            _ => 8,
+        };
+    }
+
+    Ok((Huffman::new(&lengths)?, Huffman::new(&[5; 30])?))
+}
+
+fn dynamic_codes(bits: &mut Bits<'_>) -> Result<(Huffman, Huffman), ImageError> {
+    let literals = bits.read(5)? as usize + 257;
+    let distances = bits.read(5)? as usize + 1;
+    let code_lengths = bits.read(4)? as usize + 4;
+
+    if literals > 286 || distances > 30 {
+        return Err(ImageError::Corrupt);
+    }
+
+    let mut lengths = [0u8; 19];
+    for &index in &CODE_LENGTH_ORDER[..code_lengths] {
+        lengths[index] = bits.read(3)? as u8;
+    }
+    let code_length_code = Huffman::new(&lengths)?;
+
+    // The literal/length and distance code lengths form a single sequence,
+    // compressed with the code length code.
+    let mut lengths = [0u8; 286 + 30];
+    let total = literals + distances;
+    let mut i = 0;
+
+    while i < total {
+        let symbol = code_length_code.decode(bits)?;
+
+        let (len, repeat) = match symbol {
+            0..=15 => (symbol as u8, 1),
+            16 if i > 0 => (lengths[i - 1], 3 + bits.read(2)? as usize),
+            17 => (0, 3 + bits.read(3)? as usize),
+            18 => (0, 11 + bits.read(7)? as usize),
+            _ => return Err(ImageError::Corrupt),
+        };
+
+        if i + repeat > total {
+            return Err(ImageError::Corrupt);
+        }
+
+        for length in &mut lengths[i..i + repeat] {
+            *length = len;
+        }
+        i += repeat;
+    }
+
+    // Without an end of block code, the block could never end.
+    if lengths[256] == 0 {
+        return Err(ImageError::Corrupt);
+    }
+
+    Ok((
+        Huffman::new(&lengths[..literals])?,
+        Huffman::new(&lengths[literals..total])?,
+    ))
+}
+
+fn codes(
+    bits: &mut Bits<'_>,
+    out: &mut Vec<u8>,
+    lengths: &Huffman,
+    distances: &Huffman,
+) -> Result<(), ImageError> {
+    loop {
+        let symbol = lengths.decode(bits)? as usize;
+
+        if symbol < 256 {
+            out.push(symbol as u8);
+            continue;
+        }
+
+        if symbol == 256 {
+            return Ok(());
+        }
+
+        let symbol = symbol - 257;
+        if symbol >= LENGTH_BASE.len() {
+            return Err(ImageError::Corrupt);
+        }
+
+        let len = LENGTH_BASE[symbol] as usize + bits.read(LENGTH_EXTRA[symbol] as u32)? as usize;
+
+        let symbol = distances.decode(bits)? as usize;
+        if symbol >= DIST_BASE.len() {
+            return Err(ImageError::Corrupt);
+        }
+
+        let dist = DIST_BASE[symbol] as usize + bits.read(DIST_EXTRA[symbol] as u32)? as usize;
+        if dist > out.len() {
+            return Err(ImageError::Corrupt);
+        }
+
+        // The copy may overlap the bytes it produces.
+        let start = out.len() - dist;
+        for i in 0..len {
+            let byte = out[start + i];
+            out.push(byte);
+        }
+    }
+}
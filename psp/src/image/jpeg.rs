@@ -0,0 +1,156 @@
+//! Decoding of JPEG images with the hardware decoder of `sceJpeg`.
+
+use super::{AlignedBytes, Image, ImageError};
+use crate::error;
+use crate::modules::ModuleGuard;
+use crate::sync::{critical_section, Mutex};
+use crate::sys::{self, AvModule};
+use alloc::vec::Vec;
+use core::ffi::c_void;
+
+/// Held while a decode is running, as the library has a single context.
+/// Created by the first decode.
+static mut DECODER: Option<Mutex<()>> = None;
+
+/// The size of a JPEG image, from its start of frame marker.
+fn dimensions(data: &[u8]) -> Result<(usize, usize), ImageError> {
+    let mut offset = 2;
+
+    loop {
+        // Markers may be preceded by any number of fill bytes.
+        while data.get(offset) == Some(&0xff) && data.get(offset + 1) == Some(&0xff) {
+            offset += 1;
+        }
+
+        let marker = data.get(offset..offset + 4).ok_or(ImageError::Truncated)?;
+        if marker[0] != 0xff {
+            return Err(ImageError::Corrupt);
+        }
+
+        let len = u16::from_be_bytes([marker[2], marker[3]]) as usize;
+
+        match marker[1] {
+            // Baseline and extended sequential frames.
+            0xc0 | 0xc1 => {
+                let frame = data
+                    .get(offset + 4..offset + 9)
+                    .ok_or(ImageError::Truncated)?;
+                let height = u16::from_be_bytes([frame[1], frame[2]]) as usize;
+                let width = u16::from_be_bytes([frame[3], frame[4]]) as usize;
+
+                return Ok((width, height));
+            }
+            // Progressive, lossless and arithmetic coded frames.
+            0xc2 | 0xc3 | 0xc5..=0xc7 | 0xc9..=0xcb | 0xcd..=0xcf => {
+                return Err(ImageError::Unsupported)
+            }
+            // The scan starts before any frame header.
+            0xda => return Err(ImageError::Corrupt),
+            _ => offset += 2 + len,
+        }
+    }
+}
+
+pub(super) fn decode(data: &[u8]) -> Result<Image, ImageError> {
+    if !data.starts_with(&[0xff, 0xd8]) {
+        return Err(ImageError::UnknownFormat);
+    }
+
+    let (width, height) = dimensions(data)?;
+
+    if width == 0 || height == 0 {
+        return Err(ImageError::Corrupt);
+    }
+
+    super::check_size(width, height)?;
+
+    let _modules = ModuleGuard::load(&[AvModule::AvCodec.into()])?;
+
+    // The decoder reads the input and writes the output with DMA, so both
+    // need to be in memory rather than in the data cache.
+    let mut input = AlignedBytes::new(data.len());
+    input.as_mut_slice().copy_from_slice(data);
+    let mut output = AlignedBytes::new(width * height * 4);
+
+    unsafe {
+        let input = input.as_mut_slice();
+        sys::sceKernelDcacheWritebackRange(input.as_ptr() as *const c_void, input.len() as u32);
+
+        let output = output.as_mut_slice();
+        sys::sceKernelDcacheWritebackInvalidateRange(
+            output.as_ptr() as *const c_void,
+            output.len() as u32,
+        );
+    }
+
+    let result = {
+        let _lock = decoder().lock();
+        unsafe { decode_locked(input.as_mut_slice(), output.as_mut_slice(), width, height) }
+    };
+    result?;
+
+    unsafe {
+        let output = output.as_slice();
+        sys::sceKernelDcacheInvalidateRange(output.as_ptr() as *const c_void, output.len() as u32);
+    }
+
+    // The decoder leaves the alpha channel empty.
+    let pixels: Vec<u32> = output
+        .as_slice()
+        .chunks_exact(4)
+        .map(|p| u32::from_le_bytes([p[0], p[1], p[2], 0xff]))
+        .collect();
+
+    Ok(Image::new(width, height, pixels))
+}
+
+unsafe fn decode_locked(
+    input: &mut [u8],
+    output: &mut [u8],
+    width: usize,
+    height: usize,
+) -> Result<(), ImageError> {
+    error::check(sys::sceJpegInitMJpeg())?;
+
+    let result = match error::check(sys::sceJpegCreateMJpeg(width as i32, height as i32)) {
+        Ok(_) => {
+            let ret = sys::sceJpegDecodeMJpeg(
+                input.as_mut_ptr(),
+                input.len(),
+                output.as_mut_ptr() as *mut c_void,
+                0,
+            );
+            sys::sceJpegDeleteMJpeg();
+
+            error::check(ret).map(|_| ())
+        }
+        Err(e) => Err(e),
+    };
+
+    sys::sceJpegFinishMJpeg();
+
+    Ok(result?)
+}
+
+/// The lock of the decoder.
+fn decoder() -> &'static Mutex<()> {
+    if let Some(lock) = unsafe { critical_section(|| DECODER.as_ref()) } {
+        return lock;
+    }
+
+    // Creating the semaphore is a kernel call, so it happens outside the
+    // critical section. If another thread created the lock meanwhile, this
+    // one is dropped.
+    let lock = Mutex::new(());
+    let spare = critical_section(|| unsafe {
+        if DECODER.is_none() {
+            DECODER = Some(lock);
+            None
+        } else {
+            Some(lock)
+        }
+    });
+    drop(spare);
+
+    unsafe { DECODER.as_ref().unwrap() }
+}
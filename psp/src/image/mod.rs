@@ -0,0 +1,270 @@
+//! Image decoding and texture conversion.
+//!
+//! JPEG images are decoded by the hardware decoder, PNG, BMP and TGA images
+//! in software. Decoded images can be converted to a `Texture` in any of the
+//! common `TexturePixelFormat`s, including palettized ones, ready to be
+//! bound with `sceGuTexImage`.
+//!
+//! # Example
+//!
+//! ```ignore
+//! use psp::image::Image;
+//! use psp::sys::TexturePixelFormat;
+//!
+//! let image = Image::load("ms0:/PSP/GAME/app/sprite.png")?;
+//! let texture = image.to_texture(TexturePixelFormat::PsmT8, true)?;
+//!
+//! // Inside a display list:
+//! unsafe { texture.bind() };
+//! # Ok::<(), psp::io::Error>(())
+//! ```
+
+mod bmp;
+mod inflate;
+mod jpeg;
+mod png;
+mod texture;
+mod tga;
+
+pub use texture::{Texture, MAX_TEXTURE_SIZE};
+
+use crate::error::Error;
+use crate::fs;
+use crate::io;
+use crate::sys::TexturePixelFormat;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::{fmt, slice};
+
+/// Largest width or height of an image that can be decoded.
+pub const MAX_IMAGE_SIZE: usize = 4096;
+
+/// Largest number of pixels in an image that can be decoded. At 4 bytes per
+/// pixel, this is 4 MiB, a good part of the memory of the console.
+pub const MAX_IMAGE_PIXELS: usize = 1024 * 1024;
+
+/// Error returned when decoding an image.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ImageError {
+    /// The data is not in a known image format.
+    UnknownFormat,
+    /// The data ends before the end of the image.
+    Truncated,
+    /// The data is malformed.
+    Corrupt,
+    /// The image uses a feature that is not supported, such as BMP run length
+    /// encoding, or the texture format is not supported.
+    Unsupported,
+    /// The image is too large to decode, or to fit in a texture.
+    TooLarge,
+    /// The hardware decoder failed.
+    Sce(Error),
+}
+
+impl From<Error> for ImageError {
+    fn from(error: Error) -> Self {
+        ImageError::Sce(error)
+    }
+}
+
+impl From<ImageError> for io::Error {
+    fn from(error: ImageError) -> Self {
+        match error {
+            ImageError::Sce(error) => error.into(),
+            _ => io::Error::new(io::ErrorKind::InvalidData),
+        }
+    }
+}
+
+impl fmt::Display for ImageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ImageError::UnknownFormat => f.write_str("unknown image format"),
+            ImageError::Truncated => f.write_str("truncated image"),
+            ImageError::Corrupt => f.write_str("corrupt image"),
+            ImageError::Unsupported => f.write_str("unsupported image feature"),
+            ImageError::TooLarge => f.write_str("image too large"),
+            ImageError::Sce(error) => write!(f, "hardware decoder error: {}", error),
+        }
+    }
+}
+
+/// The file formats `Image` can decode.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ImageFormat {
+    /// JPEG, decoded in hardware. Only baseline images are supported.
+    Jpeg,
+    /// PNG, in any color type and bit depth, interlaced or not.
+    Png,
+    /// Uncompressed Windows bitmaps.
+    Bmp,
+    /// Truevision TGA, raw or run length encoded.
+    Tga,
+}
+
+impl ImageFormat {
+    /// Guess the format of `data` from its first bytes.
+    pub fn detect(data: &[u8]) -> Option<Self> {
+        if data.starts_with(&[0xff, 0xd8, 0xff]) {
+            Some(ImageFormat::Jpeg)
+        } else if data.starts_with(&png::SIGNATURE) {
+            Some(ImageFormat::Png)
+        } else if data.starts_with(b"BM") {
+            Some(ImageFormat::Bmp)
+        } else if tga::is_tga(data) {
+            Some(ImageFormat::Tga)
+        } else {
+            None
+        }
+    }
+}
+
+/// A decoded image.
+///
+/// Pixels are stored row by row as `0xAABBGGRR`, the layout of the `Psm8888`
+/// pixel format.
+#[derive(Clone, PartialEq, Eq)]
+pub struct Image {
+    width: usize,
+    height: usize,
+    pixels: Vec<u32>,
+}
+
+impl Image {
+    /// Create an image from its pixels.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there are not `width * height` pixels.
+    pub fn new(width: usize, height: usize, pixels: Vec<u32>) -> Self {
+        assert_eq!(pixels.len(), width * height, "wrong number of pixels");
+
+        Self {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    /// Decode an image in any supported format.
+    pub fn decode(data: &[u8]) -> Result<Self, ImageError> {
+        let format = ImageFormat::detect(data).ok_or(ImageError::UnknownFormat)?;
+
+        Self::decode_as(data, format)
+    }
+
+    /// Decode an image in the given format.
+    pub fn decode_as(data: &[u8], format: ImageFormat) -> Result<Self, ImageError> {
+        match format {
+            ImageFormat::Jpeg => jpeg::decode(data),
+            ImageFormat::Png => png::decode(data),
+            ImageFormat::Bmp => bmp::decode(data),
+            ImageFormat::Tga => tga::decode(data),
+        }
+    }
+
+    /// Read and decode the image file at `path`.
+    pub fn load(path: &str) -> io::Result<Self> {
+        Ok(Self::decode(&fs::read(path)?)?)
+    }
+
+    /// The width of the image, in pixels.
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// The height of the image, in pixels.
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// The pixels of the image, row by row.
+    pub fn pixels(&self) -> &[u32] {
+        &self.pixels
+    }
+
+    /// Mutable access to the pixels of the image.
+    pub fn pixels_mut(&mut self) -> &mut [u32] {
+        &mut self.pixels
+    }
+
+    /// The pixel at column `x` of row `y`, if it is in the image.
+    pub fn pixel(&self, x: usize, y: usize) -> Option<u32> {
+        if x < self.width {
+            self.pixels.get(y * self.width + x).copied()
+        } else {
+            None
+        }
+    }
+
+    /// Take the pixels out of the image.
+    pub fn into_pixels(self) -> Vec<u32> {
+        self.pixels
+    }
+
+    /// Convert the image to a texture in `format`, padded to power of two
+    /// dimensions, and swizzled if `swizzle` is `true`.
+    ///
+    /// `PsmT4` and `PsmT8` images are reduced to 16 and 256 colors, with a
+    /// `Psm8888` CLUT. Textures are at most 512 pixels wide and high, and the
+    /// `PsmT16`, `PsmT32` and DXT formats are not supported.
+    pub fn to_texture(
+        &self,
+        format: TexturePixelFormat,
+        swizzle: bool,
+    ) -> Result<Texture, ImageError> {
+        Texture::new(self, format, swizzle)
+    }
+}
+
+impl fmt::Debug for Image {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Image")
+            .field("width", &self.width)
+            .field("height", &self.height)
+            .finish()
+    }
+}
+
+/// Pack a color in the layout of `Image` pixels.
+fn rgba(r: u8, g: u8, b: u8, a: u8) -> u32 {
+    u32::from_le_bytes([r, g, b, a])
+}
+
+/// Check that an image is small enough to decode.
+fn check_size(width: usize, height: usize) -> Result<(), ImageError> {
+    if width > MAX_IMAGE_SIZE || height > MAX_IMAGE_SIZE || width * height > MAX_IMAGE_PIXELS {
+        return Err(ImageError::TooLarge);
+    }
+
+    Ok(())
+}
+
+/// A line of the data cache.
+#[repr(C, align(64))]
+#[derive(Copy, Clone)]
+struct Line([u8; 64]);
+
+/// Zeroed bytes aligned to the data cache lines, as the hardware wants for
+/// textures and decoder output.
+pub(crate) struct AlignedBytes {
+    lines: Vec<Line>,
+    len: usize,
+}
+
+impl AlignedBytes {
+    pub(crate) fn new(len: usize) -> Self {
+        Self {
+            lines: vec![Line([0; 64]); (len + 63) / 64],
+            len,
+        }
+    }
+
+    pub(crate) fn as_slice(&self) -> &[u8] {
+        unsafe { slice::from_raw_parts(self.lines.as_ptr() as *const u8, self.len) }
+    }
+
+    pub(crate) fn as_mut_slice(&mut self) -> &mut [u8] {
+        unsafe { slice::from_raw_parts_mut(self.lines.as_mut_ptr() as *mut u8, self.len) }
+    }
+}
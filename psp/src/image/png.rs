@@ -0,0 +1,297 @@
+//! Decoding of PNG images, including interlaced ones.
+
+use super::{inflate, rgba, Image, ImageError};
+use alloc::vec;
+use alloc::vec::Vec;
+
+/// The signature at the start of every PNG file.
+pub(super) const SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n'];
+
+/// The starting column and row and the spacing of each Adam7 pass.
+const ADAM7: [(usize, usize, usize, usize); 7] = [
+    (0, 0, 8, 8),
+    (4, 0, 8, 8),
+    (0, 4, 4, 8),
+    (2, 0, 4, 4),
+    (0, 2, 2, 4),
+    (1, 0, 2, 2),
+    (0, 1, 1, 2),
+];
+
+/// How samples map to colors, from the `IHDR` chunk and the chunks after it.
+struct Format {
+    color_type: u8,
+    depth: u8,
+    channels: usize,
+    palette: Vec<u32>,
+    /// The transparent color of grayscale and RGB images, as raw samples.
+    transparent: Option<[u16; 3]>,
+}
+
+impl Format {
+    fn bits_per_pixel(&self) -> usize {
+        self.channels * self.depth as usize
+    }
+
+    /// The color of pixel `x` of an unfiltered row.
+    fn pixel(&self, row: &[u8], x: usize) -> Result<u32, ImageError> {
+        let sample = |c| read_sample(row, x * self.channels + c, self.depth);
+        let max = (1u32 << self.depth) - 1;
+        let scale = |value: u16| (value as u32 * 255 / max) as u8;
+
+        Ok(match self.color_type {
+            0 => {
+                let gray = sample(0);
+                let alpha = match self.transparent {
+                    Some(t) if t[0] == gray => 0,
+                    _ => 255,
+                };
+
+                rgba(scale(gray), scale(gray), scale(gray), alpha)
+            }
+            2 => {
+                let (r, g, b) = (sample(0), sample(1), sample(2));
+                let alpha = match self.transparent {
+                    Some(t) if t == [r, g, b] => 0,
+                    _ => 255,
+                };
+
+                rgba(scale(r), scale(g), scale(b), alpha)
+            }
+            3 => *self
+                .palette
+                .get(sample(0) as usize)
+                .ok_or(ImageError::Corrupt)?,
+            4 => {
+                let (gray, alpha) = (scale(sample(0)), scale(sample(1)));
+                rgba(gray, gray, gray, alpha)
+            }
+            _ => rgba(
+                scale(sample(0)),
+                scale(sample(1)),
+                scale(sample(2)),
+                scale(sample(3)),
+            ),
+        })
+    }
+}
+
+pub(super) fn decode(data: &[u8]) -> Result<Image, ImageError> {
+    if data.get(..8) != Some(&SIGNATURE[..]) {
+        return Err(ImageError::UnknownFormat);
+    }
+
+    let mut header = None;
+    let mut palette = Vec::new();
+    let mut trns = None;
+    let mut compressed = Vec::new();
+    let mut offset = 8;
+
+    loop {
+        let len = read_u32(data, offset).ok_or(ImageError::Truncated)? as usize;
+        let kind = data
+            .get(offset + 4..offset + 8)
+            .ok_or(ImageError::Truncated)?;
+        let body = data
+            .get(offset + 8..)
+            .and_then(|rest| rest.get(..len))
+            .ok_or(ImageError::Truncated)?;
+
+        match kind {
+            b"IHDR" => header = Some(body),
+            b"PLTE" => {
+                palette = body
+                    .chunks_exact(3)
+                    .map(|c| rgba(c[0], c[1], c[2], 255))
+                    .collect()
+            }
+            b"tRNS" => trns = Some(body),
+            b"IDAT" => compressed.extend_from_slice(body),
+            b"IEND" => break,
+            _ => {}
+        }
+
+        // Skip the CRC too.
+        offset += 12 + len;
+    }
+
+    let header = header.ok_or(ImageError::Corrupt)?;
+    if header.len() < 13 {
+        return Err(ImageError::Truncated);
+    }
+
+    let width = read_u32(header, 0).unwrap_or(0) as usize;
+    let height = read_u32(header, 4).unwrap_or(0) as usize;
+    let (depth, color_type, interlace) = (header[8], header[9], header[12]);
+
+    let channels = match (color_type, depth) {
+        (0, 1) | (0, 2) | (0, 4) | (0, 8) | (0, 16) => 1,
+        (3, 1) | (3, 2) | (3, 4) | (3, 8) => 1,
+        (2, 8) | (2, 16) => 3,
+        (4, 8) | (4, 16) => 2,
+        (6, 8) | (6, 16) => 4,
+        _ => return Err(ImageError::Unsupported),
+    };
+
+    if width == 0 || height == 0 || interlace > 1 {
+        return Err(ImageError::Corrupt);
+    }
+
+    super::check_size(width, height)?;
+
+    let mut transparent = None;
+    if let Some(trns) = trns {
+        match color_type {
+            3 => {
+                for (color, &alpha) in palette.iter_mut().zip(trns) {
+                    *color = (*color & 0x00ff_ffff) | (alpha as u32) << 24;
+                }
+            }
+            0 | 2 => {
+                let mut samples = [0; 3];
+                for (i, sample) in samples.iter_mut().enumerate().take(channels) {
+                    *sample = read_u16(trns, i * 2).ok_or(ImageError::Truncated)?;
+                }
+
+                transparent = Some(samples);
+            }
+            _ => {}
+        }
+    }
+
+    let format = Format {
+        color_type,
+        depth,
+        channels,
+        palette,
+        transparent,
+    };
+
+    let raw = inflate::decompress(&compressed)?;
+    let mut pixels = vec![0; width * height];
+
+    if interlace == 0 {
+        let rows = unfilter(&raw, width, height, format.bits_per_pixel())?;
+        let stride = rows.len() / height;
+
+        for (y, row) in rows.chunks_exact(stride).enumerate() {
+            for x in 0..width {
+                pixels[y * width + x] = format.pixel(row, x)?;
+            }
+        }
+    } else {
+        let mut raw = &raw[..];
+
+        for &(x0, y0, dx, dy) in &ADAM7 {
+            let pass_width = (width + dx - 1 - x0) / dx;
+            let pass_height = (height + dy - 1 - y0) / dy;
+
+            // Passes with no pixels are left out entirely.
+            if pass_width == 0 || pass_height == 0 {
+                continue;
+            }
+
+            let rows = unfilter(raw, pass_width, pass_height, format.bits_per_pixel())?;
+            let stride = rows.len() / pass_height;
+            raw = &raw[(stride + 1) * pass_height..];
+
+            for (j, row) in rows.chunks_exact(stride).enumerate() {
+                for i in 0..pass_width {
+                    pixels[(y0 + j * dy) * width + x0 + i * dx] = format.pixel(row, i)?;
+                }
+            }
+        }
+    }
+
+    Ok(Image::new(width, height, pixels))
+}
+
+/// Undo the filter of each row, returning the rows without their filter
+/// type bytes.
+fn unfilter(raw: &[u8], width: usize, height: usize, bpp: usize) -> Result<Vec<u8>, ImageError> {
+    let stride = (width * bpp + 7) / 8;
+    // Filters work on whole bytes, and look one pixel back.
+    let step = core::cmp::max(1, bpp / 8);
+
+    let raw = raw
+        .get(..(stride + 1) * height)
+        .ok_or(ImageError::Truncated)?;
+    let mut rows = vec![0u8; stride * height];
+
+    for y in 0..height {
+        let filter = raw[y * (stride + 1)];
+        let src = &raw[y * (stride + 1) + 1..(y + 1) * (stride + 1)];
+        let (done, rest) = rows.split_at_mut(y * stride);
+        let prev = if y > 0 {
+            &done[(y - 1) * stride..]
+        } else {
+            &[][..]
+        };
+        let row = &mut rest[..stride];
+
+        for x in 0..stride {
+            let a = if x >= step { row[x - step] } else { 0 };
+            let b = prev.get(x).copied().unwrap_or(0);
+            let c = if x >= step {
+                prev.get(x - step).copied().unwrap_or(0)
+            } else {
+                0
+            };
+
+            row[x] = src[x].wrapping_add(match filter {
+                0 => 0,
+                1 => a,
+                2 => b,
+                3 => ((a as u16 + b as u16) / 2) as u8,
+                4 => paeth(a, b, c),
+                _ => return Err(ImageError::Corrupt),
+            });
+        }
+    }
+
+    Ok(rows)
+}
+
+fn paeth(a: u8, b: u8, c: u8) -> u8 {
+    let p = a as i16 + b as i16 - c as i16;
+    let (pa, pb, pc) = (
+        (p - a as i16).abs(),
+        (p - b as i16).abs(),
+        (p - c as i16).abs(),
+    );
+
+    if pa <= pb && pa <= pc {
+        a
+    } else if pb <= pc {
+        b
+    } else {
+        c
+    }
+}
+
+/// Sample `index` of a row of `depth`-bit samples.
+fn read_sample(row: &[u8], index: usize, depth: u8) -> u16 {
+    match depth {
+        16 => u16::from_be_bytes([row[index * 2], row[index * 2 + 1]]),
+        8 => row[index] as u16,
+        _ => {
+            // Smaller samples are packed from the most significant bit.
+            let bit = index * depth as usize;
+            let shift = 8 - depth as usize - bit % 8;
+
+            ((row[bit / 8] >> shift) & ((1 << depth) - 1)) as u16
+        }
+    }
+}
+
+fn read_u16(data: &[u8], offset: usize) -> Option<u16> {
+    let bytes = data.get(offset..offset + 2)?;
+
+    Some(u16::from_be_bytes([bytes[0], bytes[1]]))
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Option<u32> {
+    let bytes = data.get(offset..offset + 4)?;
+
+    Some(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
@@ -0,0 +1,383 @@
+//! Conversion of images to textures.
+
+use super::{AlignedBytes, Image, ImageError};
+use crate::sys::{self, ClutPixelFormat, MipmapLevel, TexturePixelFormat};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::{cmp, fmt};
+
+/// Largest width or height of a texture.
+pub const MAX_TEXTURE_SIZE: usize = 512;
+
+/// Swizzling works on blocks of 16 bytes by 8 rows.
+const BLOCK_WIDTH: usize = 16;
+const BLOCK_HEIGHT: usize = 8;
+
+/// An image converted to a texture pixel format, ready for `sceGuTexImage`.
+///
+/// The buffer dimensions are powers of two, with the padding left
+/// transparent. Palettized textures come with a `Psm8888` CLUT.
+pub struct Texture {
+    format: TexturePixelFormat,
+    width: usize,
+    height: usize,
+    buffer_width: usize,
+    buffer_height: usize,
+    swizzled: bool,
+    data: AlignedBytes,
+    clut: Option<AlignedBytes>,
+}
+
+impl Texture {
+    pub(super) fn new(
+        image: &Image,
+        format: TexturePixelFormat,
+        swizzle: bool,
+    ) -> Result<Self, ImageError> {
+        let bits = match format {
+            TexturePixelFormat::Psm5650
+            | TexturePixelFormat::Psm5551
+            | TexturePixelFormat::Psm4444 => 16,
+            TexturePixelFormat::Psm8888 => 32,
+            TexturePixelFormat::PsmT4 => 4,
+            TexturePixelFormat::PsmT8 => 8,
+            _ => return Err(ImageError::Unsupported),
+        };
+
+        let (width, height) = (image.width(), image.height());
+        if width == 0 || height == 0 {
+            return Err(ImageError::Corrupt);
+        }
+
+        if width > MAX_TEXTURE_SIZE || height > MAX_TEXTURE_SIZE {
+            return Err(ImageError::TooLarge);
+        }
+
+        // Rows are at least 16 bytes, and swizzled textures at least 8 rows.
+        let buffer_width = cmp::max(width.next_power_of_two(), BLOCK_WIDTH * 8 / bits);
+        let mut buffer_height = height.next_power_of_two();
+        if swizzle {
+            buffer_height = cmp::max(buffer_height, BLOCK_HEIGHT);
+        }
+
+        let row_bytes = buffer_width * bits / 8;
+        let mut linear = vec![0u8; row_bytes * buffer_height];
+        let mut clut = None;
+
+        match format {
+            TexturePixelFormat::PsmT4 | TexturePixelFormat::PsmT8 => {
+                let colors = 1 << bits;
+                let (palette, indices) = quantize(image.pixels(), colors);
+
+                for (y, row) in indices.chunks_exact(width).enumerate() {
+                    let out = &mut linear[y * row_bytes..(y + 1) * row_bytes];
+
+                    for (x, &index) in row.iter().enumerate() {
+                        if bits == 8 {
+                            out[x] = index;
+                        } else {
+                            // The first pixel is in the low nibble.
+                            out[x / 2] |= index << ((x & 1) * 4);
+                        }
+                    }
+                }
+
+                let mut table = AlignedBytes::new(colors * 4);
+                for (entry, color) in table.as_mut_slice().chunks_exact_mut(4).zip(palette) {
+                    entry.copy_from_slice(&color.to_le_bytes());
+                }
+
+                clut = Some(table);
+            }
+            _ => {
+                for (y, row) in image.pixels().chunks_exact(width).enumerate() {
+                    let out = &mut linear[y * row_bytes..(y + 1) * row_bytes];
+
+                    for (x, &color) in row.iter().enumerate() {
+                        match format {
+                            TexturePixelFormat::Psm8888 => {
+                                out[x * 4..x * 4 + 4].copy_from_slice(&color.to_le_bytes())
+                            }
+                            _ => {
+                                let value = convert16(color, format);
+                                out[x * 2..x * 2 + 2].copy_from_slice(&value.to_le_bytes());
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        let mut data = AlignedBytes::new(linear.len());
+        if swizzle {
+            swizzle_blocks(&linear, data.as_mut_slice(), row_bytes, buffer_height);
+        } else {
+            data.as_mut_slice().copy_from_slice(&linear);
+        }
+
+        Ok(Self {
+            format,
+            width,
+            height,
+            buffer_width,
+            buffer_height,
+            swizzled: swizzle,
+            data,
+            clut,
+        })
+    }
+
+    /// The pixel format of the texture.
+    pub fn format(&self) -> TexturePixelFormat {
+        self.format
+    }
+
+    /// The width of the image, in pixels.
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// The height of the image, in pixels.
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// The width of the texture buffer, a power of two.
+    pub fn buffer_width(&self) -> usize {
+        self.buffer_width
+    }
+
+    /// The height of the texture buffer, a power of two.
+    pub fn buffer_height(&self) -> usize {
+        self.buffer_height
+    }
+
+    /// Returns `true` if the texture is swizzled.
+    pub fn is_swizzled(&self) -> bool {
+        self.swizzled
+    }
+
+    /// The texture data, aligned for the GE.
+    pub fn data(&self) -> &[u8] {
+        self.data.as_slice()
+    }
+
+    /// The `Psm8888` CLUT of a palettized texture, as raw bytes.
+    pub fn clut(&self) -> Option<&[u8]> {
+        self.clut.as_ref().map(AlignedBytes::as_slice)
+    }
+
+    /// Write the texture back from the data cache, then set it and its CLUT
+    /// as the current texture.
+    ///
+    /// # Safety
+    ///
+    /// This must be called while building a display list, and the texture
+    /// must outlive its rendering.
+    pub unsafe fn bind(&self) {
+        let data = self.data();
+        sys::sceKernelDcacheWritebackRange(data.as_ptr() as _, data.len() as u32);
+
+        if let Some(clut) = self.clut() {
+            sys::sceKernelDcacheWritebackRange(clut.as_ptr() as _, clut.len() as u32);
+
+            // The CLUT is loaded in blocks of 8 colors.
+            sys::sceGuClutMode(ClutPixelFormat::Psm8888, 0, 0xff, 0);
+            sys::sceGuClutLoad((clut.len() / 32) as i32, clut.as_ptr() as _);
+        }
+
+        sys::sceGuTexMode(self.format, 0, 0, self.swizzled as i32);
+        sys::sceGuTexImage(
+            MipmapLevel::None,
+            self.buffer_width as i32,
+            self.buffer_height as i32,
+            self.buffer_width as i32,
+            data.as_ptr() as _,
+        );
+        sys::sceGuTexFlush();
+    }
+}
+
+impl fmt::Debug for Texture {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Texture")
+            .field("format", &self.format)
+            .field("width", &self.width)
+            .field("height", &self.height)
+            .field("buffer_width", &self.buffer_width)
+            .field("buffer_height", &self.buffer_height)
+            .field("swizzled", &self.swizzled)
+            .finish()
+    }
+}
+
+/// Convert a `0xAABBGGRR` color to a 16-bit format.
+fn convert16(color: u32, format: TexturePixelFormat) -> u16 {
+    let [r, g, b, a] = color.to_le_bytes();
+    let (r, g, b, a) = (r as u16, g as u16, b as u16, a as u16);
+
+    match format {
+        TexturePixelFormat::Psm5650 => (r >> 3) | (g >> 2) << 5 | (b >> 3) << 11,
+        TexturePixelFormat::Psm5551 => (r >> 3) | (g >> 3) << 5 | (b >> 3) << 10 | (a >> 7) << 15,
+        _ => (r >> 4) | (g >> 4) << 4 | (b >> 4) << 8 | (a >> 4) << 12,
+    }
+}
+
+/// Rearrange rows into blocks of 16 bytes by 8 rows, stored one after the
+/// other, which the GE reads faster.
+fn swizzle_blocks(src: &[u8], dst: &mut [u8], row_bytes: usize, height: usize) {
+    let blocks_per_row = row_bytes / BLOCK_WIDTH;
+
+    for y in 0..height {
+        for bx in 0..blocks_per_row {
+            let block = (y / BLOCK_HEIGHT) * blocks_per_row + bx;
+            let to = block * BLOCK_WIDTH * BLOCK_HEIGHT + (y % BLOCK_HEIGHT) * BLOCK_WIDTH;
+            let from = y * row_bytes + bx * BLOCK_WIDTH;
+
+            dst[to..to + BLOCK_WIDTH].copy_from_slice(&src[from..from + BLOCK_WIDTH]);
+        }
+    }
+}
+
+/// Reduce `pixels` to at most `max` colors, returning the palette and the
+/// index of each pixel.
+///
+/// Images with few enough colors keep them exactly. Others are reduced with
+/// the median cut algorithm.
+fn quantize(pixels: &[u32], max: usize) -> (Vec<u32>, Vec<u8>) {
+    // The distinct colors, sorted, with how often they appear.
+    let mut sorted = pixels.to_vec();
+    sorted.sort_unstable();
+
+    let mut colors: Vec<(u32, u32)> = Vec::new();
+    for color in sorted {
+        match colors.last_mut() {
+            Some(last) if last.0 == color => last.1 += 1,
+            _ => colors.push((color, 1)),
+        }
+    }
+
+    let palette = if colors.len() <= max {
+        colors.iter().map(|&(color, _)| color).collect()
+    } else {
+        median_cut(&mut colors.clone(), max)
+    };
+
+    let nearest: Vec<u8> = colors
+        .iter()
+        .map(|&(color, _)| {
+            (0..palette.len())
+                .min_by_key(|&i| distance(color, palette[i]))
+                .unwrap_or(0) as u8
+        })
+        .collect();
+
+    let indices = pixels
+        .iter()
+        .map(
+            |color| match colors.binary_search_by_key(color, |&(c, _)| c) {
+                Ok(i) => nearest[i],
+                Err(_) => 0,
+            },
+        )
+        .collect();
+
+    (palette, indices)
+}
+
+/// Split `colors` into `max` boxes, each time cutting the box with the
+/// widest channel at its median, and return the average of each box.
+fn median_cut(colors: &mut [(u32, u32)], max: usize) -> Vec<u32> {
+    let mut boxes = vec![(0, colors.len())];
+
+    while boxes.len() < max {
+        let mut widest = None;
+
+        for (i, &(start, end)) in boxes.iter().enumerate() {
+            if end - start < 2 {
+                continue;
+            }
+
+            let (channel, range) = widest_channel(&colors[start..end]);
+            if widest.map_or(true, |(_, _, best)| range > best) {
+                widest = Some((i, channel, range));
+            }
+        }
+
+        let (i, channel, _) = match widest {
+            Some(widest) => widest,
+            None => break,
+        };
+
+        let (start, end) = boxes[i];
+        let group = &mut colors[start..end];
+        group.sort_unstable_by_key(|&(color, _)| component(color, channel));
+
+        // Cut where half of the pixels are on each side.
+        let total: u64 = group.iter().map(|&(_, count)| count as u64).sum();
+        let mut seen = 0;
+        let mut cut = 1;
+        for (j, &(_, count)) in group.iter().enumerate() {
+            seen += count as u64;
+            if seen * 2 >= total {
+                cut = j + 1;
+                break;
+            }
+        }
+        let cut = start + cmp::min(cut, group.len() - 1);
+
+        boxes[i] = (start, cut);
+        boxes.push((cut, end));
+    }
+
+    boxes
+        .iter()
+        .map(|&(start, end)| average(&colors[start..end]))
+        .collect()
+}
+
+fn component(color: u32, channel: usize) -> u8 {
+    (color >> (channel * 8)) as u8
+}
+
+fn widest_channel(colors: &[(u32, u32)]) -> (usize, u8) {
+    (0..4)
+        .map(|channel| {
+            let values = colors.iter().map(|&(color, _)| component(color, channel));
+            let min = values.clone().min().unwrap_or(0);
+            let max = values.max().unwrap_or(0);
+
+            (channel, max - min)
+        })
+        .max_by_key(|&(_, range)| range)
+        .unwrap_or((0, 0))
+}
+
+fn average(colors: &[(u32, u32)]) -> u32 {
+    let mut sums = [0u64; 4];
+    let mut total = 0u64;
+
+    for &(color, count) in colors {
+        for (channel, sum) in sums.iter_mut().enumerate() {
+            *sum += component(color, channel) as u64 * count as u64;
+        }
+        total += count as u64;
+    }
+
+    let total = cmp::max(total, 1);
+    u32::from_le_bytes([
+        (sums[0] / total) as u8,
+        (sums[1] / total) as u8,
+        (sums[2] / total) as u8,
+        (sums[3] / total) as u8,
+    ])
+}
+
+fn distance(a: u32, b: u32) -> u32 {
+    (0..4)
+        .map(|channel| {
+            let d = component(a, channel) as i32 - component(b, channel) as i32;
+            (d * d) as u32
+        })
+        .sum()
+}
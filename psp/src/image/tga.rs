@@ -0,0 +1,198 @@
+//! Decoding of Truevision TGA images.
+
+use super::{rgba, Image, ImageError};
+use alloc::vec;
+use alloc::vec::Vec;
+
+/// Size of the fixed header.
+const HEADER_SIZE: usize = 18;
+
+/// TGA files have no magic number, so this checks that the header is sane.
+pub(super) fn is_tga(data: &[u8]) -> bool {
+    if data.len() < HEADER_SIZE {
+        return false;
+    }
+
+    let width = u16::from_le_bytes([data[12], data[13]]);
+    let height = u16::from_le_bytes([data[14], data[15]]);
+
+    data[1] <= 1
+        && matches!(data[2], 1 | 2 | 3 | 9 | 10 | 11)
+        && matches!(data[16], 8 | 15 | 16 | 24 | 32)
+        && width > 0
+        && height > 0
+}
+
+/// How pixel values map to colors.
+struct Format {
+    kind: u8,
+    depth: u8,
+    alpha_bits: u8,
+    palette: Vec<u32>,
+    first_entry: usize,
+}
+
+impl Format {
+    fn bytes_per_pixel(&self) -> usize {
+        (self.depth as usize + 7) / 8
+    }
+
+    fn color(&self, p: &[u8]) -> Result<u32, ImageError> {
+        match self.kind {
+            1 => {
+                let index = match p.len() {
+                    1 => p[0] as usize,
+                    _ => u16::from_le_bytes([p[0], p[1]]) as usize,
+                };
+
+                index
+                    .checked_sub(self.first_entry)
+                    .and_then(|i| self.palette.get(i))
+                    .copied()
+                    .ok_or(ImageError::Corrupt)
+            }
+            3 => {
+                // 16-bit grayscale images hold gray and alpha.
+                let alpha = if p.len() > 1 { p[1] } else { 255 };
+                Ok(rgba(p[0], p[0], p[0], alpha))
+            }
+            _ => Ok(color(p, self.alpha_bits)),
+        }
+    }
+}
+
+pub(super) fn decode(data: &[u8]) -> Result<Image, ImageError> {
+    if !is_tga(data) {
+        return Err(ImageError::UnknownFormat);
+    }
+
+    let id_len = data[0] as usize;
+    let has_palette = data[1] == 1;
+    let image_type = data[2];
+    let first_entry = u16::from_le_bytes([data[3], data[4]]) as usize;
+    let entries = u16::from_le_bytes([data[5], data[6]]) as usize;
+    let entry_depth = data[7];
+    let width = u16::from_le_bytes([data[12], data[13]]) as usize;
+    let height = u16::from_le_bytes([data[14], data[15]]) as usize;
+    let depth = data[16];
+    let descriptor = data[17];
+
+    super::check_size(width, height)?;
+
+    let mut offset = HEADER_SIZE + id_len;
+    let mut palette = Vec::new();
+
+    if has_palette {
+        let entry_size = (entry_depth as usize + 7) / 8;
+        if entry_size < 2 || entry_size > 4 {
+            return Err(ImageError::Unsupported);
+        }
+
+        let bytes = data
+            .get(offset..offset + entries * entry_size)
+            .ok_or(ImageError::Truncated)?;
+
+        // Palettes of 16-bit entries use the top bit as alpha, like pixels.
+        let alpha_bits = if entry_depth == 16 || entry_depth == 32 {
+            1
+        } else {
+            0
+        };
+        palette = bytes
+            .chunks_exact(entry_size)
+            .map(|p| color(p, alpha_bits))
+            .collect();
+        offset += bytes.len();
+    }
+
+    let kind = image_type & 7;
+    match (kind, depth) {
+        (1, 8) | (1, 16) if has_palette => {}
+        (2, 15) | (2, 16) | (2, 24) | (2, 32) | (3, 8) | (3, 16) => {}
+        _ => return Err(ImageError::Unsupported),
+    }
+
+    let format = Format {
+        kind,
+        depth,
+        alpha_bits: descriptor & 0x0f,
+        palette,
+        first_entry,
+    };
+
+    let size = format.bytes_per_pixel();
+    let count = width * height;
+    let mut colors = Vec::with_capacity(count);
+    let mut src = data.get(offset..).ok_or(ImageError::Truncated)?;
+
+    if image_type & 8 == 0 {
+        let bytes = src.get(..count * size).ok_or(ImageError::Truncated)?;
+
+        for p in bytes.chunks_exact(size) {
+            colors.push(format.color(p)?);
+        }
+    } else {
+        // Each packet is a header byte, then either one pixel repeated or
+        // raw pixels.
+        while colors.len() < count {
+            let header = *src.first().ok_or(ImageError::Truncated)?;
+            let run = (header & 0x7f) as usize + 1;
+            let pixels = if header & 0x80 != 0 { 1 } else { run };
+            let bytes = src.get(1..1 + pixels * size).ok_or(ImageError::Truncated)?;
+
+            if header & 0x80 != 0 {
+                let color = format.color(bytes)?;
+                colors.extend(core::iter::repeat(color).take(run));
+            } else {
+                for p in bytes.chunks_exact(size) {
+                    colors.push(format.color(p)?);
+                }
+            }
+
+            src = &src[1 + bytes.len()..];
+        }
+
+        colors.truncate(count);
+    }
+
+    // Rows are stored bottom-up and left to right unless the descriptor says
+    // otherwise.
+    let right_to_left = descriptor & 0x10 != 0;
+    let top_down = descriptor & 0x20 != 0;
+    let mut pixels = vec![0; count];
+
+    for (i, row) in colors.chunks_exact(width).enumerate() {
+        let y = if top_down { i } else { height - 1 - i };
+        let out = &mut pixels[y * width..(y + 1) * width];
+        out.copy_from_slice(row);
+
+        if right_to_left {
+            out.reverse();
+        }
+    }
+
+    Ok(Image::new(width, height, pixels))
+}
+
+/// A truecolor pixel: BGR 5:5:5 with an optional alpha bit, BGR 8:8:8 or
+/// BGRA 8:8:8:8, all little-endian.
+fn color(p: &[u8], alpha_bits: u8) -> u32 {
+    match p.len() {
+        2 => {
+            let value = u16::from_le_bytes([p[0], p[1]]);
+            let scale = |v: u16| ((v & 0x1f) * 255 / 31) as u8;
+            let alpha = if alpha_bits > 0 && value & 0x8000 == 0 {
+                0
+            } else {
+                255
+            };
+
+            rgba(scale(value >> 10), scale(value >> 5), scale(value), alpha)
+        }
+        3 => rgba(p[2], p[1], p[0], 255),
+        _ => {
+            let alpha = if alpha_bits > 0 { p[3] } else { 255 };
+            rgba(p[2], p[1], p[0], alpha)
+        }
+    }
+}
@@ -0,0 +1,334 @@
+//! The buttons and analog stick of the controller.
+
+use super::{for_each_bit, now, Edges, InputEvent, Repeat};
+use crate::sys::{self, CtrlButtons, CtrlMode, SceCtrlData};
+use core::time::Duration;
+
+/// The raw value of an analog axis at rest.
+pub const STICK_CENTER: u8 = 128;
+
+/// Set up the controller to be read once per frame, with the analog stick.
+pub fn init() {
+    unsafe {
+        sys::sceCtrlSetSamplingCycle(0);
+        sys::sceCtrlSetSamplingMode(CtrlMode::Analog);
+    }
+}
+
+/// One reading of the controller.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Frame {
+    /// When the frame was read. Live frames are timed from boot, recorded
+    /// frames from the start of the recording.
+    pub time: Duration,
+    /// The buttons held.
+    pub buttons: CtrlButtons,
+    /// The raw analog stick position, from 0 at the left to 255 at the right.
+    pub lx: u8,
+    /// The raw analog stick position, from 0 at the top to 255 at the bottom.
+    pub ly: u8,
+}
+
+impl Frame {
+    /// A frame with `buttons` held and the stick at rest.
+    pub fn new(time: Duration, buttons: CtrlButtons) -> Self {
+        Self {
+            time,
+            buttons,
+            lx: STICK_CENTER,
+            ly: STICK_CENTER,
+        }
+    }
+
+    /// Read the controller without waiting for the next sample.
+    ///
+    /// If it cannot be read, nothing is held and the stick is at rest.
+    pub fn read() -> Self {
+        let mut data = SceCtrlData::default();
+
+        if unsafe { sys::sceCtrlPeekBufferPositive(&mut data, 1) } < 0 {
+            return Self::new(now(), CtrlButtons::empty());
+        }
+
+        Self::from_ctrl_data(&data, now())
+    }
+
+    /// Convert controller data read at `time`.
+    pub fn from_ctrl_data(data: &SceCtrlData, time: Duration) -> Self {
+        Self {
+            time,
+            buttons: data.buttons,
+            lx: data.lx,
+            ly: data.ly,
+        }
+    }
+}
+
+impl Default for Frame {
+    fn default() -> Self {
+        Self::new(Duration::from_secs(0), CtrlButtons::empty())
+    }
+}
+
+/// The part of the stick's range around its rest position that reads as
+/// zero, as a fraction of the full range.
+///
+/// Sticks rarely rest exactly at the center, so some deadzone is needed to
+/// keep a released stick from drifting.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Deadzone {
+    /// Each axis is zero while it is within the deadzone, so small movements
+    /// snap to the axes. Suits digital style movement.
+    Axial(f32),
+    /// The stick is zero while it is within a circle around the center.
+    /// Suits free movement.
+    Radial(f32),
+}
+
+/// How the stick's distance from the deadzone maps to its output, both
+/// from 0 to 1.
+#[derive(Debug, Copy, Clone)]
+pub enum Curve {
+    /// The output is proportional to the distance.
+    Linear,
+    /// The distance squared, for finer control near the center.
+    Quadratic,
+    /// The distance cubed, for even finer control near the center.
+    Cubic,
+    /// A custom mapping.
+    Custom(fn(f32) -> f32),
+}
+
+impl Curve {
+    /// Apply the curve to a distance from 0 to 1.
+    pub fn apply(self, distance: f32) -> f32 {
+        match self {
+            Curve::Linear => distance,
+            Curve::Quadratic => distance * distance,
+            Curve::Cubic => distance * distance * distance,
+            Curve::Custom(f) => f(distance),
+        }
+    }
+}
+
+/// How raw stick positions turn into axes.
+#[derive(Debug, Copy, Clone)]
+pub struct StickConfig {
+    /// The part of the range around the rest position that reads as zero.
+    pub deadzone: Deadzone,
+    /// How the distance past the deadzone maps to the output.
+    pub curve: Curve,
+    /// Make up positive on the Y axis, rather than down.
+    pub invert_y: bool,
+}
+
+impl Default for StickConfig {
+    fn default() -> Self {
+        Self {
+            deadzone: Deadzone::Radial(0.1),
+            curve: Curve::Linear,
+            invert_y: false,
+        }
+    }
+}
+
+impl StickConfig {
+    /// Turn a raw stick position into X and Y axes from -1 to 1.
+    pub fn apply(&self, lx: u8, ly: u8) -> (f32, f32) {
+        let (x, y) = (normalize(lx), normalize(ly));
+
+        let (x, y) = match self.deadzone {
+            Deadzone::Axial(deadzone) => (
+                self.scale(x, abs(x), deadzone),
+                self.scale(y, abs(y), deadzone),
+            ),
+            Deadzone::Radial(deadzone) => {
+                let length = unsafe { core::intrinsics::sqrtf32(x * x + y * y) };
+
+                (
+                    self.scale(x, length, deadzone),
+                    self.scale(y, length, deadzone),
+                )
+            }
+        };
+
+        (limit(x), if self.invert_y { -limit(y) } else { limit(y) })
+    }
+
+    /// Scale the component `value` of a vector of `length` so that the
+    /// length runs from 0 at the edge of the deadzone to 1 at the edge of the
+    /// range, then through the curve.
+    fn scale(&self, value: f32, length: f32, deadzone: f32) -> f32 {
+        if length <= deadzone || deadzone >= 1.0 {
+            return 0.0;
+        }
+
+        let distance = ((length - deadzone) / (1.0 - deadzone)).min(1.0);
+
+        value / length * self.curve.apply(distance)
+    }
+}
+
+/// Map a raw axis to -1 at 0, 0 at rest and 1 at 255.
+fn normalize(raw: u8) -> f32 {
+    let offset = raw as f32 - STICK_CENTER as f32;
+
+    if offset < 0.0 {
+        offset / STICK_CENTER as f32
+    } else {
+        offset / (255 - STICK_CENTER) as f32
+    }
+}
+
+fn abs(value: f32) -> f32 {
+    if value < 0.0 {
+        -value
+    } else {
+        value
+    }
+}
+
+fn limit(value: f32) -> f32 {
+    value.max(-1.0).min(1.0)
+}
+
+/// The controller as of the last update: which buttons were pressed,
+/// released, held or repeated, and where the stick is.
+#[derive(Debug, Clone)]
+pub struct InputState {
+    buttons: Edges,
+    frame: Frame,
+    stick: (f32, f32),
+    stick_config: StickConfig,
+    repeat: Repeat,
+}
+
+impl InputState {
+    /// Create a state with nothing held and the stick at rest.
+    pub fn new() -> Self {
+        Self {
+            buttons: Edges::new(),
+            frame: Frame::default(),
+            stick: (0.0, 0.0),
+            stick_config: StickConfig::default(),
+            repeat: Repeat::default(),
+        }
+    }
+
+    /// The repeat timing of held buttons.
+    pub fn repeat(&self) -> Repeat {
+        self.repeat
+    }
+
+    /// Change the repeat timing of held buttons.
+    pub fn set_repeat(&mut self, repeat: Repeat) {
+        self.repeat = repeat;
+    }
+
+    /// How the stick is read.
+    pub fn stick_config(&self) -> StickConfig {
+        self.stick_config
+    }
+
+    /// Change how the stick is read, from the next update.
+    pub fn set_stick_config(&mut self, config: StickConfig) {
+        self.stick_config = config;
+    }
+
+    /// Forget the buttons held and the stick position, keeping the repeat
+    /// timing and stick configuration.
+    pub fn reset(&mut self) {
+        self.buttons = Edges::new();
+        self.frame = Frame::default();
+        self.stick = (0.0, 0.0);
+    }
+
+    /// Move to a new frame.
+    pub fn update(&mut self, frame: Frame) {
+        self.update_with(frame, |_| {});
+    }
+
+    /// Move to a new frame, passing the changes to `emit`.
+    ///
+    /// Repeats are timed by the frames' times, so replaying the same frames
+    /// repeats the same buttons.
+    pub fn update_with(&mut self, frame: Frame, mut emit: impl FnMut(InputEvent)) {
+        self.buttons
+            .update(frame.buttons.bits(), frame.time, self.repeat);
+        self.frame = frame;
+        self.stick = self.stick_config.apply(frame.lx, frame.ly);
+
+        let button = CtrlButtons::from_bits_truncate;
+        for_each_bit(self.buttons.released(), |bit| {
+            emit(InputEvent::Released(button(bit)))
+        });
+        for_each_bit(self.buttons.pressed(), |bit| {
+            emit(InputEvent::Pressed(button(bit)))
+        });
+        for_each_bit(self.buttons.repeated(), |bit| {
+            emit(InputEvent::Repeated(button(bit)))
+        });
+    }
+
+    /// The last frame.
+    pub fn frame(&self) -> Frame {
+        self.frame
+    }
+
+    /// The buttons held.
+    pub fn buttons(&self) -> CtrlButtons {
+        CtrlButtons::from_bits_truncate(self.buttons.held())
+    }
+
+    /// The buttons held at the update before the last.
+    pub fn previous_buttons(&self) -> CtrlButtons {
+        CtrlButtons::from_bits_truncate(self.buttons.previous())
+    }
+
+    /// The buttons pressed at the last update.
+    pub fn pressed(&self) -> CtrlButtons {
+        CtrlButtons::from_bits_truncate(self.buttons.pressed())
+    }
+
+    /// The buttons released at the last update.
+    pub fn released(&self) -> CtrlButtons {
+        CtrlButtons::from_bits_truncate(self.buttons.released())
+    }
+
+    /// The buttons pressed or repeated at the last update.
+    pub fn repeated(&self) -> CtrlButtons {
+        CtrlButtons::from_bits_truncate(self.buttons.pressed() | self.buttons.repeated())
+    }
+
+    /// Returns `true` if any of `buttons` was pressed at the last update.
+    pub fn is_pressed(&self, buttons: CtrlButtons) -> bool {
+        self.pressed().intersects(buttons)
+    }
+
+    /// Returns `true` if any of `buttons` was released at the last update.
+    pub fn is_released(&self, buttons: CtrlButtons) -> bool {
+        self.released().intersects(buttons)
+    }
+
+    /// Returns `true` if all of `buttons` are held.
+    pub fn is_held(&self, buttons: CtrlButtons) -> bool {
+        self.buttons().contains(buttons)
+    }
+
+    /// Returns `true` if any of `buttons` was pressed or repeated at the last
+    /// update, as menus want.
+    pub fn is_repeated(&self, buttons: CtrlButtons) -> bool {
+        self.repeated().intersects(buttons)
+    }
+
+    /// The stick's X and Y axes, from -1 to 1, after the deadzone and curve.
+    pub fn stick(&self) -> (f32, f32) {
+        self.stick
+    }
+}
+
+impl Default for InputState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
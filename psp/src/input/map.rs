@@ -0,0 +1,133 @@
+//! Mapping buttons to application actions.
+
+use super::InputState;
+use crate::sys::CtrlButtons;
+use alloc::vec::Vec;
+
+/// A table of which buttons trigger which actions, so that games can check
+/// for "jump" rather than cross, and let players change the buttons.
+///
+/// An action may have several bindings, and a binding may be a combination
+/// of buttons that must all be held.
+///
+/// # Example
+///
+/// ```ignore
+/// use psp::input::{ActionMap, InputState};
+/// use psp::sys::CtrlButtons;
+///
+/// #[derive(Copy, Clone, PartialEq)]
+/// enum Action {
+///     Jump,
+///     Pause,
+/// }
+///
+/// let mut map = ActionMap::new();
+/// map.bind(Action::Jump, CtrlButtons::CROSS);
+/// map.bind(Action::Pause, CtrlButtons::START);
+///
+/// // Let the player jump with circle instead.
+/// map.rebind(Action::Jump, CtrlButtons::CIRCLE);
+///
+/// # let state = InputState::new();
+/// if map.is_pressed(&state, Action::Jump) {
+///     // Jump.
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct ActionMap<A> {
+    bindings: Vec<(A, CtrlButtons)>,
+}
+
+impl<A: Copy + PartialEq> ActionMap<A> {
+    /// Create a table with no bindings.
+    pub fn new() -> Self {
+        Self {
+            bindings: Vec::new(),
+        }
+    }
+
+    /// Add a binding of `buttons` to `action`, keeping its other bindings.
+    pub fn bind(&mut self, action: A, buttons: CtrlButtons) {
+        if !buttons.is_empty() && !self.bindings.contains(&(action, buttons)) {
+            self.bindings.push((action, buttons));
+        }
+    }
+
+    /// Replace the bindings of `action` with `buttons`.
+    pub fn rebind(&mut self, action: A, buttons: CtrlButtons) {
+        self.unbind(action);
+        self.bind(action, buttons);
+    }
+
+    /// Remove the bindings of `action`.
+    pub fn unbind(&mut self, action: A) {
+        self.bindings.retain(|&(bound, _)| bound != action);
+    }
+
+    /// Remove every binding.
+    pub fn clear(&mut self) {
+        self.bindings.clear();
+    }
+
+    /// The bindings of `action`.
+    pub fn bindings(&self, action: A) -> impl Iterator<Item = CtrlButtons> + '_ {
+        self.bindings
+            .iter()
+            .filter(move |&&(bound, _)| bound == action)
+            .map(|&(_, buttons)| buttons)
+    }
+
+    /// The actions that `buttons` is bound to.
+    pub fn actions(&self, buttons: CtrlButtons) -> impl Iterator<Item = A> + '_ {
+        self.bindings
+            .iter()
+            .filter(move |&&(_, bound)| bound == buttons)
+            .map(|&(action, _)| action)
+    }
+
+    /// Returns `true` if all the buttons of a binding of `action` are held.
+    pub fn is_held(&self, state: &InputState, action: A) -> bool {
+        self.bindings(action).any(|buttons| state.is_held(buttons))
+    }
+
+    /// Returns `true` if a binding of `action` became held at the last
+    /// update.
+    pub fn is_pressed(&self, state: &InputState, action: A) -> bool {
+        self.bindings(action)
+            .any(|buttons| state.is_held(buttons) && state.is_pressed(buttons))
+    }
+
+    /// Returns `true` if a binding of `action` stopped being held at the last
+    /// update.
+    pub fn is_released(&self, state: &InputState, action: A) -> bool {
+        self.bindings(action)
+            .any(|buttons| state.previous_buttons().contains(buttons) && !state.is_held(buttons))
+    }
+
+    /// Returns `true` if a binding of `action` became held or repeated at the
+    /// last update.
+    pub fn is_repeated(&self, state: &InputState, action: A) -> bool {
+        self.bindings(action)
+            .any(|buttons| state.is_held(buttons) && state.is_repeated(buttons))
+    }
+
+    /// The actions that became held at the last update, without duplicates.
+    pub fn pressed_actions(&self, state: &InputState) -> Vec<A> {
+        let mut actions = Vec::new();
+
+        for &(action, _) in &self.bindings {
+            if !actions.contains(&action) && self.is_pressed(state, action) {
+                actions.push(action);
+            }
+        }
+
+        actions
+    }
+}
+
+impl<A: Copy + PartialEq> Default for ActionMap<A> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
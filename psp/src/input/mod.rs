@@ -0,0 +1,330 @@
+//! Input with edge detection and key repeat.
+//!
+//! The hardware only reports which buttons are down. `Input` samples it once
+//! per frame, works out what was pressed, released or held since the last
+//! frame, repeats held buttons for menus, and turns the changes into
+//! `InputEvent`s. The analog stick is read through a configurable deadzone
+//! and response curve, and an `ActionMap` lets games check for actions
+//! rather than buttons.
+//!
+//! Keys of the headphone remote are read alongside, and plugging or
+//! unplugging headphones, the remote or a microphone is reported as events
+//! too. Applications without a frame loop, such as music players, can have a
+//! `RemoteWatcher` deliver remote events from a background thread instead.
+//!
+//! Controller frames can be recorded to a file, and a recording replayed in
+//! place of the controller, to reproduce bugs and to test UI flows. See the
+//! `replay` module.
+//!
+//! # Example
+//!
+//! ```ignore
+//! use psp::input::{Input, InputEvent};
+//! use psp::sys::{CtrlButtons, HprmKey};
+//!
+//! let mut input = Input::new();
+//!
+//! loop {
+//!     input.update();
+//!
+//!     if input.controller().is_repeated(CtrlButtons::DOWN) {
+//!         // Move down a menu.
+//!     }
+//!
+//!     let (x, y) = input.controller().stick();
+//!
+//!     while let Some(event) = input.poll_event() {
+//!         if let InputEvent::RemotePressed(HprmKey::PLAY_PAUSE) = event {
+//!             // Toggle playback.
+//!         }
+//!     }
+//!
+//!     psp::display::wait_vblank_start();
+//! }
+//! ```
+
+pub mod controller;
+pub mod map;
+pub mod remote;
+pub mod replay;
+
+pub use controller::{Curve, Deadzone, Frame, InputState, StickConfig};
+pub use map::ActionMap;
+pub use remote::{Accessory, Plugs, RemoteState, RemoteWatcher};
+pub use replay::{InputRecorder, Recording, Replay, ReplayError};
+
+use crate::fs::File;
+use crate::io;
+use crate::sys::{self, CtrlButtons, HprmKey};
+use alloc::collections::VecDeque;
+use core::time::Duration;
+
+/// Maximum number of undelivered events kept by `Input`. When the queue is
+/// full, the oldest event is discarded.
+pub const QUEUE_CAPACITY: usize = 64;
+
+/// When held keys repeat.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Repeat {
+    /// How long a key is held before it first repeats.
+    pub delay: Duration,
+    /// The time between repeats after that.
+    pub interval: Duration,
+}
+
+impl Default for Repeat {
+    fn default() -> Self {
+        Self {
+            delay: Duration::from_millis(500),
+            interval: Duration::from_millis(100),
+        }
+    }
+}
+
+/// A change of input.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum InputEvent {
+    /// A button was pressed.
+    Pressed(CtrlButtons),
+    /// A button was released.
+    Released(CtrlButtons),
+    /// A button has been held long enough to repeat.
+    Repeated(CtrlButtons),
+    /// A key of the remote was pressed.
+    RemotePressed(HprmKey),
+    /// A key of the remote was released.
+    RemoteReleased(HprmKey),
+    /// A key of the remote has been held long enough to repeat.
+    RemoteRepeated(HprmKey),
+    /// An accessory was plugged in.
+    Plugged(Accessory),
+    /// An accessory was unplugged.
+    Unplugged(Accessory),
+}
+
+/// Samples input once per frame and queues the changes as events.
+pub struct Input {
+    controller: InputState,
+    remote: RemoteState,
+    events: VecDeque<InputEvent>,
+    /// Frames played instead of reading the controller.
+    replay: Option<Replay>,
+    recorder: Option<InputRecorder<File>>,
+}
+
+impl Input {
+    /// Create an input reader, and set up the controller to be read with the
+    /// analog stick. Nothing is read until the first `update`.
+    pub fn new() -> Self {
+        controller::init();
+
+        Self {
+            controller: InputState::new(),
+            remote: RemoteState::new(),
+            events: VecDeque::with_capacity(QUEUE_CAPACITY),
+            replay: None,
+            recorder: None,
+        }
+    }
+
+    /// Read the hardware and queue events for what changed since the last
+    /// update. Call this once per frame.
+    pub fn update(&mut self) {
+        let now = now();
+        let frame = match self.replay.as_mut().map(Replay::next_frame) {
+            Some(Some(frame)) => frame,
+            Some(None) => {
+                self.stop_replay();
+                Frame::read()
+            }
+            None => Frame::read(),
+        };
+        let remote = remote::read_keys();
+        let plugs = Plugs::read();
+
+        // A failed write is reported by `stop_recording`.
+        if let Some(recorder) = self.recorder.as_mut() {
+            let _ = recorder.record(&frame);
+        }
+
+        let events = &mut self.events;
+        self.controller
+            .update_with(frame, |event| push_event(events, event));
+        self.remote
+            .update_with(remote, plugs, now, |event| push_event(events, event));
+    }
+
+    /// Take the oldest queued event.
+    pub fn poll_event(&mut self) -> Option<InputEvent> {
+        self.events.pop_front()
+    }
+
+    /// The state of the controller.
+    pub fn controller(&self) -> &InputState {
+        &self.controller
+    }
+
+    /// Mutable access to the state of the controller, to change its repeat
+    /// timing or how the stick is read.
+    pub fn controller_mut(&mut self) -> &mut InputState {
+        &mut self.controller
+    }
+
+    /// Play the frames of `replay` instead of reading the controller, one per
+    /// update, until it finishes or `stop_replay` is called.
+    ///
+    /// The controller state is reset, so that the replay produces the same
+    /// events as when it was recorded.
+    pub fn start_replay(&mut self, replay: Replay) {
+        self.controller.reset();
+        self.replay = Some(replay);
+    }
+
+    /// Go back to reading the controller, returning the replay if one was
+    /// playing.
+    pub fn stop_replay(&mut self) -> Option<Replay> {
+        let replay = self.replay.take();
+        if replay.is_some() {
+            self.controller.reset();
+        }
+
+        replay
+    }
+
+    /// Returns `true` while a replay is playing.
+    pub fn is_replaying(&self) -> bool {
+        self.replay.is_some()
+    }
+
+    /// Record every frame to a new file at `path`, until `stop_recording` is
+    /// called. Any recording already running is stopped first.
+    pub fn start_recording(&mut self, path: &str) -> io::Result<()> {
+        self.stop_recording()?;
+        self.recorder = Some(InputRecorder::create(path)?);
+
+        Ok(())
+    }
+
+    /// Stop recording and write the rest of the frames, returning the first
+    /// error that happened while recording.
+    pub fn stop_recording(&mut self) -> io::Result<()> {
+        match self.recorder.take() {
+            Some(recorder) => recorder.finish().map(drop),
+            None => Ok(()),
+        }
+    }
+
+    /// Returns `true` while recording.
+    pub fn is_recording(&self) -> bool {
+        self.recorder.is_some()
+    }
+
+    /// The state of the headphone remote.
+    pub fn remote(&self) -> &RemoteState {
+        &self.remote
+    }
+
+    /// Mutable access to the state of the headphone remote, to change its
+    /// repeat timing.
+    pub fn remote_mut(&mut self) -> &mut RemoteState {
+        &mut self.remote
+    }
+}
+
+impl Default for Input {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Queue `event`, discarding the oldest event if the queue is full.
+fn push_event(events: &mut VecDeque<InputEvent>, event: InputEvent) {
+    if events.len() == QUEUE_CAPACITY {
+        events.pop_front();
+    }
+
+    events.push_back(event);
+}
+
+/// The time since boot, which input timestamps are measured from.
+pub(crate) fn now() -> Duration {
+    Duration::from_micros(unsafe { sys::sceKernelGetSystemTimeWide() } as u64)
+}
+
+/// Tracks which of up to 32 bits were pressed, released or repeated between
+/// two updates.
+#[derive(Debug, Clone)]
+pub(crate) struct Edges {
+    current: u32,
+    previous: u32,
+    repeated: u32,
+    /// When each held bit repeats next.
+    next_repeat: [Duration; 32],
+}
+
+impl Edges {
+    pub(crate) fn new() -> Self {
+        Self {
+            current: 0,
+            previous: 0,
+            repeated: 0,
+            next_repeat: [Duration::from_secs(0); 32],
+        }
+    }
+
+    pub(crate) fn update(&mut self, bits: u32, now: Duration, repeat: Repeat) {
+        self.previous = self.current;
+        self.current = bits;
+        self.repeated = 0;
+
+        for i in 0..32 {
+            let bit = 1 << i;
+
+            if self.pressed() & bit != 0 {
+                self.next_repeat[i] = now + repeat.delay;
+            } else if self.current & self.previous & bit != 0 && now >= self.next_repeat[i] {
+                self.repeated |= bit;
+
+                // Updates that come late skip repeats rather than bunch them.
+                self.next_repeat[i] += repeat.interval;
+                if self.next_repeat[i] <= now {
+                    self.next_repeat[i] = now + repeat.interval;
+                }
+            }
+        }
+    }
+
+    /// Bits set now, but not at the previous update.
+    pub(crate) fn pressed(&self) -> u32 {
+        self.current & !self.previous
+    }
+
+    /// Bits set at the previous update, but not now.
+    pub(crate) fn released(&self) -> u32 {
+        self.previous & !self.current
+    }
+
+    /// Bits set at the previous update.
+    pub(crate) fn previous(&self) -> u32 {
+        self.previous
+    }
+
+    /// Bits set now.
+    pub(crate) fn held(&self) -> u32 {
+        self.current
+    }
+
+    /// Held bits that repeated at this update.
+    pub(crate) fn repeated(&self) -> u32 {
+        self.repeated
+    }
+}
+
+/// Call `f` with each bit set in `bits`.
+pub(crate) fn for_each_bit(bits: u32, mut f: impl FnMut(u32)) {
+    for i in 0..32 {
+        if bits & (1 << i) != 0 {
+            f(1 << i);
+        }
+    }
+}
@@ -0,0 +1,244 @@
+//! The headphone remote, and what is plugged into the headphone jack.
+
+use super::{for_each_bit, now, Edges, InputEvent, Repeat};
+use crate::error::Result;
+use crate::sync::critical_section;
+use crate::sys::{self, HprmKey};
+use crate::thread::{self, SharedThreads};
+use core::cell::UnsafeCell;
+
+/// How often a `RemoteWatcher` reads the remote, about once per frame.
+pub const POLL_INTERVAL_US: u32 = 16_667;
+
+/// Read the keys held on the remote, or none if it cannot be read.
+pub fn read_keys() -> HprmKey {
+    let mut keys = HprmKey::empty();
+
+    if unsafe { sys::sceHprmPeekCurrentKey(&mut keys) } < 0 {
+        return HprmKey::empty();
+    }
+
+    keys
+}
+
+/// Something plugged into the headphone jack.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Accessory {
+    /// Headphones, plugged in directly or through the remote.
+    Headphones,
+    /// The wired remote control, whose keys `read_keys` reads.
+    Remote,
+    /// A microphone, such as the one in the headset, which
+    /// `psp::audio::Recorder` records.
+    Microphone,
+}
+
+/// Which accessories are plugged in.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct Plugs {
+    /// Whether headphones are plugged in.
+    pub headphones: bool,
+    /// Whether the remote control is plugged in.
+    pub remote: bool,
+    /// Whether a microphone is plugged in.
+    pub microphone: bool,
+}
+
+impl Plugs {
+    /// Read which accessories are plugged in.
+    pub fn read() -> Self {
+        unsafe {
+            Self {
+                headphones: sys::sceHprmIsHeadphoneExist() == 1,
+                remote: sys::sceHprmIsRemoteExist() == 1,
+                microphone: sys::sceHprmIsMicrophoneExist() == 1,
+            }
+        }
+    }
+
+    /// Returns `true` if `accessory` is plugged in.
+    pub fn contains(self, accessory: Accessory) -> bool {
+        match accessory {
+            Accessory::Headphones => self.headphones,
+            Accessory::Remote => self.remote,
+            Accessory::Microphone => self.microphone,
+        }
+    }
+}
+
+/// The keys of the remote and the accessories plugged in, as of the last
+/// update.
+///
+/// While the remote's hold switch is on, its other keys are ignored.
+#[derive(Debug, Clone)]
+pub struct RemoteState {
+    keys: Edges,
+    plugs: Plugs,
+    repeat: Repeat,
+}
+
+impl RemoteState {
+    /// Create a state with no keys held and nothing plugged in.
+    pub fn new() -> Self {
+        Self {
+            keys: Edges::new(),
+            plugs: Plugs::default(),
+            repeat: Repeat::default(),
+        }
+    }
+
+    /// The repeat timing of held keys.
+    pub fn repeat(&self) -> Repeat {
+        self.repeat
+    }
+
+    /// Change the repeat timing of held keys.
+    pub fn set_repeat(&mut self, repeat: Repeat) {
+        self.repeat = repeat;
+    }
+
+    /// Move to a new reading of the hardware, taken at `now`, passing the
+    /// changes to `emit`.
+    ///
+    /// Accessories plugged in before the first update are reported as
+    /// plugged in by it.
+    pub fn update_with(
+        &mut self,
+        keys: HprmKey,
+        plugs: Plugs,
+        now: core::time::Duration,
+        mut emit: impl FnMut(InputEvent),
+    ) {
+        for &accessory in &[
+            Accessory::Headphones,
+            Accessory::Remote,
+            Accessory::Microphone,
+        ] {
+            match (self.plugs.contains(accessory), plugs.contains(accessory)) {
+                (false, true) => emit(InputEvent::Plugged(accessory)),
+                (true, false) => emit(InputEvent::Unplugged(accessory)),
+                _ => {}
+            }
+        }
+        self.plugs = plugs;
+
+        // Without a remote there are no keys, whatever the hardware says.
+        let keys = if !plugs.remote {
+            HprmKey::empty()
+        } else if keys.contains(HprmKey::HOLD) {
+            HprmKey::HOLD
+        } else {
+            keys
+        };
+
+        self.keys.update(keys.bits(), now, self.repeat);
+
+        let key = HprmKey::from_bits_truncate;
+        for_each_bit(self.keys.released(), |bit| {
+            emit(InputEvent::RemoteReleased(key(bit)))
+        });
+        for_each_bit(self.keys.pressed(), |bit| {
+            emit(InputEvent::RemotePressed(key(bit)))
+        });
+        for_each_bit(self.keys.repeated(), |bit| {
+            emit(InputEvent::RemoteRepeated(key(bit)))
+        });
+    }
+
+    /// The keys held.
+    pub fn keys(&self) -> HprmKey {
+        HprmKey::from_bits_truncate(self.keys.held())
+    }
+
+    /// Returns `true` if any of `keys` was pressed at the last update.
+    pub fn is_pressed(&self, keys: HprmKey) -> bool {
+        self.keys.pressed() & keys.bits() != 0
+    }
+
+    /// Returns `true` if any of `keys` was released at the last update.
+    pub fn is_released(&self, keys: HprmKey) -> bool {
+        self.keys.released() & keys.bits() != 0
+    }
+
+    /// Returns `true` if all of `keys` are held.
+    pub fn is_held(&self, keys: HprmKey) -> bool {
+        self.keys() & keys == keys
+    }
+
+    /// Returns `true` if any of `keys` was pressed or repeated at the last
+    /// update, as menus want.
+    pub fn is_repeated(&self, keys: HprmKey) -> bool {
+        (self.keys.pressed() | self.keys.repeated()) & keys.bits() != 0
+    }
+
+    /// The accessories plugged in.
+    pub fn plugs(&self) -> Plugs {
+        self.plugs
+    }
+}
+
+impl Default for RemoteState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Whether a `RemoteWatcher`'s thread should keep reading.
+struct Running(UnsafeCell<bool>);
+
+// The flag is only accessed in a critical section.
+unsafe impl Sync for Running {}
+
+impl Running {
+    fn get(&self) -> bool {
+        critical_section(|| unsafe { *self.0.get() })
+    }
+}
+
+/// Reads the remote on a background thread, and passes its events to a
+/// handler.
+///
+/// Dropping the watcher stops the thread.
+pub struct RemoteWatcher {
+    running: SharedThreads<Running>,
+}
+
+impl RemoteWatcher {
+    /// Start reading the remote, calling `handler` on the watcher's thread
+    /// with each event.
+    pub fn start(handler: impl FnMut(InputEvent) + Send + 'static) -> Result<Self> {
+        Self::with_repeat(Repeat::default(), handler)
+    }
+
+    /// Like `start`, with custom repeat timing.
+    pub fn with_repeat(
+        repeat: Repeat,
+        mut handler: impl FnMut(InputEvent) + Send + 'static,
+    ) -> Result<Self> {
+        let mut running = SharedThreads::new(Running(UnsafeCell::new(true)));
+        let builder = thread::Builder::new()
+            .name("psp_input_remote")
+            .stack_size(8 * 1024);
+
+        running.spawn(builder, move |running| {
+            let mut state = RemoteState::new();
+            state.set_repeat(repeat);
+
+            while running.get() {
+                state.update_with(read_keys(), Plugs::read(), now(), &mut handler);
+
+                unsafe { sys::sceKernelDelayThread(POLL_INTERVAL_US) };
+            }
+        })?;
+
+        Ok(Self { running })
+    }
+}
+
+impl Drop for RemoteWatcher {
+    fn drop(&mut self) {
+        // Dropping `running` then joins the thread.
+        let running = self.running.state().0.get();
+        critical_section(|| unsafe { *running = false });
+    }
+}
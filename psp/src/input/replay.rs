@@ -0,0 +1,395 @@
+//! Recording controller frames, and replaying them in place of the
+//! controller.
+//!
+//! `InputRecorder` writes frames to a file on `ms0:` or `host0:` as they are
+//! read, and `Replay` plays a recording back one frame per update. Passed to
+//! `Input`, a replay takes the place of the controller, so that a bug report
+//! can come with the exact input that caused it. Passed to `Replay::play`, it
+//! drives an `InputState` directly, so that tests can run UI code through a
+//! recorded flow.
+//!
+//! A recording is an 8 byte header, `PSPI` followed by the format version
+//! and two reserved bytes, then 10 bytes per frame: the time since the
+//! previous frame in microseconds, the buttons held, and the stick position.
+//! Numbers are little endian.
+//!
+//! The format is simple enough to write by hand on the host, so that tests
+//! can feed `InputState` fixed input.
+
+use super::{Frame, InputEvent, InputState};
+use crate::fs::{self, File};
+use crate::io::{self, Write};
+use crate::sys::CtrlButtons;
+use alloc::vec::Vec;
+use core::convert::TryInto;
+use core::fmt;
+use core::time::Duration;
+
+/// The first bytes of a recording.
+pub const MAGIC: [u8; 4] = *b"PSPI";
+
+/// The version of the format written.
+pub const VERSION: u16 = 1;
+
+/// The length of the header.
+pub const HEADER_LEN: usize = 8;
+
+/// The length of each frame.
+pub const FRAME_LEN: usize = 10;
+
+/// The number of frames `InputRecorder` buffers between writes, about a
+/// second's worth.
+pub const WRITE_FRAMES: usize = 60;
+
+/// Error returned when reading a recording.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ReplayError {
+    /// The data does not start with the recording header.
+    NotRecording,
+    /// The recording is in a newer version of the format. Holds the version.
+    UnsupportedVersion(u16),
+    /// The data ends in the middle of a frame.
+    Truncated,
+}
+
+impl From<ReplayError> for io::Error {
+    fn from(_: ReplayError) -> Self {
+        io::Error::new(io::ErrorKind::InvalidData)
+    }
+}
+
+impl fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReplayError::NotRecording => f.write_str("not an input recording"),
+            ReplayError::UnsupportedVersion(version) => {
+                write!(f, "unsupported input recording version {}", version)
+            }
+            ReplayError::Truncated => f.write_str("truncated input recording"),
+        }
+    }
+}
+
+/// Controller frames, with times relative to the start of the recording.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Recording {
+    frames: Vec<Frame>,
+}
+
+impl Recording {
+    /// Create an empty recording.
+    pub fn new() -> Self {
+        Self { frames: Vec::new() }
+    }
+
+    /// Create a recording of `frames`, whose times must not decrease.
+    pub fn from_frames(frames: Vec<Frame>) -> Self {
+        Self { frames }
+    }
+
+    /// Add a frame, which must not be earlier than the last.
+    pub fn push(&mut self, frame: Frame) {
+        self.frames.push(frame);
+    }
+
+    /// The frames.
+    pub fn frames(&self) -> &[Frame] {
+        &self.frames
+    }
+
+    /// The number of frames.
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    /// Returns `true` if there are no frames.
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    /// The time of the last frame.
+    pub fn duration(&self) -> Duration {
+        self.frames
+            .last()
+            .map_or(Duration::from_secs(0), |frame| frame.time)
+    }
+
+    /// Write the recording in the replay format.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(HEADER_LEN + self.frames.len() * FRAME_LEN);
+        bytes.extend_from_slice(&header());
+
+        let mut previous = Duration::from_secs(0);
+        for frame in &self.frames {
+            bytes.extend_from_slice(&encode_frame(previous, frame));
+            previous = frame.time;
+        }
+
+        bytes
+    }
+
+    /// Read a recording in the replay format.
+    pub fn from_bytes(data: &[u8]) -> Result<Self, ReplayError> {
+        let frames = data.get(HEADER_LEN..).ok_or(ReplayError::NotRecording)?;
+        check_header(&data[..HEADER_LEN])?;
+
+        if frames.len() % FRAME_LEN != 0 {
+            return Err(ReplayError::Truncated);
+        }
+
+        let mut time = Duration::from_secs(0);
+        let frames = frames
+            .chunks_exact(FRAME_LEN)
+            .map(|bytes| {
+                let frame = decode_frame(time, bytes);
+                time = frame.time;
+                frame
+            })
+            .collect();
+
+        Ok(Self { frames })
+    }
+}
+
+/// Writes controller frames to a recording as they are read.
+///
+/// Frame times are stored relative to the first frame recorded. Frames are
+/// buffered and written about once a second, and when the recorder is
+/// finished or dropped.
+pub struct InputRecorder<W: Write> {
+    writer: Option<W>,
+    buffer: Vec<u8>,
+    /// The time of the first frame.
+    start: Option<Duration>,
+    /// The time of the last frame, relative to `start`.
+    previous: Duration,
+    frames: usize,
+    /// The first write error, after which nothing more is written.
+    error: Option<io::Error>,
+}
+
+impl InputRecorder<File> {
+    /// Create or truncate the file at `path`, and record to it.
+    pub fn create(path: &str) -> io::Result<Self> {
+        Self::new(File::create(path)?)
+    }
+}
+
+impl<W: Write> InputRecorder<W> {
+    /// Record to `writer`, starting with the header.
+    pub fn new(mut writer: W) -> io::Result<Self> {
+        writer.write_all(&header())?;
+
+        Ok(Self {
+            writer: Some(writer),
+            buffer: Vec::with_capacity(WRITE_FRAMES * FRAME_LEN),
+            start: None,
+            previous: Duration::from_secs(0),
+            frames: 0,
+            error: None,
+        })
+    }
+
+    /// Add a frame, which must not be earlier than the last.
+    ///
+    /// After a write fails, this keeps returning the error.
+    pub fn record(&mut self, frame: &Frame) -> io::Result<()> {
+        if let Some(error) = self.error {
+            return Err(error);
+        }
+
+        let start = *self.start.get_or_insert(frame.time);
+        let frame = Frame {
+            time: frame.time.checked_sub(start).unwrap_or_default(),
+            ..*frame
+        };
+
+        self.buffer
+            .extend_from_slice(&encode_frame(self.previous, &frame));
+        self.previous = frame.time;
+        self.frames += 1;
+
+        if self.buffer.len() >= WRITE_FRAMES * FRAME_LEN {
+            self.write_buffer()?;
+        }
+
+        Ok(())
+    }
+
+    /// The number of frames recorded.
+    pub fn len(&self) -> usize {
+        self.frames
+    }
+
+    /// Returns `true` if no frames were recorded.
+    pub fn is_empty(&self) -> bool {
+        self.frames == 0
+    }
+
+    /// Write the buffered frames and flush the writer.
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.write_buffer()?;
+
+        match self.writer.as_mut().map(|writer| writer.flush()) {
+            Some(Err(error)) => {
+                self.error = Some(error);
+
+                Err(error)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Write the buffered frames and return the writer, or the first error.
+    pub fn finish(mut self) -> io::Result<W> {
+        self.flush()?;
+
+        Ok(self.writer.take().unwrap())
+    }
+
+    fn write_buffer(&mut self) -> io::Result<()> {
+        if let Some(error) = self.error {
+            return Err(error);
+        }
+
+        if let Some(writer) = self.writer.as_mut() {
+            if let Err(error) = writer.write_all(&self.buffer) {
+                self.error = Some(error);
+
+                return Err(error);
+            }
+        }
+
+        self.buffer.clear();
+
+        Ok(())
+    }
+}
+
+impl<W: Write> Drop for InputRecorder<W> {
+    fn drop(&mut self) {
+        if self.writer.is_some() {
+            let _ = self.flush();
+        }
+    }
+}
+
+/// Plays back a recording one frame at a time.
+#[derive(Debug, Clone)]
+pub struct Replay {
+    recording: Recording,
+    position: usize,
+}
+
+impl Replay {
+    /// Play back `recording` from the start.
+    pub fn new(recording: Recording) -> Self {
+        Self {
+            recording,
+            position: 0,
+        }
+    }
+
+    /// Read the recording at `path`, and play it back from the start.
+    pub fn open(path: &str) -> io::Result<Self> {
+        Ok(Self::new(Recording::from_bytes(&fs::read(path)?)?))
+    }
+
+    /// The recording played back.
+    pub fn recording(&self) -> &Recording {
+        &self.recording
+    }
+
+    /// The next frame, or `None` once every frame was played.
+    pub fn next_frame(&mut self) -> Option<Frame> {
+        let frame = self.recording.frames.get(self.position).copied();
+        if frame.is_some() {
+            self.position += 1;
+        }
+
+        frame
+    }
+
+    /// The number of frames played.
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    /// Returns `true` once every frame was played.
+    pub fn is_finished(&self) -> bool {
+        self.position == self.recording.len()
+    }
+
+    /// Go back to the first frame.
+    pub fn rewind(&mut self) {
+        self.position = 0;
+    }
+
+    /// Update `state` with each remaining frame, calling `f` after each with
+    /// the state and the events of that frame.
+    ///
+    /// `state` is reset first, so that the events do not depend on what
+    /// came before.
+    pub fn play(&mut self, state: &mut InputState, mut f: impl FnMut(&InputState, &[InputEvent])) {
+        let mut events = Vec::new();
+        state.reset();
+
+        while let Some(frame) = self.next_frame() {
+            events.clear();
+            state.update_with(frame, |event| events.push(event));
+
+            f(state, &events);
+        }
+    }
+}
+
+/// The header of a recording.
+pub(crate) fn header() -> [u8; HEADER_LEN] {
+    let mut header = [0; HEADER_LEN];
+    header[..4].copy_from_slice(&MAGIC);
+    header[4..6].copy_from_slice(&VERSION.to_le_bytes());
+
+    header
+}
+
+/// Check the header of a recording.
+pub(crate) fn check_header(header: &[u8]) -> Result<(), ReplayError> {
+    if header.len() < HEADER_LEN || header[..4] != MAGIC {
+        return Err(ReplayError::NotRecording);
+    }
+
+    let version = u16::from_le_bytes([header[4], header[5]]);
+    if version > VERSION {
+        return Err(ReplayError::UnsupportedVersion(version));
+    }
+
+    Ok(())
+}
+
+/// Encode `frame`, which follows a frame at `previous`.
+///
+/// Gaps too long to store are shortened to about 71 minutes.
+pub(crate) fn encode_frame(previous: Duration, frame: &Frame) -> [u8; FRAME_LEN] {
+    let delta = frame.time.checked_sub(previous).unwrap_or_default();
+    let delta = delta.as_micros().min(u32::MAX as u128) as u32;
+
+    let mut bytes = [0; FRAME_LEN];
+    bytes[0..4].copy_from_slice(&delta.to_le_bytes());
+    bytes[4..8].copy_from_slice(&frame.buttons.bits().to_le_bytes());
+    bytes[8] = frame.lx;
+    bytes[9] = frame.ly;
+
+    bytes
+}
+
+/// Decode a frame that follows a frame at `previous`.
+pub(crate) fn decode_frame(previous: Duration, bytes: &[u8]) -> Frame {
+    let word = |i: usize| u32::from_le_bytes(bytes[i..i + 4].try_into().unwrap());
+
+    Frame {
+        time: previous + Duration::from_micros(word(0) as u64),
+        buttons: CtrlButtons::from_bits_truncate(word(4)),
+        lx: bytes[8],
+        ly: bytes[9],
+    }
+}
@@ -0,0 +1,415 @@
+//! Traits and types for byte-oriented I/O.
+//!
+//! These mirror the traits of the same names in `std::io`, so that code
+//! written against this module is easy to port.
+
+use alloc::vec::Vec;
+use core::fmt;
+
+/// A specialized `Result` type for I/O operations.
+pub type Result<T> = core::result::Result<T, Error>;
+
+/// A list specifying general categories of I/O error.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ErrorKind {
+    /// An entity was not found, often a file.
+    NotFound,
+    /// The operation lacked the necessary privileges to complete.
+    PermissionDenied,
+    /// An entity already exists, often a file.
+    AlreadyExists,
+    /// A parameter was incorrect, e.g. a path with an interior NUL byte.
+    InvalidInput,
+    /// Data read was not valid for the operation, e.g. invalid UTF-8.
+    InvalidData,
+    /// A path component was expected to be a directory but was not.
+    NotADirectory,
+    /// A directory was found where a file was expected.
+    IsADirectory,
+    /// A directory could not be removed because it is not empty.
+    DirectoryNotEmpty,
+    /// The device has no free space left.
+    StorageFull,
+    /// The connection was refused by the remote server.
+    ConnectionRefused,
+    /// The connection was reset by the remote server.
+    ConnectionReset,
+    /// The connection was aborted by the remote server.
+    ConnectionAborted,
+    /// The operation failed because the socket is not connected.
+    NotConnected,
+    /// A socket address could not be bound because it is in use.
+    AddrInUse,
+    /// A nonexistent interface was requested or the address was not local.
+    AddrNotAvailable,
+    /// The operation failed because a pipe or connection was closed.
+    BrokenPipe,
+    /// An operation could not be completed because it would block.
+    WouldBlock,
+    /// The operation did not complete within its timeout.
+    TimedOut,
+    /// `write` returned `Ok(0)` while writing a whole buffer.
+    WriteZero,
+    /// The end of a stream was reached before a buffer could be filled.
+    UnexpectedEof,
+    /// Any error not part of this list.
+    Other,
+}
+
+impl ErrorKind {
+    fn as_str(&self) -> &'static str {
+        match self {
+            ErrorKind::NotFound => "entity not found",
+            ErrorKind::PermissionDenied => "permission denied",
+            ErrorKind::AlreadyExists => "entity already exists",
+            ErrorKind::InvalidInput => "invalid input parameter",
+            ErrorKind::InvalidData => "invalid data",
+            ErrorKind::NotADirectory => "not a directory",
+            ErrorKind::IsADirectory => "is a directory",
+            ErrorKind::DirectoryNotEmpty => "directory not empty",
+            ErrorKind::StorageFull => "no storage space",
+            ErrorKind::ConnectionRefused => "connection refused",
+            ErrorKind::ConnectionReset => "connection reset",
+            ErrorKind::ConnectionAborted => "connection aborted",
+            ErrorKind::NotConnected => "not connected",
+            ErrorKind::AddrInUse => "address in use",
+            ErrorKind::AddrNotAvailable => "address not available",
+            ErrorKind::BrokenPipe => "broken pipe",
+            ErrorKind::WouldBlock => "operation would block",
+            ErrorKind::TimedOut => "timed out",
+            ErrorKind::WriteZero => "write zero",
+            ErrorKind::UnexpectedEof => "unexpected end of file",
+            ErrorKind::Other => "other error",
+        }
+    }
+}
+
+/// An I/O error, optionally carrying the SCE error code it came from.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Error {
+    kind: ErrorKind,
+    code: Option<crate::Error>,
+}
+
+impl Error {
+    /// Create an error of the given kind, with no SCE error code.
+    pub const fn new(kind: ErrorKind) -> Self {
+        Self { kind, code: None }
+    }
+
+    /// Create an error from a negative SCE error code, as returned by the
+    /// `sceIo*` functions.
+    pub fn from_sce(code: i32) -> Self {
+        crate::Error::from_raw(code).into()
+    }
+
+    /// Returns the corresponding `ErrorKind` for this error.
+    pub fn kind(&self) -> ErrorKind {
+        self.kind
+    }
+
+    /// Returns the SCE error code, if this error originated from a system
+    /// call.
+    pub fn sce_error(&self) -> Option<crate::Error> {
+        self.code
+    }
+}
+
+impl From<crate::Error> for Error {
+    fn from(error: crate::Error) -> Self {
+        use crate::error::*;
+
+        let kind = match error {
+            SCE_ERROR_ERRNO_ENOENT | SCE_KERNEL_ERROR_NOFILE => ErrorKind::NotFound,
+            SCE_ERROR_ERRNO_EACCES | SCE_ERROR_ERRNO_EPERM | SCE_ERROR_ERRNO_EROFS => {
+                ErrorKind::PermissionDenied
+            }
+            SCE_ERROR_ERRNO_EEXIST => ErrorKind::AlreadyExists,
+            SCE_ERROR_ERRNO_ENOTDIR => ErrorKind::NotADirectory,
+            SCE_ERROR_ERRNO_EISDIR => ErrorKind::IsADirectory,
+            SCE_ERROR_ERRNO_EINVAL | SCE_ERROR_ERRNO_ENAMETOOLONG => ErrorKind::InvalidInput,
+            SCE_ERROR_ERRNO_ENOSPC => ErrorKind::StorageFull,
+            SCE_ERROR_ERRNO_EAGAIN | SCE_ERROR_ERRNO_EINPROGRESS | SCE_ERROR_ERRNO_EALREADY => {
+                ErrorKind::WouldBlock
+            }
+            SCE_NET_ADHOC_ERROR_WOULD_BLOCK => ErrorKind::WouldBlock,
+            SCE_ERROR_ERRNO_ECONNREFUSED | SCE_NET_ADHOC_ERROR_CONNECTION_REFUSED => {
+                ErrorKind::ConnectionRefused
+            }
+            SCE_ERROR_ERRNO_ECONNRESET | SCE_NET_ADHOC_ERROR_DISCONNECTED => {
+                ErrorKind::ConnectionReset
+            }
+            SCE_ERROR_ERRNO_ECONNABORTED => ErrorKind::ConnectionAborted,
+            SCE_ERROR_ERRNO_ENOTCONN | SCE_NET_ADHOC_ERROR_NOT_CONNECTED => {
+                ErrorKind::NotConnected
+            }
+            SCE_ERROR_ERRNO_EADDRINUSE | SCE_NET_ADHOC_ERROR_PORT_IN_USE => ErrorKind::AddrInUse,
+            SCE_ERROR_ERRNO_EADDRNOTAVAIL => ErrorKind::AddrNotAvailable,
+            SCE_ERROR_ERRNO_EPIPE => ErrorKind::BrokenPipe,
+            SCE_ERROR_ERRNO_ENOTEMPTY => ErrorKind::DirectoryNotEmpty,
+            SCE_ERROR_ERRNO_ETIMEDOUT
+            | SCE_KERNEL_ERROR_WAIT_TIMEOUT
+            | SCE_NET_ADHOC_ERROR_TIMEOUT => ErrorKind::TimedOut,
+            SCE_NET_ADHOC_ERROR_INVALID_ADDR | SCE_NET_ADHOC_ERROR_INVALID_PORT => {
+                ErrorKind::InvalidInput
+            }
+            _ => ErrorKind::Other,
+        };
+
+        Self { kind, code: Some(error) }
+    }
+}
+
+impl From<ErrorKind> for Error {
+    fn from(kind: ErrorKind) -> Self {
+        Self::new(kind)
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.code {
+            Some(code) => write!(f, "{}: {}", self.kind.as_str(), code),
+            None => f.write_str(self.kind.as_str()),
+        }
+    }
+}
+
+/// Convert the return value of an `sceIo*` call into a `Result`.
+pub(crate) fn cvt(ret: i32) -> Result<i32> {
+    if ret < 0 {
+        Err(Error::from_sce(ret))
+    } else {
+        Ok(ret)
+    }
+}
+
+/// Fill `buf` from `reader`, stopping early only at the end of the stream.
+///
+/// Unlike `Read::read_exact`, a short read is not an error, and the number of
+/// bytes read is returned.
+pub(crate) fn read_full(reader: &mut impl Read, buf: &mut [u8]) -> Result<usize> {
+    let mut filled = 0;
+
+    while filled < buf.len() {
+        match reader.read(&mut buf[filled..])? {
+            0 => break,
+            n => filled += n,
+        }
+    }
+
+    Ok(filled)
+}
+
+/// A source of bytes.
+pub trait Read {
+    /// Pull some bytes from this source into `buf`, returning how many bytes
+    /// were read. A return value of `0` indicates end of file.
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize>;
+
+    /// Read the exact number of bytes required to fill `buf`.
+    fn read_exact(&mut self, mut buf: &mut [u8]) -> Result<()> {
+        while !buf.is_empty() {
+            match self.read(buf)? {
+                0 => return Err(Error::new(ErrorKind::UnexpectedEof)),
+                n => buf = &mut buf[n..],
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Read all bytes until end of file, appending them to `buf`.
+    fn read_to_end(&mut self, buf: &mut Vec<u8>) -> Result<usize> {
+        let start = buf.len();
+        let mut chunk = [0; 4096];
+
+        loop {
+            match self.read(&mut chunk)? {
+                0 => return Ok(buf.len() - start),
+                n => buf.extend_from_slice(&chunk[..n]),
+            }
+        }
+    }
+}
+
+/// A sink of bytes.
+pub trait Write {
+    /// Write a buffer into this writer, returning how many bytes were written.
+    fn write(&mut self, buf: &[u8]) -> Result<usize>;
+
+    /// Flush any buffered data to the underlying device.
+    fn flush(&mut self) -> Result<()>;
+
+    /// Write an entire buffer into this writer.
+    fn write_all(&mut self, mut buf: &[u8]) -> Result<()> {
+        while !buf.is_empty() {
+            match self.write(buf)? {
+                0 => return Err(Error::new(ErrorKind::WriteZero)),
+                n => buf = &buf[n..],
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Possible ways to seek within a stream.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum SeekFrom {
+    /// Set the offset to the given number of bytes.
+    Start(u64),
+    /// Set the offset to the size of the stream plus the given number of bytes.
+    End(i64),
+    /// Set the offset to the current position plus the given number of bytes.
+    Current(i64),
+}
+
+/// A stream with a cursor which can be moved.
+pub trait Seek {
+    /// Seek to an offset, in bytes, returning the new position from the start
+    /// of the stream.
+    fn seek(&mut self, pos: SeekFrom) -> Result<u64>;
+
+    /// Returns the current position from the start of the stream.
+    fn stream_position(&mut self) -> Result<u64> {
+        self.seek(SeekFrom::Current(0))
+    }
+}
+
+impl<R: Read + ?Sized> Read for &mut R {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        (**self).read(buf)
+    }
+}
+
+impl<W: Write + ?Sized> Write for &mut W {
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        (**self).write(buf)
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        (**self).flush()
+    }
+}
+
+impl<S: Seek + ?Sized> Seek for &mut S {
+    fn seek(&mut self, pos: SeekFrom) -> Result<u64> {
+        (**self).seek(pos)
+    }
+}
+
+impl Read for &[u8] {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        let len = core::cmp::min(buf.len(), self.len());
+        let (head, tail) = self.split_at(len);
+        buf[..len].copy_from_slice(head);
+        *self = tail;
+
+        Ok(len)
+    }
+}
+
+impl Write for Vec<u8> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        self.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// Wraps an in-memory buffer and provides it with a `Seek` implementation.
+#[derive(Debug, Clone, Default)]
+pub struct Cursor<T> {
+    inner: T,
+    pos: u64,
+}
+
+impl<T> Cursor<T> {
+    /// Create a new cursor positioned at the start of `inner`.
+    pub fn new(inner: T) -> Self {
+        Self { inner, pos: 0 }
+    }
+
+    /// Consume the cursor, returning the underlying value.
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+
+    /// Get a reference to the underlying value.
+    pub fn get_ref(&self) -> &T {
+        &self.inner
+    }
+
+    /// Returns the current position of this cursor.
+    pub fn position(&self) -> u64 {
+        self.pos
+    }
+
+    /// Set the position of this cursor.
+    pub fn set_position(&mut self, pos: u64) {
+        self.pos = pos;
+    }
+}
+
+impl<T: AsRef<[u8]>> Read for Cursor<T> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        let data = self.inner.as_ref();
+        let start = core::cmp::min(self.pos, data.len() as u64) as usize;
+        let n = (&data[start..]).read(buf)?;
+        self.pos += n as u64;
+
+        Ok(n)
+    }
+}
+
+impl Write for Cursor<Vec<u8>> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        let start = self.pos as usize;
+        let end = start + buf.len();
+
+        // Writing past the end fills the gap with zeroes.
+        if self.inner.len() < end {
+            self.inner.resize(end, 0);
+        }
+
+        self.inner[start..end].copy_from_slice(buf);
+        self.pos = end as u64;
+
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl<T: AsRef<[u8]>> Seek for Cursor<T> {
+    fn seek(&mut self, pos: SeekFrom) -> Result<u64> {
+        let (base, offset) = match pos {
+            SeekFrom::Start(n) => {
+                self.pos = n;
+                return Ok(n);
+            }
+            SeekFrom::End(n) => (self.inner.as_ref().len() as u64, n),
+            SeekFrom::Current(n) => (self.pos, n),
+        };
+
+        let new_pos = if offset >= 0 {
+            base.checked_add(offset as u64)
+        } else {
+            base.checked_sub(offset.wrapping_neg() as u64)
+        };
+
+        match new_pos {
+            Some(n) => {
+                self.pos = n;
+                Ok(n)
+            }
+            None => Err(Error::new(ErrorKind::InvalidInput)),
+        }
+    }
+}
@@ -34,8 +34,31 @@ pub mod debug;
 mod eabi;
 pub mod math;
 pub mod sys;
+pub mod error;
+pub use error::{Error, Result};
 #[cfg(not(feature = "stub-only"))] pub mod test_runner;
 #[cfg(not(feature = "stub-only"))] pub mod vram_alloc;
+#[cfg(not(feature = "stub-only"))] pub mod sync;
+#[cfg(not(feature = "stub-only"))] pub mod events;
+#[cfg(not(feature = "stub-only"))] pub mod modules;
+#[cfg(not(feature = "stub-only"))] pub mod io;
+#[cfg(not(feature = "stub-only"))] pub mod fs;
+#[cfg(not(feature = "stub-only"))] pub mod executor;
+#[cfg(not(feature = "stub-only"))] pub mod thread;
+#[cfg(not(feature = "stub-only"))] pub mod display;
+#[cfg(not(feature = "stub-only"))] pub mod audio;
+#[cfg(not(feature = "stub-only"))] pub mod video;
+#[cfg(not(feature = "stub-only"))] pub mod image;
+#[cfg(not(feature = "stub-only"))] pub mod text;
+#[cfg(not(feature = "stub-only"))] pub mod input;
+#[cfg(not(feature = "stub-only"))] pub mod dialog;
+#[cfg(not(feature = "stub-only"))] pub mod savedata;
+#[cfg(not(feature = "stub-only"))] pub mod system;
+#[cfg(not(feature = "stub-only"))] pub mod power;
+#[cfg(not(feature = "stub-only"))] pub mod env;
+#[cfg(not(feature = "stub-only"))] pub mod stdio;
+#[cfg(not(feature = "stub-only"))] pub mod net;
+#[cfg(all(feature = "std", not(feature = "stub-only")))] mod std_compat;
 
 #[cfg(not(feature = "stub-only"))] mod alloc_impl;
 #[cfg(not(feature = "stub-only"))] pub mod panic;
@@ -152,12 +175,16 @@ macro_rules! module {
             };
 
             #[no_mangle]
-            extern "C" fn module_start(_argc: isize, _argv: *const *const u8) -> isize {
+            extern "C" fn module_start(argc: isize, argv: *const *const u8) -> isize {
                 use $crate::sys::ThreadAttributes;
                 use core::ffi::c_void;
 
                 unsafe {
-                    extern fn main_thread(_argc: usize, _argv: *mut c_void) -> i32 {
+                    extern fn main_thread(argc: usize, argv: *mut c_void) -> i32 {
+                        unsafe {
+                            $crate::env::init(argc, argv as *const u8);
+                        }
+
                         // TODO: Maybe print any error to debug screen?
                         let _ = $crate::catch_unwind(|| {
                             super::psp_main();
@@ -177,7 +204,9 @@ macro_rules! module {
                         core::ptr::null_mut(),
                     );
 
-                    $crate::sys::sceKernelStartThread(id, 0, core::ptr::null_mut());
+                    // Forward the module arguments, which the kernel copies onto
+                    // the stack of the main thread.
+                    $crate::sys::sceKernelStartThread(id, argc as usize, argv as *mut c_void);
                 }
 
                 0
@@ -188,8 +217,9 @@ macro_rules! module {
 
 /// Enable the home button.
 ///
-/// This API does not have destructor support yet. You can manually setup an
-/// exit callback if you need this, see the source code of this function.
+/// This API does not have destructor support. If you need to save state before
+/// exiting, use `psp::events` instead, which delivers `Event::ExitRequested`
+/// to your handlers before quitting.
 pub fn enable_home_button() {
     use core::{ptr, ffi::c_void};
     use sys::ThreadAttributes;
@@ -0,0 +1,320 @@
+//! Reference-counted loading of the firmware's utility modules.
+//!
+//! Libraries such as `sceNetInet`, `sceMp3` or `sceUsbMic` live in modules
+//! that are loaded on demand with the `sceUtilityLoad*Module` functions. Some
+//! need others to be loaded first, and every load needs a matching unload.
+//!
+//! A `ModuleGuard` loads a set of modules along with their dependencies, in
+//! order, and unloads them in reverse when dropped. Loads are counted across
+//! guards, so a module shared by two guards stays loaded until both are gone.
+//! Modules that were already loaded by someone else are left loaded.
+//!
+//! # Example
+//!
+//! ```ignore
+//! use psp::modules::ModuleGuard;
+//! use psp::sys::AvModule;
+//!
+//! // Loads `AvCodec`, then `Mp3`.
+//! let _modules = ModuleGuard::load(&[AvModule::Mp3.into()])?;
+//! # Ok::<(), psp::Error>(())
+//! ```
+
+use crate::error::{self, Result};
+use crate::sync::critical_section;
+use crate::sys::{self, AvModule, Module, NetModule, UsbModule};
+use alloc::vec::Vec;
+
+/// A module loaded through one of the `sceUtilityLoad*Module` functions.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum UtilityModule {
+    /// Loaded with `sceUtilityLoadModule`.
+    Module(Module),
+    /// Loaded with `sceUtilityLoadAvModule`.
+    Av(AvModule),
+    /// Loaded with `sceUtilityLoadNetModule`.
+    Net(NetModule),
+    /// Loaded with `sceUtilityLoadUsbModule`.
+    Usb(UsbModule),
+}
+
+impl UtilityModule {
+    /// The modules that must be loaded before this one, in load order and
+    /// canonical form.
+    pub fn dependencies(self) -> &'static [UtilityModule] {
+        use UtilityModule::{Av, Net, Usb};
+
+        match self.canonical() {
+            Av(AvModule::Atrac3Plus)
+            | Av(AvModule::MpegBase)
+            | Av(AvModule::Mp3)
+            | Av(AvModule::Aac) => &[Av(AvModule::AvCodec)],
+            Net(NetModule::NetAdhoc) | Net(NetModule::NetInet) => &[Net(NetModule::NetCommon)],
+            Net(NetModule::NetHttp) => &[
+                Net(NetModule::NetCommon),
+                Net(NetModule::NetInet),
+                Net(NetModule::NetParseUri),
+                Net(NetModule::NetParseHttp),
+            ],
+            Net(NetModule::NetSsl) => &[Net(NetModule::NetCommon), Net(NetModule::NetInet)],
+            Usb(UsbModule::UsbMic) | Usb(UsbModule::UsbCam) | Usb(UsbModule::UsbGps) => {
+                &[Usb(UsbModule::UsbAcc)]
+            }
+            _ => &[],
+        }
+    }
+
+    /// The variant this module is counted and loaded as.
+    ///
+    /// Most modules can be named both through `Module` and through their own
+    /// family, such as `Module::NetCommon` and `NetModule::NetCommon`. Both
+    /// map to the family variant, whose load function older firmware also
+    /// has, so that a module is loaded once whichever way it is reached.
+    pub fn canonical(self) -> Self {
+        use UtilityModule::{Av, Net, Usb};
+
+        let module = match self {
+            UtilityModule::Module(module) => module,
+            _ => return self,
+        };
+
+        match module {
+            Module::NetCommon => Net(NetModule::NetCommon),
+            Module::NetAdhoc => Net(NetModule::NetAdhoc),
+            Module::NetInet => Net(NetModule::NetInet),
+            Module::NetParseUri => Net(NetModule::NetParseUri),
+            Module::NetParseHttp => Net(NetModule::NetParseHttp),
+            Module::NetHttp => Net(NetModule::NetHttp),
+            Module::NetSsl => Net(NetModule::NetSsl),
+            Module::UsbPspCm => Usb(UsbModule::UsbPspCm),
+            Module::UsbMic => Usb(UsbModule::UsbMic),
+            Module::UsbCam => Usb(UsbModule::UsbCam),
+            Module::UsbGps => Usb(UsbModule::UsbGps),
+            Module::AvCodec => Av(AvModule::AvCodec),
+            Module::AvSascore => Av(AvModule::SasCore),
+            Module::AvAtrac3Plus => Av(AvModule::Atrac3Plus),
+            Module::AvMpegBase => Av(AvModule::MpegBase),
+            Module::AvMp3 => Av(AvModule::Mp3),
+            Module::AvVaudio => Av(AvModule::Vaudio),
+            Module::AvAac => Av(AvModule::Aac),
+            Module::AvG729 => Av(AvModule::G729),
+            _ => self,
+        }
+    }
+
+    unsafe fn load(self) -> i32 {
+        match self {
+            UtilityModule::Module(module) => sys::sceUtilityLoadModule(module),
+            UtilityModule::Av(module) => sys::sceUtilityLoadAvModule(module),
+            UtilityModule::Net(module) => sys::sceUtilityLoadNetModule(module),
+            UtilityModule::Usb(module) => sys::sceUtilityLoadUsbModule(module),
+        }
+    }
+
+    unsafe fn unload(self) -> i32 {
+        match self {
+            UtilityModule::Module(module) => sys::sceUtilityUnloadModule(module),
+            UtilityModule::Av(module) => sys::sceUtilityUnloadAvModule(module),
+            UtilityModule::Net(module) => sys::sceUtilityUnloadNetModule(module),
+            UtilityModule::Usb(module) => sys::sceUtilityUnloadUsbModule(module),
+        }
+    }
+}
+
+impl From<Module> for UtilityModule {
+    fn from(module: Module) -> Self {
+        UtilityModule::Module(module)
+    }
+}
+
+impl From<AvModule> for UtilityModule {
+    fn from(module: AvModule) -> Self {
+        UtilityModule::Av(module)
+    }
+}
+
+impl From<NetModule> for UtilityModule {
+    fn from(module: NetModule) -> Self {
+        UtilityModule::Net(module)
+    }
+}
+
+impl From<UsbModule> for UtilityModule {
+    fn from(module: UsbModule) -> Self {
+        UtilityModule::Usb(module)
+    }
+}
+
+/// A set of loaded utility modules, unloaded in reverse order on drop.
+#[derive(Debug)]
+pub struct ModuleGuard {
+    modules: Vec<UtilityModule>,
+}
+
+impl ModuleGuard {
+    /// Load `modules` in order, each preceded by its dependencies.
+    ///
+    /// Modules are held in canonical form, see `UtilityModule::canonical`.
+    ///
+    /// If a module fails to load, those loaded so far are unloaded again.
+    pub fn load(modules: &[UtilityModule]) -> Result<Self> {
+        // On error, dropping `guard` releases the modules acquired so far.
+        let mut guard = Self {
+            modules: Vec::new(),
+        };
+
+        for module in resolve(modules) {
+            acquire(module)?;
+            guard.modules.push(module);
+        }
+
+        Ok(guard)
+    }
+
+    /// The modules held by this guard, in canonical form and load order.
+    pub fn modules(&self) -> &[UtilityModule] {
+        &self.modules
+    }
+}
+
+impl Drop for ModuleGuard {
+    fn drop(&mut self) {
+        for &module in self.modules.iter().rev() {
+            release(module);
+        }
+    }
+}
+
+/// Expand `modules` with their dependencies, in canonical form and without
+/// duplicates.
+fn resolve(modules: &[UtilityModule]) -> Vec<UtilityModule> {
+    let mut resolved = Vec::new();
+
+    for module in modules {
+        // The dependencies of a canonical module are canonical too.
+        let module = module.canonical();
+
+        for &dependency in module.dependencies().iter().chain(Some(&module)) {
+            if !resolved.contains(&dependency) {
+                resolved.push(dependency);
+            }
+        }
+    }
+
+    resolved
+}
+
+#[derive(Copy, Clone)]
+struct Entry {
+    module: UtilityModule,
+    count: u32,
+    /// Whether the module was loaded by us, and should be unloaded.
+    owned: bool,
+    /// Set while the module is being loaded or unloaded.
+    busy: bool,
+}
+
+const MAX_MODULES: usize = 48;
+
+static mut LOADED: [Option<Entry>; MAX_MODULES] = [None; MAX_MODULES];
+
+unsafe fn find(module: UtilityModule) -> Option<&'static mut Entry> {
+    LOADED
+        .iter_mut()
+        .flatten()
+        .find(|entry| entry.module == module)
+}
+
+enum Acquire {
+    Counted,
+    Load,
+    Wait,
+    Full,
+}
+
+fn acquire(module: UtilityModule) -> Result<()> {
+    loop {
+        let action = critical_section(|| unsafe {
+            match find(module) {
+                Some(entry) if entry.busy => Acquire::Wait,
+                Some(entry) => {
+                    entry.count += 1;
+                    Acquire::Counted
+                }
+                None => match LOADED.iter_mut().find(|slot| slot.is_none()) {
+                    Some(slot) => {
+                        *slot = Some(Entry {
+                            module,
+                            count: 1,
+                            owned: false,
+                            busy: true,
+                        });
+                        Acquire::Load
+                    }
+                    None => Acquire::Full,
+                },
+            }
+        });
+
+        match action {
+            Acquire::Counted => return Ok(()),
+            Acquire::Load => break,
+            Acquire::Wait => unsafe {
+                sys::sceKernelDelayThread(1000);
+            },
+            Acquire::Full => return Err(error::SCE_ERROR_ERRNO_ENOMEM),
+        }
+    }
+
+    // The entry is marked busy, so other threads wait for the load to finish.
+    let result = match error::check(unsafe { module.load() }) {
+        Ok(_) => Ok(true),
+        Err(error::SCE_UTILITY_ERROR_MODULE_ALREADY_LOADED) => Ok(false),
+        Err(e) => Err(e),
+    };
+
+    critical_section(|| unsafe {
+        for slot in LOADED.iter_mut() {
+            if let Some(entry) = slot {
+                if entry.module == module {
+                    match result {
+                        Ok(owned) => {
+                            entry.owned = owned;
+                            entry.busy = false;
+                        }
+                        Err(_) => *slot = None,
+                    }
+                }
+            }
+        }
+    });
+
+    result.map(drop)
+}
+
+fn release(module: UtilityModule) {
+    let unload = critical_section(|| unsafe {
+        match find(module) {
+            Some(entry) => {
+                entry.count -= 1;
+                entry.busy = entry.count == 0;
+                entry.busy && entry.owned
+            }
+            None => false,
+        }
+    });
+
+    if unload {
+        unsafe {
+            module.unload();
+        }
+    }
+
+    critical_section(|| unsafe {
+        for slot in LOADED.iter_mut() {
+            if matches!(slot, Some(entry) if entry.module == module && entry.count == 0) {
+                *slot = None;
+            }
+        }
+    });
+}
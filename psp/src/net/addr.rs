@@ -0,0 +1,341 @@
+use crate::io;
+use crate::sys::{self, in_addr, sockaddr, sockaddr_in};
+use alloc::string::String;
+use alloc::vec::{self, Vec};
+use core::{fmt, mem, str::FromStr};
+
+/// An IPv4 address.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Ipv4Addr {
+    octets: [u8; 4],
+}
+
+impl Ipv4Addr {
+    /// An IPv4 address with the address pointing to localhost: `127.0.0.1`.
+    pub const LOCALHOST: Self = Self::new(127, 0, 0, 1);
+
+    /// An IPv4 address representing an unspecified address: `0.0.0.0`.
+    pub const UNSPECIFIED: Self = Self::new(0, 0, 0, 0);
+
+    /// An IPv4 address representing the broadcast address: `255.255.255.255`.
+    pub const BROADCAST: Self = Self::new(255, 255, 255, 255);
+
+    /// Create a new IPv4 address from four eight-bit octets.
+    pub const fn new(a: u8, b: u8, c: u8, d: u8) -> Self {
+        Self { octets: [a, b, c, d] }
+    }
+
+    /// Returns the four eight-bit integers that make up this address.
+    pub const fn octets(&self) -> [u8; 4] {
+        self.octets
+    }
+
+    /// Returns `true` for the special 'unspecified' address `0.0.0.0`.
+    pub fn is_unspecified(&self) -> bool {
+        self.octets == [0; 4]
+    }
+
+    /// Returns `true` if this is a loopback address (`127.0.0.0/8`).
+    pub fn is_loopback(&self) -> bool {
+        self.octets[0] == 127
+    }
+
+    /// Returns `true` if this is a private address (`10.0.0.0/8`,
+    /// `172.16.0.0/12` or `192.168.0.0/16`).
+    pub fn is_private(&self) -> bool {
+        match self.octets {
+            [10, ..] => true,
+            [172, b, ..] => (16..=31).contains(&b),
+            [192, 168, ..] => true,
+            _ => false,
+        }
+    }
+
+    /// Returns `true` if this is the broadcast address `255.255.255.255`.
+    pub fn is_broadcast(&self) -> bool {
+        *self == Self::BROADCAST
+    }
+}
+
+impl From<[u8; 4]> for Ipv4Addr {
+    fn from(octets: [u8; 4]) -> Self {
+        Self { octets }
+    }
+}
+
+impl From<Ipv4Addr> for [u8; 4] {
+    fn from(addr: Ipv4Addr) -> Self {
+        addr.octets
+    }
+}
+
+impl From<u32> for Ipv4Addr {
+    /// Convert a host byte order `u32` into an address.
+    fn from(ip: u32) -> Self {
+        Self { octets: ip.to_be_bytes() }
+    }
+}
+
+impl From<Ipv4Addr> for u32 {
+    /// Convert an address into a host byte order `u32`.
+    fn from(addr: Ipv4Addr) -> Self {
+        u32::from_be_bytes(addr.octets)
+    }
+}
+
+impl From<in_addr> for Ipv4Addr {
+    fn from(addr: in_addr) -> Self {
+        Self { octets: addr.0.to_ne_bytes() }
+    }
+}
+
+impl From<Ipv4Addr> for in_addr {
+    fn from(addr: Ipv4Addr) -> Self {
+        in_addr(u32::from_ne_bytes(addr.octets))
+    }
+}
+
+impl fmt::Display for Ipv4Addr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let [a, b, c, d] = self.octets;
+        write!(f, "{}.{}.{}.{}", a, b, c, d)
+    }
+}
+
+impl fmt::Debug for Ipv4Addr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+impl FromStr for Ipv4Addr {
+    type Err = AddrParseError;
+
+    fn from_str(s: &str) -> Result<Self, AddrParseError> {
+        let mut octets = [0; 4];
+        let mut parts = s.split('.');
+
+        for octet in octets.iter_mut() {
+            let part = parts.next().ok_or(AddrParseError(()))?;
+
+            // Reject signs, empty parts and leading zeros, which some
+            // parsers would interpret as octal.
+            if part.is_empty()
+                || part.len() > 3
+                || (part.len() > 1 && part.starts_with('0'))
+                || !part.bytes().all(|b| b.is_ascii_digit())
+            {
+                return Err(AddrParseError(()));
+            }
+
+            *octet = part.parse().map_err(|_| AddrParseError(()))?;
+        }
+
+        if parts.next().is_some() {
+            return Err(AddrParseError(()));
+        }
+
+        Ok(Self { octets })
+    }
+}
+
+/// An IPv4 socket address: an IP address and a 16-bit port number.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct SocketAddrV4 {
+    ip: Ipv4Addr,
+    port: u16,
+}
+
+impl SocketAddrV4 {
+    /// Create a new socket address from an IP address and a port number.
+    pub const fn new(ip: Ipv4Addr, port: u16) -> Self {
+        Self { ip, port }
+    }
+
+    /// Returns the IP address associated with this socket address.
+    pub const fn ip(&self) -> &Ipv4Addr {
+        &self.ip
+    }
+
+    /// Change the IP address associated with this socket address.
+    pub fn set_ip(&mut self, ip: Ipv4Addr) {
+        self.ip = ip;
+    }
+
+    /// Returns the port number associated with this socket address.
+    pub const fn port(&self) -> u16 {
+        self.port
+    }
+
+    /// Change the port number associated with this socket address.
+    pub fn set_port(&mut self, port: u16) {
+        self.port = port;
+    }
+
+    /// Convert into the representation used by the `sceNetInet*` functions.
+    pub fn to_raw(&self) -> sockaddr_in {
+        sockaddr_in {
+            sin_len: mem::size_of::<sockaddr_in>() as u8,
+            sin_family: sys::AF_INET as u8,
+            sin_port: self.port.to_be(),
+            sin_addr: self.ip.into(),
+            sin_zero: [0; 8],
+        }
+    }
+
+    /// Convert from the representation used by the `sceNetInet*` functions.
+    ///
+    /// Returns `None` if the address family is not `AF_INET`.
+    pub fn from_raw(addr: &sockaddr_in) -> Option<Self> {
+        if addr.sin_family as i32 != sys::AF_INET {
+            return None;
+        }
+
+        Some(Self::new(addr.sin_addr.into(), u16::from_be(addr.sin_port)))
+    }
+
+    /// Like `from_raw`, for a generic `sockaddr` as filled in by
+    /// `sceNetInetAccept` and friends.
+    pub fn from_sockaddr(addr: &sockaddr) -> Option<Self> {
+        // `sockaddr` and `sockaddr_in` have the same size and alignment.
+        let addr = unsafe { &*(addr as *const sockaddr as *const sockaddr_in) };
+        Self::from_raw(addr)
+    }
+}
+
+impl From<(Ipv4Addr, u16)> for SocketAddrV4 {
+    fn from((ip, port): (Ipv4Addr, u16)) -> Self {
+        Self::new(ip, port)
+    }
+}
+
+impl fmt::Display for SocketAddrV4 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.ip, self.port)
+    }
+}
+
+impl fmt::Debug for SocketAddrV4 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+impl FromStr for SocketAddrV4 {
+    type Err = AddrParseError;
+
+    fn from_str(s: &str) -> Result<Self, AddrParseError> {
+        let colon = s.rfind(':').ok_or(AddrParseError(()))?;
+        let ip = s[..colon].parse()?;
+        let port = &s[colon + 1..];
+
+        if port.is_empty() || !port.bytes().all(|b| b.is_ascii_digit()) {
+            return Err(AddrParseError(()));
+        }
+
+        let port = port.parse().map_err(|_| AddrParseError(()))?;
+
+        Ok(Self::new(ip, port))
+    }
+}
+
+/// An error which can be returned when parsing an IP or socket address.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AddrParseError(());
+
+impl fmt::Display for AddrParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("invalid IP address syntax")
+    }
+}
+
+/// A trait for objects which can be converted to one or more `SocketAddrV4`
+/// values, in the style of `std::net::ToSocketAddrs`.
+pub trait ToSocketAddrs {
+    /// Returned iterator over socket addresses.
+    type Iter: Iterator<Item = SocketAddrV4>;
+
+    /// Convert this object to an iterator of resolved socket addresses.
+    fn to_socket_addrs(&self) -> io::Result<Self::Iter>;
+}
+
+impl ToSocketAddrs for SocketAddrV4 {
+    type Iter = core::option::IntoIter<SocketAddrV4>;
+
+    fn to_socket_addrs(&self) -> io::Result<Self::Iter> {
+        Ok(Some(*self).into_iter())
+    }
+}
+
+impl ToSocketAddrs for (Ipv4Addr, u16) {
+    type Iter = core::option::IntoIter<SocketAddrV4>;
+
+    fn to_socket_addrs(&self) -> io::Result<Self::Iter> {
+        Ok(Some(SocketAddrV4::from(*self)).into_iter())
+    }
+}
+
+impl ToSocketAddrs for (&str, u16) {
+    type Iter = vec::IntoIter<SocketAddrV4>;
+
+    /// Resolves `host` with a `Resolver`, unless it is an IP address.
+    fn to_socket_addrs(&self) -> io::Result<Self::Iter> {
+        let (host, port) = *self;
+        let addrs = super::lookup_host(host)?
+            .into_iter()
+            .map(|ip| SocketAddrV4::new(ip, port))
+            .collect::<Vec<_>>();
+
+        Ok(addrs.into_iter())
+    }
+}
+
+impl ToSocketAddrs for (String, u16) {
+    type Iter = vec::IntoIter<SocketAddrV4>;
+
+    fn to_socket_addrs(&self) -> io::Result<Self::Iter> {
+        (&*self.0, self.1).to_socket_addrs()
+    }
+}
+
+impl ToSocketAddrs for str {
+    type Iter = vec::IntoIter<SocketAddrV4>;
+
+    /// Parses `host:port`, resolving `host` with a `Resolver` unless it is
+    /// an IP address.
+    fn to_socket_addrs(&self) -> io::Result<Self::Iter> {
+        if let Ok(addr) = self.parse::<SocketAddrV4>() {
+            return Ok(alloc::vec![addr].into_iter());
+        }
+
+        let invalid = || io::Error::new(io::ErrorKind::InvalidInput);
+        let colon = self.rfind(':').ok_or_else(invalid)?;
+        let port = self[colon + 1..].parse().map_err(|_| invalid())?;
+
+        (&self[..colon], port).to_socket_addrs()
+    }
+}
+
+impl ToSocketAddrs for String {
+    type Iter = vec::IntoIter<SocketAddrV4>;
+
+    fn to_socket_addrs(&self) -> io::Result<Self::Iter> {
+        (**self).to_socket_addrs()
+    }
+}
+
+impl<T: ToSocketAddrs + ?Sized> ToSocketAddrs for &T {
+    type Iter = T::Iter;
+
+    fn to_socket_addrs(&self) -> io::Result<Self::Iter> {
+        (**self).to_socket_addrs()
+    }
+}
+
+impl ToSocketAddrs for [SocketAddrV4] {
+    type Iter = vec::IntoIter<SocketAddrV4>;
+
+    fn to_socket_addrs(&self) -> io::Result<Self::Iter> {
+        Ok(self.iter().copied().collect::<Vec<_>>().into_iter())
+    }
+}
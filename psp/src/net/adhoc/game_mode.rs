@@ -0,0 +1,190 @@
+use super::{lobby_name, Adhoc, MacAddr, EVENT_DISCONNECT, EVENT_GAME_MODE};
+use crate::error::{self, Error};
+use crate::net::{timeout_micros, NetworkError};
+use crate::sys::{self, SceNetAdhocctlGameModeInfo};
+use alloc::boxed::Box;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::{ffi::c_void, fmt, time::Duration};
+
+/// The maximum number of consoles in a game mode session, host included.
+pub const MAX_GAME_MODE_PEERS: usize = 16;
+
+impl Adhoc {
+    /// Host a game mode session called `name` for the consoles in `macs`,
+    /// which must start with this console. Waits until every peer joined.
+    pub fn create_game_mode(
+        &self,
+        name: &str,
+        macs: &[MacAddr],
+        timeout: Option<Duration>,
+    ) -> Result<(), NetworkError> {
+        if macs.is_empty() || macs.len() > MAX_GAME_MODE_PEERS {
+            return Err(NetworkError::Kernel(error::SCE_ERROR_ERRNO_EINVAL));
+        }
+
+        let name = lobby_name(name)?;
+        let mut list: Vec<u8> = macs.iter().flat_map(|mac| mac.0.iter().copied()).collect();
+
+        self.run(EVENT_GAME_MODE, timeout, || unsafe {
+            sys::sceNetAdhocctlCreateEnterGameMode(
+                name.as_ptr(),
+                1,
+                macs.len() as i32,
+                list.as_mut_ptr(),
+                timeout_micros(timeout),
+                0,
+            )
+        })
+    }
+
+    /// Join the game mode session called `name`, hosted by `host`.
+    pub fn join_game_mode(
+        &self,
+        name: &str,
+        host: MacAddr,
+        timeout: Option<Duration>,
+    ) -> Result<(), NetworkError> {
+        let name = lobby_name(name)?;
+        let mut host = host.0;
+
+        self.run(EVENT_GAME_MODE, timeout, || unsafe {
+            sys::sceNetAdhocctlJoinEnterGameMode(
+                name.as_ptr(),
+                host.as_mut_ptr(),
+                timeout_micros(timeout),
+                0,
+            )
+        })
+    }
+
+    /// Leave the game mode session.
+    pub fn exit_game_mode(&self, timeout: Option<Duration>) -> Result<(), NetworkError> {
+        self.run(EVENT_DISCONNECT, timeout, || unsafe {
+            sys::sceNetAdhocctlExitGameMode()
+        })
+    }
+
+    /// The consoles in the game mode session, host first.
+    pub fn game_mode_peers(&self) -> Result<Vec<MacAddr>, Error> {
+        let mut info = SceNetAdhocctlGameModeInfo {
+            count: 0,
+            macs: [[0; 6]; MAX_GAME_MODE_PEERS],
+        };
+        error::check(unsafe { sys::sceNetAdhocctlGetGameModeInfo(&mut info) })?;
+
+        let count = core::cmp::min(info.count.max(0) as usize, MAX_GAME_MODE_PEERS);
+        Ok(info.macs[..count].iter().map(|&mac| MacAddr(mac)).collect())
+    }
+}
+
+/// The buffer this console shares in a game mode session.
+///
+/// Every other console keeps a `GameModeReplica` of it. Only one master may
+/// exist per session; it is deleted when the value is dropped.
+pub struct GameModeMaster {
+    data: Box<[u8]>,
+}
+
+impl GameModeMaster {
+    /// Create the shared buffer, `size` bytes long and initially zeroed.
+    pub fn new(size: usize) -> Result<Self, Error> {
+        let mut data = vec![0; size].into_boxed_slice();
+
+        error::check(unsafe {
+            sys::sceNetAdhocGameModeCreateMaster(data.as_mut_ptr() as *mut c_void, size as i32)
+        })?;
+
+        Ok(Self { data })
+    }
+
+    /// The shared buffer. Changes are sent by the next `update`.
+    pub fn data_mut(&mut self) -> &mut [u8] {
+        &mut self.data
+    }
+
+    /// Send the buffer to every replica.
+    pub fn update(&mut self) -> Result<(), Error> {
+        error::check(unsafe { sys::sceNetAdhocGameModeUpdateMaster() })?;
+
+        Ok(())
+    }
+}
+
+impl fmt::Debug for GameModeMaster {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("GameModeMaster")
+            .field("size", &self.data.len())
+            .finish()
+    }
+}
+
+impl Drop for GameModeMaster {
+    fn drop(&mut self) {
+        unsafe {
+            sys::sceNetAdhocGameModeDeleteMaster();
+        }
+    }
+}
+
+/// A local copy of the buffer shared by another console.
+///
+/// The replica is deleted when the value is dropped.
+pub struct GameModeReplica {
+    id: i32,
+    mac: MacAddr,
+    data: Box<[u8]>,
+}
+
+impl GameModeReplica {
+    /// Mirror the `size` byte buffer shared by the console at `mac`.
+    pub fn new(mac: MacAddr, size: usize) -> Result<Self, Error> {
+        let mut data = vec![0; size].into_boxed_slice();
+        let mut raw_mac = mac.0;
+
+        let id = error::check(unsafe {
+            sys::sceNetAdhocGameModeCreateReplica(
+                raw_mac.as_mut_ptr(),
+                data.as_mut_ptr() as *mut c_void,
+                size as i32,
+            )
+        })?;
+
+        Ok(Self { id, mac, data })
+    }
+
+    /// The console whose buffer this replicates.
+    pub fn mac(&self) -> MacAddr {
+        self.mac
+    }
+
+    /// The buffer, as of the last `update`.
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// Copy the latest received data into the buffer.
+    pub fn update(&mut self) -> Result<(), Error> {
+        error::check(unsafe { sys::sceNetAdhocGameModeUpdateReplica(self.id, 0) })?;
+
+        Ok(())
+    }
+}
+
+impl fmt::Debug for GameModeReplica {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("GameModeReplica")
+            .field("id", &self.id)
+            .field("mac", &self.mac)
+            .field("size", &self.data.len())
+            .finish()
+    }
+}
+
+impl Drop for GameModeReplica {
+    fn drop(&mut self) {
+        unsafe {
+            sys::sceNetAdhocGameModeDeleteReplica(self.id);
+        }
+    }
+}
@@ -0,0 +1,317 @@
+use super::{read_list, MacAddr};
+use crate::io;
+use crate::sync::Mutex;
+use crate::sys;
+use alloc::collections::VecDeque;
+use alloc::vec::Vec;
+use core::{ffi::c_void, fmt, ptr, slice};
+
+pub use crate::sys::AdhocMatchingMode as MatchingMode;
+
+/// Events queued by the matching callback, per matching id. Only exists
+/// while an `Adhoc` session does.
+static mut EVENTS: Option<Mutex<Vec<(i32, VecDeque<MatchingEvent>)>>> = None;
+
+pub(super) fn init_registry() {
+    unsafe {
+        EVENTS = Some(Mutex::new(Vec::new()));
+    }
+}
+
+pub(super) fn drop_registry() {
+    unsafe {
+        EVENTS = None;
+    }
+}
+
+fn with_queue<T>(id: i32, f: impl FnOnce(&mut VecDeque<MatchingEvent>) -> T) -> Option<T> {
+    let events = unsafe { EVENTS.as_ref()? };
+    let mut events = events.lock();
+
+    events.iter_mut().find(|(i, _)| *i == id).map(|(_, queue)| f(queue))
+}
+
+/// Something that happened in a `Matching` session. Optional data sent by
+/// the peer is copied into the event.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MatchingEvent {
+    /// A host announced itself.
+    Hello { mac: MacAddr, data: Vec<u8> },
+    /// A peer asked to join.
+    Request { mac: MacAddr, data: Vec<u8> },
+    /// A peer withdrew its request.
+    Leave { mac: MacAddr },
+    /// The target refused our request.
+    Deny { mac: MacAddr, data: Vec<u8> },
+    /// A peer cancelled the matching.
+    Cancel { mac: MacAddr, data: Vec<u8> },
+    /// The target accepted our request.
+    Accept { mac: MacAddr, data: Vec<u8> },
+    /// Matching with a peer completed.
+    Established { mac: MacAddr },
+    /// A peer stopped responding.
+    Timeout { mac: MacAddr },
+    /// Matching with a peer failed.
+    Error { mac: MacAddr },
+    /// A peer left the session.
+    Bye { mac: MacAddr },
+    /// A peer sent data with `Matching::send_data`.
+    Data { mac: MacAddr, data: Vec<u8> },
+    /// A peer acknowledged our data.
+    DataAck { mac: MacAddr },
+    /// A peer did not acknowledge our data in time.
+    DataTimeout { mac: MacAddr },
+}
+
+impl MatchingEvent {
+    fn from_raw(event: i32, mac: MacAddr, data: Vec<u8>) -> Option<Self> {
+        Some(match event {
+            1 => Self::Hello { mac, data },
+            2 => Self::Request { mac, data },
+            3 => Self::Leave { mac },
+            4 => Self::Deny { mac, data },
+            5 => Self::Cancel { mac, data },
+            6 => Self::Accept { mac, data },
+            7 => Self::Established { mac },
+            8 => Self::Timeout { mac },
+            9 => Self::Error { mac },
+            10 => Self::Bye { mac },
+            11 => Self::Data { mac, data },
+            12 => Self::DataAck { mac },
+            13 => Self::DataTimeout { mac },
+            _ => return None,
+        })
+    }
+
+    /// The address of the peer the event is about.
+    pub fn mac(&self) -> MacAddr {
+        match *self {
+            Self::Hello { mac, .. }
+            | Self::Request { mac, .. }
+            | Self::Leave { mac }
+            | Self::Deny { mac, .. }
+            | Self::Cancel { mac, .. }
+            | Self::Accept { mac, .. }
+            | Self::Established { mac }
+            | Self::Timeout { mac }
+            | Self::Error { mac }
+            | Self::Bye { mac }
+            | Self::Data { mac, .. }
+            | Self::DataAck { mac }
+            | Self::DataTimeout { mac } => mac,
+        }
+    }
+}
+
+unsafe extern fn matching_handler(
+    id: i32,
+    event: i32,
+    mac: *mut u8,
+    opt_len: i32,
+    opt_data: *mut c_void,
+) {
+    let mut addr = MacAddr([0; 6]);
+    if !mac.is_null() {
+        addr.0.copy_from_slice(slice::from_raw_parts(mac, 6));
+    }
+
+    let data = if opt_len > 0 && !opt_data.is_null() {
+        Vec::from(slice::from_raw_parts(opt_data as *const u8, opt_len as usize))
+    } else {
+        Vec::new()
+    };
+
+    if let Some(event) = MatchingEvent::from_raw(event, addr, data) {
+        with_queue(id, |queue| queue.push_back(event));
+    }
+}
+
+/// Settings for a `Matching` session.
+#[derive(Debug, Copy, Clone)]
+pub struct MatchingConfig {
+    /// Whether this console hosts, joins, or matches with a single peer.
+    pub mode: MatchingMode,
+    /// The maximum number of members, including this console.
+    pub max_peers: i32,
+    /// The PDP port used by the matching protocol.
+    pub port: u16,
+    /// Size of the receive buffer in bytes.
+    pub buf_size: i32,
+    /// Microseconds between hello broadcasts from a host.
+    pub hello_delay: u32,
+    /// Microseconds between keep-alive pings.
+    pub ping_delay: u32,
+    /// Unanswered pings before a peer times out.
+    pub init_count: i32,
+    /// Microseconds between retransmissions.
+    pub msg_delay: u32,
+}
+
+impl MatchingConfig {
+    /// Settings for `mode` on `port`, with the timings used by the system
+    /// software.
+    pub fn new(mode: MatchingMode, port: u16) -> Self {
+        Self {
+            mode,
+            max_peers: match mode {
+                MatchingMode::Ptp => 2,
+                _ => 16,
+            },
+            port,
+            buf_size: 2048,
+            hello_delay: 200 * 1000,
+            ping_delay: 200 * 1000,
+            init_count: 30,
+            msg_delay: 200 * 1000,
+        }
+    }
+}
+
+/// A matchmaking session over `sceNetAdhocMatching`.
+///
+/// A host announces itself with hello messages; clients see a
+/// `MatchingEvent::Hello` and ask to join with `select_target`, which the host
+/// answers in the same way. Events are queued and read with `poll_event`.
+///
+/// The session is stopped and deleted when the value is dropped.
+pub struct Matching {
+    id: i32,
+    started: bool,
+}
+
+impl Matching {
+    /// Create a matching session. It does nothing until `start` is called.
+    pub fn new(config: MatchingConfig) -> io::Result<Self> {
+        let id = io::cvt(unsafe {
+            sys::sceNetAdhocMatchingCreate(
+                config.mode,
+                config.max_peers,
+                config.port,
+                config.buf_size,
+                config.hello_delay,
+                config.ping_delay,
+                config.init_count,
+                config.msg_delay,
+                Some(matching_handler),
+            )
+        })?;
+
+        let events = unsafe { EVENTS.as_ref() };
+        match events {
+            Some(events) => events.lock().push((id, VecDeque::new())),
+            None => {
+                unsafe {
+                    sys::sceNetAdhocMatchingDelete(id);
+                }
+
+                return Err(io::ErrorKind::NotConnected.into());
+            }
+        }
+
+        Ok(Self { id, started: false })
+    }
+
+    /// Start the session. A host sends `hello` to everyone in the lobby.
+    pub fn start(&mut self, hello: &[u8]) -> io::Result<()> {
+        let (len, data) = opt(hello);
+        io::cvt(unsafe {
+            sys::sceNetAdhocMatchingStart(self.id, 16, 0x2000, 16, 0, len, data)
+        })?;
+        self.started = true;
+
+        Ok(())
+    }
+
+    /// Replace the hello message sent by a host.
+    pub fn set_hello(&self, hello: &[u8]) -> io::Result<()> {
+        let (len, data) = opt(hello);
+        io::cvt(unsafe { sys::sceNetAdhocMatchingSetHelloOpt(self.id, len, data) })?;
+
+        Ok(())
+    }
+
+    /// Ask to match with `mac`, or accept its request.
+    pub fn select_target(&self, mac: MacAddr, data: &[u8]) -> io::Result<()> {
+        let mut mac = mac.0;
+        let (len, data) = opt(data);
+
+        io::cvt(unsafe {
+            sys::sceNetAdhocMatchingSelectTarget(self.id, mac.as_mut_ptr(), len, data)
+        })?;
+
+        Ok(())
+    }
+
+    /// Refuse or withdraw a match with `mac`.
+    pub fn cancel_target(&self, mac: MacAddr) -> io::Result<()> {
+        let mut mac = mac.0;
+        io::cvt(unsafe { sys::sceNetAdhocMatchingCancelTarget(self.id, mac.as_mut_ptr()) })?;
+
+        Ok(())
+    }
+
+    /// Send `data` to a matched peer. The peer receives it as a
+    /// `MatchingEvent::Data`.
+    pub fn send_data(&self, mac: MacAddr, data: &[u8]) -> io::Result<()> {
+        let mut mac = mac.0;
+        let (len, data) = opt(data);
+
+        io::cvt(unsafe {
+            sys::sceNetAdhocMatchingSendData(self.id, mac.as_mut_ptr(), len, data)
+        })?;
+
+        Ok(())
+    }
+
+    /// Take the oldest queued event, if any.
+    pub fn poll_event(&self) -> Option<MatchingEvent> {
+        with_queue(self.id, |queue| queue.pop_front()).flatten()
+    }
+
+    /// The addresses of the matched members, including this console.
+    pub fn members(&self) -> io::Result<Vec<MacAddr>> {
+        let members: Vec<MemberInfo> = read_list(|len, buf| unsafe {
+            sys::sceNetAdhocMatchingGetMembers(self.id, len, buf)
+        })?;
+
+        Ok(members.iter().map(|m| MacAddr(m.mac)).collect())
+    }
+}
+
+impl fmt::Debug for Matching {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Matching").field("id", &self.id).finish()
+    }
+}
+
+impl Drop for Matching {
+    fn drop(&mut self) {
+        unsafe {
+            if self.started {
+                sys::sceNetAdhocMatchingStop(self.id);
+            }
+
+            sys::sceNetAdhocMatchingDelete(self.id);
+
+            if let Some(events) = EVENTS.as_ref() {
+                events.lock().retain(|(id, _)| *id != self.id);
+            }
+        }
+    }
+}
+
+/// An entry of `sceNetAdhocMatchingGetMembers`.
+#[repr(C)]
+struct MemberInfo {
+    next: *mut MemberInfo,
+    mac: [u8; 6],
+    _padding: [u8; 2],
+}
+
+fn opt(data: &[u8]) -> (i32, *mut c_void) {
+    if data.is_empty() {
+        (0, ptr::null_mut())
+    } else {
+        (data.len() as i32, data.as_ptr() as *mut c_void)
+    }
+}
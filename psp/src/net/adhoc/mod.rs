@@ -0,0 +1,440 @@
+//! Local wireless multiplayer, without an access point.
+//!
+//! An `Adhoc` session brings up the ad-hoc libraries. Consoles then meet in a
+//! lobby (an ad-hoc network with a name of up to 8 alphanumeric characters),
+//! found with `Adhoc::scan` and entered with `Adhoc::create` or
+//! `Adhoc::join`. Once in a lobby, peers are addressed by their `MacAddr`:
+//!
+//! * `PdpSocket` sends unreliable datagrams, like UDP.
+//! * `PtpListener` and `PtpStream` provide reliable streams, like TCP.
+//! * `Matching` runs the system's host/client matchmaking protocol.
+//! * `GameModeMaster` and `GameModeReplica` keep shared buffers in sync
+//!   between all players.
+//!
+//! # Example
+//!
+//! ```ignore
+//! use core::time::Duration;
+//! use psp::net::adhoc::Adhoc;
+//!
+//! let adhoc = Adhoc::new("ULUS99999")?;
+//! adhoc.connect("MYGAME", Some(Duration::from_secs(10)))?;
+//!
+//! for peer in adhoc.peers()? {
+//!     psp::dprintln!("{} is here", peer.nickname);
+//! }
+//! # Ok::<(), psp::net::NetworkError>(())
+//! ```
+
+mod game_mode;
+mod matching;
+mod pdp;
+mod ptp;
+
+pub use game_mode::{GameModeMaster, GameModeReplica, MAX_GAME_MODE_PEERS};
+pub use matching::{Matching, MatchingConfig, MatchingEvent, MatchingMode};
+pub use pdp::PdpSocket;
+pub use ptp::{PtpListener, PtpStream};
+
+use super::stack::{claim_net, release_net};
+use super::{c_str, timeout_micros, NetworkError};
+use crate::error;
+use crate::modules::ModuleGuard;
+use crate::sync::critical_section;
+use crate::sys::{
+    self, EventFlagAttributes, EventFlagWaitTypes, NetModule, SceNetAdhocctlAdhocId,
+    SceNetAdhocctlPeerInfo, SceNetAdhocctlScanInfo, SceUid,
+};
+use crate::Error;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::{ffi::c_void, fmt, mem, ptr, time::Duration};
+
+/// A 48-bit hardware address, identifying a console.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct MacAddr(pub [u8; 6]);
+
+impl MacAddr {
+    /// The broadcast address, `ff:ff:ff:ff:ff:ff`.
+    pub const BROADCAST: Self = Self([0xff; 6]);
+
+    /// The address of this console's wireless adapter.
+    pub fn local() -> Result<Self, Error> {
+        let mut mac = [0; 8];
+        error::check(unsafe { sys::sceWlanGetEtherAddr(mac.as_mut_ptr()) })?;
+
+        Ok(Self([mac[0], mac[1], mac[2], mac[3], mac[4], mac[5]]))
+    }
+}
+
+impl fmt::Display for MacAddr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let [a, b, c, d, e, g] = self.0;
+        write!(f, "{:02x}:{:02x}:{:02x}:{:02x}:{:02x}:{:02x}", a, b, c, d, e, g)
+    }
+}
+
+impl fmt::Debug for MacAddr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+/// A lobby found by `Adhoc::scan`.
+#[derive(Debug, Copy, Clone)]
+pub struct Lobby {
+    raw: SceNetAdhocctlScanInfo,
+}
+
+impl Lobby {
+    /// The name of the lobby.
+    pub fn name(&self) -> &str {
+        c_str(&self.raw.name)
+    }
+
+    /// The wireless channel the lobby is on.
+    pub fn channel(&self) -> i32 {
+        self.raw.channel
+    }
+
+    /// The hardware address of the console hosting the lobby.
+    pub fn bssid(&self) -> MacAddr {
+        MacAddr(self.raw.bssid)
+    }
+}
+
+/// A console in the same lobby.
+#[derive(Debug, Clone)]
+pub struct Peer {
+    /// The hardware address of the peer.
+    pub mac: MacAddr,
+    /// The nickname set in the peer's system settings.
+    pub nickname: String,
+    /// When the peer was last seen, in microseconds.
+    pub timestamp: u32,
+}
+
+// Bits of the event flag set by the adhocctl handler, one per event.
+const EVENT_ERROR: u32 = 1 << 0;
+const EVENT_CONNECT: u32 = 1 << 1;
+const EVENT_DISCONNECT: u32 = 1 << 2;
+const EVENT_SCAN: u32 = 1 << 3;
+const EVENT_GAME_MODE: u32 = 1 << 4;
+const ALL_EVENTS: u32 = 0x1f;
+
+static mut EVENT_FLAG: SceUid = SceUid(-1);
+static mut LAST_ERROR: Option<Error> = None;
+
+/// The last ad-hoc library initialized. A failed `Adhoc::new` drops the
+/// session, which leaves the lobby only if `sceNetAdhocctl` got that far.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+enum Stage {
+    None,
+    NetInit,
+    AdhocInit,
+    MatchingInit,
+    AdhocctlInit,
+}
+
+/// An ad-hoc session: the ad-hoc libraries, from module loading to lobby
+/// management.
+///
+/// Creating the session loads the `NetCommon` and `NetAdhoc` modules and
+/// initializes `sceNet`, `sceNetAdhoc`, `sceNetAdhocMatching` and
+/// `sceNetAdhocctl`, in that order. Dropping it leaves the lobby and tears
+/// everything down in reverse. Sockets and matching objects must not outlive
+/// it.
+///
+/// Only one session may exist at a time, and not at the same time as a
+/// `NetworkStack`.
+pub struct Adhoc {
+    stage: Stage,
+    handler: Option<i32>,
+    modules: Option<ModuleGuard>,
+}
+
+impl Adhoc {
+    /// Start an ad-hoc session.
+    ///
+    /// `product_code` is the 9 character product code of the game, such as
+    /// `"ULUS99999"`. Only consoles using the same code see each other.
+    pub fn new(product_code: &str) -> Result<Self, NetworkError> {
+        if product_code.len() != 9 {
+            return Err(NetworkError::Kernel(error::SCE_ERROR_ERRNO_EINVAL));
+        }
+
+        if !claim_net() {
+            return Err(NetworkError::AlreadyInitialized);
+        }
+
+        let mut adhoc = Self {
+            stage: Stage::None,
+            handler: None,
+            modules: None,
+        };
+
+        let evf = unsafe {
+            sys::sceKernelCreateEventFlag(
+                &b"psp_adhocctl\0"[0],
+                EventFlagAttributes::WAIT_MULTIPLE,
+                0,
+                ptr::null_mut(),
+            )
+        };
+        error::check_uid(evf)?;
+        critical_section(|| unsafe {
+            EVENT_FLAG = evf;
+            LAST_ERROR = None;
+        });
+
+        let mut id = SceNetAdhocctlAdhocId {
+            unknown: 0,
+            adhoc_id: [0; 9],
+            unk: [0; 3],
+        };
+        id.adhoc_id.copy_from_slice(product_code.as_bytes());
+
+        // On error, dropping `adhoc` undoes the steps completed so far.
+        adhoc.modules = Some(ModuleGuard::load(&[
+            NetModule::NetCommon.into(),
+            NetModule::NetAdhoc.into(),
+        ])?);
+        adhoc.step(Stage::NetInit, || unsafe {
+            sys::sceNetInit(128 * 1024, 42, 4 * 1024, 42, 4 * 1024)
+        })?;
+        adhoc.step(Stage::AdhocInit, || unsafe { sys::sceNetAdhocInit() })?;
+        adhoc.step(Stage::MatchingInit, || unsafe {
+            sys::sceNetAdhocMatchingInit(0x20000)
+        })?;
+        adhoc.step(Stage::AdhocctlInit, || unsafe {
+            sys::sceNetAdhocctlInit(0x2000, 0x30, &mut id)
+        })?;
+
+        let handler = error::check(unsafe {
+            sys::sceNetAdhocctlAddHandler(Some(adhocctl_handler), ptr::null_mut())
+        })?;
+        adhoc.handler = Some(handler);
+
+        matching::init_registry();
+
+        Ok(adhoc)
+    }
+
+    fn step(&mut self, stage: Stage, f: impl FnOnce() -> i32) -> Result<(), Error> {
+        error::check(f())?;
+        self.stage = stage;
+
+        Ok(())
+    }
+
+    /// Start an adhocctl operation, then wait for one of `events`.
+    fn run(
+        &self,
+        events: u32,
+        timeout: Option<Duration>,
+        f: impl FnOnce() -> i32,
+    ) -> Result<(), NetworkError> {
+        let evf = unsafe { EVENT_FLAG };
+
+        unsafe {
+            sys::sceKernelClearEventFlag(evf, !ALL_EVENTS);
+        }
+        critical_section(|| unsafe { LAST_ERROR = None });
+
+        error::check(f())?;
+
+        let mut out = 0;
+        let mut micros = timeout.map(|_| timeout_micros(timeout));
+        let timeout_ptr = micros.as_mut().map_or(ptr::null_mut(), |t| t as *mut u32);
+
+        let ret = unsafe {
+            sys::sceKernelWaitEventFlag(
+                evf,
+                events | EVENT_ERROR,
+                EventFlagWaitTypes::OR | EventFlagWaitTypes::CLEAR,
+                &mut out,
+                timeout_ptr,
+            )
+        };
+
+        match error::check(ret) {
+            Err(error::SCE_KERNEL_ERROR_WAIT_TIMEOUT) => Err(NetworkError::TimedOut),
+            Err(e) => Err(e.into()),
+            Ok(_) if out & events != 0 => Ok(()),
+            Ok(_) => {
+                let error = critical_section(|| unsafe { LAST_ERROR });
+                Err(NetworkError::ConnectionFailed(error))
+            }
+        }
+    }
+
+    /// Scan all channels for lobbies.
+    pub fn scan(&self, timeout: Option<Duration>) -> Result<Vec<Lobby>, NetworkError> {
+        self.run(EVENT_SCAN, timeout, || unsafe { sys::sceNetAdhocctlScan() })?;
+
+        let infos: Vec<SceNetAdhocctlScanInfo> =
+            read_list(|len, buf| unsafe { sys::sceNetAdhocctlGetScanInfo(len, buf) })?;
+
+        Ok(infos.into_iter().map(|raw| Lobby { raw }).collect())
+    }
+
+    /// Create a lobby called `name` and wait until it is up.
+    pub fn create(&self, name: &str, timeout: Option<Duration>) -> Result<(), NetworkError> {
+        let name = lobby_name(name)?;
+        self.run(EVENT_CONNECT, timeout, || unsafe {
+            sys::sceNetAdhocctlCreate(name.as_ptr())
+        })
+    }
+
+    /// Join a lobby found by `scan`.
+    pub fn join(&self, lobby: &Lobby, timeout: Option<Duration>) -> Result<(), NetworkError> {
+        let mut raw = lobby.raw;
+        self.run(EVENT_CONNECT, timeout, || unsafe {
+            sys::sceNetAdhocctlJoin(&mut raw)
+        })
+    }
+
+    /// Join the lobby called `name`, creating it if nobody hosts it yet.
+    pub fn connect(&self, name: &str, timeout: Option<Duration>) -> Result<(), NetworkError> {
+        let name = lobby_name(name)?;
+        self.run(EVENT_CONNECT, timeout, || unsafe {
+            sys::sceNetAdhocctlConnect(name.as_ptr())
+        })
+    }
+
+    /// Leave the current lobby.
+    pub fn disconnect(&self, timeout: Option<Duration>) -> Result<(), NetworkError> {
+        self.run(EVENT_DISCONNECT, timeout, || unsafe {
+            sys::sceNetAdhocctlDisconnect()
+        })
+    }
+
+    /// Returns `true` while in a lobby.
+    pub fn is_connected(&self) -> bool {
+        let mut state = 0;
+        let ret = unsafe { sys::sceNetAdhocctlGetState(&mut state) };
+
+        ret >= 0 && state == 1
+    }
+
+    /// The other consoles in the lobby.
+    pub fn peers(&self) -> Result<Vec<Peer>, Error> {
+        let infos: Vec<SceNetAdhocctlPeerInfo> =
+            read_list(|len, buf| unsafe { sys::sceNetAdhocctlGetPeerList(len, buf) })?;
+
+        Ok(infos
+            .iter()
+            .map(|info| Peer {
+                mac: MacAddr(info.mac),
+                nickname: String::from(c_str(&info.nickname)),
+                timestamp: info.timestamp,
+            })
+            .collect())
+    }
+
+    /// The nickname of the peer with address `mac`.
+    pub fn nickname(&self, mac: MacAddr) -> Result<String, Error> {
+        let mut mac = mac.0;
+        let mut name = [0u8; 128];
+
+        error::check(unsafe {
+            sys::sceNetAdhocctlGetNameByAddr(mac.as_mut_ptr(), name.as_mut_ptr())
+        })?;
+
+        Ok(String::from(c_str(&name)))
+    }
+
+    /// The address of this console.
+    pub fn local_mac(&self) -> Result<MacAddr, Error> {
+        MacAddr::local()
+    }
+}
+
+impl Drop for Adhoc {
+    fn drop(&mut self) {
+        unsafe {
+            if self.stage >= Stage::AdhocctlInit {
+                sys::sceNetAdhocctlDisconnect();
+
+                if let Some(handler) = self.handler {
+                    sys::sceNetAdhocctlDelHandler(handler);
+                }
+
+                sys::sceNetAdhocctlTerm();
+            }
+
+            if self.stage >= Stage::MatchingInit {
+                sys::sceNetAdhocMatchingTerm();
+            }
+
+            if self.stage >= Stage::AdhocInit {
+                sys::sceNetAdhocTerm();
+            }
+
+            if self.stage >= Stage::NetInit {
+                sys::sceNetTerm();
+            }
+
+            self.modules = None;
+
+            let evf = critical_section(|| mem::replace(&mut EVENT_FLAG, SceUid(-1)));
+            if evf.0 >= 0 {
+                sys::sceKernelDeleteEventFlag(evf);
+            }
+        }
+
+        matching::drop_registry();
+        release_net();
+    }
+}
+
+unsafe extern fn adhocctl_handler(event: i32, error: i32, _arg: *mut c_void) {
+    let bit = match event {
+        0 => {
+            critical_section(|| LAST_ERROR = Some(Error::from_raw(error)));
+            EVENT_ERROR
+        }
+        1 => EVENT_CONNECT,
+        2 => EVENT_DISCONNECT,
+        3 => EVENT_SCAN,
+        4 => EVENT_GAME_MODE,
+        _ => return,
+    };
+
+    sys::sceKernelSetEventFlag(EVENT_FLAG, bit);
+}
+
+/// Validate a lobby name and copy it into a NUL-terminated buffer.
+fn lobby_name(name: &str) -> Result<[u8; 9], NetworkError> {
+    if name.is_empty() || name.len() > 8 || !name.bytes().all(|b| b.is_ascii_alphanumeric()) {
+        return Err(NetworkError::Kernel(error::SCE_ERROR_ERRNO_EINVAL));
+    }
+
+    let mut buf = [0; 9];
+    buf[..name.len()].copy_from_slice(name.as_bytes());
+
+    Ok(buf)
+}
+
+/// Read a list through one of the `Get*List` style functions, which report
+/// the required buffer size when called with a null buffer.
+fn read_list<T>(f: impl Fn(*mut i32, *mut c_void) -> i32) -> Result<Vec<T>, Error> {
+    let mut len = 0;
+    error::check(f(&mut len, ptr::null_mut()))?;
+
+    let count = len as usize / mem::size_of::<T>();
+    if count == 0 {
+        return Ok(Vec::new());
+    }
+
+    let mut list = Vec::<T>::with_capacity(count);
+    let mut len = (count * mem::size_of::<T>()) as i32;
+    error::check(f(&mut len, list.as_mut_ptr() as *mut c_void))?;
+
+    // The system may have fewer entries by now.
+    unsafe {
+        list.set_len(core::cmp::min(count, len as usize / mem::size_of::<T>()));
+    }
+
+    Ok(list)
+}
@@ -0,0 +1,114 @@
+use super::MacAddr;
+use crate::io;
+use crate::net::timeout_micros;
+use crate::sys;
+use core::cell::Cell;
+use core::{ffi::c_void, fmt, time::Duration};
+
+/// An ad-hoc datagram socket, the PDP equivalent of a `UdpSocket`.
+///
+/// Datagrams may be lost or arrive out of order. The socket is deleted when
+/// the value is dropped.
+pub struct PdpSocket {
+    id: i32,
+    port: u16,
+    timeout: Cell<u32>,
+    nonblocking: Cell<bool>,
+}
+
+impl PdpSocket {
+    /// Create a socket bound to `port`, with a receive buffer of `buf_size`
+    /// bytes.
+    pub fn bind(port: u16, buf_size: u32) -> io::Result<Self> {
+        let mut mac = MacAddr::local()?.0;
+        let id =
+            io::cvt(unsafe { sys::sceNetAdhocPdpCreate(mac.as_mut_ptr(), port, buf_size, 0) })?;
+
+        Ok(Self {
+            id,
+            port,
+            timeout: Cell::new(0),
+            nonblocking: Cell::new(false),
+        })
+    }
+
+    /// The port this socket is bound to.
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+
+    /// Send `buf` to `port` on the console with address `mac`. Use
+    /// `MacAddr::BROADCAST` to send to every peer in the lobby.
+    pub fn send_to(&self, buf: &[u8], mac: MacAddr, port: u16) -> io::Result<usize> {
+        let mut mac = mac.0;
+
+        io::cvt(unsafe {
+            sys::sceNetAdhocPdpSend(
+                self.id,
+                mac.as_mut_ptr(),
+                port,
+                buf.as_ptr() as *mut c_void,
+                buf.len() as u32,
+                self.timeout.get(),
+                self.nonblocking.get() as i32,
+            )
+        })?;
+
+        Ok(buf.len())
+    }
+
+    /// Receive a single datagram, returning the number of bytes read and the
+    /// address and port it came from.
+    pub fn recv_from(&self, buf: &mut [u8]) -> io::Result<(usize, MacAddr, u16)> {
+        let mut mac = [0; 6];
+        let mut port = 0;
+        let mut len = buf.len() as i32;
+
+        io::cvt(unsafe {
+            sys::sceNetAdhocPdpRecv(
+                self.id,
+                mac.as_mut_ptr(),
+                &mut port,
+                buf.as_mut_ptr() as *mut c_void,
+                &mut len as *mut i32 as *mut c_void,
+                self.timeout.get(),
+                self.nonblocking.get() as i32,
+            )
+        })?;
+
+        Ok((len as usize, MacAddr(mac), port))
+    }
+
+    /// Set how long blocking operations wait before failing with
+    /// `ErrorKind::TimedOut`. `None` waits forever.
+    pub fn set_timeout(&self, timeout: Option<Duration>) {
+        self.timeout.set(timeout_micros(timeout));
+    }
+
+    /// Make operations fail with `ErrorKind::WouldBlock` instead of waiting.
+    pub fn set_nonblocking(&self, nonblocking: bool) {
+        self.nonblocking.set(nonblocking);
+    }
+
+    /// Returns the underlying PDP id.
+    pub fn as_raw_id(&self) -> i32 {
+        self.id
+    }
+}
+
+impl fmt::Debug for PdpSocket {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PdpSocket")
+            .field("id", &self.id)
+            .field("port", &self.port)
+            .finish()
+    }
+}
+
+impl Drop for PdpSocket {
+    fn drop(&mut self) {
+        unsafe {
+            sys::sceNetAdhocPdpDelete(self.id, 0);
+        }
+    }
+}
@@ -0,0 +1,213 @@
+use super::MacAddr;
+use crate::io;
+use crate::net::timeout_micros;
+use crate::sys;
+use core::cell::Cell;
+use core::{ffi::c_void, fmt, time::Duration};
+
+// Retransmission settings used by the system software.
+const BUF_SIZE: u32 = 8192;
+const RETRY_DELAY: u32 = 200 * 1000;
+const RETRY_COUNT: i32 = 300;
+
+/// A listening ad-hoc stream socket, the PTP equivalent of a `TcpListener`.
+///
+/// The socket is closed when the value is dropped.
+pub struct PtpListener {
+    id: i32,
+    port: u16,
+    timeout: Cell<u32>,
+}
+
+impl PtpListener {
+    /// Listen for connections on `port`, queueing at most `backlog` pending
+    /// connections.
+    pub fn bind(port: u16, backlog: i32) -> io::Result<Self> {
+        let mut mac = MacAddr::local()?.0;
+
+        let id = io::cvt(unsafe {
+            sys::sceNetAdhocPtpListen(
+                mac.as_mut_ptr(),
+                port,
+                BUF_SIZE,
+                RETRY_DELAY,
+                RETRY_COUNT,
+                backlog,
+                0,
+            )
+        })?;
+
+        Ok(Self {
+            id,
+            port,
+            timeout: Cell::new(0),
+        })
+    }
+
+    /// The port this listener is bound to.
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+
+    /// Wait for a connection, returning the stream and the address and port
+    /// of the peer.
+    pub fn accept(&self) -> io::Result<(PtpStream, MacAddr, u16)> {
+        let mut mac = [0; 6];
+        let mut port = 0;
+
+        let id = io::cvt(unsafe {
+            sys::sceNetAdhocPtpAccept(self.id, mac.as_mut_ptr(), &mut port, self.timeout.get(), 0)
+        })?;
+
+        Ok((PtpStream::from_raw_id(id), MacAddr(mac), port))
+    }
+
+    /// Set how long `accept` waits before failing with `ErrorKind::TimedOut`.
+    /// `None` waits forever.
+    pub fn set_timeout(&self, timeout: Option<Duration>) {
+        self.timeout.set(timeout_micros(timeout));
+    }
+}
+
+impl fmt::Debug for PtpListener {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PtpListener")
+            .field("id", &self.id)
+            .field("port", &self.port)
+            .finish()
+    }
+}
+
+impl Drop for PtpListener {
+    fn drop(&mut self) {
+        unsafe {
+            sys::sceNetAdhocPtpClose(self.id, 0);
+        }
+    }
+}
+
+/// A reliable, ordered ad-hoc stream, the PTP equivalent of a `TcpStream`.
+///
+/// The stream is closed when the value is dropped.
+pub struct PtpStream {
+    id: i32,
+    timeout: Cell<u32>,
+    nonblocking: Cell<bool>,
+}
+
+impl PtpStream {
+    fn from_raw_id(id: i32) -> Self {
+        Self {
+            id,
+            timeout: Cell::new(0),
+            nonblocking: Cell::new(false),
+        }
+    }
+
+    /// Connect to `port` on the console with address `mac`, using
+    /// `local_port` on this side. Waits at most `timeout` for the peer to
+    /// accept, or forever if `None`.
+    pub fn connect(
+        mac: MacAddr,
+        port: u16,
+        local_port: u16,
+        timeout: Option<Duration>,
+    ) -> io::Result<Self> {
+        let mut local = MacAddr::local()?.0;
+        let mut mac = mac.0;
+
+        let id = io::cvt(unsafe {
+            sys::sceNetAdhocPtpOpen(
+                local.as_mut_ptr(),
+                local_port,
+                mac.as_mut_ptr(),
+                port,
+                BUF_SIZE,
+                RETRY_DELAY,
+                RETRY_COUNT,
+                0,
+            )
+        })?;
+
+        // Close the socket if connecting fails.
+        let stream = Self::from_raw_id(id);
+        io::cvt(unsafe { sys::sceNetAdhocPtpConnect(id, timeout_micros(timeout), 0) })?;
+
+        Ok(stream)
+    }
+
+    /// Set how long reads and writes wait before failing with
+    /// `ErrorKind::TimedOut`. `None` waits forever.
+    pub fn set_timeout(&self, timeout: Option<Duration>) {
+        self.timeout.set(timeout_micros(timeout));
+    }
+
+    /// Make reads and writes fail with `ErrorKind::WouldBlock` instead of
+    /// waiting.
+    pub fn set_nonblocking(&self, nonblocking: bool) {
+        self.nonblocking.set(nonblocking);
+    }
+
+    /// Returns the underlying PTP id.
+    pub fn as_raw_id(&self) -> i32 {
+        self.id
+    }
+}
+
+impl io::Read for PtpStream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let mut len = buf.len() as i32;
+
+        io::cvt(unsafe {
+            sys::sceNetAdhocPtpRecv(
+                self.id,
+                buf.as_mut_ptr() as *mut c_void,
+                &mut len,
+                self.timeout.get(),
+                self.nonblocking.get() as i32,
+            )
+        })?;
+
+        Ok(len as usize)
+    }
+}
+
+impl io::Write for PtpStream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut len = buf.len() as i32;
+
+        io::cvt(unsafe {
+            sys::sceNetAdhocPtpSend(
+                self.id,
+                buf.as_ptr() as *mut c_void,
+                &mut len,
+                self.timeout.get(),
+                self.nonblocking.get() as i32,
+            )
+        })?;
+
+        Ok(len as usize)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        io::cvt(unsafe {
+            sys::sceNetAdhocPtpFlush(self.id, self.timeout.get(), self.nonblocking.get() as i32)
+        })?;
+
+        Ok(())
+    }
+}
+
+impl fmt::Debug for PtpStream {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PtpStream").field("id", &self.id).finish()
+    }
+}
+
+impl Drop for PtpStream {
+    fn drop(&mut self) {
+        unsafe {
+            sys::sceNetAdhocPtpClose(self.id, 0);
+        }
+    }
+}
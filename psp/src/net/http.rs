@@ -0,0 +1,576 @@
+//! An HTTP client over the `sceHttp` library.
+//!
+//! The client owns the HTTP library, and each `Response` owns the connection
+//! and request it was received on, so no IDs need to be managed by hand.
+//!
+//! # Example
+//!
+//! ```ignore
+//! use psp::io::Read;
+//! use psp::net::http::HttpClient;
+//!
+//! let client = HttpClient::builder().user_agent("my-game/1.0").https(true).build()?;
+//! let mut response = client.get("https://example.com/version.txt").send()?;
+//!
+//! if response.status()? == 200 {
+//!     let version = response.text()?;
+//! }
+//! # Ok::<(), psp::net::http::HttpError>(())
+//! ```
+
+use super::timeout_micros;
+use crate::io;
+use crate::modules::ModuleGuard;
+use crate::sync::critical_section;
+use crate::sys::{self, NetModule};
+use crate::Error;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::{ffi::c_void, ptr, time::Duration};
+
+pub use crate::sys::HttpMethod;
+
+/// Error returned by HTTP operations.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum HttpError {
+    /// Another `HttpClient` is alive.
+    AlreadyInitialized,
+    /// A URL, header name or header value contained a NUL byte.
+    InvalidInput,
+    /// A call to the HTTP library failed.
+    Http(Error),
+}
+
+impl From<Error> for HttpError {
+    fn from(error: Error) -> Self {
+        HttpError::Http(error)
+    }
+}
+
+impl From<HttpError> for io::Error {
+    fn from(error: HttpError) -> Self {
+        match error {
+            HttpError::AlreadyInitialized => io::Error::new(io::ErrorKind::Other),
+            HttpError::InvalidInput => io::Error::new(io::ErrorKind::InvalidInput),
+            HttpError::Http(e) => e.into(),
+        }
+    }
+}
+
+type Result<T> = core::result::Result<T, HttpError>;
+
+fn check(ret: i32) -> Result<i32> {
+    crate::error::check(ret).map_err(HttpError::Http)
+}
+
+/// Copy `s` into a NUL-terminated buffer.
+fn c_string(s: &str) -> Result<Vec<u8>> {
+    if s.bytes().any(|b| b == 0) {
+        return Err(HttpError::InvalidInput);
+    }
+
+    let mut buf = Vec::with_capacity(s.len() + 1);
+    buf.extend_from_slice(s.as_bytes());
+    buf.push(0);
+
+    Ok(buf)
+}
+
+static mut CLIENT_IN_USE: bool = false;
+
+/// Configuration for an `HttpClient`.
+#[derive(Debug, Clone)]
+pub struct HttpClientBuilder {
+    user_agent: String,
+    pool_size: u32,
+    https: bool,
+    redirects: bool,
+    cookies: bool,
+    keep_alive: bool,
+    resolve_timeout: Option<Duration>,
+    connect_timeout: Option<Duration>,
+    send_timeout: Option<Duration>,
+    recv_timeout: Option<Duration>,
+    headers: Vec<(String, String)>,
+}
+
+impl HttpClientBuilder {
+    fn new() -> Self {
+        Self {
+            user_agent: String::from("rust-psp"),
+            pool_size: 0x25800,
+            https: false,
+            redirects: true,
+            cookies: false,
+            keep_alive: true,
+            resolve_timeout: None,
+            connect_timeout: None,
+            send_timeout: None,
+            recv_timeout: None,
+            headers: Vec::new(),
+        }
+    }
+
+    /// Set the `User-Agent` sent with every request.
+    pub fn user_agent(mut self, user_agent: &str) -> Self {
+        self.user_agent = String::from(user_agent);
+        self
+    }
+
+    /// Set the size of the HTTP library's memory pool.
+    pub fn pool_size(mut self, pool_size: u32) -> Self {
+        self.pool_size = pool_size;
+        self
+    }
+
+    /// Enable `https://` URLs, using the system's default certificates.
+    pub fn https(mut self, enabled: bool) -> Self {
+        self.https = enabled;
+        self
+    }
+
+    /// Follow redirects. Enabled by default.
+    pub fn redirects(mut self, enabled: bool) -> Self {
+        self.redirects = enabled;
+        self
+    }
+
+    /// Store and send cookies. Disabled by default.
+    pub fn cookies(mut self, enabled: bool) -> Self {
+        self.cookies = enabled;
+        self
+    }
+
+    /// Reuse connections. Enabled by default.
+    pub fn keep_alive(mut self, enabled: bool) -> Self {
+        self.keep_alive = enabled;
+        self
+    }
+
+    /// Set the timeout for resolving host names.
+    pub fn resolve_timeout(mut self, timeout: Duration) -> Self {
+        self.resolve_timeout = Some(timeout);
+        self
+    }
+
+    /// Set the timeout for establishing connections.
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.connect_timeout = Some(timeout);
+        self
+    }
+
+    /// Set the timeout for sending requests.
+    pub fn send_timeout(mut self, timeout: Duration) -> Self {
+        self.send_timeout = Some(timeout);
+        self
+    }
+
+    /// Set the timeout for receiving responses.
+    pub fn recv_timeout(mut self, timeout: Duration) -> Self {
+        self.recv_timeout = Some(timeout);
+        self
+    }
+
+    /// Add a header sent with every request.
+    pub fn default_header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((String::from(name), String::from(value)));
+        self
+    }
+
+    /// Load the HTTP modules and create the client.
+    ///
+    /// A `NetworkStack` must be alive, and must outlive the client.
+    pub fn build(self) -> Result<HttpClient> {
+        let in_use = critical_section(|| unsafe {
+            let in_use = CLIENT_IN_USE;
+            CLIENT_IN_USE = true;
+            in_use
+        });
+
+        if in_use {
+            return Err(HttpError::AlreadyInitialized);
+        }
+
+        let mut client = HttpClient {
+            stage: Stage::None,
+            template: -1,
+            http_modules: None,
+            ssl_modules: None,
+        };
+
+        // On error, dropping `client` undoes the steps completed so far.
+        client.http_modules = Some(ModuleGuard::load(&[
+            NetModule::NetParseHttp.into(),
+            NetModule::NetHttp.into(),
+        ])?);
+        client.step(Stage::HttpInit, || unsafe { sys::sceHttpInit(self.pool_size) })?;
+
+        if self.https {
+            client.ssl_modules = Some(ModuleGuard::load(&[NetModule::NetSsl.into()])?);
+            client.step(Stage::SslInit, || unsafe { sys::sceSslInit(0x28000) })?;
+            client.step(Stage::HttpsInit, || unsafe { sys::sceHttpsInit(0, 0, 0, 0) })?;
+            check(unsafe { sys::sceHttpsLoadDefaultCert(0, 0) })?;
+        }
+
+        let mut agent = c_string(&self.user_agent)?;
+        client.template = check(unsafe { sys::sceHttpCreateTemplate(agent.as_mut_ptr(), 1, 0) })?;
+        let id = client.template;
+
+        unsafe {
+            check(if self.redirects {
+                sys::sceHttpEnableRedirect(id)
+            } else {
+                sys::sceHttpDisableRedirect(id)
+            })?;
+            check(if self.cookies {
+                sys::sceHttpEnableCookie(id)
+            } else {
+                sys::sceHttpDisableCookie(id)
+            })?;
+            check(if self.keep_alive {
+                sys::sceHttpEnableKeepAlive(id)
+            } else {
+                sys::sceHttpDisableKeepAlive(id)
+            })?;
+
+            if let Some(t) = self.resolve_timeout {
+                check(sys::sceHttpSetResolveTimeOut(id, timeout_micros(Some(t))))?;
+            }
+            if let Some(t) = self.connect_timeout {
+                check(sys::sceHttpSetConnectTimeOut(id, timeout_micros(Some(t))))?;
+            }
+            if let Some(t) = self.send_timeout {
+                check(sys::sceHttpSetSendTimeOut(id, timeout_micros(Some(t))))?;
+            }
+            if let Some(t) = self.recv_timeout {
+                check(sys::sceHttpSetRecvTimeOut(id, timeout_micros(Some(t))))?;
+            }
+        }
+
+        for (name, value) in &self.headers {
+            add_header(id, name, value)?;
+        }
+
+        Ok(client)
+    }
+}
+
+fn add_header(id: i32, name: &str, value: &str) -> Result<()> {
+    let mut name = c_string(name)?;
+    let mut value = c_string(value)?;
+
+    check(unsafe { sys::sceHttpAddExtraHeader(id, name.as_mut_ptr(), value.as_mut_ptr(), 0) })
+        .map(|_| ())
+}
+
+/// The value of the first header called `name` in `headers`, a block of
+/// `Name: value` lines as returned by `Response::headers`. Names are compared
+/// case-insensitively, and the value is trimmed.
+pub fn find_header<'a>(headers: &'a str, name: &str) -> Option<&'a str> {
+    headers.lines().find_map(|line| {
+        let colon = line.find(':')?;
+
+        if line[..colon].trim().eq_ignore_ascii_case(name) {
+            Some(line[colon + 1..].trim())
+        } else {
+            None
+        }
+    })
+}
+
+/// Append `name=value` to the query of `url`, percent-encoding both.
+pub fn append_query(url: &mut String, name: &str, value: &str) {
+    match url.chars().last() {
+        Some('?') | Some('&') => {}
+        _ if url.contains('?') => url.push('&'),
+        _ => url.push('?'),
+    }
+
+    percent_encode(url, name);
+    url.push('=');
+    percent_encode(url, value);
+}
+
+/// Append `s` to `out`, escaping all but the unreserved characters of
+/// RFC 3986.
+fn percent_encode(out: &mut String, s: &str) {
+    const HEX: &[u8; 16] = b"0123456789ABCDEF";
+
+    for b in s.bytes() {
+        match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                out.push(b as char)
+            }
+            _ => {
+                out.push('%');
+                out.push(HEX[(b >> 4) as usize] as char);
+                out.push(HEX[(b & 0xf) as usize] as char);
+            }
+        }
+    }
+}
+
+/// The last of the HTTP, SSL and HTTPS libraries the client initialized.
+/// HTTPS is only set up when asked for, so a client may stop at `HttpInit`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+enum Stage {
+    None,
+    HttpInit,
+    SslInit,
+    HttpsInit,
+}
+
+/// An HTTP client. See the module documentation for an example.
+///
+/// Only one client may exist at a time, as it owns the HTTP library.
+#[derive(Debug)]
+pub struct HttpClient {
+    stage: Stage,
+    template: i32,
+    http_modules: Option<ModuleGuard>,
+    ssl_modules: Option<ModuleGuard>,
+}
+
+impl HttpClient {
+    /// Returns a builder to configure a client.
+    pub fn builder() -> HttpClientBuilder {
+        HttpClientBuilder::new()
+    }
+
+    /// Create a client with the default configuration, without HTTPS.
+    pub fn new() -> Result<Self> {
+        Self::builder().build()
+    }
+
+    fn step(&mut self, stage: Stage, f: impl FnOnce() -> i32) -> Result<()> {
+        check(f())?;
+        self.stage = stage;
+
+        Ok(())
+    }
+
+    /// Start building a request.
+    pub fn request(&self, method: HttpMethod, url: &str) -> RequestBuilder<'_> {
+        RequestBuilder {
+            client: self,
+            method,
+            url: String::from(url),
+            headers: Vec::new(),
+            body: Vec::new(),
+        }
+    }
+
+    /// Start building a `GET` request.
+    pub fn get(&self, url: &str) -> RequestBuilder<'_> {
+        self.request(HttpMethod::Get, url)
+    }
+
+    /// Start building a `POST` request.
+    pub fn post(&self, url: &str) -> RequestBuilder<'_> {
+        self.request(HttpMethod::Post, url)
+    }
+
+    /// Start building a `HEAD` request.
+    pub fn head(&self, url: &str) -> RequestBuilder<'_> {
+        self.request(HttpMethod::Head, url)
+    }
+}
+
+impl Drop for HttpClient {
+    fn drop(&mut self) {
+        unsafe {
+            if self.template >= 0 {
+                sys::sceHttpDeleteTemplate(self.template);
+            }
+
+            if self.stage >= Stage::HttpsInit {
+                sys::sceHttpsEnd();
+            }
+
+            if self.stage >= Stage::SslInit {
+                sys::sceSslEnd();
+            }
+
+            self.ssl_modules = None;
+
+            if self.stage >= Stage::HttpInit {
+                sys::sceHttpEnd();
+            }
+
+            self.http_modules = None;
+        }
+
+        critical_section(|| unsafe { CLIENT_IN_USE = false });
+    }
+}
+
+/// A request being built, created by `HttpClient::request`.
+#[derive(Debug)]
+pub struct RequestBuilder<'a> {
+    client: &'a HttpClient,
+    method: HttpMethod,
+    url: String,
+    headers: Vec<(String, String)>,
+    body: Vec<u8>,
+}
+
+impl<'a> RequestBuilder<'a> {
+    /// Add a header to this request.
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((String::from(name), String::from(value)));
+        self
+    }
+
+    /// Add a query parameter to the URL, percent-encoding `name` and `value`.
+    pub fn query(mut self, name: &str, value: &str) -> Self {
+        append_query(&mut self.url, name, value);
+        self
+    }
+
+    /// Set the request body. Only sent with `POST` requests.
+    pub fn body(mut self, body: impl Into<Vec<u8>>) -> Self {
+        self.body = body.into();
+        self
+    }
+
+    /// Send the request and wait for the response headers.
+    ///
+    /// The body is streamed by reading from the returned `Response`.
+    pub fn send(mut self) -> Result<Response<'a>> {
+        let mut url = c_string(&self.url)?;
+
+        let connection = check(unsafe {
+            sys::sceHttpCreateConnectionWithURL(self.client.template, url.as_ptr(), 0)
+        })?;
+
+        // From here on, `response` cleans up on error.
+        let mut response = Response {
+            connection,
+            request: -1,
+            _client: core::marker::PhantomData,
+        };
+
+        let content_length = match self.method {
+            HttpMethod::Post => self.body.len() as u64,
+            _ => 0,
+        };
+
+        response.request = check(unsafe {
+            sys::sceHttpCreateRequestWithURL(
+                connection,
+                self.method,
+                url.as_mut_ptr(),
+                content_length,
+            )
+        })?;
+
+        for (name, value) in &self.headers {
+            add_header(response.request, name, value)?;
+        }
+
+        let (data, len) = match self.method {
+            HttpMethod::Post => (self.body.as_mut_ptr() as *mut c_void, self.body.len() as u32),
+            _ => (ptr::null_mut(), 0),
+        };
+
+        check(unsafe { sys::sceHttpSendRequest(response.request, data, len) })?;
+
+        Ok(response)
+    }
+}
+
+/// A response to an HTTP request.
+///
+/// The body is streamed through the `Read` implementation. The underlying
+/// request and connection are deleted on drop.
+#[derive(Debug)]
+pub struct Response<'a> {
+    connection: i32,
+    request: i32,
+    _client: core::marker::PhantomData<&'a HttpClient>,
+}
+
+impl Response<'_> {
+    /// The HTTP status code, e.g. 200.
+    pub fn status(&self) -> Result<u16> {
+        let mut status = 0;
+        check(unsafe { sys::sceHttpGetStatusCode(self.request, &mut status) })?;
+
+        Ok(status as u16)
+    }
+
+    /// The value of the `Content-Length` header, if the server sent one.
+    pub fn content_length(&self) -> Option<u64> {
+        let mut len = 0;
+
+        match unsafe { sys::sceHttpGetContentLength(self.request, &mut len) } {
+            ret if ret < 0 => None,
+            _ => Some(len),
+        }
+    }
+
+    /// All response headers, as received.
+    pub fn headers(&self) -> Result<String> {
+        let mut header = ptr::null_mut();
+        let mut size = 0;
+
+        check(unsafe { sys::sceHttpGetAllHeader(self.request, &mut header, &mut size) })?;
+
+        if header.is_null() {
+            return Ok(String::new());
+        }
+
+        // The buffer is owned by the library, so copy it out.
+        let bytes = unsafe { core::slice::from_raw_parts(header, size as usize) };
+        Ok(String::from_utf8_lossy(bytes).into_owned())
+    }
+
+    /// The value of the first header called `name`, compared
+    /// case-insensitively.
+    pub fn header(&self, name: &str) -> Option<String> {
+        let headers = self.headers().ok()?;
+
+        find_header(&headers, name).map(String::from)
+    }
+
+    /// Read the whole body.
+    pub fn bytes(&mut self) -> io::Result<Vec<u8>> {
+        let mut body = Vec::with_capacity(self.content_length().unwrap_or(0) as usize);
+        io::Read::read_to_end(self, &mut body)?;
+
+        Ok(body)
+    }
+
+    /// Read the whole body as UTF-8 text.
+    pub fn text(&mut self) -> io::Result<String> {
+        String::from_utf8(self.bytes()?).map_err(|_| io::Error::new(io::ErrorKind::InvalidData))
+    }
+
+    /// Abort the request, e.g. from another thread while it is being read.
+    pub fn abort(&self) -> Result<()> {
+        check(unsafe { sys::sceHttpAbortRequest(self.request) }).map(|_| ())
+    }
+}
+
+impl io::Read for Response<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let len = core::cmp::min(buf.len(), u32::MAX as usize) as u32;
+        let ret = unsafe {
+            sys::sceHttpReadData(self.request, buf.as_mut_ptr() as *mut c_void, len)
+        };
+
+        crate::error::check(ret).map(|n| n as usize).map_err(Into::into)
+    }
+}
+
+impl Drop for Response<'_> {
+    fn drop(&mut self) {
+        unsafe {
+            if self.request >= 0 {
+                sys::sceHttpDeleteRequest(self.request);
+            }
+
+            sys::sceHttpDeleteConnection(self.connection);
+        }
+    }
+}
@@ -0,0 +1,66 @@
+//! Networking primitives for TCP/UDP communication over `sceNetInet`.
+//!
+//! The types here are modelled on `std::net`, but only support IPv4, which is
+//! all the PSP network stack provides. A `NetworkStack` must be created, and
+//! an access point connected, before any socket can be used.
+//!
+//! Local multiplayer without an access point lives in `adhoc`.
+
+pub mod adhoc;
+pub mod http;
+
+mod addr;
+mod resolver;
+mod socket;
+mod stack;
+mod tcp;
+mod udp;
+
+pub use addr::{AddrParseError, Ipv4Addr, SocketAddrV4, ToSocketAddrs};
+pub use resolver::{lookup_host, Resolver};
+pub use socket::Shutdown;
+pub use stack::{ApctlHandlerId, ApctlTransition, NetworkConfig, NetworkError, NetworkStack};
+pub use tcp::{Incoming, TcpListener, TcpStream};
+pub use udp::UdpSocket;
+
+use crate::io;
+use core::time::Duration;
+
+/// Run `f` on each address yielded by `addr`, returning the first success or
+/// the last error.
+fn each_addr<A, F, T>(addr: A, mut f: F) -> io::Result<T>
+where
+    A: ToSocketAddrs,
+    F: FnMut(&SocketAddrV4) -> io::Result<T>,
+{
+    let mut last_err = None;
+
+    for addr in addr.to_socket_addrs()? {
+        match f(&addr) {
+            Ok(t) => return Ok(t),
+            Err(e) => last_err = Some(e),
+        }
+    }
+
+    Err(last_err.unwrap_or_else(|| io::Error::new(io::ErrorKind::InvalidInput)))
+}
+
+/// Interpret a NUL-padded buffer as a string, dropping invalid UTF-8.
+pub(crate) fn c_str(buf: &[u8]) -> &str {
+    let len = buf.iter().position(|&b| b == 0).unwrap_or(buf.len());
+
+    match core::str::from_utf8(&buf[..len]) {
+        Ok(s) => s,
+        Err(e) => core::str::from_utf8(&buf[..e.valid_up_to()]).unwrap(),
+    }
+}
+
+/// Convert a timeout to the microseconds the system takes, where 0 means no
+/// timeout. Timeouts are at least 1 microsecond, so that a tiny timeout does
+/// not turn into none.
+pub(crate) fn timeout_micros(timeout: Option<Duration>) -> u32 {
+    match timeout {
+        Some(t) => core::cmp::max(core::cmp::min(t.as_micros(), u32::MAX as u128), 1) as u32,
+        None => 0,
+    }
+}
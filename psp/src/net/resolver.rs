@@ -0,0 +1,163 @@
+use super::Ipv4Addr;
+use crate::io;
+use crate::sys::{self, in_addr};
+use alloc::boxed::Box;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::{ffi::c_void, fmt, time::Duration};
+
+/// Size of the scratch buffer the resolver works in.
+const BUF_SIZE: usize = 1024;
+
+/// Maximum length of a host name returned by a reverse lookup.
+const MAX_HOST_NAME: usize = 256;
+
+/// A DNS resolver, using the servers of the current access point connection.
+///
+/// The resolver owns its working buffer, and is deleted on drop. Lookups
+/// block the calling thread, and take `&self` so that a resolver shared with
+/// another thread can be stopped from there.
+///
+/// # Example
+///
+/// ```ignore
+/// use psp::net::Resolver;
+///
+/// let resolver = Resolver::new()?;
+/// let addrs = resolver.lookup_host("example.com")?;
+/// # Ok::<(), psp::io::Error>(())
+/// ```
+pub struct Resolver {
+    id: i32,
+    // The resolver keeps using this until it is deleted.
+    _buf: Box<[u8; BUF_SIZE]>,
+    timeout: Duration,
+    retries: i32,
+}
+
+impl Resolver {
+    /// Create a resolver with a timeout of 5 seconds and 3 retries.
+    ///
+    /// A `NetworkStack` must be alive.
+    pub fn new() -> io::Result<Self> {
+        let mut buf = Box::new([0; BUF_SIZE]);
+        let mut id = 0;
+
+        io::cvt(unsafe {
+            sys::sceNetResolverCreate(&mut id, buf.as_mut_ptr() as *mut c_void, BUF_SIZE as u32)
+        })?;
+
+        Ok(Self {
+            id,
+            _buf: buf,
+            timeout: Duration::from_secs(5),
+            retries: 3,
+        })
+    }
+
+    /// Set the timeout of each attempt. It is rounded up to whole seconds.
+    pub fn set_timeout(&mut self, timeout: Duration) {
+        self.timeout = timeout;
+    }
+
+    /// Set how many times a query is retried after timing out.
+    pub fn set_retries(&mut self, retries: i32) {
+        self.retries = retries;
+    }
+
+    fn timeout_secs(&self) -> u32 {
+        let secs = self.timeout.as_secs() + (self.timeout.subsec_nanos() > 0) as u64;
+        core::cmp::max(core::cmp::min(secs, u32::MAX as u64), 1) as u32
+    }
+
+    /// Resolve a host name to its addresses.
+    ///
+    /// IP address literals are returned without a query. The PSP resolver
+    /// only reports one address per name.
+    pub fn lookup_host(&self, host: &str) -> io::Result<Vec<Ipv4Addr>> {
+        if let Ok(ip) = host.parse() {
+            return Ok(alloc::vec![ip]);
+        }
+
+        let host = c_host(host)?;
+        let mut addr = in_addr(0);
+
+        io::cvt(unsafe {
+            sys::sceNetResolverStartNtoA(
+                self.id,
+                host.as_ptr(),
+                &mut addr,
+                self.timeout_secs(),
+                self.retries,
+            )
+        })?;
+
+        Ok(alloc::vec![addr.into()])
+    }
+
+    /// Find the host name of an address.
+    pub fn lookup_addr(&self, addr: Ipv4Addr) -> io::Result<String> {
+        let mut name = [0u8; MAX_HOST_NAME];
+        let addr = in_addr::from(addr);
+
+        io::cvt(unsafe {
+            sys::sceNetResolverStartAtoN(
+                self.id,
+                &addr,
+                name.as_mut_ptr(),
+                name.len() as u32,
+                self.timeout_secs(),
+                self.retries,
+            )
+        })?;
+
+        let len = name.iter().position(|&b| b == 0).unwrap_or(name.len());
+
+        String::from_utf8(name[..len].into()).map_err(|_| io::Error::new(io::ErrorKind::InvalidData))
+    }
+
+    /// Abort a lookup in progress on another thread, which then returns an
+    /// error.
+    pub fn stop(&self) -> io::Result<()> {
+        io::cvt(unsafe { sys::sceNetResolverStop(self.id) }).map(|_| ())
+    }
+}
+
+impl Drop for Resolver {
+    fn drop(&mut self) {
+        unsafe {
+            sys::sceNetResolverDelete(self.id);
+        }
+    }
+}
+
+impl fmt::Debug for Resolver {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Resolver")
+            .field("id", &self.id)
+            .field("timeout", &self.timeout)
+            .field("retries", &self.retries)
+            .finish()
+    }
+}
+
+/// Resolve a host name with a temporary `Resolver`.
+pub fn lookup_host(host: &str) -> io::Result<Vec<Ipv4Addr>> {
+    if let Ok(ip) = host.parse() {
+        return Ok(alloc::vec![ip]);
+    }
+
+    Resolver::new()?.lookup_host(host)
+}
+
+fn c_host(host: &str) -> io::Result<Vec<u8>> {
+    if host.is_empty() || host.len() >= MAX_HOST_NAME || host.bytes().any(|b| b == 0) {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput));
+    }
+
+    let mut buf = Vec::with_capacity(host.len() + 1);
+    buf.extend_from_slice(host.as_bytes());
+    buf.push(0);
+
+    Ok(buf)
+}
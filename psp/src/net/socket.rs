@@ -0,0 +1,306 @@
+use super::{timeout_micros, SocketAddrV4};
+use crate::io;
+use crate::sys::{self, sockaddr, sockaddr_in, socklen_t};
+use core::{ffi::c_void, mem, time::Duration};
+
+/// Possible values which can be passed to `TcpStream::shutdown`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Shutdown {
+    /// The reading portion of the stream should be shut down.
+    Read,
+    /// The writing portion of the stream should be shut down.
+    Write,
+    /// Both the reading and writing portions should be shut down.
+    Both,
+}
+
+/// The error of the last failed `sceNetInet*` call on this thread.
+pub(crate) fn last_error() -> io::Error {
+    let errno = unsafe { sys::sceNetInetGetErrno() };
+    crate::Error::from_errno(errno as u16).into()
+}
+
+/// Convert the return value of an `sceNetInet*` call into a `Result`.
+///
+/// These functions return -1 on failure and report the reason through
+/// `sceNetInetGetErrno`.
+pub(crate) fn cvt(ret: i32) -> io::Result<i32> {
+    if ret < 0 {
+        Err(last_error())
+    } else {
+        Ok(ret)
+    }
+}
+
+/// An owned socket descriptor, closed on drop.
+pub(crate) struct Socket(i32);
+
+impl Socket {
+    pub fn new(ty: i32) -> io::Result<Self> {
+        let fd = cvt(unsafe { sys::sceNetInetSocket(sys::AF_INET, ty, 0) })?;
+        Ok(Self(fd))
+    }
+
+    pub fn as_raw(&self) -> i32 {
+        self.0
+    }
+
+    pub fn into_raw(self) -> i32 {
+        let fd = self.0;
+        mem::forget(self);
+        fd
+    }
+
+    pub unsafe fn from_raw(fd: i32) -> Self {
+        Self(fd)
+    }
+
+    pub fn bind(&self, addr: &SocketAddrV4) -> io::Result<()> {
+        let raw = addr.to_raw();
+        let ret = unsafe {
+            sys::sceNetInetBind(
+                self.0,
+                &raw as *const sockaddr_in as *const sockaddr,
+                mem::size_of::<sockaddr_in>() as socklen_t,
+            )
+        };
+
+        cvt(ret).map(|_| ())
+    }
+
+    pub fn connect(&self, addr: &SocketAddrV4) -> io::Result<()> {
+        let raw = addr.to_raw();
+        let ret = unsafe {
+            sys::sceNetInetConnect(
+                self.0,
+                &raw as *const sockaddr_in as *const sockaddr,
+                mem::size_of::<sockaddr_in>() as socklen_t,
+            )
+        };
+
+        cvt(ret).map(|_| ())
+    }
+
+    /// Connect, failing with `TimedOut` if the connection is not established
+    /// within `timeout`.
+    pub fn connect_timeout(&self, addr: &SocketAddrV4, timeout: Duration) -> io::Result<()> {
+        if timeout == Duration::from_secs(0) {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput));
+        }
+
+        self.set_nonblocking(true)?;
+        let result = self.connect(addr).or_else(|e| {
+            if e.kind() != io::ErrorKind::WouldBlock {
+                return Err(e);
+            }
+
+            let ready = self.poll(sys::POLLOUT, Some(timeout))?;
+            if !ready {
+                return Err(io::Error::new(io::ErrorKind::TimedOut));
+            }
+
+            match self.take_error()? {
+                Some(e) => Err(e),
+                None => Ok(()),
+            }
+        });
+        self.set_nonblocking(false)?;
+
+        result
+    }
+
+    pub fn accept(&self) -> io::Result<(Socket, SocketAddrV4)> {
+        let mut raw: sockaddr_in = unsafe { mem::zeroed() };
+        let mut len = mem::size_of::<sockaddr_in>() as socklen_t;
+
+        let fd = cvt(unsafe {
+            sys::sceNetInetAccept(self.0, &mut raw as *mut sockaddr_in as *mut sockaddr, &mut len)
+        })?;
+        let socket = Socket(fd);
+
+        let addr = SocketAddrV4::from_raw(&raw)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData))?;
+
+        Ok((socket, addr))
+    }
+
+    pub fn listen(&self, backlog: i32) -> io::Result<()> {
+        cvt(unsafe { sys::sceNetInetListen(self.0, backlog) }).map(|_| ())
+    }
+
+    /// Wait until the socket is ready for `events`, returning `false` on
+    /// timeout.
+    pub fn poll(&self, events: i16, timeout: Option<Duration>) -> io::Result<bool> {
+        let mut fd = sys::pollfd {
+            fd: self.0,
+            events,
+            revents: 0,
+        };
+
+        let timeout = match timeout {
+            Some(t) => core::cmp::min(t.as_millis(), i32::MAX as u128) as i32,
+            None => -1,
+        };
+
+        let ready = cvt(unsafe { sys::sceNetInetPoll(&mut fd, 1, timeout) })?;
+        Ok(ready > 0)
+    }
+
+    pub fn recv_with_flags(&self, buf: &mut [u8], flags: i32) -> io::Result<usize> {
+        let ret = unsafe {
+            sys::sceNetInetRecv(self.0, buf.as_mut_ptr() as *mut c_void, buf.len(), flags)
+        };
+
+        cvt(ret as i32).map(|n| n as usize)
+    }
+
+    pub fn recv_from_with_flags(
+        &self,
+        buf: &mut [u8],
+        flags: i32,
+    ) -> io::Result<(usize, SocketAddrV4)> {
+        let mut raw: sockaddr_in = unsafe { mem::zeroed() };
+        let mut len = mem::size_of::<sockaddr_in>() as socklen_t;
+
+        let ret = unsafe {
+            sys::sceNetInetRecvfrom(
+                self.0,
+                buf.as_mut_ptr() as *mut c_void,
+                buf.len(),
+                flags,
+                &mut raw as *mut sockaddr_in as *mut sockaddr,
+                &mut len,
+            )
+        };
+        let n = cvt(ret as i32)? as usize;
+
+        let addr = SocketAddrV4::from_raw(&raw)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData))?;
+
+        Ok((n, addr))
+    }
+
+    pub fn send(&self, buf: &[u8]) -> io::Result<usize> {
+        let ret = unsafe {
+            sys::sceNetInetSend(self.0, buf.as_ptr() as *const c_void, buf.len(), 0)
+        };
+
+        cvt(ret as i32).map(|n| n as usize)
+    }
+
+    pub fn send_to(&self, buf: &[u8], addr: &SocketAddrV4) -> io::Result<usize> {
+        let raw = addr.to_raw();
+        let ret = unsafe {
+            sys::sceNetInetSendto(
+                self.0,
+                buf.as_ptr() as *const c_void,
+                buf.len(),
+                0,
+                &raw as *const sockaddr_in as *const sockaddr,
+                mem::size_of::<sockaddr_in>() as socklen_t,
+            )
+        };
+
+        cvt(ret as i32).map(|n| n as usize)
+    }
+
+    pub fn shutdown(&self, how: Shutdown) -> io::Result<()> {
+        let how = match how {
+            Shutdown::Read => sys::SHUT_RD,
+            Shutdown::Write => sys::SHUT_WR,
+            Shutdown::Both => sys::SHUT_RDWR,
+        };
+
+        cvt(unsafe { sys::sceNetInetShutdown(self.0, how) }).map(|_| ())
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddrV4> {
+        self.addr_with(sys::sceNetInetGetsockname)
+    }
+
+    pub fn peer_addr(&self) -> io::Result<SocketAddrV4> {
+        self.addr_with(sys::sceNetInetGetpeername)
+    }
+
+    fn addr_with(
+        &self,
+        f: unsafe extern "C" fn(i32, *mut sockaddr, *mut socklen_t) -> i32,
+    ) -> io::Result<SocketAddrV4> {
+        let mut raw: sockaddr_in = unsafe { mem::zeroed() };
+        let mut len = mem::size_of::<sockaddr_in>() as socklen_t;
+
+        cvt(unsafe { f(self.0, &mut raw as *mut sockaddr_in as *mut sockaddr, &mut len) })?;
+
+        SocketAddrV4::from_raw(&raw).ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData))
+    }
+
+    pub fn setsockopt<T>(&self, level: i32, name: i32, value: T) -> io::Result<()> {
+        let ret = unsafe {
+            sys::sceNetInetSetsockopt(
+                self.0,
+                level,
+                name,
+                &value as *const T as *const c_void,
+                mem::size_of::<T>() as socklen_t,
+            )
+        };
+
+        cvt(ret).map(|_| ())
+    }
+
+    pub fn getsockopt<T: Copy>(&self, level: i32, name: i32) -> io::Result<T> {
+        let mut value: T = unsafe { mem::zeroed() };
+        let mut len = mem::size_of::<T>() as socklen_t;
+
+        cvt(unsafe {
+            sys::sceNetInetGetsockopt(
+                self.0,
+                level,
+                name,
+                &mut value as *mut T as *mut c_void,
+                &mut len,
+            )
+        })?;
+
+        Ok(value)
+    }
+
+    pub fn set_timeout(&self, timeout: Option<Duration>, name: i32) -> io::Result<()> {
+        // Like `std`, a zero timeout is an error rather than "no timeout".
+        if timeout == Some(Duration::from_secs(0)) {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput));
+        }
+
+        self.setsockopt(sys::SOL_SOCKET, name, timeout_micros(timeout))
+    }
+
+    pub fn timeout(&self, name: i32) -> io::Result<Option<Duration>> {
+        let micros: u32 = self.getsockopt(sys::SOL_SOCKET, name)?;
+
+        Ok(match micros {
+            0 => None,
+            n => Some(Duration::from_micros(n as u64)),
+        })
+    }
+
+    pub fn set_nonblocking(&self, nonblocking: bool) -> io::Result<()> {
+        self.setsockopt(sys::SOL_SOCKET, sys::SO_NONBLOCK, nonblocking as i32)
+    }
+
+    pub fn take_error(&self) -> io::Result<Option<io::Error>> {
+        let errno: i32 = self.getsockopt(sys::SOL_SOCKET, sys::SO_ERROR)?;
+
+        Ok(match errno {
+            0 => None,
+            n => Some(crate::Error::from_errno(n as u16).into()),
+        })
+    }
+}
+
+impl Drop for Socket {
+    fn drop(&mut self) {
+        unsafe {
+            sys::sceNetInetClose(self.0);
+        }
+    }
+}
@@ -0,0 +1,425 @@
+use super::{c_str, Ipv4Addr};
+use crate::dialog::NetconfDialog;
+use crate::modules::ModuleGuard;
+use crate::sync::critical_section;
+use crate::sys::{self, ApctlEvent, ApctlInfo, ApctlState, NetModule, SceNetApctlInfo};
+use crate::Error;
+use alloc::boxed::Box;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::{ffi::c_void, mem, ptr, time::Duration};
+
+/// Error returned by `NetworkStack` operations.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum NetworkError {
+    /// Another `NetworkStack` or `Adhoc` session is alive.
+    AlreadyInitialized,
+    /// No IP address was obtained within the timeout.
+    TimedOut,
+    /// The connection failed, with the error reported by the system if there
+    /// was one.
+    ConnectionFailed(Option<Error>),
+    /// The user backed out of a dialog.
+    Cancelled,
+    /// A system call failed.
+    Kernel(Error),
+}
+
+impl From<Error> for NetworkError {
+    fn from(error: Error) -> Self {
+        NetworkError::Kernel(error)
+    }
+}
+
+/// Tuning parameters for the network libraries.
+///
+/// The defaults are the values used by the PSPSDK samples.
+#[derive(Debug, Copy, Clone)]
+pub struct NetworkConfig {
+    /// Size of the memory pool shared by the network libraries.
+    pub pool_size: i32,
+    /// Priority of the `SceNetCallout` thread.
+    pub callout_priority: i32,
+    /// Stack size of the `SceNetCallout` thread.
+    pub callout_stack_size: i32,
+    /// Priority of the `SceNetNetintr` thread.
+    pub netintr_priority: i32,
+    /// Stack size of the `SceNetNetintr` thread.
+    pub netintr_stack_size: i32,
+    /// Priority of the apctl thread.
+    pub apctl_priority: i32,
+    /// Stack size of the apctl thread.
+    pub apctl_stack_size: i32,
+}
+
+impl Default for NetworkConfig {
+    fn default() -> Self {
+        Self {
+            pool_size: 128 * 1024,
+            callout_priority: 42,
+            callout_stack_size: 4 * 1024,
+            netintr_priority: 42,
+            netintr_stack_size: 4 * 1024,
+            apctl_priority: 48,
+            apctl_stack_size: 0x8000,
+        }
+    }
+}
+
+/// A change of the access point connection state, as passed to handlers
+/// registered with `NetworkStack::add_handler`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct ApctlTransition {
+    /// The state before the event.
+    pub old: Option<ApctlState>,
+    /// The state after the event.
+    pub new: Option<ApctlState>,
+    /// What happened.
+    pub event: Option<ApctlEvent>,
+    /// The error, for `ApctlEvent::Error`.
+    pub error: Option<Error>,
+}
+
+/// Identifies a handler registered with `NetworkStack::add_handler`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct ApctlHandlerId(i32);
+
+type Handler = Box<dyn FnMut(&ApctlTransition) + Send>;
+
+/// The last library `NetworkStack::new` initialized, in order. `Drop`
+/// terminates this one and those before it.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+enum Stage {
+    None,
+    NetInit,
+    InetInit,
+    ResolverInit,
+    ApctlInit,
+}
+
+/// Set while `sceNet` is initialized, by a `NetworkStack` or an `Adhoc`
+/// session.
+static mut NET_IN_USE: bool = false;
+
+/// Claim the network libraries, returning `false` if they are already in use.
+pub(super) fn claim_net() -> bool {
+    critical_section(|| unsafe {
+        let in_use = NET_IN_USE;
+        NET_IN_USE = true;
+        !in_use
+    })
+}
+
+pub(super) fn release_net() {
+    critical_section(|| unsafe { NET_IN_USE = false });
+}
+
+/// The last error reported through the internal apctl handler.
+static mut LAST_APCTL_ERROR: Option<Error> = None;
+
+/// Access to the network libraries, from module loading to an IP address.
+///
+/// Creating the stack loads the `NetCommon` and `NetInet` modules and
+/// initializes `sceNet`, `sceNetInet`, `sceNetResolver` and `sceNetApctl`, in
+/// that order.
+/// Dropping it disconnects and tears everything down in reverse. Sockets from
+/// `psp::net` must not outlive it.
+///
+/// Only one `NetworkStack` may exist at a time, and not at the same time as
+/// an `Adhoc` session.
+///
+/// # Example
+///
+/// ```ignore
+/// use core::time::Duration;
+/// use psp::net::NetworkStack;
+///
+/// let net = NetworkStack::new()?;
+/// net.connect(1, Some(Duration::from_secs(30)))?;
+/// psp::dprintln!("Got IP {}", net.ip()?);
+/// # Ok::<(), psp::net::NetworkError>(())
+/// ```
+pub struct NetworkStack {
+    stage: Stage,
+    internal_handler: Option<i32>,
+    handlers: Vec<(i32, *mut Handler)>,
+    modules: Option<ModuleGuard>,
+}
+
+impl NetworkStack {
+    /// Bring up the network stack with the default configuration.
+    pub fn new() -> Result<Self, NetworkError> {
+        Self::with_config(&NetworkConfig::default())
+    }
+
+    /// Bring up the network stack with a custom configuration.
+    pub fn with_config(config: &NetworkConfig) -> Result<Self, NetworkError> {
+        if !claim_net() {
+            return Err(NetworkError::AlreadyInitialized);
+        }
+
+        let mut stack = Self {
+            stage: Stage::None,
+            internal_handler: None,
+            handlers: Vec::new(),
+            modules: None,
+        };
+
+        // On error, dropping `stack` undoes the steps completed so far.
+        stack.modules = Some(ModuleGuard::load(&[
+            NetModule::NetCommon.into(),
+            NetModule::NetInet.into(),
+        ])?);
+        stack.step(Stage::NetInit, || unsafe {
+            sys::sceNetInit(
+                config.pool_size,
+                config.callout_priority,
+                config.callout_stack_size,
+                config.netintr_priority,
+                config.netintr_stack_size,
+            )
+        })?;
+        stack.step(Stage::InetInit, || unsafe { sys::sceNetInetInit() })?;
+        stack.step(Stage::ResolverInit, || unsafe { sys::sceNetResolverInit() })?;
+        stack.step(Stage::ApctlInit, || unsafe {
+            sys::sceNetApctlInit(config.apctl_stack_size, config.apctl_priority)
+        })?;
+
+        let id = crate::error::check(unsafe {
+            sys::sceNetApctlAddHandler(Some(record_error), ptr::null_mut())
+        })?;
+        stack.internal_handler = Some(id);
+
+        Ok(stack)
+    }
+
+    fn step(&mut self, stage: Stage, f: impl FnOnce() -> i32) -> Result<(), Error> {
+        crate::error::check(f())?;
+        self.stage = stage;
+
+        Ok(())
+    }
+
+    /// Connect to the access point stored in network configuration `profile`
+    /// (starting from 1), and block until an IP address is obtained.
+    ///
+    /// With a `timeout` of `None`, this waits until the connection succeeds
+    /// or fails.
+    pub fn connect(&self, profile: i32, timeout: Option<Duration>) -> Result<(), NetworkError> {
+        critical_section(|| unsafe { LAST_APCTL_ERROR = None });
+        crate::error::check(unsafe { sys::sceNetApctlConnect(profile) })?;
+
+        const POLL_INTERVAL: Duration = Duration::from_millis(50);
+        let mut waited = Duration::from_secs(0);
+        let mut started = false;
+
+        loop {
+            match self.state()? {
+                ApctlState::GotIp => return Ok(()),
+
+                // Once the connection attempt has started, dropping back to
+                // `Disconnected` means it failed.
+                ApctlState::Disconnected if started => {
+                    let error = critical_section(|| unsafe { LAST_APCTL_ERROR });
+                    return Err(NetworkError::ConnectionFailed(error));
+                }
+
+                ApctlState::Disconnected => (),
+                _ => started = true,
+            }
+
+            if let Some(timeout) = timeout {
+                if waited >= timeout {
+                    unsafe {
+                        sys::sceNetApctlDisconnect();
+                    }
+
+                    return Err(NetworkError::TimedOut);
+                }
+            }
+
+            crate::thread::sleep_cb(POLL_INTERVAL);
+            waited += POLL_INTERVAL;
+        }
+    }
+
+    /// Let the user pick a connection in the system network configuration
+    /// dialog, and block until it closes.
+    ///
+    /// Utility dialogs draw on top of the current frame, so `render_frame`
+    /// is called once per frame while the dialog is open. It must draw and
+    /// finish a GU frame, wait for vblank and swap buffers.
+    pub fn connect_with_dialog<F: FnMut()>(&self, render_frame: F) -> Result<(), NetworkError> {
+        if !NetconfDialog::connect().show(render_frame)? {
+            return Err(NetworkError::Cancelled);
+        }
+
+        match self.state()? {
+            ApctlState::GotIp => Ok(()),
+            _ => Err(NetworkError::ConnectionFailed(None)),
+        }
+    }
+
+    /// Disconnect from the access point.
+    pub fn disconnect(&self) -> Result<(), Error> {
+        crate::error::check(unsafe { sys::sceNetApctlDisconnect() }).map(|_| ())
+    }
+
+    /// The current state of the access point connection.
+    pub fn state(&self) -> Result<ApctlState, Error> {
+        let mut state = ApctlState::Disconnected;
+        crate::error::check(unsafe { sys::sceNetApctlGetState(&mut state) })?;
+
+        Ok(state)
+    }
+
+    /// Returns `true` if connected with an IP address.
+    pub fn is_connected(&self) -> bool {
+        self.state().map(|s| s == ApctlState::GotIp).unwrap_or(false)
+    }
+
+    /// Register a handler for access point connection events.
+    ///
+    /// Handlers run on the apctl thread, so should return quickly.
+    pub fn add_handler<F>(&mut self, handler: F) -> Result<ApctlHandlerId, Error>
+    where
+        F: FnMut(&ApctlTransition) + Send + 'static,
+    {
+        let handler: *mut Handler = Box::into_raw(Box::new(Box::new(handler)));
+
+        let id = unsafe { sys::sceNetApctlAddHandler(Some(dispatch), handler as *mut c_void) };
+
+        match crate::error::check(id) {
+            Ok(id) => {
+                self.handlers.push((id, handler));
+                Ok(ApctlHandlerId(id))
+            }
+            Err(e) => {
+                unsafe { drop(Box::from_raw(handler)) };
+                Err(e)
+            }
+        }
+    }
+
+    /// Remove a handler registered with `add_handler`.
+    pub fn remove_handler(&mut self, id: ApctlHandlerId) {
+        if let Some(i) = self.handlers.iter().position(|(h, _)| *h == id.0) {
+            let (id, handler) = self.handlers.swap_remove(i);
+
+            unsafe {
+                sys::sceNetApctlDelHandler(id);
+                drop(Box::from_raw(handler));
+            }
+        }
+    }
+
+    fn info(&self, code: ApctlInfo) -> Result<SceNetApctlInfo, Error> {
+        let mut info: SceNetApctlInfo = unsafe { mem::zeroed() };
+        crate::error::check(unsafe { sys::sceNetApctlGetInfo(code, &mut info) })?;
+
+        Ok(info)
+    }
+
+    fn info_ip(&self, code: ApctlInfo) -> Result<Ipv4Addr, Error> {
+        let info = self.info(code)?;
+        let s = c_str(unsafe { &info.ip });
+
+        s.parse().map_err(|_| crate::error::SCE_ERROR_ERRNO_EINVAL)
+    }
+
+    /// The IP address assigned to this console.
+    pub fn ip(&self) -> Result<Ipv4Addr, Error> {
+        self.info_ip(ApctlInfo::Ip)
+    }
+
+    /// The subnet mask of the current connection.
+    pub fn subnet_mask(&self) -> Result<Ipv4Addr, Error> {
+        self.info_ip(ApctlInfo::SubnetMask)
+    }
+
+    /// The default gateway of the current connection.
+    pub fn gateway(&self) -> Result<Ipv4Addr, Error> {
+        self.info_ip(ApctlInfo::Gateway)
+    }
+
+    /// The primary DNS server of the current connection.
+    pub fn primary_dns(&self) -> Result<Ipv4Addr, Error> {
+        self.info_ip(ApctlInfo::PrimaryDns)
+    }
+
+    /// The SSID of the access point.
+    pub fn ssid(&self) -> Result<String, Error> {
+        let info = self.info(ApctlInfo::Ssid)?;
+        Ok(String::from(c_str(unsafe { &info.ssid })))
+    }
+
+    /// The name of the network configuration in use.
+    pub fn profile_name(&self) -> Result<String, Error> {
+        let info = self.info(ApctlInfo::ProfileName)?;
+        Ok(String::from(c_str(unsafe { &info.name })))
+    }
+
+    /// The signal strength, in percent.
+    pub fn signal_strength(&self) -> Result<u8, Error> {
+        let info = self.info(ApctlInfo::Strength)?;
+        Ok(unsafe { info.strength })
+    }
+}
+
+impl Drop for NetworkStack {
+    fn drop(&mut self) {
+        unsafe {
+            if self.stage >= Stage::ApctlInit {
+                sys::sceNetApctlDisconnect();
+
+                for (id, handler) in self.handlers.drain(..) {
+                    sys::sceNetApctlDelHandler(id);
+                    drop(Box::from_raw(handler));
+                }
+
+                if let Some(id) = self.internal_handler {
+                    sys::sceNetApctlDelHandler(id);
+                }
+
+                sys::sceNetApctlTerm();
+            }
+
+            if self.stage >= Stage::ResolverInit {
+                sys::sceNetResolverTerm();
+            }
+
+            if self.stage >= Stage::InetInit {
+                sys::sceNetInetTerm();
+            }
+
+            if self.stage >= Stage::NetInit {
+                sys::sceNetTerm();
+            }
+        }
+
+        self.modules = None;
+        release_net();
+    }
+}
+
+fn transition(old: i32, new: i32, event: i32, error: i32) -> ApctlTransition {
+    use core::convert::TryFrom;
+
+    ApctlTransition {
+        old: ApctlState::try_from(old as u32).ok(),
+        new: ApctlState::try_from(new as u32).ok(),
+        event: ApctlEvent::try_from(event as u32).ok(),
+        error: if error != 0 { Some(Error::from_raw(error)) } else { None },
+    }
+}
+
+unsafe extern "C" fn record_error(_old: i32, _new: i32, event: i32, error: i32, _arg: *mut c_void) {
+    if event == ApctlEvent::Error as i32 && error != 0 {
+        critical_section(|| LAST_APCTL_ERROR = Some(Error::from_raw(error)));
+    }
+}
+
+unsafe extern "C" fn dispatch(old: i32, new: i32, event: i32, error: i32, arg: *mut c_void) {
+    let handler = &mut *(arg as *mut Handler);
+    handler(&transition(old, new, event, error));
+}
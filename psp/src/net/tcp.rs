@@ -0,0 +1,279 @@
+use super::socket::{Shutdown, Socket};
+use super::{each_addr, SocketAddrV4, ToSocketAddrs};
+use crate::io::{self, Read, Write};
+use crate::sys;
+use core::{fmt, time::Duration};
+
+/// A TCP stream between a local and a remote socket.
+///
+/// The connection is closed when the value is dropped.
+///
+/// # Example
+///
+/// ```ignore
+/// use psp::io::{Read, Write};
+/// use psp::net::TcpStream;
+///
+/// let mut stream = TcpStream::connect("192.168.0.2:8080")?;
+/// stream.write_all(b"ping")?;
+///
+/// let mut buf = [0; 4];
+/// stream.read_exact(&mut buf)?;
+/// # Ok::<(), psp::io::Error>(())
+/// ```
+pub struct TcpStream {
+    socket: Socket,
+}
+
+impl TcpStream {
+    /// Open a TCP connection to a remote host.
+    ///
+    /// `addr` may contain a host name, such as `"example.com:80"`, which is
+    /// resolved with a `Resolver`. If it yields multiple addresses, each is
+    /// tried in order until one succeeds.
+    pub fn connect<A: ToSocketAddrs>(addr: A) -> io::Result<Self> {
+        each_addr(addr, |addr| {
+            let socket = Socket::new(sys::SOCK_STREAM)?;
+            socket.connect(addr)?;
+
+            Ok(Self { socket })
+        })
+    }
+
+    /// Open a TCP connection to a remote host, with a timeout.
+    pub fn connect_timeout(addr: &SocketAddrV4, timeout: Duration) -> io::Result<Self> {
+        let socket = Socket::new(sys::SOCK_STREAM)?;
+        socket.connect_timeout(addr, timeout)?;
+
+        Ok(Self { socket })
+    }
+
+    /// Returns the socket address of the remote peer of this connection.
+    pub fn peer_addr(&self) -> io::Result<SocketAddrV4> {
+        self.socket.peer_addr()
+    }
+
+    /// Returns the socket address of the local half of this connection.
+    pub fn local_addr(&self) -> io::Result<SocketAddrV4> {
+        self.socket.local_addr()
+    }
+
+    /// Shut down the read, write, or both halves of this connection.
+    pub fn shutdown(&self, how: Shutdown) -> io::Result<()> {
+        self.socket.shutdown(how)
+    }
+
+    /// Set the read timeout. `None` blocks indefinitely.
+    ///
+    /// Reads which time out fail with `WouldBlock` or `TimedOut`.
+    ///
+    /// # Errors
+    ///
+    /// Passing a zero `Duration` is an error.
+    pub fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        self.socket.set_timeout(timeout, sys::SO_RCVTIMEO)
+    }
+
+    /// Set the write timeout. `None` blocks indefinitely.
+    ///
+    /// # Errors
+    ///
+    /// Passing a zero `Duration` is an error.
+    pub fn set_write_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        self.socket.set_timeout(timeout, sys::SO_SNDTIMEO)
+    }
+
+    /// Returns the read timeout.
+    pub fn read_timeout(&self) -> io::Result<Option<Duration>> {
+        self.socket.timeout(sys::SO_RCVTIMEO)
+    }
+
+    /// Returns the write timeout.
+    pub fn write_timeout(&self) -> io::Result<Option<Duration>> {
+        self.socket.timeout(sys::SO_SNDTIMEO)
+    }
+
+    /// Receive data without removing it from the queue.
+    pub fn peek(&self, buf: &mut [u8]) -> io::Result<usize> {
+        self.socket.recv_with_flags(buf, sys::MSG_PEEK)
+    }
+
+    /// Set the `TCP_NODELAY` option, disabling Nagle's algorithm.
+    pub fn set_nodelay(&self, nodelay: bool) -> io::Result<()> {
+        self.socket.setsockopt(sys::IPPROTO_TCP, sys::TCP_NODELAY, nodelay as i32)
+    }
+
+    /// Returns the value of the `TCP_NODELAY` option.
+    pub fn nodelay(&self) -> io::Result<bool> {
+        let nodelay: i32 = self.socket.getsockopt(sys::IPPROTO_TCP, sys::TCP_NODELAY)?;
+        Ok(nodelay != 0)
+    }
+
+    /// Move this stream into or out of nonblocking mode.
+    ///
+    /// In nonblocking mode, operations which would block fail with
+    /// `WouldBlock` instead.
+    pub fn set_nonblocking(&self, nonblocking: bool) -> io::Result<()> {
+        self.socket.set_nonblocking(nonblocking)
+    }
+
+    /// Get and clear the pending socket error, `SO_ERROR`.
+    pub fn take_error(&self) -> io::Result<Option<io::Error>> {
+        self.socket.take_error()
+    }
+
+    /// Returns the underlying socket descriptor.
+    pub fn as_raw_fd(&self) -> i32 {
+        self.socket.as_raw()
+    }
+
+    /// Consume the stream, returning the socket descriptor without closing
+    /// it.
+    pub fn into_raw_fd(self) -> i32 {
+        self.socket.into_raw()
+    }
+
+    /// Create a stream from a connected socket descriptor.
+    ///
+    /// # Safety
+    ///
+    /// `fd` must be an open, connected TCP socket, which is closed when the
+    /// stream is dropped.
+    pub unsafe fn from_raw_fd(fd: i32) -> Self {
+        Self {
+            socket: Socket::from_raw(fd),
+        }
+    }
+}
+
+impl Read for TcpStream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.socket.recv_with_flags(buf, 0)
+    }
+}
+
+impl Write for TcpStream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.socket.send(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Read for &TcpStream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.socket.recv_with_flags(buf, 0)
+    }
+}
+
+impl Write for &TcpStream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.socket.send(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl fmt::Debug for TcpStream {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut d = f.debug_struct("TcpStream");
+
+        if let Ok(addr) = self.local_addr() {
+            d.field("addr", &addr);
+        }
+
+        if let Ok(peer) = self.peer_addr() {
+            d.field("peer", &peer);
+        }
+
+        d.field("fd", &self.socket.as_raw()).finish()
+    }
+}
+
+/// A TCP socket server, listening for connections.
+pub struct TcpListener {
+    socket: Socket,
+}
+
+impl TcpListener {
+    /// Create a listener bound to `addr`.
+    ///
+    /// Binding with a port number of 0 lets the stack pick a port, which can
+    /// be queried with `local_addr`.
+    pub fn bind<A: ToSocketAddrs>(addr: A) -> io::Result<Self> {
+        each_addr(addr, |addr| {
+            let socket = Socket::new(sys::SOCK_STREAM)?;
+            socket.setsockopt(sys::SOL_SOCKET, sys::SO_REUSEADDR, 1i32)?;
+            socket.bind(addr)?;
+            socket.listen(128)?;
+
+            Ok(Self { socket })
+        })
+    }
+
+    /// Returns the local socket address of this listener.
+    pub fn local_addr(&self) -> io::Result<SocketAddrV4> {
+        self.socket.local_addr()
+    }
+
+    /// Accept a new incoming connection, blocking until one arrives.
+    pub fn accept(&self) -> io::Result<(TcpStream, SocketAddrV4)> {
+        let (socket, addr) = self.socket.accept()?;
+        Ok((TcpStream { socket }, addr))
+    }
+
+    /// Returns an iterator over the connections being received.
+    ///
+    /// The iterator never returns `None`.
+    pub fn incoming(&self) -> Incoming<'_> {
+        Incoming { listener: self }
+    }
+
+    /// Move this listener into or out of nonblocking mode.
+    ///
+    /// In nonblocking mode, `accept` fails with `WouldBlock` if there is no
+    /// pending connection.
+    pub fn set_nonblocking(&self, nonblocking: bool) -> io::Result<()> {
+        self.socket.set_nonblocking(nonblocking)
+    }
+
+    /// Get and clear the pending socket error, `SO_ERROR`.
+    pub fn take_error(&self) -> io::Result<Option<io::Error>> {
+        self.socket.take_error()
+    }
+
+    /// Returns the underlying socket descriptor.
+    pub fn as_raw_fd(&self) -> i32 {
+        self.socket.as_raw()
+    }
+}
+
+impl fmt::Debug for TcpListener {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut d = f.debug_struct("TcpListener");
+
+        if let Ok(addr) = self.local_addr() {
+            d.field("addr", &addr);
+        }
+
+        d.field("fd", &self.socket.as_raw()).finish()
+    }
+}
+
+/// An iterator over the connections of a `TcpListener`.
+#[derive(Debug)]
+pub struct Incoming<'a> {
+    listener: &'a TcpListener,
+}
+
+impl Iterator for Incoming<'_> {
+    type Item = io::Result<TcpStream>;
+
+    fn next(&mut self) -> Option<io::Result<TcpStream>> {
+        Some(self.listener.accept().map(|(stream, _)| stream))
+    }
+}
@@ -0,0 +1,145 @@
+use super::socket::Socket;
+use super::{each_addr, SocketAddrV4, ToSocketAddrs};
+use crate::io;
+use crate::sys;
+use core::{fmt, time::Duration};
+
+/// A UDP socket.
+///
+/// The socket is closed when the value is dropped.
+pub struct UdpSocket {
+    socket: Socket,
+}
+
+impl UdpSocket {
+    /// Create a UDP socket bound to `addr`.
+    pub fn bind<A: ToSocketAddrs>(addr: A) -> io::Result<Self> {
+        each_addr(addr, |addr| {
+            let socket = Socket::new(sys::SOCK_DGRAM)?;
+            socket.bind(addr)?;
+
+            Ok(Self { socket })
+        })
+    }
+
+    /// Receive a single datagram, returning the number of bytes read and the
+    /// address it came from.
+    ///
+    /// If `buf` is too small for the datagram, the excess is discarded.
+    pub fn recv_from(&self, buf: &mut [u8]) -> io::Result<(usize, SocketAddrV4)> {
+        self.socket.recv_from_with_flags(buf, 0)
+    }
+
+    /// Like `recv_from`, without removing the datagram from the queue.
+    pub fn peek_from(&self, buf: &mut [u8]) -> io::Result<(usize, SocketAddrV4)> {
+        self.socket.recv_from_with_flags(buf, sys::MSG_PEEK)
+    }
+
+    /// Send `buf` to the given address, returning the number of bytes sent.
+    ///
+    /// Only the first address yielded by `addr` is used.
+    pub fn send_to<A: ToSocketAddrs>(&self, buf: &[u8], addr: A) -> io::Result<usize> {
+        match addr.to_socket_addrs()?.next() {
+            Some(addr) => self.socket.send_to(buf, &addr),
+            None => Err(io::Error::new(io::ErrorKind::InvalidInput)),
+        }
+    }
+
+    /// Set the default destination for `send`, and only receive from that
+    /// address.
+    pub fn connect<A: ToSocketAddrs>(&self, addr: A) -> io::Result<()> {
+        each_addr(addr, |addr| self.socket.connect(addr))
+    }
+
+    /// Send `buf` to the connected address.
+    pub fn send(&self, buf: &[u8]) -> io::Result<usize> {
+        self.socket.send(buf)
+    }
+
+    /// Receive a single datagram from the connected address.
+    pub fn recv(&self, buf: &mut [u8]) -> io::Result<usize> {
+        self.socket.recv_with_flags(buf, 0)
+    }
+
+    /// Like `recv`, without removing the datagram from the queue.
+    pub fn peek(&self, buf: &mut [u8]) -> io::Result<usize> {
+        self.socket.recv_with_flags(buf, sys::MSG_PEEK)
+    }
+
+    /// Returns the local socket address of this socket.
+    pub fn local_addr(&self) -> io::Result<SocketAddrV4> {
+        self.socket.local_addr()
+    }
+
+    /// Returns the address this socket is connected to.
+    pub fn peer_addr(&self) -> io::Result<SocketAddrV4> {
+        self.socket.peer_addr()
+    }
+
+    /// Set the `SO_BROADCAST` option, allowing datagrams to be sent to the
+    /// broadcast address.
+    pub fn set_broadcast(&self, broadcast: bool) -> io::Result<()> {
+        self.socket.setsockopt(sys::SOL_SOCKET, sys::SO_BROADCAST, broadcast as i32)
+    }
+
+    /// Returns the value of the `SO_BROADCAST` option.
+    pub fn broadcast(&self) -> io::Result<bool> {
+        let broadcast: i32 = self.socket.getsockopt(sys::SOL_SOCKET, sys::SO_BROADCAST)?;
+        Ok(broadcast != 0)
+    }
+
+    /// Set the read timeout. `None` blocks indefinitely.
+    ///
+    /// # Errors
+    ///
+    /// Passing a zero `Duration` is an error.
+    pub fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        self.socket.set_timeout(timeout, sys::SO_RCVTIMEO)
+    }
+
+    /// Set the write timeout. `None` blocks indefinitely.
+    ///
+    /// # Errors
+    ///
+    /// Passing a zero `Duration` is an error.
+    pub fn set_write_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        self.socket.set_timeout(timeout, sys::SO_SNDTIMEO)
+    }
+
+    /// Returns the read timeout.
+    pub fn read_timeout(&self) -> io::Result<Option<Duration>> {
+        self.socket.timeout(sys::SO_RCVTIMEO)
+    }
+
+    /// Returns the write timeout.
+    pub fn write_timeout(&self) -> io::Result<Option<Duration>> {
+        self.socket.timeout(sys::SO_SNDTIMEO)
+    }
+
+    /// Move this socket into or out of nonblocking mode.
+    pub fn set_nonblocking(&self, nonblocking: bool) -> io::Result<()> {
+        self.socket.set_nonblocking(nonblocking)
+    }
+
+    /// Get and clear the pending socket error, `SO_ERROR`.
+    pub fn take_error(&self) -> io::Result<Option<io::Error>> {
+        self.socket.take_error()
+    }
+
+    /// Returns the underlying socket descriptor.
+    pub fn as_raw_fd(&self) -> i32 {
+        self.socket.as_raw()
+    }
+}
+
+impl fmt::Debug for UdpSocket {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut d = f.debug_struct("UdpSocket");
+
+        if let Ok(addr) = self.local_addr() {
+            d.field("addr", &addr);
+        }
+
+        d.field("fd", &self.socket.as_raw()).finish()
+    }
+}
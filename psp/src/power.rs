@@ -0,0 +1,208 @@
+//! Clock speed, battery status and suspend handling.
+//!
+//! The CPU and bus only run reliably at a few combinations of clock
+//! frequencies, which `ClockProfile` names. Faster clocks drain the battery
+//! faster, so applications usually run at the default 222 MHz and raise the
+//! clock only for demanding scenes.
+//!
+//! ```ignore
+//! use psp::power::{self, ClockProfile, PowerLock};
+//!
+//! power::set_clock(ClockProfile::Mhz333).unwrap();
+//!
+//! if let Some(battery) = power::BatteryStatus::read() {
+//!     psp::dprintln!("Battery at {}%", battery.percent);
+//! }
+//!
+//! // Keep the console from suspending in the middle of a save.
+//! let lock = PowerLock::new().unwrap();
+//! // Write the save file.
+//! drop(lock);
+//! ```
+
+use crate::error::{self, Result};
+use crate::events::{self, Event, HandlerId};
+use crate::sys;
+
+/// A supported combination of CPU and bus clock frequencies.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ClockProfile {
+    /// CPU at 222 MHz and bus at 111 MHz, the default.
+    Mhz222,
+    /// CPU at 266 MHz and bus at 133 MHz.
+    Mhz266,
+    /// CPU at 333 MHz and bus at 166 MHz, the fastest.
+    Mhz333,
+}
+
+impl ClockProfile {
+    /// Every profile, from slowest to fastest.
+    pub const ALL: [ClockProfile; 3] = [
+        ClockProfile::Mhz222,
+        ClockProfile::Mhz266,
+        ClockProfile::Mhz333,
+    ];
+
+    /// The CPU frequency in MHz. The PLL runs at the same frequency.
+    pub fn cpu(self) -> u32 {
+        match self {
+            ClockProfile::Mhz222 => 222,
+            ClockProfile::Mhz266 => 266,
+            ClockProfile::Mhz333 => 333,
+        }
+    }
+
+    /// The bus frequency in MHz, half the CPU frequency.
+    pub fn bus(self) -> u32 {
+        self.cpu() / 2
+    }
+
+    /// The profile with these frequencies, if there is one.
+    pub fn from_frequencies(cpu: u32, bus: u32) -> Option<Self> {
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|profile| profile.cpu() == cpu && profile.bus() == bus)
+    }
+}
+
+/// Change the clock frequencies to those of `profile`.
+pub fn set_clock(profile: ClockProfile) -> Result<()> {
+    let (cpu, bus) = (profile.cpu() as i32, profile.bus() as i32);
+    error::check(unsafe { sys::scePowerSetClockFrequency(cpu, cpu, bus) })?;
+
+    Ok(())
+}
+
+/// The CPU frequency in MHz.
+pub fn cpu_frequency() -> u32 {
+    unsafe { sys::scePowerGetCpuClockFrequencyInt() as u32 }
+}
+
+/// The bus frequency in MHz.
+pub fn bus_frequency() -> u32 {
+    unsafe { sys::scePowerGetBusClockFrequencyInt() as u32 }
+}
+
+/// The profile the clocks run at, or `None` if they were set to another
+/// combination.
+pub fn clock() -> Option<ClockProfile> {
+    ClockProfile::from_frequencies(cpu_frequency(), bus_frequency())
+}
+
+/// Returns `true` if the console runs from the AC adapter.
+pub fn is_ac_power() -> bool {
+    unsafe { sys::scePowerIsPowerOnline() == 1 }
+}
+
+/// The state of the battery.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct BatteryStatus {
+    /// The charge left, from 0 to 100.
+    pub percent: u8,
+    /// The estimated time left in minutes, or `None` while on AC power or
+    /// before an estimate is available.
+    pub minutes: Option<u32>,
+    /// Whether the battery is charging.
+    pub charging: bool,
+    /// Whether the battery is low, when the power LED blinks.
+    pub low: bool,
+    /// The temperature in degrees Celsius.
+    pub temperature: i32,
+    /// The voltage in millivolts.
+    pub voltage: u32,
+}
+
+impl BatteryStatus {
+    /// Read the state of the battery, or `None` if there is no battery.
+    pub fn read() -> Option<Self> {
+        unsafe {
+            if sys::scePowerIsBatteryExist() != 1 {
+                return None;
+            }
+
+            let percent = sys::scePowerGetBatteryLifePercent();
+            let minutes = sys::scePowerGetBatteryLifeTime();
+
+            Some(Self {
+                percent: percent.max(0).min(100) as u8,
+                minutes: if minutes < 0 {
+                    None
+                } else {
+                    Some(minutes as u32)
+                },
+                charging: sys::scePowerIsBatteryCharging() == 1,
+                low: sys::scePowerIsLowBattery() == 1,
+                temperature: sys::scePowerGetBatteryTemp(),
+                voltage: sys::scePowerGetBatteryVolt().max(0) as u32,
+            })
+        }
+    }
+}
+
+/// Run `handler` when the console is about to suspend, such as when the
+/// power switch is flipped. This is the place to close files and sockets.
+///
+/// The handler runs on the callback thread of `psp::events`. Remove it with
+/// `psp::events::remove_handler`.
+///
+/// # Panics
+///
+/// Panics if `psp::events::init` has not been called.
+pub fn on_suspend<F>(mut handler: F) -> HandlerId
+where
+    F: FnMut() + Send + 'static,
+{
+    events::add_handler(move |event| {
+        if *event == Event::PowerSuspend {
+            handler();
+        }
+    })
+}
+
+/// Run `handler` when the console resumes from suspend. This is the place
+/// to reopen what was closed when suspending.
+///
+/// The handler runs on the callback thread of `psp::events`. Remove it with
+/// `psp::events::remove_handler`.
+///
+/// # Panics
+///
+/// Panics if `psp::events::init` has not been called.
+pub fn on_resume<F>(mut handler: F) -> HandlerId
+where
+    F: FnMut() + Send + 'static,
+{
+    events::add_handler(move |event| {
+        if *event == Event::PowerResume {
+            handler();
+        }
+    })
+}
+
+/// Keeps the power switch from suspending or turning off the console while
+/// it is alive, for writes that must not be interrupted.
+///
+/// Locks nest, and flipping the switch while locked takes effect once the
+/// last lock is dropped.
+#[derive(Debug)]
+pub struct PowerLock {
+    _private: (),
+}
+
+impl PowerLock {
+    /// Lock the power switch.
+    pub fn new() -> Result<Self> {
+        error::check(unsafe { sys::scePowerLock(0) })?;
+
+        Ok(Self { _private: () })
+    }
+}
+
+impl Drop for PowerLock {
+    fn drop(&mut self) {
+        unsafe {
+            sys::scePowerUnlock(0);
+        }
+    }
+}
@@ -0,0 +1,481 @@
+//! Saving and loading game data with the system save data utility.
+//!
+//! Saves live in `ms0:/PSP/SAVEDATA/<game name><save name>/`, and are shown in
+//! the system's save data browser with the title, details and icons given
+//! here. The payload is an opaque byte buffer, so any serialization format
+//! can be used for it. With the `savedata-serde` feature,
+//! `SaveData::save_value` and `Loaded::value` store any `serde` type in the
+//! `postcard` format.
+//!
+//! Like other utility dialogs, the save data utility must be pumped once per
+//! frame while it runs, even when it shows no UI. See `psp::dialog` for the
+//! requirements on `render_frame`.
+//!
+//! # Example
+//!
+//! ```ignore
+//! use psp::savedata::{SaveData, SaveInfo, Slot};
+//!
+//! # fn render_frame() {}
+//! let saves = SaveData::new("ULUS99999").title("My Game");
+//! let info = SaveInfo { title: "Chapter 1", ..SaveInfo::default() };
+//!
+//! saves.save(Slot::Auto("0000"), &info, b"progress", render_frame)?;
+//! let loaded = saves.load(Slot::Auto("0000"), render_frame)?;
+//! # Ok::<(), psp::savedata::SaveDataError>(())
+//! ```
+
+use crate::dialog::{self, Pump, Utility};
+use crate::error::{self, Error};
+use crate::fs;
+use crate::io;
+use crate::sys::{
+    self, SceUtilitySavedataParam, UtilitySavedataFileData, UtilitySavedataFocus,
+    UtilitySavedataMode,
+};
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::{ffi::c_void, mem, ptr};
+
+/// The directory containing all saves.
+pub const SAVEDATA_DIR: &str = "ms0:/PSP/SAVEDATA";
+
+/// Default name of the file holding the payload.
+pub const DEFAULT_FILE_NAME: &str = "DATA.BIN";
+
+/// Default size of the buffer used by `SaveData::load`.
+pub const DEFAULT_MAX_SIZE: usize = 64 * 1024;
+
+/// Error returned by `SaveData` operations.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum SaveDataError {
+    /// The save does not exist.
+    NoData,
+    /// The save exists but could not be read or decrypted.
+    Corrupted,
+    /// There is not enough space on the memory stick.
+    MemoryStickFull,
+    /// No memory stick is inserted.
+    NoMemoryStick,
+    /// The user backed out of the dialog.
+    Cancelled,
+    /// A name is too long, or a list of names is empty.
+    InvalidInput,
+    /// The payload could not be serialized, or did not deserialize to the
+    /// type asked for.
+    InvalidData,
+    /// Any other failure.
+    Kernel(Error),
+}
+
+impl From<Error> for SaveDataError {
+    fn from(error: Error) -> Self {
+        match error {
+            error::SCE_UTILITY_SAVEDATA_ERROR_LOAD_NO_DATA
+            | error::SCE_UTILITY_SAVEDATA_ERROR_RW_NO_DATA
+            | error::SCE_UTILITY_SAVEDATA_ERROR_DELETE_NO_DATA => SaveDataError::NoData,
+            error::SCE_UTILITY_SAVEDATA_ERROR_LOAD_DATA_BROKEN
+            | error::SCE_UTILITY_SAVEDATA_ERROR_RW_DATA_BROKEN => SaveDataError::Corrupted,
+            error::SCE_UTILITY_SAVEDATA_ERROR_SAVE_MS_NOSPACE
+            | error::SCE_UTILITY_SAVEDATA_ERROR_RW_MEMSTICK_FULL => {
+                SaveDataError::MemoryStickFull
+            }
+            error::SCE_UTILITY_SAVEDATA_ERROR_LOAD_NO_MS
+            | error::SCE_UTILITY_SAVEDATA_ERROR_RW_NO_MEMSTICK
+            | error::SCE_UTILITY_SAVEDATA_ERROR_DELETE_NO_MS
+            | error::SCE_UTILITY_SAVEDATA_ERROR_SAVE_NO_MS => SaveDataError::NoMemoryStick,
+            e => SaveDataError::Kernel(e),
+        }
+    }
+}
+
+impl From<io::Error> for SaveDataError {
+    fn from(error: io::Error) -> Self {
+        match (error.kind(), error.sce_error()) {
+            (io::ErrorKind::NotFound, _) => SaveDataError::NoData,
+            (io::ErrorKind::StorageFull, _) => SaveDataError::MemoryStickFull,
+            (_, Some(e)) => e.into(),
+            (_, None) => SaveDataError::Kernel(error::SCE_ERROR_ERRNO_EIO),
+        }
+    }
+}
+
+/// Which save an operation applies to, and how much UI is shown.
+#[derive(Debug, Copy, Clone)]
+pub enum Slot<'a> {
+    /// The save with this name, without any UI.
+    Auto(&'a str),
+    /// The save with this name, after confirmation in a dialog.
+    Confirm(&'a str),
+    /// One of these saves, picked by the user from a list.
+    List(&'a [&'a str]),
+}
+
+/// The description of a save, shown in the save data browser.
+#[derive(Debug, Copy, Clone, Default)]
+pub struct SaveInfo<'a> {
+    /// The title of this particular save, such as the chapter reached.
+    pub title: &'a str,
+    /// A longer description. May contain line breaks.
+    pub detail: &'a str,
+    /// The parental control level, 0 to 11.
+    pub parental_level: u8,
+}
+
+/// A save read by `SaveData::load`.
+#[derive(Debug, Clone)]
+pub struct Loaded {
+    /// The name of the save, as picked by the user for `Slot::List`.
+    pub name: String,
+    /// The payload.
+    pub data: Vec<u8>,
+    /// The title of the save.
+    pub title: String,
+    /// The description of the save.
+    pub detail: String,
+}
+
+impl Loaded {
+    /// Deserialize a payload written by `SaveData::save_value`.
+    #[cfg(feature = "savedata-serde")]
+    pub fn value<T: serde::de::DeserializeOwned>(&self) -> Result<T, SaveDataError> {
+        postcard::from_bytes(&self.data).map_err(|_| SaveDataError::InvalidData)
+    }
+}
+
+static SAVEDATA: Utility = Utility {
+    status: || unsafe { sys::sceUtilitySavedataGetStatus() },
+    update: || unsafe { sys::sceUtilitySavedataUpdate(1) },
+    shutdown: || unsafe {
+        sys::sceUtilitySavedataShutdownStart();
+    },
+};
+
+/// The saves of one game.
+///
+/// The game-wide settings, such as the title and icons, are set once with the
+/// builder methods and used for every save.
+#[derive(Debug, Clone)]
+pub struct SaveData<'a> {
+    game_name: &'a str,
+    game_title: &'a str,
+    file_name: &'a str,
+    icon0: Option<&'a [u8]>,
+    icon1: Option<&'a [u8]>,
+    pic1: Option<&'a [u8]>,
+    snd0: Option<&'a [u8]>,
+    key: Option<[u8; 16]>,
+    max_size: usize,
+}
+
+impl<'a> SaveData<'a> {
+    /// Manage the saves of `game_name`, usually the product code such as
+    /// `"ULUS99999"`. At most 12 bytes.
+    pub fn new(game_name: &'a str) -> Self {
+        Self {
+            game_name,
+            game_title: "",
+            file_name: DEFAULT_FILE_NAME,
+            icon0: None,
+            icon1: None,
+            pic1: None,
+            snd0: None,
+            key: None,
+            max_size: DEFAULT_MAX_SIZE,
+        }
+    }
+
+    /// Set the title of the game.
+    pub fn title(mut self, title: &'a str) -> Self {
+        self.game_title = title;
+        self
+    }
+
+    /// Set the name of the file holding the payload. At most 12 bytes.
+    pub fn file_name(mut self, file_name: &'a str) -> Self {
+        self.file_name = file_name;
+        self
+    }
+
+    /// Attach the 144x80 PNG icon shown in the save list.
+    pub fn icon0(mut self, png: &'a [u8]) -> Self {
+        self.icon0 = Some(png);
+        self
+    }
+
+    /// Attach the animated PMF icon shown when a save is selected.
+    pub fn icon1(mut self, pmf: &'a [u8]) -> Self {
+        self.icon1 = Some(pmf);
+        self
+    }
+
+    /// Attach the 480x272 PNG background shown when a save is selected.
+    pub fn pic1(mut self, png: &'a [u8]) -> Self {
+        self.pic1 = Some(png);
+        self
+    }
+
+    /// Attach the ATRAC3 sound played when a save is selected.
+    pub fn snd0(mut self, at3: &'a [u8]) -> Self {
+        self.snd0 = Some(at3);
+        self
+    }
+
+    /// Encrypt saves with `key`. Requires firmware 2.00 or later, and the
+    /// same key must be used to load them.
+    pub fn key(mut self, key: [u8; 16]) -> Self {
+        self.key = Some(key);
+        self
+    }
+
+    /// Set the largest payload `load` can read.
+    pub fn max_size(mut self, max_size: usize) -> Self {
+        self.max_size = max_size;
+        self
+    }
+
+    /// Write `data` to a save, returning the name of the save written.
+    pub fn save(
+        &self,
+        slot: Slot<'_>,
+        info: &SaveInfo<'_>,
+        data: &[u8],
+        render_frame: impl FnMut(),
+    ) -> Result<String, SaveDataError> {
+        let modes = [
+            UtilitySavedataMode::AutoSave,
+            UtilitySavedataMode::Save,
+            UtilitySavedataMode::ListSave,
+        ];
+
+        let mut request = self.request(slot, modes)?;
+        let params = &mut request.params;
+
+        params.overwrite = 1;
+        params.data_buf = data.as_ptr() as *mut c_void;
+        params.data_buf_size = data.len();
+        params.data_size = data.len();
+
+        copy_str(&mut params.sfo_param.title, self.game_title)?;
+        copy_str(&mut params.sfo_param.savedata_title, info.title)?;
+        copy_str(&mut params.sfo_param.detail, info.detail)?;
+        params.sfo_param.parental_level = info.parental_level;
+
+        params.icon0_file_data = file_data(self.icon0);
+        params.icon1_file_data = file_data(self.icon1);
+        params.pic1_file_data = file_data(self.pic1);
+        params.snd0_file_data = file_data(self.snd0);
+
+        request.run(render_frame)?;
+
+        Ok(c_string(&request.params.save_name))
+    }
+
+    /// Serialize `value` and write it to a save, returning the name of the
+    /// save written. Read it back with `Loaded::value`.
+    #[cfg(feature = "savedata-serde")]
+    pub fn save_value<T: serde::Serialize>(
+        &self,
+        slot: Slot<'_>,
+        info: &SaveInfo<'_>,
+        value: &T,
+        render_frame: impl FnMut(),
+    ) -> Result<String, SaveDataError> {
+        let data = postcard::to_allocvec(value).map_err(|_| SaveDataError::InvalidData)?;
+
+        self.save(slot, info, &data, render_frame)
+    }
+
+    /// Read the payload of a save.
+    pub fn load(
+        &self,
+        slot: Slot<'_>,
+        render_frame: impl FnMut(),
+    ) -> Result<Loaded, SaveDataError> {
+        let modes = [
+            UtilitySavedataMode::AutoLoad,
+            UtilitySavedataMode::Load,
+            UtilitySavedataMode::ListLoad,
+        ];
+
+        let mut data = alloc::vec![0; self.max_size];
+        let mut request = self.request(slot, modes)?;
+
+        request.params.data_buf = data.as_mut_ptr() as *mut c_void;
+        request.params.data_buf_size = data.len();
+
+        request.run(render_frame)?;
+
+        let params = &request.params;
+        data.truncate(params.data_size);
+
+        Ok(Loaded {
+            name: c_string(&params.save_name),
+            data,
+            title: c_string(&params.sfo_param.savedata_title),
+            detail: c_string(&params.sfo_param.detail),
+        })
+    }
+
+    /// Delete a save, returning the name of the save deleted.
+    ///
+    /// `Slot::Auto` requires firmware 2.00 or later.
+    pub fn delete(
+        &self,
+        slot: Slot<'_>,
+        render_frame: impl FnMut(),
+    ) -> Result<String, SaveDataError> {
+        let modes = [
+            UtilitySavedataMode::AutoDelete,
+            UtilitySavedataMode::SingleDelete,
+            UtilitySavedataMode::ListDelete,
+        ];
+
+        let mut request = self.request(slot, modes)?;
+        request.run(render_frame)?;
+
+        Ok(c_string(&request.params.save_name))
+    }
+
+    /// The names of the existing saves of this game, in no particular order.
+    pub fn list(&self) -> io::Result<Vec<String>> {
+        let entries = match fs::read_dir(SAVEDATA_DIR) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e),
+        };
+
+        let mut names = Vec::new();
+
+        for entry in entries {
+            let entry = entry?;
+
+            if entry.file_type().is_dir() && entry.file_name().starts_with(self.game_name) {
+                names.push(String::from(&entry.file_name()[self.game_name.len()..]));
+            }
+        }
+
+        Ok(names)
+    }
+
+    /// Returns `true` if a save called `name` exists.
+    pub fn exists(&self, name: &str) -> bool {
+        fs::exists(&self.path(name))
+    }
+
+    /// The directory holding the save called `name`.
+    pub fn path(&self, name: &str) -> String {
+        let mut dir = String::from(self.game_name);
+        dir.push_str(name);
+
+        fs::join(SAVEDATA_DIR, &dir)
+    }
+
+    /// Build the parameters common to all operations. `modes` are the modes
+    /// used for `Slot::Auto`, `Slot::Confirm` and `Slot::List`.
+    fn request(
+        &self,
+        slot: Slot<'_>,
+        modes: [UtilitySavedataMode; 3],
+    ) -> Result<Request, SaveDataError> {
+        let mut request = Request {
+            params: unsafe { mem::zeroed() },
+            names: Vec::new(),
+        };
+        let params = &mut request.params;
+
+        params.base = dialog::common::<SceUtilitySavedataParam>();
+        params.focus = UtilitySavedataFocus::Latest;
+        copy_str(&mut params.game_name, self.game_name)?;
+        copy_str(&mut params.file_name, self.file_name)?;
+
+        if let Some(key) = self.key {
+            params.key = key;
+        }
+
+        match slot {
+            Slot::Auto(name) => {
+                params.mode = modes[0];
+                copy_str(&mut params.save_name, name)?;
+            }
+            Slot::Confirm(name) => {
+                params.mode = modes[1];
+                copy_str(&mut params.save_name, name)?;
+            }
+            Slot::List(names) => {
+                if names.is_empty() {
+                    return Err(SaveDataError::InvalidInput);
+                }
+
+                params.mode = modes[2];
+
+                // The list is terminated by an empty name.
+                request.names = alloc::vec![[0; 20]; names.len() + 1];
+                for (dst, name) in request.names.iter_mut().zip(names) {
+                    copy_str(dst, name)?;
+                }
+            }
+        }
+
+        Ok(request)
+    }
+}
+
+/// Parameters of a running save data operation, kept in one place because
+/// the utility holds pointers into them.
+struct Request {
+    params: SceUtilitySavedataParam,
+    names: Vec<[u8; 20]>,
+}
+
+impl Request {
+    fn run(&mut self, render_frame: impl FnMut()) -> Result<(), SaveDataError> {
+        if !self.names.is_empty() {
+            self.params.save_name_list = self.names.as_mut_ptr();
+        }
+
+        let mut pump = Pump::new(&SAVEDATA);
+        let params = &mut self.params;
+
+        pump.start(|| unsafe { sys::sceUtilitySavedataInitStart(params) })?;
+        pump.run(render_frame)?;
+
+        match self.params.base.result {
+            0 => Ok(()),
+            1 => Err(SaveDataError::Cancelled),
+            e => Err(Error::from_raw(e).into()),
+        }
+    }
+}
+
+fn file_data(data: Option<&[u8]>) -> UtilitySavedataFileData {
+    let data = data.unwrap_or(&[]);
+
+    UtilitySavedataFileData {
+        buf: if data.is_empty() {
+            ptr::null_mut()
+        } else {
+            data.as_ptr() as *mut c_void
+        },
+        buf_size: data.len(),
+        size: data.len(),
+        unknown: 0,
+    }
+}
+
+/// Copy `src` into a NUL-terminated buffer.
+fn copy_str(dst: &mut [u8], src: &str) -> Result<(), SaveDataError> {
+    if src.len() >= dst.len() || src.bytes().any(|b| b == 0) {
+        return Err(SaveDataError::InvalidInput);
+    }
+
+    dst[..src.len()].copy_from_slice(src.as_bytes());
+    dst[src.len()..].iter_mut().for_each(|b| *b = 0);
+
+    Ok(())
+}
+
+fn c_string(buf: &[u8]) -> String {
+    let len = buf.iter().position(|&b| b == 0).unwrap_or(buf.len());
+    String::from_utf8_lossy(&buf[..len]).into_owned()
+}
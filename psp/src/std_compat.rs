@@ -0,0 +1,138 @@
+//! Interoperability with `std`, when the `std` feature is enabled.
+//!
+//! This lets the safe wrappers in this crate be used with APIs written against
+//! `std::io`, and converts our error types into `std::io::Error`.
+//!
+//! This is not a port of `std` itself. `std::thread`, `std::env` and `std::fs`
+//! still do not work on the PSP, so use `psp::thread`, `psp::env` and
+//! `psp::fs` instead.
+
+use crate::{fs, io, stdio};
+use std::io as std_io;
+
+impl std::error::Error for crate::Error {}
+impl std::error::Error for io::Error {}
+
+impl From<io::ErrorKind> for std_io::ErrorKind {
+    fn from(kind: io::ErrorKind) -> Self {
+        match kind {
+            io::ErrorKind::NotFound => std_io::ErrorKind::NotFound,
+            io::ErrorKind::PermissionDenied => std_io::ErrorKind::PermissionDenied,
+            io::ErrorKind::AlreadyExists => std_io::ErrorKind::AlreadyExists,
+            io::ErrorKind::InvalidInput => std_io::ErrorKind::InvalidInput,
+            io::ErrorKind::InvalidData => std_io::ErrorKind::InvalidData,
+            io::ErrorKind::ConnectionRefused => std_io::ErrorKind::ConnectionRefused,
+            io::ErrorKind::ConnectionReset => std_io::ErrorKind::ConnectionReset,
+            io::ErrorKind::ConnectionAborted => std_io::ErrorKind::ConnectionAborted,
+            io::ErrorKind::NotConnected => std_io::ErrorKind::NotConnected,
+            io::ErrorKind::AddrInUse => std_io::ErrorKind::AddrInUse,
+            io::ErrorKind::AddrNotAvailable => std_io::ErrorKind::AddrNotAvailable,
+            io::ErrorKind::BrokenPipe => std_io::ErrorKind::BrokenPipe,
+            io::ErrorKind::WouldBlock => std_io::ErrorKind::WouldBlock,
+            io::ErrorKind::TimedOut => std_io::ErrorKind::TimedOut,
+            io::ErrorKind::WriteZero => std_io::ErrorKind::WriteZero,
+            io::ErrorKind::UnexpectedEof => std_io::ErrorKind::UnexpectedEof,
+            _ => std_io::ErrorKind::Other,
+        }
+    }
+}
+
+impl From<io::Error> for std_io::Error {
+    fn from(error: io::Error) -> Self {
+        std_io::Error::new(error.kind().into(), error)
+    }
+}
+
+impl From<crate::Error> for std_io::Error {
+    fn from(error: crate::Error) -> Self {
+        io::Error::from(error).into()
+    }
+}
+
+impl std_io::Read for fs::File {
+    fn read(&mut self, buf: &mut [u8]) -> std_io::Result<usize> {
+        Ok(io::Read::read(self, buf)?)
+    }
+}
+
+impl std_io::Write for fs::File {
+    fn write(&mut self, buf: &[u8]) -> std_io::Result<usize> {
+        Ok(io::Write::write(self, buf)?)
+    }
+
+    fn flush(&mut self) -> std_io::Result<()> {
+        Ok(io::Write::flush(self)?)
+    }
+}
+
+impl std_io::Seek for fs::File {
+    fn seek(&mut self, pos: std_io::SeekFrom) -> std_io::Result<u64> {
+        let pos = match pos {
+            std_io::SeekFrom::Start(n) => io::SeekFrom::Start(n),
+            std_io::SeekFrom::End(n) => io::SeekFrom::End(n),
+            std_io::SeekFrom::Current(n) => io::SeekFrom::Current(n),
+        };
+
+        Ok(io::Seek::seek(self, pos)?)
+    }
+}
+
+impl std_io::Write for stdio::Stdout {
+    fn write(&mut self, buf: &[u8]) -> std_io::Result<usize> {
+        Ok(io::Write::write(self, buf)?)
+    }
+
+    fn flush(&mut self) -> std_io::Result<()> {
+        Ok(())
+    }
+}
+
+impl std_io::Write for stdio::Stderr {
+    fn write(&mut self, buf: &[u8]) -> std_io::Result<usize> {
+        Ok(io::Write::write(self, buf)?)
+    }
+
+    fn flush(&mut self) -> std_io::Result<()> {
+        Ok(())
+    }
+}
+
+impl std_io::Read for crate::net::TcpStream {
+    fn read(&mut self, buf: &mut [u8]) -> std_io::Result<usize> {
+        Ok(io::Read::read(self, buf)?)
+    }
+}
+
+impl std_io::Write for crate::net::TcpStream {
+    fn write(&mut self, buf: &[u8]) -> std_io::Result<usize> {
+        Ok(io::Write::write(self, buf)?)
+    }
+
+    fn flush(&mut self) -> std_io::Result<()> {
+        Ok(())
+    }
+}
+
+impl From<crate::net::Ipv4Addr> for std::net::Ipv4Addr {
+    fn from(addr: crate::net::Ipv4Addr) -> Self {
+        addr.octets().into()
+    }
+}
+
+impl From<std::net::Ipv4Addr> for crate::net::Ipv4Addr {
+    fn from(addr: std::net::Ipv4Addr) -> Self {
+        addr.octets().into()
+    }
+}
+
+impl From<crate::net::SocketAddrV4> for std::net::SocketAddrV4 {
+    fn from(addr: crate::net::SocketAddrV4) -> Self {
+        std::net::SocketAddrV4::new((*addr.ip()).into(), addr.port())
+    }
+}
+
+impl From<std::net::SocketAddrV4> for crate::net::SocketAddrV4 {
+    fn from(addr: std::net::SocketAddrV4) -> Self {
+        crate::net::SocketAddrV4::new((*addr.ip()).into(), addr.port())
+    }
+}
@@ -0,0 +1,68 @@
+//! Handles to the kernel's standard output and error streams.
+//!
+//! These write to the file descriptors returned by `sceKernelStdout` and
+//! `sceKernelStderr`, which are forwarded to the host by PSPLink and most
+//! emulators. Use `dprintln!` to print to the screen instead.
+
+use crate::io::{self, cvt, Write};
+use crate::sys::{self, SceUid};
+use core::{ffi::c_void, fmt};
+
+/// A handle to the standard output stream.
+#[derive(Debug, Copy, Clone)]
+pub struct Stdout {
+    _private: (),
+}
+
+/// A handle to the standard error stream.
+#[derive(Debug, Copy, Clone)]
+pub struct Stderr {
+    _private: (),
+}
+
+/// Returns a handle to the standard output stream.
+pub fn stdout() -> Stdout {
+    Stdout { _private: () }
+}
+
+/// Returns a handle to the standard error stream.
+pub fn stderr() -> Stderr {
+    Stderr { _private: () }
+}
+
+fn write_fd(fd: SceUid, buf: &[u8]) -> io::Result<usize> {
+    let ret = unsafe { sys::sceIoWrite(fd, buf.as_ptr() as *const c_void, buf.len()) };
+    cvt(ret).map(|n| n as usize)
+}
+
+impl Write for Stdout {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        write_fd(unsafe { sys::sceKernelStdout() }, buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Write for Stderr {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        write_fd(unsafe { sys::sceKernelStderr() }, buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl fmt::Write for Stdout {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.write_all(s.as_bytes()).map_err(|_| fmt::Error)
+    }
+}
+
+impl fmt::Write for Stderr {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.write_all(s.as_bytes()).map_err(|_| fmt::Error)
+    }
+}
@@ -0,0 +1,182 @@
+//! Synchronization primitives backed by kernel semaphores.
+
+use crate::error::{self, Result};
+use crate::sys::{self, SceUid};
+use core::cell::UnsafeCell;
+use core::ops::{Deref, DerefMut};
+use core::{ptr, time::Duration};
+
+/// Run `f` with interrupts suspended.
+///
+/// This is the only way to share data with interrupt handlers. Keep `f` as
+/// short as possible.
+pub(crate) fn critical_section<T>(f: impl FnOnce() -> T) -> T {
+    unsafe {
+        let flags = sys::sceKernelCpuSuspendIntr();
+        let ret = f();
+        sys::sceKernelCpuResumeIntr(flags);
+        ret
+    }
+}
+
+/// A mutual exclusion lock built on top of a binary kernel semaphore.
+///
+/// Unlike `std::sync::Mutex`, this does not support poisoning. The underlying
+/// semaphore is created in `new`, so this cannot be used in a `const` context.
+pub struct Mutex<T: ?Sized> {
+    sema: SceUid,
+    data: UnsafeCell<T>,
+}
+
+unsafe impl<T: ?Sized + Send> Send for Mutex<T> {}
+unsafe impl<T: ?Sized + Send> Sync for Mutex<T> {}
+
+impl<T> Mutex<T> {
+    /// Create a new, unlocked mutex.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the kernel refuses to create a semaphore.
+    pub fn new(value: T) -> Self {
+        let sema = unsafe {
+            sys::sceKernelCreateSema(&b"psp_mutex\0"[0], 0, 1, 1, ptr::null_mut())
+        };
+
+        if sema.0 < 0 {
+            panic!("Unable to create mutex semaphore: {:#x}", sema.0);
+        }
+
+        Self {
+            sema,
+            data: UnsafeCell::new(value),
+        }
+    }
+
+    /// Consume the mutex, returning the inner value.
+    pub fn into_inner(self) -> T {
+        let data = unsafe { ptr::read(self.data.get()) };
+
+        unsafe {
+            sys::sceKernelDeleteSema(self.sema);
+        }
+
+        core::mem::forget(self);
+        data
+    }
+}
+
+impl<T: ?Sized> Mutex<T> {
+    /// Block the current thread until the lock can be acquired.
+    pub fn lock(&self) -> MutexGuard<'_, T> {
+        unsafe {
+            sys::sceKernelWaitSema(self.sema, 1, ptr::null_mut());
+        }
+
+        MutexGuard { mutex: self }
+    }
+
+    /// Attempt to acquire the lock without blocking.
+    pub fn try_lock(&self) -> Option<MutexGuard<'_, T>> {
+        if unsafe { sys::sceKernelPollSema(self.sema, 1) } < 0 {
+            None
+        } else {
+            Some(MutexGuard { mutex: self })
+        }
+    }
+
+    /// Mutably borrow the inner value. No locking is required as the borrow
+    /// is statically guaranteed to be unique.
+    pub fn get_mut(&mut self) -> &mut T {
+        unsafe { &mut *self.data.get() }
+    }
+}
+
+impl<T: ?Sized> Drop for Mutex<T> {
+    fn drop(&mut self) {
+        unsafe {
+            sys::sceKernelDeleteSema(self.sema);
+        }
+    }
+}
+
+/// An RAII guard for a locked `Mutex`. The lock is released on drop.
+pub struct MutexGuard<'a, T: ?Sized> {
+    mutex: &'a Mutex<T>,
+}
+
+impl<T: ?Sized> Deref for MutexGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        unsafe { &*self.mutex.data.get() }
+    }
+}
+
+impl<T: ?Sized> DerefMut for MutexGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        unsafe { &mut *self.mutex.data.get() }
+    }
+}
+
+impl<T: ?Sized> Drop for MutexGuard<'_, T> {
+    fn drop(&mut self) {
+        unsafe {
+            sys::sceKernelSignalSema(self.mutex.sema, 1);
+        }
+    }
+}
+
+/// A counting semaphore.
+pub struct Semaphore {
+    id: SceUid,
+}
+
+impl Semaphore {
+    /// Create a semaphore with an initial and maximum count.
+    pub fn new(initial: i32, max: i32) -> Result<Self> {
+        let id = unsafe {
+            sys::sceKernelCreateSema(&b"psp_semaphore\0"[0], 0, initial, max, ptr::null_mut())
+        };
+
+        error::check_uid(id).map(|id| Self { id })
+    }
+
+    /// Increment the count by `count`, waking waiting threads.
+    pub fn signal(&self, count: i32) -> Result<()> {
+        error::check(unsafe { sys::sceKernelSignalSema(self.id, count) }).map(|_| ())
+    }
+
+    /// Block until the count is at least `count`, then decrement it.
+    pub fn wait(&self, count: i32) -> Result<()> {
+        error::check(unsafe { sys::sceKernelWaitSema(self.id, count, ptr::null_mut()) })
+            .map(|_| ())
+    }
+
+    /// Like `wait`, failing with `SCE_KERNEL_ERROR_WAIT_TIMEOUT` if the count
+    /// is not reached within `timeout`.
+    pub fn wait_timeout(&self, count: i32, timeout: Duration) -> Result<()> {
+        let mut timeout = timeout.as_micros() as u32;
+
+        error::check(unsafe { sys::sceKernelWaitSema(self.id, count, &mut timeout) })
+            .map(|_| ())
+    }
+
+    /// Decrement the count by `count` without blocking, failing with
+    /// `SCE_KERNEL_ERROR_SEMA_ZERO` if it is too low.
+    pub fn try_wait(&self, count: i32) -> Result<()> {
+        error::check(unsafe { sys::sceKernelPollSema(self.id, count) }).map(|_| ())
+    }
+
+    /// Returns the underlying semaphore id.
+    pub fn as_raw(&self) -> SceUid {
+        self.id
+    }
+}
+
+impl Drop for Semaphore {
+    fn drop(&mut self) {
+        unsafe {
+            sys::sceKernelDeleteSema(self.id);
+        }
+    }
+}
@@ -44,6 +44,7 @@ pub enum AudioOutputFrequency {
 }
 
 #[repr(i32)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum AudioInputFrequency {
     Khz44_1 = 44100,
     Khz22_05 = 22050,
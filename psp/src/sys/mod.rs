@@ -76,6 +76,8 @@ pub use mpeg::*;
 
 mod hprm;
 pub use hprm::*;
+// `registry` also exports a `Key`, which makes the glob imports ambiguous.
+pub use hprm::Key as HprmKey;
 
 mod gu;
 pub use gu::*;
@@ -14,6 +14,12 @@ impl SceMpeg {
     pub fn null() -> Self {
         Self(core::ptr::null_mut())
     }
+
+    /// Create a handle stored at `ptr`, which needs to be initialized with
+    /// `sceMpegCreate`.
+    pub fn from_ptr(ptr: *mut *mut c_void) -> Self {
+        Self(ptr)
+    }
 }
 
 /// Internal structure. Passed around but never created manually.
@@ -21,6 +27,13 @@ impl SceMpeg {
 #[derive(Copy, Clone, Debug)]
 pub struct SceMpegStream(*mut c_void);
 
+impl SceMpegStream {
+    /// Returns `true` if registering the stream failed.
+    pub fn is_null(&self) -> bool {
+        self.0.is_null()
+    }
+}
+
 /// Ringbuffer callback.
 pub type SceMpegRingbufferCb = Option<
     unsafe extern "C" fn(data: *mut c_void, num_packets: i32, param: *mut c_void) -> i32,
@@ -1,4 +1,5 @@
 use core::ffi::c_void;
+use num_enum::TryFromPrimitive;
 
 #[repr(C)]
 #[derive(Debug, Copy, Clone)]
@@ -1224,7 +1225,7 @@ psp_extern! {
 }
 
 #[repr(u32)]
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, TryFromPrimitive)]
 pub enum ApctlState {
     Disconnected,
     Scanning,
@@ -1236,7 +1237,7 @@ pub enum ApctlState {
 }
 
 #[repr(u32)]
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, TryFromPrimitive)]
 pub enum ApctlEvent {
     ConnectRequest,
     ScanRequest,
@@ -1441,6 +1442,108 @@ pub struct sockaddr {
     pub sa_data: [u8; 14],
 }
 
+/// An IPv4 socket address.
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct sockaddr_in {
+    /// Total length, `size_of::<sockaddr_in>()`
+    pub sin_len: u8,
+    /// Address family, always `AF_INET`
+    pub sin_family: u8,
+    /// Port, in network byte order
+    pub sin_port: u16,
+    /// Address, in network byte order
+    pub sin_addr: in_addr,
+    pub sin_zero: [u8; 8],
+}
+
+/// Number of descriptors an `fd_set` can hold.
+pub const FD_SETSIZE: usize = 256;
+
+/// A set of socket descriptors, for use with `sceNetInetSelect`.
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct fd_set {
+    pub fds_bits: [u32; FD_SETSIZE / 32],
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct timeval {
+    pub tv_sec: i32,
+    pub tv_usec: i32,
+}
+
+/// A descriptor to wait on, for use with `sceNetInetPoll`.
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct pollfd {
+    pub fd: i32,
+    /// Requested events, a combination of the `POLL*` flags
+    pub events: i16,
+    /// Returned events
+    pub revents: i16,
+}
+
+pub const AF_INET: i32 = 2;
+
+pub const SOCK_STREAM: i32 = 1;
+pub const SOCK_DGRAM: i32 = 2;
+pub const SOCK_RAW: i32 = 3;
+
+pub const IPPROTO_IP: i32 = 0;
+pub const IPPROTO_TCP: i32 = 6;
+pub const IPPROTO_UDP: i32 = 17;
+
+pub const INADDR_ANY: u32 = 0;
+pub const INADDR_BROADCAST: u32 = 0xffff_ffff;
+
+pub const SOL_SOCKET: i32 = 0xffff;
+
+pub const SO_DEBUG: i32 = 0x0001;
+pub const SO_ACCEPTCONN: i32 = 0x0002;
+pub const SO_REUSEADDR: i32 = 0x0004;
+pub const SO_KEEPALIVE: i32 = 0x0008;
+pub const SO_DONTROUTE: i32 = 0x0010;
+pub const SO_BROADCAST: i32 = 0x0020;
+pub const SO_LINGER: i32 = 0x0080;
+pub const SO_OOBINLINE: i32 = 0x0100;
+pub const SO_REUSEPORT: i32 = 0x0200;
+pub const SO_SNDBUF: i32 = 0x1001;
+pub const SO_RCVBUF: i32 = 0x1002;
+pub const SO_SNDLOWAT: i32 = 0x1003;
+pub const SO_RCVLOWAT: i32 = 0x1004;
+/// Send timeout, as a `u32` in microseconds
+pub const SO_SNDTIMEO: i32 = 0x1005;
+/// Receive timeout, as a `u32` in microseconds
+pub const SO_RCVTIMEO: i32 = 0x1006;
+pub const SO_ERROR: i32 = 0x1007;
+pub const SO_TYPE: i32 = 0x1008;
+/// Non-blocking mode, as an `i32` boolean (PSP specific)
+pub const SO_NONBLOCK: i32 = 0x1009;
+
+pub const TCP_NODELAY: i32 = 0x01;
+pub const TCP_MAXSEG: i32 = 0x02;
+
+pub const IP_TTL: i32 = 4;
+pub const IP_MULTICAST_TTL: i32 = 10;
+pub const IP_MULTICAST_LOOP: i32 = 11;
+
+pub const SHUT_RD: i32 = 0;
+pub const SHUT_WR: i32 = 1;
+pub const SHUT_RDWR: i32 = 2;
+
+pub const MSG_OOB: i32 = 0x01;
+pub const MSG_PEEK: i32 = 0x02;
+pub const MSG_DONTWAIT: i32 = 0x80;
+
+pub const POLLIN: i16 = 0x0001;
+pub const POLLPRI: i16 = 0x0002;
+pub const POLLOUT: i16 = 0x0004;
+pub const POLLERR: i16 = 0x0008;
+pub const POLLHUP: i16 = 0x0010;
+pub const POLLNVAL: i16 = 0x0020;
+
 psp_extern! {
     #![name = "sceNetInet"]
     #![flags = 0x0009]
@@ -1549,6 +1652,55 @@ psp_extern! {
     #[psp(0x8D7284EA)]
     pub fn sceNetInetClose(s: i32) -> i32;
 
+    #[psp(0x805502DD)]
+    pub fn sceNetInetCloseWithRST(s: i32) -> i32;
+
+    #[psp(0x162E6FD5)]
+    pub fn sceNetInetGetsockname(
+        s: i32,
+        name: *mut sockaddr,
+        name_len: *mut socklen_t,
+    ) -> i32;
+
+    #[psp(0xE247B6D6)]
+    pub fn sceNetInetGetpeername(
+        s: i32,
+        name: *mut sockaddr,
+        name_len: *mut socklen_t,
+    ) -> i32;
+
+    #[psp(0x5BE8D595)]
+    /// Wait for descriptors to become ready.
+    ///
+    /// # Return Value
+    ///
+    /// The number of ready descriptors, 0 on timeout, < 0 on error.
+    pub fn sceNetInetSelect(
+        nfds: i32,
+        readfds: *mut fd_set,
+        writefds: *mut fd_set,
+        exceptfds: *mut fd_set,
+        timeout: *mut timeval,
+    ) -> i32;
+
+    #[psp(0xFAABB1DD)]
+    /// Wait for events on descriptors.
+    ///
+    /// # Parameters
+    ///
+    /// - `fds`: Array of descriptors to wait on.
+    /// - `nfds`: Number of entries in `fds`.
+    /// - `timeout`: Timeout in milliseconds, or -1 to wait forever.
+    ///
+    /// # Return Value
+    ///
+    /// The number of ready descriptors, 0 on timeout, < 0 on error.
+    pub fn sceNetInetPoll(
+        fds: *mut pollfd,
+        nfds: u32,
+        timeout: i32,
+    ) -> i32;
+
     #[psp(0xFBABE411)]
     pub fn sceNetInetGetErrno() -> i32;
 
@@ -1607,7 +1759,7 @@ psp_extern! {
 }
 
 #[repr(u32)]
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum HttpMethod {
     Get,
     Post,
@@ -2171,7 +2323,9 @@ psp_extern! {
 
 }
 
+/// An IPv4 address, in network byte order.
 #[repr(C)]
+#[derive(Debug, Copy, Clone)]
 pub struct in_addr(pub u32);
 
 psp_extern! {
@@ -33,7 +33,7 @@ pub enum UtilityMsgDialogMode {
 }
 
 #[repr(u32)]
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum UtilityMsgDialogPressed {
     Unknown1,
     Yes,
@@ -86,6 +86,7 @@ pub enum SceUtilityOskInputType {
     Url = 0x80000,
 }
 
+#[repr(u32)]
 #[derive(Debug, Clone, Copy)]
 pub enum SceUtilityOskState {
     None,
@@ -96,7 +97,8 @@ pub enum SceUtilityOskState {
     Finished,
 }
 
-#[derive(Debug, Clone, Copy)]
+#[repr(u32)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum SceUtilityOskResult {
     Unchanged,
     Cancelled,
@@ -178,7 +180,7 @@ pub enum SystemParamDaylightSavings {
 }
 
 #[repr(u32)]
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum AvModule {
     AvCodec,
     SasCore,
@@ -193,12 +195,13 @@ pub enum AvModule {
 }
 
 #[repr(u32)]
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Module {
     NetCommon = 0x100,
     NetAdhoc,
     NetInet,
     NetParseUri,
+    NetParseHttp,
     NetHttp,
     NetSsl,
 
@@ -225,18 +228,19 @@ pub enum Module {
 }
 
 #[repr(u32)]
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum NetModule {
     NetCommon = 1,
     NetAdhoc,
     NetInet,
     NetParseUri,
+    NetParseHttp,
     NetHttp,
     NetSsl,
 }
 
 #[repr(u32)]
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum UsbModule {
     UsbPspCm = 1,
     UsbAcc,
@@ -289,6 +293,7 @@ pub enum NetParam {
     Unknown2,
 }
 
+#[repr(u32)]
 #[derive(Debug, Copy, Clone)]
 pub enum UtilityNetconfAction {
     ConnectAP,
@@ -367,6 +372,13 @@ pub enum UtilitySavedataMode {
     ListSave,
     ListDelete,
     Delete,
+    /// Report the space used and free on the memory stick.
+    Sizes,
+    /// Delete the named save without a dialog. Requires firmware 2.00 or
+    /// later.
+    AutoDelete,
+    /// Delete the named save after confirmation in a dialog.
+    SingleDelete,
 }
 
 #[repr(u32)]
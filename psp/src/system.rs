@@ -0,0 +1,192 @@
+//! Typed access to the system settings.
+//!
+//! These are the values the user picked in the XMB, read with
+//! `sceUtilityGetSystemParamInt` and `sceUtilityGetSystemParamString`. They
+//! can be used to localize an application and to follow the console's choice
+//! of confirm button.
+
+use crate::error::{self, Result};
+use crate::sys::{
+    self, SystemParamAdhocChannel, SystemParamDateFormat, SystemParamDaylightSavings,
+    SystemParamId, SystemParamLanguage, SystemParamTimeFormat, SystemParamWlanPowerSaveState,
+    UtilityDialogButtonAccept,
+};
+use alloc::string::String;
+use core::convert::TryFrom;
+use core::time::Duration;
+
+/// The button used to confirm in menus and dialogs.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ConfirmButton {
+    /// Circle confirms and cross cancels, as on Japanese consoles.
+    Circle,
+    /// Cross confirms and circle cancels.
+    Cross,
+}
+
+impl ConfirmButton {
+    /// The button used to cancel, which is the other one.
+    pub fn cancel_button(self) -> Self {
+        match self {
+            ConfirmButton::Circle => ConfirmButton::Cross,
+            ConfirmButton::Cross => ConfirmButton::Circle,
+        }
+    }
+}
+
+impl From<ConfirmButton> for UtilityDialogButtonAccept {
+    fn from(button: ConfirmButton) -> Self {
+        match button {
+            ConfirmButton::Circle => UtilityDialogButtonAccept::Circle,
+            ConfirmButton::Cross => UtilityDialogButtonAccept::Cross,
+        }
+    }
+}
+
+/// The offset of the local time zone from UTC.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct UtcOffset {
+    minutes: i32,
+}
+
+impl UtcOffset {
+    /// An offset of `minutes`, positive east of UTC.
+    pub const fn from_minutes(minutes: i32) -> Self {
+        Self { minutes }
+    }
+
+    /// The offset in minutes, positive east of UTC.
+    pub const fn minutes(&self) -> i32 {
+        self.minutes
+    }
+
+    /// The size of the offset. Use `is_negative` for its direction.
+    pub fn as_duration(&self) -> Duration {
+        Duration::from_secs((self.minutes as i64).abs() as u64 * 60)
+    }
+
+    /// Returns `true` if local time is behind UTC.
+    pub const fn is_negative(&self) -> bool {
+        self.minutes < 0
+    }
+}
+
+/// All system settings, read at once.
+#[derive(Debug, Clone)]
+pub struct Settings {
+    /// The nickname of the console's owner.
+    pub nickname: String,
+    /// The system language.
+    pub language: SystemParamLanguage,
+    /// The button used to confirm.
+    pub confirm_button: ConfirmButton,
+    /// How dates are written.
+    pub date_format: SystemParamDateFormat,
+    /// Whether the clock shows 12 or 24 hours.
+    pub time_format: SystemParamTimeFormat,
+    /// The offset of the time zone from UTC, without daylight saving time.
+    pub timezone: UtcOffset,
+    /// Whether daylight saving time is in effect.
+    pub daylight_savings: SystemParamDaylightSavings,
+    /// The channel used for ad-hoc networking.
+    pub adhoc_channel: SystemParamAdhocChannel,
+    /// Whether the wireless adapter saves power.
+    pub wlan_power_save: SystemParamWlanPowerSaveState,
+}
+
+impl Settings {
+    /// Read all system settings.
+    pub fn read() -> Result<Self> {
+        Ok(Self {
+            nickname: nickname()?,
+            language: language()?,
+            confirm_button: confirm_button()?,
+            date_format: date_format()?,
+            time_format: time_format()?,
+            timezone: timezone()?,
+            daylight_savings: daylight_savings()?,
+            adhoc_channel: adhoc_channel()?,
+            wlan_power_save: wlan_power_save()?,
+        })
+    }
+
+    /// The local offset from UTC, including daylight saving time.
+    pub fn local_offset(&self) -> UtcOffset {
+        match self.daylight_savings {
+            SystemParamDaylightSavings::Dst => {
+                UtcOffset::from_minutes(self.timezone.minutes + 60)
+            }
+            SystemParamDaylightSavings::Std => self.timezone,
+        }
+    }
+}
+
+fn get_int(id: SystemParamId) -> Result<i32> {
+    let mut value = 0;
+    error::check(unsafe { sys::sceUtilityGetSystemParamInt(id, &mut value) })?;
+
+    Ok(value)
+}
+
+fn get_enum<T: TryFrom<u32>>(id: SystemParamId) -> Result<T> {
+    T::try_from(get_int(id)? as u32).map_err(|_| error::SCE_ERROR_ERRNO_EINVAL)
+}
+
+/// The nickname of the console's owner.
+pub fn nickname() -> Result<String> {
+    let mut buf = [0u8; 128];
+    error::check(unsafe {
+        sys::sceUtilityGetSystemParamString(
+            SystemParamId::StringNickname,
+            buf.as_mut_ptr(),
+            buf.len() as i32,
+        )
+    })?;
+
+    let len = buf.iter().position(|&b| b == 0).unwrap_or(buf.len());
+    Ok(String::from_utf8_lossy(&buf[..len]).into_owned())
+}
+
+/// The system language.
+pub fn language() -> Result<SystemParamLanguage> {
+    get_enum(SystemParamId::Language)
+}
+
+/// The button used to confirm in menus and dialogs.
+pub fn confirm_button() -> Result<ConfirmButton> {
+    // Parameter 9 is the button swap setting, 0 on Japanese consoles.
+    match get_int(SystemParamId::Unknown)? {
+        0 => Ok(ConfirmButton::Circle),
+        _ => Ok(ConfirmButton::Cross),
+    }
+}
+
+/// How dates are written.
+pub fn date_format() -> Result<SystemParamDateFormat> {
+    get_enum(SystemParamId::DateFormat)
+}
+
+/// Whether the clock shows 12 or 24 hours.
+pub fn time_format() -> Result<SystemParamTimeFormat> {
+    get_enum(SystemParamId::TimeFormat)
+}
+
+/// The offset of the time zone from UTC, without daylight saving time.
+pub fn timezone() -> Result<UtcOffset> {
+    get_int(SystemParamId::Timezone).map(UtcOffset::from_minutes)
+}
+
+/// Whether daylight saving time is in effect.
+pub fn daylight_savings() -> Result<SystemParamDaylightSavings> {
+    get_enum(SystemParamId::DaylightSavings)
+}
+
+/// The channel used for ad-hoc networking.
+pub fn adhoc_channel() -> Result<SystemParamAdhocChannel> {
+    get_enum(SystemParamId::AdhocChannel)
+}
+
+/// Whether the wireless adapter saves power.
+pub fn wlan_power_save() -> Result<SystemParamWlanPowerSaveState> {
+    get_enum(SystemParamId::WlanPowerSave)
+}
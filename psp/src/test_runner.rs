@@ -1,3 +1,4 @@
+use crate::error;
 use crate::sys::{self, SceUid};
 use core::ffi::c_void;
 
@@ -9,6 +10,7 @@ pub const SUCCESS_TOKEN: &str = "FINAL_SUCCESS";
 pub const FAILURE_TOKEN: &str = "FINAL_FAILURE";
 
 use alloc::format;
+use alloc::string::String;
 use alloc::vec::Vec;
 use core::fmt::Arguments;
 
@@ -193,38 +195,43 @@ impl<'a> TestRunner<'a> {
 }
 
 fn get_test_output_pipe() -> SceUid {
-    unsafe {
-        let fd = sys::sceIoOpen(
-            psp_filename(OUTPUT_FIFO),
+    let filename = psp_filename(OUTPUT_FIFO);
+    let fd = unsafe {
+        sys::sceIoOpen(
+            filename.as_ptr(),
             sys::IoOpenFlags::APPEND | sys::IoOpenFlags::WR_ONLY,
             0o777,
-        );
-        if fd.0 < 0 {
-            panic!(
-                "Unable to open pipe \"{}\" for output! \
-                You must create it yourself with `mkfifo`."
-            );
-        }
-        return fd;
+        )
+    };
+
+    match error::check_uid(fd) {
+        Ok(fd) => fd,
+        Err(e) => panic!(
+            "Unable to open pipe \"{}\" for output ({})! \
+            You must create it yourself with `mkfifo`.",
+            OUTPUT_FIFO, e,
+        ),
     }
 }
 
 fn get_test_output_file() -> SceUid {
-    unsafe {
-        let fd = sys::sceIoOpen(
-            psp_filename(OUTPUT_FILENAME),
+    let filename = psp_filename(OUTPUT_FILENAME);
+    let fd = unsafe {
+        sys::sceIoOpen(
+            filename.as_ptr(),
             sys::IoOpenFlags::TRUNC | sys::IoOpenFlags::CREAT | sys::IoOpenFlags::RD_WR,
             0o777,
-        );
-        if fd.0 < 0 {
-            panic!("Unable to open file \"{}\" for output!", OUTPUT_FILENAME);
-        }
-        return fd;
+        )
+    };
+
+    match error::check_uid(fd) {
+        Ok(fd) => fd,
+        Err(e) => panic!("Unable to open file \"{}\" for output ({})!", OUTPUT_FILENAME, e),
     }
 }
 
-fn psp_filename(filename: &str) -> *const u8 {
-    format!("host0:/{}\0", filename).as_bytes().as_ptr()
+fn psp_filename(filename: &str) -> String {
+    format!("host0:/{}\0", filename)
 }
 
 fn write_to_psp_output_fd(fd: SceUid, msg: &str) {
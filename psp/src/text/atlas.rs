@@ -0,0 +1,117 @@
+//! The texture glyphs are rasterized into.
+
+use crate::image::AlignedBytes;
+use crate::sys::{self, ClutPixelFormat, MipmapLevel, TexturePixelFormat};
+use core::ffi::c_void;
+
+/// Width and height of the atlas, in pixels.
+pub(super) const ATLAS_SIZE: usize = 512;
+
+/// Space left around each glyph, so that filtering does not pick up its
+/// neighbours.
+const PADDING: usize = 1;
+
+/// A `PsmT8` texture of glyph coverage, filled row by row.
+///
+/// Each pixel is an alpha value, looked up in a CLUT of white with
+/// increasing alpha, so that the texture function can tint it.
+pub(super) struct Atlas {
+    pixels: AlignedBytes,
+    clut: AlignedBytes,
+    /// Where the next glyph goes, and the height of the current row.
+    x: usize,
+    y: usize,
+    row_height: usize,
+    /// Set when glyphs were drawn since the last writeback.
+    dirty: bool,
+}
+
+impl Atlas {
+    pub(super) fn new() -> Self {
+        let mut clut = AlignedBytes::new(256 * 4);
+        for (alpha, entry) in clut.as_mut_slice().chunks_exact_mut(4).enumerate() {
+            entry.copy_from_slice(&[0xff, 0xff, 0xff, alpha as u8]);
+        }
+
+        Self {
+            pixels: AlignedBytes::new(ATLAS_SIZE * ATLAS_SIZE),
+            clut,
+            x: 0,
+            y: 0,
+            row_height: 0,
+            dirty: true,
+        }
+    }
+
+    /// Reserve a `width` by `height` area, returning its top left corner, or
+    /// `None` if the atlas is full.
+    pub(super) fn allocate(&mut self, width: usize, height: usize) -> Option<(usize, usize)> {
+        let (width, height) = (width + PADDING, height + PADDING);
+        if width > ATLAS_SIZE || height > ATLAS_SIZE {
+            return None;
+        }
+
+        if self.x + width > ATLAS_SIZE {
+            self.x = 0;
+            self.y += self.row_height;
+            self.row_height = 0;
+        }
+
+        if self.y + height > ATLAS_SIZE {
+            return None;
+        }
+
+        let position = (self.x, self.y);
+        self.x += width;
+        self.row_height = core::cmp::max(self.row_height, height);
+        self.dirty = true;
+
+        Some(position)
+    }
+
+    /// Forget every glyph and clear the pixels.
+    pub(super) fn clear(&mut self) {
+        for pixel in self.pixels.as_mut_slice() {
+            *pixel = 0;
+        }
+
+        self.x = 0;
+        self.y = 0;
+        self.row_height = 0;
+        self.dirty = true;
+    }
+
+    /// The pixels, for the font library to draw into.
+    pub(super) fn as_mut_ptr(&mut self) -> *mut u8 {
+        self.pixels.as_mut_slice().as_mut_ptr()
+    }
+
+    /// Write back new glyphs from the data cache, then set the atlas and its
+    /// CLUT as the current texture.
+    pub(super) unsafe fn bind(&mut self) {
+        if self.dirty {
+            let pixels = self.pixels.as_slice();
+            sys::sceKernelDcacheWritebackRange(
+                pixels.as_ptr() as *const c_void,
+                pixels.len() as u32,
+            );
+
+            let clut = self.clut.as_slice();
+            sys::sceKernelDcacheWritebackRange(clut.as_ptr() as *const c_void, clut.len() as u32);
+
+            self.dirty = false;
+        }
+
+        sys::sceGuClutMode(ClutPixelFormat::Psm8888, 0, 0xff, 0);
+        sys::sceGuClutLoad(256 / 8, self.clut.as_slice().as_ptr() as *const c_void);
+        sys::sceGuTexMode(TexturePixelFormat::PsmT8, 0, 0, 0);
+        sys::sceGuTexImage(
+            MipmapLevel::None,
+            ATLAS_SIZE as i32,
+            ATLAS_SIZE as i32,
+            ATLAS_SIZE as i32,
+            self.pixels.as_slice().as_ptr() as *const c_void,
+        );
+        sys::sceGuTexFlush();
+    }
+}
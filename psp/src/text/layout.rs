@@ -0,0 +1,223 @@
+//! Measuring and laying out text, independent of the font library.
+
+use alloc::vec::Vec;
+
+/// Horizontal alignment of the lines of a `Layout`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Align {
+    /// Lines start at the left edge.
+    Left,
+    /// Lines are centered.
+    Center,
+    /// Lines end at the right edge.
+    Right,
+}
+
+/// The metrics of a font, in pixels.
+pub trait GlyphMetrics {
+    /// How far the pen moves after drawing `c`.
+    fn advance(&mut self, c: char) -> f32;
+
+    /// An adjustment to the advance of `left` when it is followed by `right`.
+    fn kerning(&mut self, _left: char, _right: char) -> f32 {
+        0.0
+    }
+
+    /// The distance from the top of a line to its baseline.
+    fn ascender(&self) -> f32;
+
+    /// The distance between the baselines of consecutive lines.
+    fn line_height(&self) -> f32;
+}
+
+/// How to lay out text.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct LayoutOptions {
+    /// Wrap lines longer than this, between words or around CJK characters.
+    pub max_width: Option<f32>,
+    /// Alignment of each line, within `max_width` if set, or else within the
+    /// longest line.
+    pub align: Align,
+    /// Extra space added after every character.
+    pub letter_spacing: f32,
+    /// A multiple of the font's line height.
+    pub line_spacing: f32,
+}
+
+impl Default for LayoutOptions {
+    fn default() -> Self {
+        Self {
+            max_width: None,
+            align: Align::Left,
+            letter_spacing: 0.0,
+            line_spacing: 1.0,
+        }
+    }
+}
+
+/// A character placed by `layout`.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct PositionedGlyph {
+    /// The character drawn.
+    pub c: char,
+    /// The pen position, relative to the top left corner of the layout.
+    pub x: f32,
+    /// The baseline, relative to the top left corner of the layout.
+    pub y: f32,
+}
+
+/// Text broken into lines, with the position of every visible character.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Layout {
+    glyphs: Vec<PositionedGlyph>,
+    lines: usize,
+    width: f32,
+    height: f32,
+}
+
+impl Layout {
+    /// The visible characters, in order. Whitespace is left out.
+    pub fn glyphs(&self) -> &[PositionedGlyph] {
+        &self.glyphs
+    }
+
+    /// The number of lines, after wrapping.
+    pub fn line_count(&self) -> usize {
+        self.lines
+    }
+
+    /// The width of the longest line.
+    pub fn width(&self) -> f32 {
+        self.width
+    }
+
+    /// The height of all lines.
+    pub fn height(&self) -> f32 {
+        self.height
+    }
+}
+
+/// Break `text` into lines and place its characters.
+///
+/// Lines end at each `'\n'`, and are wrapped to `options.max_width` at
+/// whitespace or next to CJK characters, which need no spaces between words.
+/// Words longer than a line are broken anywhere.
+pub fn layout<M: GlyphMetrics + ?Sized>(
+    metrics: &mut M,
+    text: &str,
+    options: &LayoutOptions,
+) -> Layout {
+    let max_width = options.max_width.unwrap_or(f32::INFINITY);
+    let mut lines: Vec<(Vec<(char, f32)>, f32)> = Vec::new();
+
+    for paragraph in text.split('\n') {
+        let paragraph = paragraph.trim_end_matches('\r');
+
+        let chars: Vec<char> = paragraph.chars().collect();
+        let mut items: Vec<(char, f32)> = Vec::with_capacity(chars.len());
+        for (i, &c) in chars.iter().enumerate() {
+            let mut advance = metrics.advance(c) + options.letter_spacing;
+            if let Some(&next) = chars.get(i + 1) {
+                advance += metrics.kerning(c, next);
+            }
+
+            items.push((c, advance));
+        }
+
+        let mut start = 0;
+        loop {
+            let (end, next) = break_line(&items, start, max_width);
+
+            let line = &items[start..end];
+            let visible = line
+                .iter()
+                .rposition(|&(c, _)| !c.is_whitespace())
+                .map_or(0, |i| i + 1);
+            let width = line[..visible].iter().map(|&(_, advance)| advance).sum();
+            lines.push((line.to_vec(), width));
+
+            if next >= items.len() {
+                break;
+            }
+            start = next;
+        }
+    }
+
+    let width = lines
+        .iter()
+        .map(|&(_, width)| width)
+        .fold(0.0, |a: f32, b| if b > a { b } else { a });
+    let area = options.max_width.unwrap_or(width);
+    let line_height = metrics.line_height() * options.line_spacing;
+    let ascender = metrics.ascender();
+
+    let mut glyphs = Vec::new();
+    for (i, (line, line_width)) in lines.iter().enumerate() {
+        let mut x = match options.align {
+            Align::Left => 0.0,
+            Align::Center => (area - line_width) / 2.0,
+            Align::Right => area - line_width,
+        };
+        let y = ascender + i as f32 * line_height;
+
+        for &(c, advance) in line {
+            if !c.is_whitespace() {
+                glyphs.push(PositionedGlyph { c, x, y });
+            }
+            x += advance;
+        }
+    }
+
+    Layout {
+        glyphs,
+        lines: lines.len(),
+        width,
+        height: lines.len() as f32 * line_height,
+    }
+}
+
+/// Find where the line starting at `start` ends, and where the next one
+/// starts, after the whitespace it wraps at.
+fn break_line(items: &[(char, f32)], start: usize, max_width: f32) -> (usize, usize) {
+    let mut x = 0.0;
+    let mut end = start;
+    let mut last_break = None;
+
+    while end < items.len() {
+        let (c, advance) = items[end];
+
+        // Whitespace may hang past the end of the line.
+        if !c.is_whitespace() && x + advance > max_width && end > start {
+            break;
+        }
+
+        x += advance;
+        end += 1;
+
+        let next = items.get(end).map(|&(c, _)| c);
+        if c.is_whitespace() || is_wide(c) || next.map_or(false, is_wide) {
+            last_break = Some(end);
+        }
+    }
+
+    if end < items.len() {
+        if let Some(at) = last_break {
+            end = at;
+        }
+    }
+
+    let mut next = end;
+    while next < items.len() && items[next].0.is_whitespace() {
+        next += 1;
+    }
+
+    (end, next)
+}
+
+/// Returns `true` for CJK characters, which lines can be broken around.
+fn is_wide(c: char) -> bool {
+    matches!(
+        c as u32,
+        0x1100..=0x11ff | 0x2e80..=0xa4cf | 0xac00..=0xd7a3 | 0xf900..=0xfaff | 0xff00..=0xffef
+    )
+}
@@ -0,0 +1,580 @@
+//! Text rendering with the system fonts.
+//!
+//! The firmware comes with PGF fonts for Latin and Japanese text, which
+//! `sceLibFont` rasterizes. A `FontLib` loads the library, and a `Font` opens
+//! the system font closest to a `SceFontStyle`. Fonts cache the glyphs they
+//! rasterize in a texture atlas, lay out UTF-8 strings and draw them as GU
+//! sprites.
+//!
+//! The pixel size of glyphs depends on the resolution set on the `FontLib`.
+//!
+//! # Example
+//!
+//! ```ignore
+//! use psp::sys::{SceFontFamilyCode, SceFontLanguageCode, SceFontStyleCode};
+//! use psp::text::{Align, Font, FontLib, LayoutOptions};
+//!
+//! let lib = FontLib::new(4)?;
+//! let mut font = Font::find(
+//!     &lib,
+//!     SceFontFamilyCode::SansSerif,
+//!     SceFontStyleCode::Regular,
+//!     SceFontLanguageCode::Latin,
+//! )?;
+//!
+//! let options = LayoutOptions {
+//!     max_width: Some(200.0),
+//!     align: Align::Center,
+//!     ..LayoutOptions::default()
+//! };
+//!
+//! // Inside a display list:
+//! unsafe { font.draw("Hello, world!", 140.0, 100.0, 0xff_ff_ff_ff, &options) };
+//! # Ok::<(), psp::Error>(())
+//! ```
+
+mod atlas;
+mod layout;
+
+pub use layout::{layout, Align, GlyphMetrics, Layout, LayoutOptions, PositionedGlyph};
+
+use crate::error::{self, Error, Result};
+use crate::sync::critical_section;
+use crate::sys::{
+    self, GuPrimitive, GuState, SceFontCharInfo, SceFontErrorCode, SceFontFamilyCode,
+    SceFontGlyphImage, SceFontInfo, SceFontLanguageCode, SceFontNewLibParams,
+    SceFontPixelFormatCode, SceFontStyle, SceFontStyleCode, TextureColorComponent, TextureEffect,
+    TextureFilter, VertexType,
+};
+use alloc::alloc::{alloc, dealloc, Layout as AllocLayout};
+use alloc::boxed::Box;
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+use atlas::{Atlas, ATLAS_SIZE};
+use core::ffi::c_void;
+use core::{mem, ptr};
+
+/// The firmware module that provides `sceLibFont`.
+const LIBFONT_PATH: &[u8] = b"flash0:/vsh/module/libfont_hv.prx\0";
+
+/// Set once the font library module is loaded.
+static mut LIBFONT_LOADED: bool = false;
+
+/// Load and start the font library module, if it is not loaded yet.
+fn load_libfont() -> Result<()> {
+    if critical_section(|| unsafe { LIBFONT_LOADED }) {
+        return Ok(());
+    }
+
+    unsafe {
+        match error::check_uid(sys::sceKernelLoadModule(
+            LIBFONT_PATH.as_ptr(),
+            0,
+            ptr::null_mut(),
+        )) {
+            Ok(id) => {
+                let mut status = 0;
+                error::check(sys::sceKernelStartModule(
+                    id,
+                    0,
+                    ptr::null_mut(),
+                    &mut status,
+                    ptr::null_mut(),
+                ))?;
+            }
+            // Someone else loaded it.
+            Err(error::SCE_KERNEL_ERROR_EXCLUSIVE_LOAD) => {}
+            Err(e) => return Err(e),
+        }
+    }
+
+    critical_section(|| unsafe { LIBFONT_LOADED = true });
+
+    Ok(())
+}
+
+/// Turn the error code the font functions report into a `Result`.
+fn check_font(code: SceFontErrorCode) -> Result<()> {
+    match code {
+        SceFontErrorCode::Success => Ok(()),
+        code => Err(Error::from_raw(code as i32)),
+    }
+}
+
+/// Allocations made by the font library store their size in front of them.
+const HEADER_SIZE: usize = 16;
+
+extern "C" fn font_alloc(_data: *mut c_void, size: usize) -> *mut c_void {
+    let layout = match AllocLayout::from_size_align(size + HEADER_SIZE, HEADER_SIZE) {
+        Ok(layout) => layout,
+        Err(_) => return ptr::null_mut(),
+    };
+
+    unsafe {
+        let block = alloc(layout);
+        if block.is_null() {
+            return ptr::null_mut();
+        }
+
+        *(block as *mut usize) = size;
+        block.add(HEADER_SIZE) as *mut c_void
+    }
+}
+
+extern "C" fn font_free(_data: *mut c_void, memory: *mut c_void) {
+    if memory.is_null() {
+        return;
+    }
+
+    unsafe {
+        let block = (memory as *mut u8).sub(HEADER_SIZE);
+        let size = *(block as *mut usize);
+        dealloc(
+            block,
+            AllocLayout::from_size_align_unchecked(size + HEADER_SIZE, HEADER_SIZE),
+        );
+    }
+}
+
+/// An instance of the font library, which fonts are opened from.
+pub struct FontLib {
+    handle: u32,
+    _params: Box<SceFontNewLibParams>,
+}
+
+impl FontLib {
+    /// Load the font library, with room for up to `max_fonts` open fonts.
+    pub fn new(max_fonts: u32) -> Result<Self> {
+        load_libfont()?;
+
+        let params = Box::new(SceFontNewLibParams {
+            user_data_addr: 0,
+            num_fonts: max_fonts,
+            cache_data: 0,
+            alloc_func: Some(font_alloc),
+            free_func: Some(font_free),
+            open_func: None,
+            close_func: None,
+            read_func: None,
+            seek_func: None,
+            error_func: None,
+            io_finish_func: None,
+        });
+
+        let mut code = SceFontErrorCode::Success;
+        let handle = unsafe { sys::sceFontNewLib(&params, &mut code) };
+        check_font(code)?;
+
+        Ok(Self {
+            handle,
+            _params: params,
+        })
+    }
+
+    /// Set the resolution glyphs are rasterized at, in dots per inch. This
+    /// affects fonts opened afterwards.
+    pub fn set_resolution(&self, horizontal: f32, vertical: f32) -> Result<()> {
+        error::check(unsafe { sys::sceFontSetResolution(self.handle, horizontal, vertical) })?;
+
+        Ok(())
+    }
+
+    /// The styles of the installed fonts.
+    pub fn fonts(&self) -> Result<Vec<SceFontStyle>> {
+        let mut code = SceFontErrorCode::Success;
+        let count = unsafe { sys::sceFontGetNumFontList(self.handle, &mut code) };
+        check_font(code)?;
+        let count = error::check(count)? as usize;
+
+        let mut styles = Vec::with_capacity(count);
+        unsafe {
+            error::check(sys::sceFontGetFontList(
+                self.handle,
+                styles.as_mut_ptr(),
+                count as i32,
+            ))?;
+            styles.set_len(count);
+        }
+
+        Ok(styles)
+    }
+}
+
+impl Drop for FontLib {
+    fn drop(&mut self) {
+        unsafe {
+            sys::sceFontDoneLib(self.handle);
+        }
+    }
+}
+
+/// The metrics of a character, and where it is in the atlas.
+#[derive(Debug, Copy, Clone)]
+struct Glyph {
+    advance: f32,
+    /// The space between the pen position and the left of the ink, and
+    /// between the right of the ink and the next pen position.
+    bearings: (f32, f32),
+    left: i32,
+    top: i32,
+    width: usize,
+    height: usize,
+    cell: Option<(usize, usize)>,
+}
+
+impl Glyph {
+    const BLANK: Glyph = Glyph {
+        advance: 0.0,
+        bearings: (0.0, 0.0),
+        left: 0,
+        top: 0,
+        width: 0,
+        height: 0,
+        cell: None,
+    };
+}
+
+/// A vertex of a glyph sprite.
+#[repr(C, align(4))]
+struct Vertex {
+    u: f32,
+    v: f32,
+    x: f32,
+    y: f32,
+    z: f32,
+}
+
+/// The character whose spacing `Font` kerns other pairs against.
+const SPACING_CHAR: char = 'n';
+
+/// An open system font, with its cache of rasterized glyphs.
+///
+/// The system fonts have no kerning tables, so pairs are kerned by their
+/// ink: where the space between the ink of two characters is more than
+/// twice that between two n's, it is narrowed to that. Pairs whose shapes
+/// overlap, such as "AV", are left as they are.
+pub struct Font<'a> {
+    lib: &'a FontLib,
+    handle: u32,
+    info: SceFontInfo,
+    glyphs: BTreeMap<char, Glyph>,
+    atlas: Atlas,
+    /// The space between the ink of two `SPACING_CHAR`s, once known.
+    spacing: Option<f32>,
+}
+
+impl<'a> Font<'a> {
+    /// Open the installed font that best matches `style`. Fields of `style`
+    /// left zero match any font.
+    pub fn open(lib: &'a FontLib, style: &SceFontStyle) -> Result<Self> {
+        let mut code = SceFontErrorCode::Success;
+        let index = unsafe { sys::sceFontFindOptimumFont(lib.handle, style, &mut code) };
+        check_font(code)?;
+        let index = error::check(index)?;
+
+        let handle = unsafe { sys::sceFontOpen(lib.handle, index as u32, 0, &mut code) };
+        check_font(code)?;
+
+        let mut info: SceFontInfo = unsafe { mem::zeroed() };
+        if let Err(e) = error::check(unsafe { sys::sceFontGetFontInfo(handle, &mut info) }) {
+            unsafe { sys::sceFontClose(handle) };
+            return Err(e);
+        }
+
+        Ok(Self {
+            lib,
+            handle,
+            info,
+            glyphs: BTreeMap::new(),
+            atlas: Atlas::new(),
+            spacing: None,
+        })
+    }
+
+    /// Open the installed font closest to a family, style and language.
+    pub fn find(
+        lib: &'a FontLib,
+        family: SceFontFamilyCode,
+        style: SceFontStyleCode,
+        language: SceFontLanguageCode,
+    ) -> Result<Self> {
+        let mut font_style: SceFontStyle = unsafe { mem::zeroed() };
+        font_style.font_family = family;
+        font_style.font_style = style;
+        font_style.font_language = language;
+
+        Self::open(lib, &font_style)
+    }
+
+    /// The library the font was opened from.
+    pub fn lib(&self) -> &'a FontLib {
+        self.lib
+    }
+
+    /// Information about the font, such as its style and largest glyphs.
+    pub fn info(&self) -> &SceFontInfo {
+        &self.info
+    }
+
+    /// Lay out `text`.
+    pub fn layout(&mut self, text: &str, options: &LayoutOptions) -> Layout {
+        layout(self, text, options)
+    }
+
+    /// The width and height of `text`, laid out with `options`.
+    pub fn measure(&mut self, text: &str, options: &LayoutOptions) -> (f32, f32) {
+        let layout = self.layout(text, options);
+
+        (layout.width(), layout.height())
+    }
+
+    /// Draw `text` with its top left corner at `x`, `y`, tinted by `color`.
+    ///
+    /// # Safety
+    ///
+    /// See `draw_layout`.
+    pub unsafe fn draw(&mut self, text: &str, x: f32, y: f32, color: u32, options: &LayoutOptions) {
+        let layout = self.layout(text, options);
+        self.draw_layout(&layout, x, y, color);
+    }
+
+    /// Draw text laid out by this font with its top left corner at `x`, `y`,
+    /// tinted by `color`.
+    ///
+    /// This enables texturing and alpha blending, and sets the texture,
+    /// texture function and filter.
+    ///
+    /// # Safety
+    ///
+    /// This must be called while building a display list. If the atlas fills
+    /// up it is cleared, so glyphs drawn earlier in the same frame may be
+    /// replaced; the list must be finished before the next frame's text is
+    /// drawn.
+    pub unsafe fn draw_layout(&mut self, layout: &Layout, x: f32, y: f32, color: u32) {
+        // Rasterize everything first, so that a full atlas is cleared before
+        // any sprite refers to it.
+        if !self.rasterize_all(layout) {
+            self.clear_cache();
+            self.rasterize_all(layout);
+        }
+
+        let glyphs = layout.glyphs();
+        let vertices = sys::sceGuGetMemory((glyphs.len() * 2 * mem::size_of::<Vertex>()) as i32)
+            as *mut Vertex;
+        let mut count = 0;
+
+        for positioned in glyphs {
+            let glyph = match self.glyphs.get(&positioned.c) {
+                Some(glyph) => *glyph,
+                None => continue,
+            };
+            let (u, v) = match glyph.cell {
+                Some(cell) => (cell.0 as f32, cell.1 as f32),
+                None => continue,
+            };
+
+            let left = snap(x + positioned.x) + glyph.left as f32;
+            let top = snap(y + positioned.y) - glyph.top as f32;
+            let (width, height) = (glyph.width as f32, glyph.height as f32);
+
+            vertices.add(count).write(Vertex {
+                u,
+                v,
+                x: left,
+                y: top,
+                z: 0.0,
+            });
+            vertices.add(count + 1).write(Vertex {
+                u: u + width,
+                v: v + height,
+                x: left + width,
+                y: top + height,
+                z: 0.0,
+            });
+            count += 2;
+        }
+
+        if count == 0 {
+            return;
+        }
+
+        sys::sceGuEnable(GuState::Texture2D);
+        sys::sceGuEnable(GuState::Blend);
+        sys::sceGuBlendFunc(
+            sys::BlendOp::Add,
+            sys::BlendSrc::SrcAlpha,
+            sys::BlendDst::OneMinusSrcAlpha,
+            0,
+            0,
+        );
+        sys::sceGuTexFunc(TextureEffect::Modulate, TextureColorComponent::Rgba);
+        sys::sceGuTexFilter(TextureFilter::Nearest, TextureFilter::Nearest);
+        self.atlas.bind();
+        sys::sceGuColor(color);
+
+        sys::sceGuDrawArray(
+            GuPrimitive::Sprites,
+            VertexType::TEXTURE_32BITF | VertexType::VERTEX_32BITF | VertexType::TRANSFORM_2D,
+            count as i32,
+            ptr::null(),
+            vertices as *const c_void,
+        );
+    }
+
+    /// Forget every rasterized glyph.
+    pub fn clear_cache(&mut self) {
+        self.atlas.clear();
+
+        for glyph in self.glyphs.values_mut() {
+            glyph.cell = None;
+        }
+    }
+
+    /// The metrics of `c`, fetched from the library the first time.
+    fn glyph(&mut self, c: char) -> Glyph {
+        if let Some(glyph) = self.glyphs.get(&c) {
+            return *glyph;
+        }
+
+        // The library only knows the Basic Multilingual Plane.
+        let mut info = SceFontCharInfo::default();
+        let glyph = if (c as u32) > 0xffff
+            || unsafe { sys::sceFontGetCharInfo(self.handle, c as u32, &mut info) } < 0
+        {
+            Glyph::BLANK
+        } else {
+            let advance = info.sfp26_advance_h as f32 / 64.0;
+            let left = info.sfp26_bearing_hx as f32 / 64.0;
+            let width = info.sfp26_width as i32 as f32 / 64.0;
+
+            Glyph {
+                advance,
+                bearings: (left, advance - left - width),
+                left: info.bitmap_left as i32,
+                top: info.bitmap_top as i32,
+                width: info.bitmap_width as usize,
+                height: info.bitmap_height as usize,
+                cell: None,
+            }
+        };
+
+        self.glyphs.insert(c, glyph);
+        glyph
+    }
+
+    /// The space between the ink of two `SPACING_CHAR`s, at least a pixel,
+    /// or infinity if the font does not have it, which turns kerning off.
+    fn spacing(&mut self) -> f32 {
+        if let Some(spacing) = self.spacing {
+            return spacing;
+        }
+
+        let glyph = self.glyph(SPACING_CHAR);
+        let spacing = if glyph.width == 0 {
+            f32::INFINITY
+        } else {
+            (glyph.bearings.0 + glyph.bearings.1).max(1.0)
+        };
+
+        self.spacing = Some(spacing);
+        spacing
+    }
+
+    /// Rasterize every glyph of `layout` into the atlas, returning `false` if
+    /// some did not fit.
+    fn rasterize_all(&mut self, layout: &Layout) -> bool {
+        let mut fits = true;
+
+        for positioned in layout.glyphs() {
+            fits &= self.rasterize(positioned.c);
+        }
+
+        fits
+    }
+
+    /// Rasterize `c` into the atlas if it is not there yet, returning `false`
+    /// if it does not fit.
+    fn rasterize(&mut self, c: char) -> bool {
+        let mut glyph = self.glyph(c);
+        if glyph.cell.is_some() || glyph.width == 0 || glyph.height == 0 {
+            return true;
+        }
+
+        let (x, y) = match self.atlas.allocate(glyph.width, glyph.height) {
+            Some(position) => position,
+            None => return false,
+        };
+
+        // The glyph is drawn with its top left corner at the given position.
+        let mut image = SceFontGlyphImage {
+            pixel_format: SceFontPixelFormatCode::Format8,
+            x_pos_64: (x as i32) << 6,
+            y_pos_64: (y as i32) << 6,
+            buf_width: ATLAS_SIZE as u16,
+            buf_height: ATLAS_SIZE as u16,
+            bytes_per_line: ATLAS_SIZE as u16,
+            pad: 0,
+            buffer_ptr: self.atlas.as_mut_ptr() as u32,
+        };
+
+        if unsafe { sys::sceFontGetCharGlyphImage(self.handle, c as u32, &mut image) } < 0 {
+            glyph.width = 0;
+            glyph.height = 0;
+        } else {
+            glyph.cell = Some((x, y));
+        }
+
+        self.glyphs.insert(c, glyph);
+        true
+    }
+}
+
+impl GlyphMetrics for Font<'_> {
+    fn advance(&mut self, c: char) -> f32 {
+        self.glyph(c).advance
+    }
+
+    fn kerning(&mut self, left: char, right: char) -> f32 {
+        let (left, right) = (self.glyph(left), self.glyph(right));
+
+        // Spaces have no ink to measure.
+        if left.width == 0 || right.width == 0 {
+            return 0.0;
+        }
+
+        let gap = left.bearings.1 + right.bearings.0;
+        let limit = 2.0 * self.spacing();
+
+        if gap > limit {
+            limit - gap
+        } else {
+            0.0
+        }
+    }
+
+    fn ascender(&self) -> f32 {
+        self.info.max_glyph_ascender_i as f32 / 64.0
+    }
+
+    fn line_height(&self) -> f32 {
+        // The descender may be stored as a negative distance.
+        (self.info.max_glyph_ascender_i + self.info.max_glyph_descender_i.abs()) as f32 / 64.0
+    }
+}
+
+impl Drop for Font<'_> {
+    fn drop(&mut self) {
+        unsafe {
+            sys::sceFontClose(self.handle);
+        }
+    }
+}
+
+/// Round a coordinate to the nearest pixel, so glyphs map to whole texels.
+fn snap(value: f32) -> f32 {
+    if value < 0.0 {
+        (value - 0.5) as i32 as f32
+    } else {
+        (value + 0.5) as i32 as f32
+    }
+}
@@ -0,0 +1,360 @@
+//! Kernel threads running Rust closures.
+//!
+//! This mirrors the basic `std::thread` API on top of `sceKernelCreateThread`.
+
+use crate::error::{self, Error, Result};
+use crate::sync::critical_section;
+use crate::sys::{self, SceUid, ThreadAttributes};
+use alloc::boxed::Box;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::cell::UnsafeCell;
+use core::{ffi::c_void, mem, ptr, time::Duration};
+
+/// Default priority of spawned threads, the same as the main thread.
+pub const DEFAULT_PRIORITY: i32 = 32;
+
+/// Default stack size of spawned threads.
+pub const DEFAULT_STACK_SIZE: usize = 64 * 1024;
+
+/// Error returned by `JoinHandle::join`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum JoinError {
+    /// The thread panicked.
+    Panicked,
+    /// Waiting for the thread failed.
+    Kernel(Error),
+}
+
+const RUNNING: u8 = 0;
+const FINISHED: u8 = 1;
+const DETACHED: u8 = 2;
+
+/// State shared between a `JoinHandle` and its thread.
+///
+/// Ownership of the packet is resolved through `state`, which must only be
+/// accessed in a critical section: whichever side observes the other as
+/// finished or detached frees it.
+struct Packet<T> {
+    state: UnsafeCell<u8>,
+    result: UnsafeCell<Option<T>>,
+    main: UnsafeCell<Option<Box<dyn FnOnce() -> T + Send>>>,
+}
+
+/// Thread configuration, in the style of `std::thread::Builder`.
+#[derive(Debug, Clone)]
+pub struct Builder {
+    name: Option<String>,
+    priority: i32,
+    stack_size: usize,
+    attributes: ThreadAttributes,
+}
+
+impl Builder {
+    /// Create a builder with the default settings.
+    pub fn new() -> Self {
+        Self {
+            name: None,
+            priority: DEFAULT_PRIORITY,
+            stack_size: DEFAULT_STACK_SIZE,
+            attributes: ThreadAttributes::USER,
+        }
+    }
+
+    /// Name the thread. The name shows up in debuggers such as PSPLink.
+    pub fn name(mut self, name: &str) -> Self {
+        self.name = Some(String::from(name));
+        self
+    }
+
+    /// Set the initial priority. Lower values mean higher priority.
+    pub fn priority(mut self, priority: i32) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    /// Set the stack size in bytes.
+    pub fn stack_size(mut self, stack_size: usize) -> Self {
+        self.stack_size = stack_size;
+        self
+    }
+
+    /// Allow the thread to use the VFPU.
+    pub fn vfpu(mut self, enabled: bool) -> Self {
+        self.attributes.set(ThreadAttributes::VFPU, enabled);
+        self
+    }
+
+    /// Spawn a thread running `f`.
+    pub fn spawn<F, T>(self, f: F) -> Result<JoinHandle<T>>
+    where
+        F: FnOnce() -> T + Send + 'static,
+        T: Send + 'static,
+    {
+        let mut name: Vec<u8> = self.name.unwrap_or_else(|| String::from("psp_thread")).into();
+        name.retain(|&b| b != 0);
+        name.push(0);
+
+        let thid = unsafe {
+            sys::sceKernelCreateThread(
+                name.as_ptr(),
+                thread_start::<T>,
+                self.priority,
+                self.stack_size as i32,
+                self.attributes,
+                ptr::null_mut(),
+            )
+        };
+        error::check_uid(thid)?;
+
+        let packet = Box::into_raw(Box::new(Packet {
+            state: UnsafeCell::new(RUNNING),
+            result: UnsafeCell::new(None),
+            main: UnsafeCell::new(Some(Box::new(f))),
+        }));
+
+        // The kernel copies the argument block onto the new thread's stack,
+        // so we pass the packet pointer by value.
+        let mut arg = packet as usize;
+        let ret = unsafe {
+            sys::sceKernelStartThread(
+                thid,
+                mem::size_of::<usize>(),
+                &mut arg as *mut usize as *mut c_void,
+            )
+        };
+
+        if let Err(e) = error::check(ret) {
+            unsafe {
+                sys::sceKernelDeleteThread(thid);
+                drop(Box::from_raw(packet));
+            }
+
+            return Err(e);
+        }
+
+        Ok(JoinHandle {
+            thid,
+            packet,
+            joined: false,
+        })
+    }
+}
+
+impl Default for Builder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+unsafe extern fn thread_start<T>(_args: usize, argp: *mut c_void) -> i32 {
+    let packet = *(argp as *const usize) as *mut Packet<T>;
+    let main = (*(*packet).main.get()).take().unwrap();
+
+    let status = match crate::catch_unwind(core::panic::AssertUnwindSafe(main)) {
+        Ok(result) => {
+            *(*packet).result.get() = Some(result);
+            0
+        }
+        Err(_) => -1,
+    };
+
+    let detached = critical_section(|| {
+        let state = (*packet).state.get();
+
+        if *state == DETACHED {
+            true
+        } else {
+            *state = FINISHED;
+            false
+        }
+    });
+
+    if detached {
+        drop(Box::from_raw(packet));
+        sys::sceKernelExitDeleteThread(status);
+    }
+
+    status
+}
+
+/// An owned permission to join on a thread.
+///
+/// Dropping the handle detaches the thread, which then cleans up after itself
+/// when it exits.
+pub struct JoinHandle<T> {
+    thid: SceUid,
+    packet: *mut Packet<T>,
+    joined: bool,
+}
+
+unsafe impl<T: Send> Send for JoinHandle<T> {}
+
+impl<T> JoinHandle<T> {
+    /// The kernel id of the thread.
+    pub fn id(&self) -> SceUid {
+        self.thid
+    }
+
+    /// Wait for the thread to finish and return its result.
+    pub fn join(mut self) -> core::result::Result<T, JoinError> {
+        let ret = unsafe { sys::sceKernelWaitThreadEnd(self.thid, ptr::null_mut()) };
+        error::check(ret).map_err(JoinError::Kernel)?;
+
+        self.joined = true;
+
+        unsafe {
+            sys::sceKernelDeleteThread(self.thid);
+            let packet = Box::from_raw(self.packet);
+            packet.result.into_inner().ok_or(JoinError::Panicked)
+        }
+    }
+}
+
+impl<T> Drop for JoinHandle<T> {
+    fn drop(&mut self) {
+        if self.joined {
+            return;
+        }
+
+        let packet = self.packet;
+        let finished = critical_section(|| unsafe {
+            let state = (*packet).state.get();
+
+            if *state == FINISHED {
+                true
+            } else {
+                *state = DETACHED;
+                false
+            }
+        });
+
+        if finished {
+            unsafe {
+                sys::sceKernelWaitThreadEnd(self.thid, ptr::null_mut());
+                sys::sceKernelDeleteThread(self.thid);
+                drop(Box::from_raw(packet));
+            }
+        }
+    }
+}
+
+/// Spawn a thread with the default settings.
+///
+/// # Panics
+///
+/// Panics if the kernel fails to create the thread. Use `Builder::spawn` to
+/// handle this case.
+pub fn spawn<F, T>(f: F) -> JoinHandle<T>
+where
+    F: FnOnce() -> T + Send + 'static,
+    T: Send + 'static,
+{
+    Builder::new().spawn(f).expect("failed to spawn thread")
+}
+
+/// State shared between its owner and the threads working on it.
+///
+/// The state is boxed so that its address stays the same, and each thread
+/// spawned with `spawn` borrows it. Dropping the `SharedThreads` joins the
+/// threads before freeing the state, so a borrow never outlives it. Telling
+/// the threads to stop, such as through a flag in the state, is up to the
+/// owner.
+pub struct SharedThreads<S> {
+    state: *mut S,
+    threads: Vec<JoinHandle<()>>,
+}
+
+unsafe impl<S: Send + Sync> Send for SharedThreads<S> {}
+unsafe impl<S: Sync> Sync for SharedThreads<S> {}
+
+impl<S: Sync + 'static> SharedThreads<S> {
+    /// Box `state`, without any thread yet.
+    pub fn new(state: S) -> Self {
+        Self {
+            state: Box::into_raw(Box::new(state)),
+            threads: Vec::new(),
+        }
+    }
+
+    /// The shared state.
+    pub fn state(&self) -> &S {
+        unsafe { &*self.state }
+    }
+
+    /// Mutably borrow the state, or `None` while a thread may be using it.
+    pub fn state_mut(&mut self) -> Option<&mut S> {
+        if self.threads.is_empty() {
+            Some(unsafe { &mut *self.state })
+        } else {
+            None
+        }
+    }
+
+    /// Spawn a thread configured by `builder`, running `f` with the state.
+    pub fn spawn<F>(&mut self, builder: Builder, f: F) -> Result<()>
+    where
+        F: FnOnce(&S) + Send + 'static,
+    {
+        // Raw pointers are not `Send`, so the address is passed instead. The
+        // thread is joined before the state is freed.
+        let addr = self.state as usize;
+        let thread = builder.spawn(move || f(unsafe { &*(addr as *const S) }))?;
+        self.threads.push(thread);
+
+        Ok(())
+    }
+
+    /// Wait for every thread to finish. The state lives on until `self` is
+    /// dropped.
+    pub fn join(&mut self) {
+        for thread in self.threads.drain(..) {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl<S> Drop for SharedThreads<S> {
+    fn drop(&mut self) {
+        for thread in self.threads.drain(..) {
+            let _ = thread.join();
+        }
+
+        unsafe { drop(Box::from_raw(self.state)) };
+    }
+}
+
+/// Put the current thread to sleep for at least `duration`.
+pub fn sleep(duration: Duration) {
+    let mut micros = duration.as_micros();
+
+    while micros > 0 {
+        let chunk = core::cmp::min(micros, u32::MAX as u128);
+
+        unsafe {
+            sys::sceKernelDelayThread(chunk as u32);
+        }
+
+        micros -= chunk;
+    }
+}
+
+/// Like `sleep`, but also runs any callbacks registered on this thread.
+pub fn sleep_cb(duration: Duration) {
+    let micros = core::cmp::min(duration.as_micros(), u32::MAX as u128);
+
+    unsafe {
+        sys::sceKernelDelayThreadCB(micros as u32);
+    }
+}
+
+/// The kernel id of the calling thread.
+pub fn current_id() -> Result<SceUid> {
+    error::check(unsafe { sys::sceKernelGetThreadId() }).map(SceUid)
+}
+
+/// Change the priority of a thread. Pass `None` for the current thread.
+pub fn set_priority(thread: Option<SceUid>, priority: i32) -> Result<()> {
+    let thid = thread.unwrap_or(SceUid(0));
+    error::check(unsafe { sys::sceKernelChangeThreadPriority(thid, priority) }).map(|_| ())
+}
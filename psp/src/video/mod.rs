@@ -0,0 +1,947 @@
+//! Playback of PMF movies with the `sceMpeg` library.
+//!
+//! PMF is the MPEG program stream format used for game cutscenes and for the
+//! `xmb_icon_pmf` shown in the XMB. It holds AVC video and, optionally,
+//! ATRAC3plus audio.
+//!
+//! A `VideoPlayer` reads the file from a demux thread, decodes video and
+//! audio on two more threads, and keeps the video in sync with the audio.
+//! Decoded frames can be copied to the display with `present`, or borrowed
+//! with `frame` to be used as a GU texture.
+//!
+//! # Example
+//!
+//! ```ignore
+//! use psp::sys::{self, CtrlButtons, SceCtrlData};
+//! use psp::video::VideoPlayer;
+//!
+//! let mut player = VideoPlayer::open("umd0:/PSP_GAME/USRDIR/intro.pmf")?;
+//!
+//! // Play until the end, or until X is pressed.
+//! player.play_fullscreen(|| {
+//!     let mut pad = SceCtrlData::default();
+//!     unsafe { sys::sceCtrlPeekBufferPositive(&mut pad, 1) };
+//!     pad.buttons.contains(CtrlButtons::CROSS)
+//! })?;
+//! # Ok::<(), psp::io::Error>(())
+//! ```
+
+pub mod pmf;
+
+pub use pmf::{HeaderError, PmfHeader, HEADER_SIZE};
+
+use crate::display;
+use crate::error;
+use crate::fs::File;
+use crate::io::{self, read_full, Seek, SeekFrom};
+use crate::modules::ModuleGuard;
+use crate::sync::{critical_section, Mutex, MutexGuard};
+use crate::sys::{
+    self, AudioFormat, AvModule, DisplayPixelFormat, DisplaySetBufSync, SceMpeg, SceMpegAu,
+    SceMpegAvcMode, SceMpegRingbuffer, SceMpegStream,
+};
+use crate::thread::{self, SharedThreads};
+use alloc::alloc::{alloc_zeroed, dealloc, handle_alloc_error, Layout};
+use alloc::vec;
+use core::cell::UnsafeCell;
+use core::ffi::c_void;
+use core::{cmp, fmt, mem, ptr, slice, time::Duration};
+use pmf::TIMESTAMP_RATE;
+
+/// Width of the decoded frames in pixels, including padding.
+pub const FRAME_BUFFER_WIDTH: usize = 512;
+
+/// Largest height of a decoded frame, the height of the screen.
+pub const FRAME_HEIGHT: usize = 272;
+
+/// Default priority of the audio thread. The video and demux threads run
+/// just below it.
+pub const DEFAULT_PRIORITY: i32 = 16;
+
+/// Size of a packet of the MPEG stream.
+const PACKET_SIZE: usize = 2048;
+
+/// Number of packets in the ring buffer, as used by the system software.
+const RING_PACKETS: i32 = 0x3c0;
+
+/// Stream numbers of the first AVC and ATRAC3plus streams.
+const AVC_STREAM: i32 = 0;
+const ATRAC_STREAM: i32 = 1;
+
+/// How early a frame may be shown, in timestamp units: a third of a frame.
+const SYNC_MARGIN: u64 = 1000;
+
+/// The last step of setting up the decoder that succeeded. A player that
+/// failed halfway through is dropped like any other, so `Drop` checks this
+/// before deleting the MPEG instance, ringbuffer and library.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+enum Stage {
+    None,
+    MpegInit,
+    RingbufferConstructed,
+    Created,
+}
+
+/// A zeroed heap buffer with the 64-byte alignment the decoders want.
+struct Buffer {
+    ptr: *mut u8,
+    layout: Layout,
+}
+
+impl Buffer {
+    fn new(size: usize) -> Self {
+        let layout = Layout::from_size_align(cmp::max(size, 1), 64).unwrap();
+        let ptr = unsafe { alloc_zeroed(layout) };
+
+        if ptr.is_null() {
+            handle_alloc_error(layout);
+        }
+
+        // The zeroes may still be in the data cache, and must not be
+        // written back over what the hardware puts in the buffer.
+        unsafe {
+            sys::sceKernelDcacheWritebackInvalidateRange(ptr as *const c_void, size as u32);
+        }
+
+        Self { ptr, layout }
+    }
+
+    fn as_ptr(&self) -> *mut c_void {
+        self.ptr as *mut c_void
+    }
+}
+
+impl Drop for Buffer {
+    fn drop(&mut self) {
+        unsafe { dealloc(self.ptr, self.layout) }
+    }
+}
+
+/// Playback state, only accessed in a critical section.
+#[derive(Default)]
+struct State {
+    paused: bool,
+    stopped: bool,
+    /// Set once the whole file is in the ring buffer.
+    eof: bool,
+    video_done: bool,
+    audio_done: bool,
+    /// Timestamp of the audio being played, which the video follows.
+    audio_clock: Option<u64>,
+    /// System time, in microseconds, of the first timestamp. Used when there
+    /// is no audio to follow.
+    wall_start: Option<i64>,
+    /// System time at which playback was paused.
+    paused_at: Option<i64>,
+}
+
+/// The frame shown last, guarded by `Shared::front`.
+struct Front {
+    index: Option<usize>,
+    timestamp: u64,
+}
+
+/// The audio stream and its output channel.
+struct Audio {
+    stream: SceMpegStream,
+    au: UnsafeCell<SceMpegAu>,
+    _es: Buffer,
+    out: [Buffer; 2],
+    channel: i32,
+}
+
+impl Drop for Audio {
+    fn drop(&mut self) {
+        unsafe {
+            // The channel cannot be released while it still plays.
+            while sys::sceAudioGetChannelRestLen(self.channel) > 0 {
+                sys::sceKernelDelayThread(1000);
+            }
+
+            sys::sceAudioChRelease(self.channel);
+        }
+    }
+}
+
+/// State shared between a `VideoPlayer` and its threads.
+struct Shared {
+    stage: Stage,
+    header: PmfHeader,
+    /// Only read by the ring buffer callback, on the demux thread.
+    file: UnsafeCell<File>,
+    ringbuffer: UnsafeCell<SceMpegRingbuffer>,
+    ring_data: Option<Buffer>,
+    /// Storage for the MPEG handle.
+    mpeg: UnsafeCell<*mut c_void>,
+    mpeg_data: Option<Buffer>,
+    avc: Option<SceMpegStream>,
+    avc_es: *mut c_void,
+    video_au: UnsafeCell<SceMpegAu>,
+    audio: Option<Audio>,
+    frames: [Buffer; 2],
+    front: Mutex<Front>,
+    state: UnsafeCell<State>,
+    _modules: ModuleGuard,
+}
+
+// The decoder state is only touched by the thread that owns that part of
+// playback, and the rest of the state is behind `front` or accessed in a
+// critical section.
+unsafe impl Send for Shared {}
+unsafe impl Sync for Shared {}
+
+impl Shared {
+    fn handle(&self) -> SceMpeg {
+        SceMpeg::from_ptr(self.mpeg.get())
+    }
+
+    fn state<T>(&self, f: impl FnOnce(&mut State) -> T) -> T {
+        critical_section(|| unsafe { f(&mut *self.state.get()) })
+    }
+
+    fn stopped(&self) -> bool {
+        self.state(|state| state.stopped)
+    }
+
+    fn paused(&self) -> bool {
+        self.state(|state| state.paused)
+    }
+
+    /// Create the decoder and register the streams of the file.
+    ///
+    /// `header` holds the first `HEADER_SIZE` bytes of the file.
+    unsafe fn setup(&mut self, header: &mut [u8]) -> io::Result<()> {
+        error::check(sys::sceMpegInit())?;
+        self.stage = Stage::MpegInit;
+
+        let size = error::check(sys::sceMpegRingbufferQueryMemSize(RING_PACKETS))?;
+        let ring_data = self
+            .ring_data
+            .get_or_insert(Buffer::new(size as usize))
+            .as_ptr();
+        error::check(sys::sceMpegRingbufferConstruct(
+            self.ringbuffer.get(),
+            RING_PACKETS,
+            ring_data,
+            size,
+            Some(read_packets),
+            self.file.get() as *mut c_void,
+        ))?;
+        self.stage = Stage::RingbufferConstructed;
+
+        let size = error::check(sys::sceMpegQueryMemSize(0))?;
+        let mpeg_data = self
+            .mpeg_data
+            .get_or_insert(Buffer::new(size as usize))
+            .as_ptr();
+        error::check(sys::sceMpegCreate(
+            self.handle(),
+            mpeg_data,
+            size,
+            self.ringbuffer.get(),
+            FRAME_BUFFER_WIDTH as i32,
+            0,
+            0,
+        ))?;
+        self.stage = Stage::Created;
+
+        let handle = self.handle();
+        let mut mode = SceMpegAvcMode {
+            unk0: -1,
+            pixel_format: DisplayPixelFormat::Psm8888,
+        };
+        error::check(sys::sceMpegAvcDecodeMode(handle, &mut mode))?;
+
+        let mut offset = 0;
+        error::check(sys::sceMpegQueryStreamOffset(
+            handle,
+            header.as_mut_ptr() as *mut c_void,
+            &mut offset,
+        ))?;
+        (*self.file.get()).seek(SeekFrom::Start(offset as u64))?;
+
+        self.avc = Some(register(handle, AVC_STREAM)?);
+        self.avc_es = sys::sceMpegMallocAvcEsBuf(handle);
+
+        if self.avc_es.is_null() {
+            return Err(error::SCE_ERROR_ERRNO_ENOMEM.into());
+        }
+
+        error::check(sys::sceMpegInitAu(handle, self.avc_es, self.video_au.get()))?;
+
+        if self.header.has_audio {
+            self.audio = Some(self.setup_audio()?);
+        }
+
+        // Fill the ring buffer before the threads start.
+        let free = sys::sceMpegRingbufferAvailableSize(self.ringbuffer.get());
+        error::check(sys::sceMpegRingbufferPut(self.ringbuffer.get(), free, free))?;
+
+        Ok(())
+    }
+
+    unsafe fn setup_audio(&mut self) -> io::Result<Audio> {
+        let handle = self.handle();
+        let stream = register(handle, ATRAC_STREAM)?;
+
+        let (mut es_size, mut out_size) = (0, 0);
+        error::check(sys::sceMpegQueryAtracEsSize(
+            handle,
+            &mut es_size,
+            &mut out_size,
+        ))?;
+
+        let es = Buffer::new(es_size as usize);
+        let out = [
+            Buffer::new(out_size as usize),
+            Buffer::new(out_size as usize),
+        ];
+        let mut au = mem::zeroed();
+        error::check(sys::sceMpegInitAu(handle, es.as_ptr(), &mut au))?;
+
+        // Decoded audio is 16-bit stereo, so 4 bytes per frame.
+        let frames = sys::audio_sample_align(out_size / 4);
+        let channel = error::check(sys::sceAudioChReserve(
+            sys::AUDIO_NEXT_CHANNEL,
+            frames,
+            AudioFormat::Stereo,
+        ));
+
+        match channel {
+            Ok(channel) => Ok(Audio {
+                stream,
+                au: UnsafeCell::new(au),
+                _es: es,
+                out,
+                channel,
+            }),
+            Err(e) => {
+                sys::sceMpegUnRegistStream(handle, stream);
+                Err(e.into())
+            }
+        }
+    }
+
+    /// The current playback time, if playback has started.
+    fn clock(&self, state: &State, now: i64) -> Option<u64> {
+        if let Some(timestamp) = state.audio_clock {
+            if !state.audio_done {
+                return Some(timestamp);
+            }
+        }
+
+        let start = state.wall_start?;
+        let elapsed = (state.paused_at.unwrap_or(now) - start).max(0) as u64;
+
+        Some(self.header.first_timestamp + elapsed * TIMESTAMP_RATE / 1_000_000)
+    }
+
+    /// Wait until the frame with timestamp `pts` is due. Returns `false` if
+    /// playback was stopped in the meantime.
+    fn wait_until(&self, pts: u64) -> bool {
+        let has_audio = self.audio.is_some();
+        let first = self.header.first_timestamp;
+
+        loop {
+            let now = now();
+            let due = self.state(|state| {
+                if state.stopped {
+                    return None;
+                }
+
+                if state.paused {
+                    return Some(false);
+                }
+
+                match self.clock(state, now) {
+                    Some(clock) => Some(clock + SYNC_MARGIN >= pts),
+                    // Without audio, the first frame starts the clock.
+                    None if !has_audio || state.audio_done => {
+                        state.wall_start = Some(now - ticks_to_micros(pts.saturating_sub(first)));
+                        Some(true)
+                    }
+                    None => Some(false),
+                }
+            });
+
+            match due {
+                None => return false,
+                Some(true) => return true,
+                Some(false) => unsafe {
+                    sys::sceKernelDelayThread(1000);
+                },
+            }
+        }
+    }
+
+    /// Make the frame decoded into `frames[index]` the one shown.
+    fn publish(&self, index: usize, pts: u64) {
+        unsafe {
+            sys::sceKernelDcacheInvalidateRange(
+                self.frames[index].as_ptr(),
+                (FRAME_BUFFER_WIDTH * FRAME_HEIGHT * 4) as u32,
+            );
+        }
+
+        let mut front = self.front.lock();
+        front.index = Some(index);
+        front.timestamp = pts;
+    }
+}
+
+impl Drop for Shared {
+    fn drop(&mut self) {
+        unsafe {
+            let handle = self.handle();
+
+            if self.stage >= Stage::Created {
+                // Dropping the audio also releases its channel.
+                if let Some(audio) = self.audio.take() {
+                    sys::sceMpegUnRegistStream(handle, audio.stream);
+                }
+
+                if let Some(avc) = self.avc {
+                    sys::sceMpegUnRegistStream(handle, avc);
+                }
+
+                if !self.avc_es.is_null() {
+                    sys::sceMpegFreeAvcEsBuf(handle, self.avc_es);
+                }
+
+                sys::sceMpegDelete(handle);
+            }
+
+            if self.stage >= Stage::RingbufferConstructed {
+                sys::sceMpegRingbufferDestruct(self.ringbuffer.get());
+            }
+
+            if self.stage >= Stage::MpegInit {
+                sys::sceMpegFinish();
+            }
+        }
+    }
+}
+
+/// A decoded frame, borrowed from a `VideoPlayer`.
+///
+/// Pixels are RGBA 8:8:8:8, in rows of `FRAME_BUFFER_WIDTH` pixels. The
+/// decoder does not replace the frame while it is borrowed, so it can be used
+/// directly as a 512-pixel-wide GU texture, as long as drawing has finished
+/// (for example with `sceGuSync`) before the frame is dropped.
+pub struct Frame<'a> {
+    pixels: &'a [u32],
+    width: usize,
+    height: usize,
+    position: Duration,
+    front: MutexGuard<'a, Front>,
+}
+
+impl Frame<'_> {
+    /// The pixels of the frame, `FRAME_BUFFER_WIDTH` per row.
+    pub fn pixels(&self) -> &[u32] {
+        self.pixels
+    }
+
+    /// The width of the picture, in pixels.
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// The height of the picture, in pixels.
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// When the frame is shown, from the start of the movie.
+    pub fn position(&self) -> Duration {
+        self.position
+    }
+}
+
+impl fmt::Debug for Frame<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Frame")
+            .field("width", &self.width)
+            .field("height", &self.height)
+            .field("position", &self.position)
+            .finish()
+    }
+}
+
+/// Plays a PMF movie.
+///
+/// Playback starts as soon as the player is opened, on three threads: one
+/// filling the demuxer from the file, one decoding video and one decoding
+/// audio to its own hardware channel. The video follows the audio clock, or
+/// the system clock if the movie has no audio.
+///
+/// Dropping the player stops playback and frees the decoder.
+pub struct VideoPlayer {
+    shared: SharedThreads<Shared>,
+    /// Timestamp of the frame last copied by `present`.
+    presented: Option<u64>,
+}
+
+impl VideoPlayer {
+    /// Open the PMF file at `path` and start playing it.
+    pub fn open(path: &str) -> io::Result<Self> {
+        Self::with_priority(path, DEFAULT_PRIORITY)
+    }
+
+    /// Like `open`, with a custom priority for the audio thread. Lower values
+    /// mean higher priority.
+    pub fn with_priority(path: &str, priority: i32) -> io::Result<Self> {
+        let mut file = File::open(path)?;
+        let mut data = vec![0; HEADER_SIZE];
+
+        if read_full(&mut file, &mut data)? < HEADER_SIZE {
+            return Err(HeaderError::Truncated.into());
+        }
+
+        let header = PmfHeader::parse(&data)?;
+        let modules = ModuleGuard::load(&[AvModule::MpegBase.into()])?;
+
+        let shared = SharedThreads::new(Shared {
+            stage: Stage::None,
+            header,
+            file: UnsafeCell::new(file),
+            ringbuffer: UnsafeCell::new(unsafe { mem::zeroed() }),
+            ring_data: None,
+            mpeg: UnsafeCell::new(ptr::null_mut()),
+            mpeg_data: None,
+            avc: None,
+            avc_es: ptr::null_mut(),
+            video_au: UnsafeCell::new(unsafe { mem::zeroed() }),
+            audio: None,
+            frames: [
+                Buffer::new(FRAME_BUFFER_WIDTH * FRAME_HEIGHT * 4),
+                Buffer::new(FRAME_BUFFER_WIDTH * FRAME_HEIGHT * 4),
+            ],
+            front: Mutex::new(Front {
+                index: None,
+                timestamp: 0,
+            }),
+            state: UnsafeCell::new(State::default()),
+            _modules: modules,
+        });
+
+        // From here on, dropping `player` stops the threads and tears down
+        // whatever was set up.
+        let mut player = Self {
+            shared,
+            presented: None,
+        };
+
+        // No thread has started yet, so the state can be borrowed mutably.
+        unsafe { player.shared.state_mut().unwrap().setup(&mut data)? };
+
+        player.spawn("psp_video_demux", priority + 2, demux_thread)?;
+        player.spawn("psp_video_decode", priority + 1, video_thread)?;
+
+        if player.shared().audio.is_some() {
+            player.spawn("psp_video_audio", priority, audio_thread)?;
+        }
+
+        Ok(player)
+    }
+
+    fn shared(&self) -> &Shared {
+        self.shared.state()
+    }
+
+    fn spawn(&mut self, name: &str, priority: i32, f: fn(&Shared)) -> io::Result<()> {
+        let builder = thread::Builder::new()
+            .name(name)
+            .priority(priority)
+            .stack_size(16 * 1024);
+        self.shared.spawn(builder, f)?;
+
+        Ok(())
+    }
+
+    /// The information in the file header.
+    pub fn header(&self) -> &PmfHeader {
+        &self.shared().header
+    }
+
+    /// The width of the video, in pixels.
+    pub fn width(&self) -> usize {
+        cmp::min(self.header().width as usize, FRAME_BUFFER_WIDTH)
+    }
+
+    /// The height of the video, in pixels.
+    pub fn height(&self) -> usize {
+        cmp::min(self.header().height as usize, FRAME_HEIGHT)
+    }
+
+    /// The length of the movie.
+    pub fn duration(&self) -> Duration {
+        self.header().duration()
+    }
+
+    /// How far playback got, from the start of the movie.
+    pub fn position(&self) -> Duration {
+        let shared = self.shared();
+        let now = now();
+        let clock = shared.state(|state| shared.clock(state, now));
+        let ticks = clock.map_or(0, |clock| {
+            clock.saturating_sub(shared.header.first_timestamp)
+        });
+
+        Duration::from_micros(ticks_to_micros(ticks) as u64)
+    }
+
+    /// Pause playback. Audio stops once the queued buffer has played.
+    pub fn pause(&self) {
+        let now = now();
+        self.shared().state(|state| {
+            if !state.paused {
+                state.paused = true;
+                state.paused_at = Some(now);
+            }
+        });
+    }
+
+    /// Resume playback after `pause`.
+    pub fn resume(&self) {
+        let now = now();
+        self.shared().state(|state| {
+            if let Some(paused_at) = state.paused_at.take() {
+                state.wall_start = state.wall_start.map(|start| start + now - paused_at);
+            }
+
+            state.paused = false;
+        });
+    }
+
+    /// Returns `true` while playback is paused.
+    pub fn is_paused(&self) -> bool {
+        self.shared().paused()
+    }
+
+    /// Stop playback for good, such as when the user skips a cutscene.
+    pub fn skip(&self) {
+        self.shared().state(|state| state.stopped = true);
+    }
+
+    /// Returns `true` once the movie has played to the end or was skipped.
+    pub fn is_finished(&self) -> bool {
+        let has_audio = self.shared().audio.is_some();
+
+        self.shared()
+            .state(|state| state.stopped || (state.video_done && (!has_audio || state.audio_done)))
+    }
+
+    /// The hardware audio channel of the movie, if it has audio.
+    pub fn audio_channel(&self) -> Option<i32> {
+        self.shared().audio.as_ref().map(|audio| audio.channel)
+    }
+
+    /// Borrow the frame being shown, or `None` before the first one is
+    /// decoded.
+    ///
+    /// The decoder waits while the frame is borrowed, so drop it quickly.
+    pub fn frame(&self) -> Option<Frame<'_>> {
+        let shared = self.shared();
+        let front = shared.front.lock();
+        let index = front.index?;
+        let ticks = front
+            .timestamp
+            .saturating_sub(shared.header.first_timestamp);
+
+        let pixels = unsafe {
+            slice::from_raw_parts(
+                shared.frames[index].as_ptr() as *const u32,
+                FRAME_BUFFER_WIDTH * FRAME_HEIGHT,
+            )
+        };
+
+        Some(Frame {
+            pixels,
+            width: self.width(),
+            height: self.height(),
+            position: Duration::from_micros(ticks_to_micros(ticks) as u64),
+            front,
+        })
+    }
+
+    /// Copy the frame being shown to the center of the framebuffer on
+    /// display, if it changed since the last call. Returns `true` if a new
+    /// frame was copied.
+    ///
+    /// The framebuffer must be in the `Psm8888` pixel format.
+    pub fn present(&mut self) -> io::Result<bool> {
+        let fb = display::frame_buf(DisplaySetBufSync::Immediate)?;
+
+        match fb.pixel_format {
+            DisplayPixelFormat::Psm8888 => {}
+            _ => return Err(error::SCE_ERROR_ERRNO_EINVAL.into()),
+        }
+
+        let (_, screen_width, screen_height) = display::mode()?;
+        let presented = self.presented;
+
+        let frame = match self.frame() {
+            Some(frame) if Some(frame.front.timestamp) != presented => frame,
+            _ => return Ok(false),
+        };
+
+        let width = cmp::min(frame.width, screen_width as usize);
+        let height = cmp::min(frame.height, screen_height as usize);
+        let x = (screen_width as usize - width) / 2;
+        let y = (screen_height as usize - height) / 2;
+        let dst = fb.top_addr as *mut u32;
+
+        for row in 0..height {
+            unsafe {
+                ptr::copy_nonoverlapping(
+                    frame.pixels[row * FRAME_BUFFER_WIDTH..].as_ptr(),
+                    dst.add((y + row) * fb.buffer_width + x),
+                    width,
+                );
+            }
+        }
+
+        let timestamp = frame.front.timestamp;
+        drop(frame);
+
+        unsafe {
+            sys::sceKernelDcacheWritebackAll();
+        }
+
+        self.presented = Some(timestamp);
+
+        Ok(true)
+    }
+
+    /// Present frames on the display until the movie ends, or until `skip`
+    /// returns `true`. `skip` is called once per vertical blank.
+    pub fn play_fullscreen(&mut self, mut skip: impl FnMut() -> bool) -> io::Result<()> {
+        while !self.is_finished() {
+            if skip() {
+                self.skip();
+                break;
+            }
+
+            self.present()?;
+            display::wait_vblank_start()?;
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Debug for VideoPlayer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("VideoPlayer")
+            .field("header", self.header())
+            .field("paused", &self.is_paused())
+            .field("finished", &self.is_finished())
+            .finish()
+    }
+}
+
+impl Drop for VideoPlayer {
+    fn drop(&mut self) {
+        // The threads see this and return, then dropping `shared` joins them
+        // and frees the decoder.
+        self.skip();
+    }
+}
+
+/// Called by `sceMpegRingbufferPut` to read `packets` packets into `data`.
+unsafe extern "C" fn read_packets(data: *mut c_void, packets: i32, param: *mut c_void) -> i32 {
+    let file = &mut *(param as *mut File);
+    let buf = slice::from_raw_parts_mut(data as *mut u8, packets.max(0) as usize * PACKET_SIZE);
+
+    match read_full(file, buf) {
+        Ok(n) => (n / PACKET_SIZE) as i32,
+        Err(_) => -1,
+    }
+}
+
+/// Keeps the ring buffer filled from the file.
+fn demux_thread(shared: &Shared) {
+    let ringbuffer = shared.ringbuffer.get();
+
+    while !shared.stopped() {
+        let free = unsafe { sys::sceMpegRingbufferAvailableSize(ringbuffer) };
+
+        if free > 0 && unsafe { sys::sceMpegRingbufferPut(ringbuffer, free, free) } <= 0 {
+            shared.state(|state| state.eof = true);
+            break;
+        }
+
+        unsafe {
+            sys::sceKernelDelayThread(5000);
+        }
+    }
+}
+
+/// Decodes video into the back buffer and publishes frames when they are due.
+fn video_thread(shared: &Shared) {
+    let handle = shared.handle();
+    let avc = match shared.avc {
+        Some(avc) => avc,
+        None => return,
+    };
+    let mut back = 0;
+
+    while !shared.stopped() {
+        if shared.paused() {
+            unsafe { sys::sceKernelDelayThread(10_000) };
+            continue;
+        }
+
+        let mut unk = 0;
+        let ret = unsafe { sys::sceMpegGetAvcAu(handle, avc, shared.video_au.get(), &mut unk) };
+
+        if ret < 0 {
+            if !shared.state(|state| state.eof) {
+                // The demux thread has not caught up yet.
+                unsafe { sys::sceKernelDelayThread(1000) };
+                continue;
+            }
+
+            // Flush the frames still held by the decoder.
+            let mut dst = shared.frames[back].as_ptr();
+            let mut status = 0;
+            let ret = unsafe {
+                sys::sceMpegAvcDecodeStop(
+                    handle,
+                    FRAME_BUFFER_WIDTH as i32,
+                    &mut dst as *mut *mut c_void as *mut c_void,
+                    &mut status,
+                )
+            };
+
+            if ret >= 0 && status > 0 {
+                shared.publish(back, shared.header.last_timestamp);
+            }
+
+            break;
+        }
+
+        let pts = timestamp(unsafe { &*shared.video_au.get() });
+        let mut dst = shared.frames[back].as_ptr();
+        let mut decoded = 0;
+        let ret = unsafe {
+            sys::sceMpegAvcDecode(
+                handle,
+                shared.video_au.get(),
+                FRAME_BUFFER_WIDTH as i32,
+                &mut dst as *mut *mut c_void as *mut c_void,
+                &mut decoded,
+            )
+        };
+
+        // A frame that fails to decode is skipped.
+        if ret < 0 || decoded == 0 {
+            continue;
+        }
+
+        if !shared.wait_until(pts) {
+            break;
+        }
+
+        shared.publish(back, pts);
+        back ^= 1;
+    }
+
+    shared.state(|state| state.video_done = true);
+}
+
+/// Decodes audio to the hardware channel, advancing the audio clock.
+fn audio_thread(shared: &Shared) {
+    let handle = shared.handle();
+    let audio = match &shared.audio {
+        Some(audio) => audio,
+        None => return,
+    };
+    let mut index = 0;
+    let mut init = 1;
+    let mut queued = None;
+
+    while !shared.stopped() {
+        if shared.paused() {
+            unsafe { sys::sceKernelDelayThread(10_000) };
+            continue;
+        }
+
+        let mut unk = 0i32;
+        let ret = unsafe {
+            sys::sceMpegGetAtracAu(
+                handle,
+                audio.stream,
+                audio.au.get(),
+                &mut unk as *mut i32 as *mut c_void,
+            )
+        };
+
+        if ret < 0 {
+            if shared.state(|state| state.eof) {
+                break;
+            }
+
+            unsafe { sys::sceKernelDelayThread(1000) };
+            continue;
+        }
+
+        let pts = timestamp(unsafe { &*audio.au.get() });
+        let buf = audio.out[index].as_ptr();
+
+        if unsafe { sys::sceMpegAtracDecode(handle, audio.au.get(), buf, init) } < 0 {
+            continue;
+        }
+
+        init = 0;
+
+        unsafe {
+            sys::sceAudioOutputBlocking(audio.channel, sys::AUDIO_VOLUME_MAX as i32, buf);
+        }
+
+        // The call returns once the previous buffer starts playing.
+        if let Some(playing) = queued {
+            shared.state(|state| state.audio_clock = Some(playing));
+        }
+
+        queued = Some(pts);
+        index ^= 1;
+    }
+
+    // Hand the clock over to the system time, so that the video can keep
+    // going if it is longer than the audio.
+    let now = now();
+    let first = shared.header.first_timestamp;
+    shared.state(|state| {
+        if let Some(clock) = state.audio_clock {
+            state.wall_start = Some(now - ticks_to_micros(clock.saturating_sub(first)));
+        }
+
+        state.audio_done = true;
+    });
+}
+
+fn register(handle: SceMpeg, id: i32) -> io::Result<SceMpegStream> {
+    let stream = unsafe { sys::sceMpegRegistStream(handle, id, 0) };
+
+    if stream.is_null() {
+        return Err(error::SCE_MPEG_ERROR_INVALID_VALUE.into());
+    }
+
+    Ok(stream)
+}
+
+/// The presentation timestamp of an access unit.
+fn timestamp(au: &SceMpegAu) -> u64 {
+    ((au.pts_msb as u64) << 32) | au.pts as u64
+}
+
+fn ticks_to_micros(ticks: u64) -> i64 {
+    (ticks * 1_000_000 / TIMESTAMP_RATE) as i64
+}
+
+/// The system time, in microseconds.
+fn now() -> i64 {
+    unsafe { sys::sceKernelGetSystemTimeWide() }
+}
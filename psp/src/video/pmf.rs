@@ -0,0 +1,132 @@
+//! Parsing of the headers of PMF (`.pmf`) movies.
+//!
+//! A PMF file starts with a 2048 byte header giving the size of the MPEG
+//! stream, its duration and the streams it holds. `VideoPlayer` reads it to
+//! size its buffers, and `PmfHeader::parse` can be used on its own, such as
+//! to show how long a movie is before playing it.
+
+use crate::io;
+use core::time::Duration;
+
+/// Size of the PMF header, and of the packets of the stream after it.
+pub const HEADER_SIZE: usize = 2048;
+
+/// Timestamps count in units of 1/90000 s.
+pub const TIMESTAMP_RATE: u64 = 90_000;
+
+/// Offset of the stream table, after its 16-bit entry count.
+const STREAM_TABLE: usize = 0x82;
+
+/// Size of an entry of the stream table.
+const STREAM_ENTRY_SIZE: usize = 16;
+
+/// Error returned when parsing a PMF header.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum HeaderError {
+    /// The data does not start with the `PSMF` magic.
+    NotPmf,
+    /// The data ends before the end of the stream table.
+    Truncated,
+    /// There is no video stream.
+    NoVideo,
+}
+
+impl From<HeaderError> for io::Error {
+    fn from(_: HeaderError) -> Self {
+        io::Error::new(io::ErrorKind::InvalidData)
+    }
+}
+
+/// The information in the header of a PMF file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PmfHeader {
+    /// The offset of the MPEG stream from the start of the file.
+    pub stream_offset: u32,
+    /// The size of the MPEG stream, in bytes.
+    pub stream_size: u32,
+    /// The width of the video, in pixels.
+    pub width: u32,
+    /// The height of the video, in pixels.
+    pub height: u32,
+    /// Whether the movie has an ATRAC3plus audio stream.
+    pub has_audio: bool,
+    /// The timestamp of the first frame, in units of 1/90000 s.
+    pub first_timestamp: u64,
+    /// The timestamp of the last frame, in units of 1/90000 s.
+    pub last_timestamp: u64,
+}
+
+impl PmfHeader {
+    /// Parse the header at the start of `data`, which should hold the first
+    /// `HEADER_SIZE` bytes of the file.
+    pub fn parse(data: &[u8]) -> Result<Self, HeaderError> {
+        if data.len() < 4 || &data[..4] != b"PSMF" {
+            return Err(HeaderError::NotPmf);
+        }
+
+        let stream_offset = read_u32(data, 0x08).ok_or(HeaderError::Truncated)?;
+        let stream_size = read_u32(data, 0x0c).ok_or(HeaderError::Truncated)?;
+        let first_timestamp = read_timestamp(data, 0x54).ok_or(HeaderError::Truncated)?;
+        let last_timestamp = read_timestamp(data, 0x5a).ok_or(HeaderError::Truncated)?;
+        let count = read_u16(data, 0x80).ok_or(HeaderError::Truncated)? as usize;
+
+        let mut video = None;
+        let mut has_audio = false;
+
+        for i in 0..count {
+            let offset = STREAM_TABLE + i * STREAM_ENTRY_SIZE;
+            let entry = data
+                .get(offset..offset + STREAM_ENTRY_SIZE)
+                .ok_or(HeaderError::Truncated)?;
+
+            // Entries start with the MPEG stream id: 0xe0 and up for video,
+            // 0xbd (private stream 1) for ATRAC3plus audio. The size of a
+            // video stream is stored in macroblocks of 16 pixels.
+            match entry[0] {
+                0xe0..=0xef if video.is_none() => {
+                    video = Some((entry[12] as u32 * 16, entry[13] as u32 * 16));
+                }
+                0xbd => has_audio = true,
+                _ => {}
+            }
+        }
+
+        let (width, height) = video.ok_or(HeaderError::NoVideo)?;
+
+        Ok(Self {
+            stream_offset,
+            stream_size,
+            width,
+            height,
+            has_audio,
+            first_timestamp,
+            last_timestamp,
+        })
+    }
+
+    /// The length of the movie.
+    pub fn duration(&self) -> Duration {
+        let ticks = self.last_timestamp.saturating_sub(self.first_timestamp);
+
+        Duration::from_micros(ticks * 1_000_000 / TIMESTAMP_RATE)
+    }
+}
+
+fn read_u16(data: &[u8], offset: usize) -> Option<u16> {
+    let bytes = data.get(offset..offset + 2)?;
+
+    Some(u16::from_be_bytes([bytes[0], bytes[1]]))
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Option<u32> {
+    let bytes = data.get(offset..offset + 4)?;
+
+    Some(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+/// Read a 48-bit big-endian timestamp.
+fn read_timestamp(data: &[u8], offset: usize) -> Option<u64> {
+    let bytes = data.get(offset..offset + 6)?;
+
+    Some(bytes.iter().fold(0, |ts, &b| (ts << 8) | b as u64))
+}